@@ -13,6 +13,7 @@ use tracing::info;
 
 use crate::services::authentication_service;
 use crate::services::account_service;
+use crate::money::Money;
 use crate::dtos::auth as auth_dtos;
 use crate::utils::{ApiResponse, is_valid_phone, is_valid_email};
 
@@ -54,8 +55,8 @@ pub async fn login(
 
             let login_response =  auth_dtos::LoginResponse {
                 token: token,
-                balance: balance.unwrap_or(0.0),
-                credit_balance: cbalance.unwrap_or(0.0)
+                balance: balance.unwrap_or(Money::ZERO),
+                credit_balance: cbalance.unwrap_or(Money::ZERO)
             };
             return ApiResponse::<auth_dtos::LoginResponse>::success(Some(login_response))
           
@@ -1,5 +1,7 @@
 
 
+use std::str::FromStr;
+
 use axum::routing::{post,get};
 use sqlx::MySqlPool;
 use axum::debug_handler;
@@ -13,18 +15,68 @@ use axum::{
 };
 use crate::dtos::chama::{
     ChamaDto, 
+    ChamaInviteDto,
+    ChamaInviteUrlDto,
     ChamaLoadLimitDto, 
     ChamaLoanApproverDto, 
+    ChamaLoanApprovalSettingDto, 
+    ChamaLoanDefaultSettingDto, 
     ChamaLoanQuaranteeSettingDto, 
     ChamaLoanRepaymentLimitDto, 
-    ChamaMemberApproveDto, 
     ChamaMemberDetailDto, 
-    ChamaPositionDetailDto
+    ChamaMemberUpdateDto,
+    ChamaMembershipSettingDto,
+    ChamaPositionDetailDto,
+    ChamaPositionDto,
+    ChamaPositionPermissionDto,
+    MembershipApproveDto,
+    MembershipRejectDto,
+    MembershipRequestDetailDto
+};
+use crate::dtos::chama_wallet::{
+    ChamaWalletDto,
+    ChamaWithdrawalDetailDto,
+    ChamaWithdrawalRequestDto,
+    ChamaWithdrawalSignDto,
+    ChamaWithdrawalSignatoryDto
+};
+use crate::dtos::contribution::{
+    ContributionCycleSummaryDto,
+    ContributionRecordDto,
+    ContributionScheduleDto,
+    MemberArrearsDto,
+    MemberStatementDto
 };
+use crate::dtos::dividend::{DividendRunDetailDto, DividendRunDto};
+use crate::dtos::fine::{FineWaiveDto, PenaltyRuleDto};
+use crate::dtos::meeting::{
+    AgendaItemDto,
+    MeetingAttendanceDto,
+    MeetingDetailDto,
+    MeetingDto,
+    MeetingMinutesDto,
+    ResolutionDto,
+    ResolutionLinkDto
+};
+use crate::dtos::member_exit::{ExitStatementDto, MemberExitDetailDto, MemberExitDto, MemberExitRejectDto};
+use crate::dtos::proposal::{ProposalDetailDto, ProposalDto, ProposalVoteDto};
+use crate::dtos::rotation::{RotationBidDto, RotationDetailDto, RotationDto};
+use crate::enums::LoanRepaymentFrequecyEnum;
+use crate::gateways::SharedGateway;
+use crate::models::chama::{ChamaInvite, ChamaMember, ChamaMembershipRequest, ChamaMembershipSetting, ChamaPermission};
+use crate::models::chama_wallet::{ChamaWithdrawal, ChamaWithdrawalSignatory};
+use crate::models::contribution::{Contribution, ContributionSchedule};
+use crate::models::dividend::DividendRun;
+use crate::models::fine::{Fine, PenaltyRule};
+use crate::models::meeting::{Meeting, MeetingAgendaItem, MeetingResolution};
+use crate::models::member_exit::MemberExit;
+use crate::models::proposal::Proposal;
+use crate::models::rotation::RotationBid;
 use crate::utils::{ApiResponse, is_valid_phone};
 use crate::middleware::auth::require_auth;
+use crate::middleware::chama_guard::ChamaGuard;
 use crate::dtos::auth::Claims;
-use crate::services::chama_service;
+use crate::services::{chama_service, chama_wallet_service, contribution_service, dividend_service, exit_service, fine_service, meeting_service, membership_service, proposal_service, rotation_service};
 
 
 #[debug_handler]
@@ -59,19 +111,18 @@ pub async fn create_new_chama(
 }
 
 pub async fn update_chama(
-    Extension(claims): Extension<Claims>, 
+    guard: ChamaGuard,
     Extension(pool): Extension<MySqlPool>, 
     Json(mut payload): Json<ChamaDto>) -> impl IntoResponse {
 
         let mut contact_number: String = payload.contact_number.clone();
-        let user_id = claims.sub;
-        let chama_id = payload.id.unwrap();
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        let Some(chama_id) = payload.id else {
+            return ApiResponse::<&str>::error("Chama id is required", StatusCode::BAD_REQUEST.as_u16())
+        };
+        if let Err(e) = guard.require(&chama_id, ChamaPermission::MANAGECHAMA).await {
+            return ApiResponse::<&str>::from(e);
         }
+        let user_id = guard.user_id;
         
         if let Some(phone)= is_valid_phone(&contact_number){
             contact_number = phone;
@@ -99,16 +150,9 @@ pub async fn join_chama(
     Extension(pool): Extension<MySqlPool>, 
     Path(invite_hash): Path<String>) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-
-        let last_insert_id = chama_service::join_chama(&pool, &user_id, &invite_hash).await;
-         
-        if last_insert_id == -1  {
-            return ApiResponse::<&str>::error(&format!("No such chama "), StatusCode::IM_USED.as_u16())
-        } else if last_insert_id  != 0 { 
-            return ApiResponse::success(Some("User added to Chama"))
-        } else {
-            return ApiResponse::<&str>::error(&format!("Could not add user to chama"), StatusCode::EXPECTATION_FAILED.as_u16())
+        match chama_service::join_chama(&pool, &claims.sub, &invite_hash).await {
+            Ok(request) => ApiResponse::<ChamaMembershipRequest>::success(Some(request)),
+            Err(e) => e.into(),
         }
 }
 
@@ -116,68 +160,143 @@ pub async fn get_invite(
     Extension(claims): Extension<Claims>, 
     Extension(pool): Extension<MySqlPool>, Path(chama_id): Path<i64>, ) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<String>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        let payload = ChamaInviteDto { chama_id, phone_number:None, email:None, max_uses:None, expiry_days:None };
+        match chama_service::create_invite(&pool, &claims.sub, &payload).await {
+            Ok(invite) => ApiResponse::<String>::success(Some(invite.url)),
+            Err(e) => e.into(),
         }
+}
 
-        let invite_url = chama_service::get_invite(&pool, &user_id, &chama_id).await;
-         
-        if invite_url == "-1".to_string()  {
-            return ApiResponse::<String>::error(&format!("Chama with such name exists"), StatusCode::IM_USED.as_u16())
-        } else if invite_url  != "0".to_string() { 
-            return ApiResponse::<String>::success(Some(String::from(invite_url)))
-        } else {
-            return ApiResponse::<String>::error(&format!("Could not created user"), StatusCode::EXPECTATION_FAILED.as_u16())
+pub async fn create_invite(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<ChamaInviteDto>) -> impl IntoResponse {
+
+        match chama_service::create_invite(&pool, &claims.sub, &payload).await {
+            Ok(invite) => ApiResponse::<ChamaInviteUrlDto>::success(Some(invite)),
+            Err(e) => e.into(),
         }
 }
 
-pub async fn approve_member(
-    Extension(claims): Extension<Claims>, 
-    Extension(pool): Extension<MySqlPool>, Json(payload):Json<ChamaMemberApproveDto>) -> impl IntoResponse {
+pub async fn get_invites(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-        let chama_id = payload.chama_id;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
+        match chama_service::get_invites(&pool, &claims.sub, &chama_id).await {
+            Ok(invites) => ApiResponse::<Vec<ChamaInvite>>::success(Some(invites)),
+            Err(e) => e.into(),
+        }
+}
 
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+pub async fn revoke_invite(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(invite_id): Path<i64>) -> impl IntoResponse {
+
+        match chama_service::revoke_invite(&pool, &claims.sub, &invite_id).await {
+            Ok(invite) => ApiResponse::<ChamaInvite>::success(Some(invite)),
+            Err(e) => e.into(),
         }
+}
 
-        let approved = chama_service::approve_member(&pool, &user_id, &payload).await;
-         
-        if approved == -1 {
-            return ApiResponse::<&str>::error(&format!("No such member"), StatusCode::IM_USED.as_u16())
-        } else if approved  != 0 { 
-            return ApiResponse::success(Some("Member approved"))
-        } else {
-            return ApiResponse::<&str>::error(&format!("Could not approve member"), StatusCode::EXPECTATION_FAILED.as_u16())
+pub async fn set_membership_setting(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<ChamaMembershipSettingDto>) -> impl IntoResponse {
+
+        match membership_service::set_setting(&pool, &claims.sub, &payload).await {
+            Ok(setting) => ApiResponse::<ChamaMembershipSetting>::success(Some(setting)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_membership_requests(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match membership_service::get_pending_requests(&pool, &claims.sub, &chama_id).await {
+            Ok(requests) => ApiResponse::<Vec<MembershipRequestDetailDto>>::success(Some(requests)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn pay_joining_fee(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(request_id): Path<i64>) -> impl IntoResponse {
+
+        match membership_service::pay_joining_fee(&pool, &claims.sub, &request_id).await {
+            Ok(request) => ApiResponse::<ChamaMembershipRequest>::success(Some(request)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn approve_membership_request(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(request_id): Path<i64>,
+    Json(payload): Json<MembershipApproveDto>) -> impl IntoResponse {
+
+        match membership_service::approve_request(&pool, &claims.sub, &request_id, &payload).await {
+            Ok(member) => ApiResponse::<ChamaMember>::success(Some(member)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn reject_membership_request(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(request_id): Path<i64>,
+    Json(payload): Json<MembershipRejectDto>) -> impl IntoResponse {
+
+        match membership_service::reject_request(&pool, &claims.sub, &request_id, &payload).await {
+            Ok(request) => ApiResponse::<ChamaMembershipRequest>::success(Some(request)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn cancel_membership_request(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(request_id): Path<i64>) -> impl IntoResponse {
+
+        match membership_service::cancel_request(&pool, &claims.sub, &request_id).await {
+            Ok(request) => ApiResponse::<ChamaMembershipRequest>::success(Some(request)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn update_member(
+    guard: ChamaGuard,
+    Extension(pool): Extension<MySqlPool>, Json(payload):Json<ChamaMemberUpdateDto>) -> impl IntoResponse {
+
+        if let Err(e) = guard.require(&payload.chama_id, ChamaPermission::MANAGEMEMBERS).await {
+            return ApiResponse::<ChamaMember>::from(e);
+        }
+
+        match chama_service::update_member(&pool, &guard.user_id, &payload).await {
+            Ok(member) => ApiResponse::<ChamaMember>::success(Some(member)),
+            Err(e) => e.into(),
         }
 }
 
 pub async fn remove_member(
-    Extension(claims): Extension<Claims>, 
+    guard: ChamaGuard,
     Extension(pool): Extension<MySqlPool>, 
-    Path(chama_id):Path<i64>, Path(member_id):Path<i64>) -> impl IntoResponse {
+    Path((chama_id, member_id)):Path<(i64, i64)>) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
-
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        if let Err(e) = guard.require(&chama_id, ChamaPermission::MANAGEMEMBERS).await {
+            return ApiResponse::<&str>::from(e);
         }
 
-        let removed = chama_service::remove_member(&pool, &member_id).await;
+        let removed = chama_service::remove_member(&pool, &chama_id, &member_id).await;
          
         if removed == -1 {
             return ApiResponse::<&str>::error(&format!("No such member"), StatusCode::IM_USED.as_u16())
+        } else if removed == -2 {
+            ApiResponse::<&str>::error("Member must leave through an exit settlement", StatusCode::CONFLICT.as_u16())
         } else if removed  != 0 { 
             return ApiResponse::success(Some("Member removed"))
         } else {
@@ -202,7 +321,7 @@ pub async fn get_loan_approvers(
     Extension(pool): Extension<MySqlPool>, Path(chama_id):Path<i64>) -> impl IntoResponse {
 
         let user_id = claims.sub;
-        match chama_service::get_loan_approvers(&pool, &chama_id.to_string(), &user_id).await {
+        match chama_service::get_loan_approvers(&pool, &user_id, &chama_id.to_string()).await {
             Ok(members) => ApiResponse::<Vec<ChamaMemberDetailDto>>::success(Some(members)),
             Err(_) => ApiResponse::<Vec<ChamaMemberDetailDto>>::error(&format!("Could not get members"), StatusCode::EXPECTATION_FAILED.as_u16()),
         }
@@ -212,14 +331,48 @@ pub async fn get_loan_approvers(
 
 pub async fn get_chama_positions(
     Extension(claims): Extension<Claims>, 
-    Extension(pool): Extension<MySqlPool>) -> impl IntoResponse {
-        match chama_service::get_chama_positions(&pool).await {
+    Extension(pool): Extension<MySqlPool>, Path(chama_id):Path<i64>) -> impl IntoResponse {
+        match chama_service::get_chama_positions(&pool, &claims.sub, &chama_id).await {
             Ok(positions) => ApiResponse::<Vec<ChamaPositionDetailDto>>::success(Some(positions)),
-            Err(_) => ApiResponse::<Vec<ChamaPositionDetailDto>>::error(&format!("Could not get positions"), StatusCode::EXPECTATION_FAILED.as_u16()),
+            Err(e) => e.into(),
         }
         
 }
 
+pub async fn create_chama_position(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<ChamaPositionDto>) -> impl IntoResponse {
+
+        match chama_service::create_chama_position(&pool, &claims.sub, &payload).await {
+            Ok(position) => ApiResponse::<ChamaPositionDetailDto>::success(Some(position)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn set_position_permissions(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(position_id): Path<i64>,
+    Json(payload): Json<ChamaPositionPermissionDto>) -> impl IntoResponse {
+
+        match chama_service::set_position_permissions(&pool, &claims.sub, &position_id, &payload).await {
+            Ok(position) => ApiResponse::<ChamaPositionDetailDto>::success(Some(position)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_my_permissions(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match chama_service::get_my_permissions(&pool, &claims.sub, &chama_id).await {
+            Ok(permissions) => ApiResponse::<Vec<ChamaPermission>>::success(Some(permissions)),
+            Err(e) => e.into(),
+        }
+}
+
 
 pub async fn get_guaranter_setting(
     Extension(claims): Extension<Claims>, 
@@ -234,19 +387,16 @@ pub async fn get_guaranter_setting(
 
 
 pub async fn add_loan_approver(
-    Extension(claims): Extension<Claims>, 
+    guard: ChamaGuard,
     Extension(pool): Extension<MySqlPool>, Json(payload):Json<ChamaLoanApproverDto>) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &payload.chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
-
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        if let Err(e) = guard.require(&payload.chama_id, ChamaPermission::MANAGELOANSETTINGS).await {
+            return ApiResponse::<&str>::from(e);
         }
+        let user_id = guard.user_id;
 
         match chama_service::add_loan_approver(&pool, &user_id, &payload).await {
+            Ok(0) => ApiResponse::<&str>::error("No such position in this chama", StatusCode::NOT_FOUND.as_u16()),
             Ok(_) => ApiResponse::success(Some("Approver added")),
             Err(_) => ApiResponse::<&str>::error(&format!("Could not add approver"), StatusCode::EXPECTATION_FAILED.as_u16()),
         }
@@ -255,16 +405,11 @@ pub async fn add_loan_approver(
 
 
 pub async fn add_guaranter_setting(
-    Extension(claims): Extension<Claims>, 
+    guard: ChamaGuard,
     Extension(pool): Extension<MySqlPool>, Json(payload):Json<ChamaLoanQuaranteeSettingDto>) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &payload.chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
-
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        if let Err(e) = guard.require(&payload.chama_id, ChamaPermission::MANAGELOANSETTINGS).await {
+            return ApiResponse::<&str>::from(e);
         }
 
         match chama_service::add_guaranter_setting(&pool, &payload).await {
@@ -276,16 +421,11 @@ pub async fn add_guaranter_setting(
 
 
 pub async fn add_chama_loan_limit(
-    Extension(claims): Extension<Claims>, 
+    guard: ChamaGuard,
     Extension(pool): Extension<MySqlPool>, Json(payload):Json<ChamaLoadLimitDto>) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &payload.chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
-
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        if let Err(e) = guard.require(&payload.chama_id, ChamaPermission::MANAGELOANSETTINGS).await {
+            return ApiResponse::<&str>::from(e);
         }
 
         match chama_service::add_loan_limit(&pool, &payload).await {
@@ -294,17 +434,60 @@ pub async fn add_chama_loan_limit(
         }
         
 }
+pub async fn add_chama_loan_approval_setting(
+    guard: ChamaGuard,
+    Extension(pool): Extension<MySqlPool>, Json(payload):Json<ChamaLoanApprovalSettingDto>) -> impl IntoResponse {
+
+        if let Err(e) = guard.require(&payload.chama_id, ChamaPermission::MANAGELOANSETTINGS).await {
+            return ApiResponse::<&str>::from(e);
+        }
+
+        if payload.quorum < 1 {
+            return ApiResponse::<&str>::error("Quorum must be at least one approver", StatusCode::BAD_REQUEST.as_u16())
+        }
+
+        match chama_service::add_loan_approval_setting(&pool, &payload).await {
+            Ok(_) => ApiResponse::success(Some("Loan approval quorum set")),
+            Err(_) => ApiResponse::<&str>::error("Could not set loan approval quorum", StatusCode::EXPECTATION_FAILED.as_u16()),
+        }
+        
+}
+
+pub async fn add_chama_loan_default_setting(
+    guard: ChamaGuard,
+    Extension(pool): Extension<MySqlPool>, Json(payload):Json<ChamaLoanDefaultSettingDto>) -> impl IntoResponse {
+
+        if let Err(e) = guard.require(&payload.chama_id, ChamaPermission::MANAGELOANSETTINGS).await {
+            return ApiResponse::<&str>::from(e);
+        }
+
+        if payload.grace_period_days < 0 {
+            return ApiResponse::<&str>::error("Grace period cannot be negative", StatusCode::BAD_REQUEST.as_u16())
+        }
+
+        match chama_service::add_loan_default_setting(&pool, &payload).await {
+            Ok(_) => ApiResponse::success(Some("Loan default grace period set")),
+            Err(_) => ApiResponse::<&str>::error("Could not set loan default grace period", StatusCode::EXPECTATION_FAILED.as_u16()),
+        }
+        
+}
+
 pub async fn add_chama_loan_repayment_limit(
-    Extension(claims): Extension<Claims>, 
+    guard: ChamaGuard,
     Extension(pool): Extension<MySqlPool>, Json(payload):Json<ChamaLoanRepaymentLimitDto>) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &payload.chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
+        if let Err(e) = guard.require(&payload.chama_id, ChamaPermission::MANAGELOANSETTINGS).await {
+            return ApiResponse::<&str>::from(e);
+        }
 
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        if LoanRepaymentFrequecyEnum::from_str(&payload.repayment_frequency).is_err() {
+            return ApiResponse::<&str>::error("Repayment frequency must be WEEKLY, BIWEEKLY or MONTHLY", StatusCode::BAD_REQUEST.as_u16())
+        }
+        if payload.max_repayment_months < 1 {
+            return ApiResponse::<&str>::error("Maximum repayment months must be at least one", StatusCode::BAD_REQUEST.as_u16())
+        }
+        if payload.interest_rate.unwrap_or(0.0) < 0.0 || payload.processing_fee_centage.unwrap_or(0.0) < 0.0 {
+            return ApiResponse::<&str>::error("Interest rate and fees cannot be negative", StatusCode::BAD_REQUEST.as_u16())
         }
 
         match chama_service::add_loan_repayment_limit(&pool, &payload).await {
@@ -315,21 +498,15 @@ pub async fn add_chama_loan_repayment_limit(
 }
 
 pub async fn remove_from_loan_approver(
-    Extension(claims): Extension<Claims>, 
+    guard: ChamaGuard,
     Extension(pool): Extension<MySqlPool>, 
-    Path(position_id):Path<i64>,
-    Path(chama_id):Path<i64>) -> impl IntoResponse {
-
-        let user_id = claims.sub;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
+    Path((chama_id, position_id)):Path<(i64, i64)>) -> impl IntoResponse {
 
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        if let Err(e) = guard.require(&chama_id, ChamaPermission::MANAGELOANSETTINGS).await {
+            return ApiResponse::<&str>::from(e);
         }
 
-        match chama_service::remove_from_loan_approver(&pool, &position_id).await {
+        match chama_service::remove_from_loan_approver(&pool, &chama_id, &position_id).await {
             Ok(_) => ApiResponse::success(Some("Approver added")),
             Err(_) => ApiResponse::<&str>::error(&format!("Could not add approver"), StatusCode::EXPECTATION_FAILED.as_u16()),
         }
@@ -337,45 +514,611 @@ pub async fn remove_from_loan_approver(
 }
 
 pub async fn remove_guaranter_setting(
-    Extension(claims): Extension<Claims>, 
+    guard: ChamaGuard,
     Extension(pool): Extension<MySqlPool>, 
-    Path(g_id):Path<i64>, Path(chama_id):Path<i64>) -> impl IntoResponse {
+    Path((chama_id, g_id)):Path<(i64, i64)>) -> impl IntoResponse {
 
-        let user_id = claims.sub;
-        let roles = chama_service::get_chama_roles(&pool, &user_id, &chama_id.to_string()).await;
-        let roles = roles.unwrap_or_default();
-
-        
-        if !roles.contains(&String::from("chama-admin")) {
-            return ApiResponse::<&str>::error(&format!("User not allowed to perform this action"), StatusCode::FORBIDDEN.as_u16()) 
+        if let Err(e) = guard.require(&chama_id, ChamaPermission::MANAGELOANSETTINGS).await {
+            return ApiResponse::<&str>::from(e);
         }
 
-        match chama_service::remove_guarantee_setting(&pool, &g_id).await {
+        match chama_service::remove_guarantee_setting(&pool, &chama_id, &g_id).await {
             Ok(_) => ApiResponse::success(Some("Guarantee removed")),
             Err(_) => ApiResponse::<&str>::error(&format!("Could not remove setting"), StatusCode::EXPECTATION_FAILED.as_u16()),
         }
         
 }
 
+pub async fn set_contribution_schedule(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<ContributionScheduleDto>) -> impl IntoResponse {
+
+        match contribution_service::set_schedule(&pool, &claims.sub, &payload).await {
+            Ok(schedule) => ApiResponse::<ContributionSchedule>::success(Some(schedule)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn record_contribution(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<ContributionRecordDto>) -> impl IntoResponse {
+
+        match contribution_service::record_contribution(&pool, &claims.sub, &payload).await {
+            Ok(contribution) => ApiResponse::<Contribution>::success(Some(contribution)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_contribution_cycles(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match contribution_service::get_cycles(&pool, &claims.sub, &chama_id).await {
+            Ok(cycles) => ApiResponse::<Vec<ContributionCycleSummaryDto>>::success(Some(cycles)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_contribution_arrears(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match contribution_service::get_arrears(&pool, &claims.sub, &chama_id).await {
+            Ok(members) => ApiResponse::<Vec<MemberArrearsDto>>::success(Some(members)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_member_statement(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path((chama_id, member_id)): Path<(i64, i64)>) -> impl IntoResponse {
+
+        match contribution_service::get_member_statement(&pool, &claims.sub, &chama_id, &member_id).await {
+            Ok(statement) => ApiResponse::<MemberStatementDto>::success(Some(statement)),
+            Err(e) => e.into(),
+        }
+}
+pub async fn create_rotation(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<RotationDto>) -> impl IntoResponse {
+
+        match rotation_service::create_rotation(&pool, &claims.sub, &payload).await {
+            Ok(rotation) => ApiResponse::<RotationDetailDto>::success(Some(rotation)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_rotation(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match rotation_service::get_rotation(&pool, &claims.sub, &chama_id).await {
+            Ok(rotation) => ApiResponse::<RotationDetailDto>::success(Some(rotation)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn place_rotation_bid(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(rotation_id): Path<i64>,
+    Json(payload): Json<RotationBidDto>) -> impl IntoResponse {
+
+        match rotation_service::place_bid(&pool, &claims.sub, &rotation_id, &payload).await {
+            Ok(bid) => ApiResponse::<RotationBid>::success(Some(bid)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn run_rotation(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(rotation_id): Path<i64>) -> impl IntoResponse {
+
+        match rotation_service::run_rotation(&pool, &claims.sub, &rotation_id).await {
+            Ok(rotation) => ApiResponse::<RotationDetailDto>::success(Some(rotation)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn cancel_rotation(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(rotation_id): Path<i64>) -> impl IntoResponse {
+
+        match rotation_service::cancel_rotation(&pool, &claims.sub, &rotation_id).await {
+            Ok(rotation) => ApiResponse::<RotationDetailDto>::success(Some(rotation)),
+            Err(e) => e.into(),
+        }
+}
+pub async fn set_penalty_rule(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<PenaltyRuleDto>) -> impl IntoResponse {
+
+        match fine_service::set_rule(&pool, &claims.sub, &payload).await {
+            Ok(rule) => ApiResponse::<PenaltyRule>::success(Some(rule)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_penalty_rules(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match fine_service::get_rules(&pool, &claims.sub, &chama_id).await {
+            Ok(rules) => ApiResponse::<Vec<PenaltyRule>>::success(Some(rules)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_fines(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match fine_service::get_fines(&pool, &claims.sub, &chama_id).await {
+            Ok(fines) => ApiResponse::<Vec<Fine>>::success(Some(fines)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn waive_fine(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(fine_id): Path<i64>,
+    Json(payload): Json<FineWaiveDto>) -> impl IntoResponse {
+
+        match fine_service::waive_fine(&pool, &claims.sub, &fine_id, &payload).await {
+            Ok(fine) => ApiResponse::<Fine>::success(Some(fine)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn pay_fine(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(fine_id): Path<i64>) -> impl IntoResponse {
+
+        match fine_service::pay_fine(&pool, &claims.sub, &fine_id).await {
+            Ok(fine) => ApiResponse::<Fine>::success(Some(fine)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn preview_dividend_run(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<DividendRunDto>) -> impl IntoResponse {
+
+        match dividend_service::preview_run(&pool, &claims.sub, &payload).await {
+            Ok(run) => ApiResponse::<DividendRunDetailDto>::success(Some(run)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn post_dividend_run(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<DividendRunDto>) -> impl IntoResponse {
+
+        match dividend_service::post_run(&pool, &claims.sub, &payload).await {
+            Ok(run) => ApiResponse::<DividendRunDetailDto>::success(Some(run)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_dividend_runs(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match dividend_service::get_runs(&pool, &claims.sub, &chama_id).await {
+            Ok(runs) => ApiResponse::<Vec<DividendRun>>::success(Some(runs)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_dividend_run(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(run_id): Path<i64>) -> impl IntoResponse {
+
+        match dividend_service::get_run(&pool, &claims.sub, &run_id).await {
+            Ok(run) => ApiResponse::<DividendRunDetailDto>::success(Some(run)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn schedule_meeting(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<MeetingDto>,) -> impl IntoResponse {
+
+        match meeting_service::schedule_meeting(&pool, &claims.sub, &payload).await {
+            Ok(meeting) => ApiResponse::<MeetingDetailDto>::success(Some(meeting)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_meetings(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>,) -> impl IntoResponse {
+
+        match meeting_service::get_meetings(&pool, &claims.sub, &chama_id).await {
+            Ok(meetings) => ApiResponse::<Vec<Meeting>>::success(Some(meetings)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_meeting(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(meeting_id): Path<i64>,) -> impl IntoResponse {
+
+        match meeting_service::get_meeting(&pool, &claims.sub, &meeting_id).await {
+            Ok(meeting) => ApiResponse::<MeetingDetailDto>::success(Some(meeting)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn add_agenda_item(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(meeting_id): Path<i64>,
+    Json(payload): Json<AgendaItemDto>,) -> impl IntoResponse {
+
+        match meeting_service::add_agenda_item(&pool, &claims.sub, &meeting_id, &payload).await {
+            Ok(item) => ApiResponse::<MeetingAgendaItem>::success(Some(item)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn record_attendance(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(meeting_id): Path<i64>,
+    Json(payload): Json<MeetingAttendanceDto>,) -> impl IntoResponse {
+
+        match meeting_service::record_attendance(&pool, &claims.sub, &meeting_id, &payload).await {
+            Ok(meeting) => ApiResponse::<MeetingDetailDto>::success(Some(meeting)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn add_resolution(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(meeting_id): Path<i64>,
+    Json(payload): Json<ResolutionDto>,) -> impl IntoResponse {
+
+        match meeting_service::add_resolution(&pool, &claims.sub, &meeting_id, &payload).await {
+            Ok(resolution) => ApiResponse::<MeetingResolution>::success(Some(resolution)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn link_resolution(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(resolution_id): Path<i64>,
+    Json(payload): Json<ResolutionLinkDto>,) -> impl IntoResponse {
+
+        match meeting_service::link_resolution(&pool, &claims.sub, &resolution_id, &payload).await {
+            Ok(resolution) => ApiResponse::<MeetingResolution>::success(Some(resolution)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_resolutions(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>,) -> impl IntoResponse {
+
+        match meeting_service::get_resolutions(&pool, &claims.sub, &chama_id).await {
+            Ok(resolutions) => ApiResponse::<Vec<MeetingResolution>>::success(Some(resolutions)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn record_minutes(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(meeting_id): Path<i64>,
+    Json(payload): Json<MeetingMinutesDto>,) -> impl IntoResponse {
+
+        match meeting_service::record_minutes(&pool, &claims.sub, &meeting_id, &payload).await {
+            Ok(meeting) => ApiResponse::<Meeting>::success(Some(meeting)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn cancel_meeting(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(meeting_id): Path<i64>,) -> impl IntoResponse {
+
+        match meeting_service::cancel_meeting(&pool, &claims.sub, &meeting_id).await {
+            Ok(meeting) => ApiResponse::<Meeting>::success(Some(meeting)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn create_proposal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<ProposalDto>,) -> impl IntoResponse {
+
+        match proposal_service::create_proposal(&pool, &claims.sub, &payload).await {
+            Ok(proposal) => ApiResponse::<Proposal>::success(Some(proposal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_proposals(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>,) -> impl IntoResponse {
+
+        match proposal_service::get_proposals(&pool, &claims.sub, &chama_id).await {
+            Ok(proposals) => ApiResponse::<Vec<Proposal>>::success(Some(proposals)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_proposal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(proposal_id): Path<i64>,) -> impl IntoResponse {
 
+        match proposal_service::get_proposal(&pool, &claims.sub, &proposal_id).await {
+            Ok(proposal) => ApiResponse::<ProposalDetailDto>::success(Some(proposal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn cast_vote(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(proposal_id): Path<i64>,
+    Json(payload): Json<ProposalVoteDto>,) -> impl IntoResponse {
+
+        match proposal_service::cast_vote(&pool, &claims.sub, &proposal_id, &payload).await {
+            Ok(proposal) => ApiResponse::<Proposal>::success(Some(proposal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn cancel_proposal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(proposal_id): Path<i64>,) -> impl IntoResponse {
+
+        match proposal_service::cancel_proposal(&pool, &claims.sub, &proposal_id).await {
+            Ok(proposal) => ApiResponse::<Proposal>::success(Some(proposal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn set_withdrawal_signatories(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<ChamaWithdrawalSignatoryDto>,) -> impl IntoResponse {
+
+        match chama_wallet_service::set_signatories(&pool, &claims.sub, &payload).await {
+            Ok(signatories) => ApiResponse::<Vec<ChamaWithdrawalSignatory>>::success(Some(signatories)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_withdrawal_signatories(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>,) -> impl IntoResponse {
+
+        match chama_wallet_service::get_signatories(&pool, &claims.sub, &chama_id).await {
+            Ok(signatories) => ApiResponse::<Vec<ChamaWithdrawalSignatory>>::success(Some(signatories)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_chama_wallet(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>,) -> impl IntoResponse {
+
+        match chama_wallet_service::get_wallet(&pool, &claims.sub, &chama_id).await {
+            Ok(wallet) => ApiResponse::<ChamaWalletDto>::success(Some(wallet)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn request_chama_withdrawal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Extension(gateway): Extension<SharedGateway>,
+    Json(payload): Json<ChamaWithdrawalRequestDto>,) -> impl IntoResponse {
+
+        match chama_wallet_service::request_withdrawal(&pool, gateway.as_ref(), &claims.sub, &payload).await {
+            Ok(withdrawal) => ApiResponse::<ChamaWithdrawalDetailDto>::success(Some(withdrawal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn sign_chama_withdrawal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Extension(gateway): Extension<SharedGateway>,
+    Path(withdrawal_id): Path<i64>,
+    Json(payload): Json<ChamaWithdrawalSignDto>,) -> impl IntoResponse {
+
+        match chama_wallet_service::sign_withdrawal(&pool, gateway.as_ref(), &claims.sub, &withdrawal_id, &payload).await {
+            Ok(withdrawal) => ApiResponse::<ChamaWithdrawalDetailDto>::success(Some(withdrawal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn cancel_chama_withdrawal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(withdrawal_id): Path<i64>,) -> impl IntoResponse {
+
+        match chama_wallet_service::cancel_withdrawal(&pool, &claims.sub, &withdrawal_id).await {
+            Ok(withdrawal) => ApiResponse::<ChamaWithdrawalDetailDto>::success(Some(withdrawal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_chama_withdrawals(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>,) -> impl IntoResponse {
+
+        match chama_wallet_service::get_withdrawals(&pool, &claims.sub, &chama_id).await {
+            Ok(withdrawals) => ApiResponse::<Vec<ChamaWithdrawal>>::success(Some(withdrawals)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_chama_withdrawal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(withdrawal_id): Path<i64>,) -> impl IntoResponse {
+
+        match chama_wallet_service::get_withdrawal(&pool, &claims.sub, &withdrawal_id).await {
+            Ok(withdrawal) => ApiResponse::<ChamaWithdrawalDetailDto>::success(Some(withdrawal)),
+            Err(e) => e.into(),
+        }
+}
+
+
+pub async fn get_exit_statement(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path((chama_id, member_id)): Path<(i64, i64)>) -> impl IntoResponse {
+
+        match exit_service::get_statement(&pool, &claims.sub, &chama_id, &member_id).await {
+            Ok(statement) => ApiResponse::<ExitStatementDto>::success(Some(statement)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn request_exit(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<MemberExitDto>) -> impl IntoResponse {
+
+        match exit_service::request_exit(&pool, &claims.sub, &payload).await {
+            Ok(exit) => ApiResponse::<MemberExitDetailDto>::success(Some(exit)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_exit(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(exit_id): Path<i64>) -> impl IntoResponse {
+
+        match exit_service::get_exit(&pool, &claims.sub, &exit_id).await {
+            Ok(exit) => ApiResponse::<MemberExitDetailDto>::success(Some(exit)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_exits(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match exit_service::get_exits(&pool, &claims.sub, &chama_id).await {
+            Ok(exits) => ApiResponse::<Vec<MemberExit>>::success(Some(exits)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn settle_exit(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(exit_id): Path<i64>) -> impl IntoResponse {
+
+        match exit_service::settle_exit(&pool, &claims.sub, &exit_id).await {
+            Ok(exit) => ApiResponse::<MemberExitDetailDto>::success(Some(exit)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn reject_exit(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(exit_id): Path<i64>,
+    Json(payload): Json<MemberExitRejectDto>) -> impl IntoResponse {
+
+        match exit_service::reject_exit(&pool, &claims.sub, &exit_id, &payload).await {
+            Ok(exit) => ApiResponse::<MemberExit>::success(Some(exit)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn cancel_exit(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(exit_id): Path<i64>) -> impl IntoResponse {
+
+        match exit_service::cancel_exit(&pool, &claims.sub, &exit_id).await {
+            Ok(exit) => ApiResponse::<MemberExit>::success(Some(exit)),
+            Err(e) => e.into(),
+        }
+}
 
 pub fn routes() -> Router {
     Router::new()
         .route("/chama/create", post(create_new_chama))
         .route("/chama/update", post(update_chama))
         .route("/chama/invite/:chama_id", get(get_invite))
+        .route("/chama/invite", post(create_invite))
+        .route("/chama/invites/:chama_id", get(get_invites))
+        .route("/chama/invite-revoke/:invite_id", post(revoke_invite))
         .route("/chama/join/:invite_hash", get(join_chama))
+        //create or update
+        .route("/chama/membership-setting", post(set_membership_setting))
+        .route("/chama/membership-requests/:chama_id", get(get_membership_requests))
+        .route("/chama/membership-fee/:request_id", post(pay_joining_fee))
+        .route("/chama/membership-approve/:request_id", post(approve_membership_request))
+        .route("/chama/membership-reject/:request_id", post(reject_membership_request))
+        .route("/chama/membership-cancel/:request_id", post(cancel_membership_request))
 
-        .route("/chama/approve-member", post(approve_member)) 
+        .route("/chama/update-member", post(update_member))
 
         .route("/chama/members/:chama_id", get(members))
         .route("/chama/remove-member/:chama_id/:member_id", get(remove_member))
+        .route("/chama/exit-statement/:chama_id/:member_id", get(get_exit_statement))
+        .route("/chama/exit", post(request_exit))
+        .route("/chama/exit/:exit_id", get(get_exit))
+        .route("/chama/exits/:chama_id", get(get_exits))
+        .route("/chama/exit-settle/:exit_id", post(settle_exit))
+        .route("/chama/exit-reject/:exit_id", post(reject_exit))
+        .route("/chama/exit-cancel/:exit_id", post(cancel_exit))
 
         .route("/chama/add-approver", post(add_loan_approver))
         .route("/chama/approvers/:chama_id", get(get_loan_approvers))
         .route("/chama/remove-approver/:chama_id/:position_id", post(remove_from_loan_approver))
+        //create or update
+        .route("/chama/loan-approval-setting", post(add_chama_loan_approval_setting))
+        .route("/chama/loan-default-setting", post(add_chama_loan_default_setting))
 
-        .route("/chama/positions", get(get_chama_positions))
+        .route("/chama/positions/:chama_id", get(get_chama_positions))
+        .route("/chama/position", post(create_chama_position))
+        .route("/chama/position-permissions/:position_id", post(set_position_permissions))
+        .route("/chama/permissions/:chama_id", get(get_my_permissions))
         
         .route("/chama/add-guaranter-setting", post(add_guaranter_setting))
         .route("/chama/remove-guaranter-setting/:chama_id/:g_id", post(remove_guaranter_setting))
@@ -384,6 +1127,58 @@ pub fn routes() -> Router {
         .route("/chama/loan-limit", post(add_chama_loan_limit))
         //create or update
         .route("/chama/add-loan-repayment-limit", post(add_chama_loan_repayment_limit))
+
+        //create or update
+        .route("/chama/contribution-schedule", post(set_contribution_schedule))
+        .route("/chama/contributions", post(record_contribution))
+        .route("/chama/contribution-cycles/:chama_id", get(get_contribution_cycles))
+        .route("/chama/contribution-arrears/:chama_id", get(get_contribution_arrears))
+        .route("/chama/contribution-statement/:chama_id/:member_id", get(get_member_statement))
+
+        .route("/chama/rotation", post(create_rotation))
+        .route("/chama/rotation/:chama_id", get(get_rotation))
+        .route("/chama/rotation-bid/:rotation_id", post(place_rotation_bid))
+        .route("/chama/rotation-run/:rotation_id", post(run_rotation))
+        .route("/chama/rotation-cancel/:rotation_id", post(cancel_rotation))
+
+        //create or update
+        .route("/chama/penalty-rule", post(set_penalty_rule))
+        .route("/chama/penalty-rules/:chama_id", get(get_penalty_rules))
+        .route("/chama/fines/:chama_id", get(get_fines))
+        .route("/chama/fine-waive/:fine_id", post(waive_fine))
+        .route("/chama/fine-pay/:fine_id", post(pay_fine))
+
+        .route("/chama/dividend-preview", post(preview_dividend_run))
+        .route("/chama/dividend-run", post(post_dividend_run))
+        .route("/chama/dividend-runs/:chama_id", get(get_dividend_runs))
+        .route("/chama/dividend-run/:run_id", get(get_dividend_run))
+
+        .route("/chama/meeting", post(schedule_meeting))
+        .route("/chama/meetings/:chama_id", get(get_meetings))
+        .route("/chama/meeting/:meeting_id", get(get_meeting))
+        .route("/chama/meeting-agenda/:meeting_id", post(add_agenda_item))
+        .route("/chama/meeting-attendance/:meeting_id", post(record_attendance))
+        .route("/chama/meeting-resolution/:meeting_id", post(add_resolution))
+        .route("/chama/meeting-minutes/:meeting_id", post(record_minutes))
+        .route("/chama/meeting-cancel/:meeting_id", post(cancel_meeting))
+        .route("/chama/resolution-link/:resolution_id", post(link_resolution))
+        .route("/chama/resolutions/:chama_id", get(get_resolutions))
+
+        .route("/chama/proposal", post(create_proposal))
+        .route("/chama/proposals/:chama_id", get(get_proposals))
+        .route("/chama/proposal/:proposal_id", get(get_proposal))
+        .route("/chama/proposal-vote/:proposal_id", post(cast_vote))
+        .route("/chama/proposal-cancel/:proposal_id", post(cancel_proposal))
+
+        //create or update
+        .route("/chama/withdrawal-signatories", post(set_withdrawal_signatories))
+        .route("/chama/withdrawal-signatories/:chama_id", get(get_withdrawal_signatories))
+        .route("/chama/wallet/:chama_id", get(get_chama_wallet))
+        .route("/chama/withdrawal", post(request_chama_withdrawal))
+        .route("/chama/withdrawal/:withdrawal_id", get(get_chama_withdrawal))
+        .route("/chama/withdrawals/:chama_id", get(get_chama_withdrawals))
+        .route("/chama/withdrawal-sign/:withdrawal_id", post(sign_chama_withdrawal))
+        .route("/chama/withdrawal-cancel/:withdrawal_id", post(cancel_chama_withdrawal))
         .layer(middleware::from_fn(require_auth))
 
 
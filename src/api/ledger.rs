@@ -0,0 +1,53 @@
+use axum::routing::get;
+use sqlx::MySqlPool;
+
+use axum::{
+    Router, response::IntoResponse,
+    Extension,
+    middleware
+};
+use crate::dtos::ledger::LedgerDiscrepancyDto;
+use crate::models::ledger::LedgerPosting;
+use crate::utils::ApiResponse;
+use crate::middleware::auth::require_auth;
+use crate::dtos::auth::Claims;
+use crate::services::{authentication_service, ledger_service, loan_service};
+use crate::services::ledger_service::LedgerAccountKey;
+
+
+pub async fn get_wallet_postings(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>) -> impl IntoResponse {
+
+        let user_id = match loan_service::parse_user_id(&claims.sub) {
+            Ok(user_id) => user_id,
+            Err(e) => return e.into(),
+        };
+
+        match ledger_service::get_account_postings(&pool, &LedgerAccountKey::Wallet(user_id)).await {
+            Ok(postings) => ApiResponse::<Vec<LedgerPosting>>::success(Some(postings)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn reconcile(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>) -> impl IntoResponse {
+
+        if let Err(e) = authentication_service::require_staff(&pool, &claims.sub).await {
+            return e.into();
+        }
+
+        match ledger_service::reconcile(&pool).await {
+            Ok(discrepancies) => ApiResponse::<Vec<LedgerDiscrepancyDto>>::success(Some(discrepancies)),
+            Err(e) => e.into(),
+        }
+}
+
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/ledger/wallet", get(get_wallet_postings))
+        .route("/ledger/reconcile", get(reconcile))
+        .layer(middleware::from_fn(require_auth))
+}
@@ -0,0 +1,244 @@
+use axum::routing::{post, get};
+use sqlx::MySqlPool;
+
+use axum::{
+    Router, Json, response::IntoResponse,
+    Extension,
+    extract::{Path, Query},
+    middleware
+};
+use crate::dtos::loan::{
+    GuaranteePledgeDto,
+    GuarantorInviteDto,
+    LoanApplicationDto,
+    LoanApplicationResponseDto,
+    LoanListQueryDto,
+    LoanRepaymentDto,
+    LoanVoteDto
+};
+use crate::models::loan::{
+    LoadRequestStatus,
+    LoanRepayment,
+    LoanRepaymentSchedule,
+    LoanRequest,
+    LoanRequestApproval,
+    LoanRequestGuarantee
+};
+use crate::utils::ApiResponse;
+use crate::middleware::auth::require_auth;
+use crate::dtos::auth::Claims;
+use crate::services::{guarantee_service, loan_service, repayment_service, schedule_service};
+
+
+pub async fn apply_for_loan(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<LoanApplicationDto>) -> impl IntoResponse {
+
+        match loan_service::apply_for_loan(&pool, &claims.sub, &payload).await {
+            Ok(loan_id) => ApiResponse::success(Some(LoanApplicationResponseDto {
+                loan_id,
+                status: LoadRequestStatus::PENDING,
+            })),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_loans(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Query(query): Query<LoanListQueryDto>) -> impl IntoResponse {
+
+        match loan_service::get_user_loans(&pool, &claims.sub, query.status).await {
+            Ok(loans) => ApiResponse::<Vec<LoanRequest>>::success(Some(loans)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_loan(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>) -> impl IntoResponse {
+
+        match loan_service::get_loan(&pool, &claims.sub, &loan_id).await {
+            Ok(loan) => ApiResponse::<LoanRequest>::success(Some(loan)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn cancel_loan(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>) -> impl IntoResponse {
+
+        match loan_service::cancel_loan(&pool, &claims.sub, &loan_id).await {
+            Ok(_) => ApiResponse::success(Some("Loan cancelled")),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_chama_loans(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>,
+    Query(query): Query<LoanListQueryDto>) -> impl IntoResponse {
+
+        match loan_service::get_chama_loans(&pool, &claims.sub, &chama_id, query.status).await {
+            Ok(loans) => ApiResponse::<Vec<LoanRequest>>::success(Some(loans)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_loan_ceiling(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(chama_id): Path<i64>) -> impl IntoResponse {
+
+        match loan_service::get_member_loan_ceiling(&pool, &claims.sub, &chama_id).await {
+            Ok(ceiling) => ApiResponse::success(Some(ceiling)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn vote_on_loan(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>,
+    Json(payload): Json<LoanVoteDto>) -> impl IntoResponse {
+
+        match loan_service::vote_on_loan(&pool, &claims.sub, &loan_id, &payload).await {
+            Ok(result) => ApiResponse::success(Some(result)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_loan_votes(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>) -> impl IntoResponse {
+
+        match loan_service::get_loan_votes(&pool, &claims.sub, &loan_id).await {
+            Ok(votes) => ApiResponse::<Vec<LoanRequestApproval>>::success(Some(votes)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn invite_guarantor(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>,
+    Json(payload): Json<GuarantorInviteDto>) -> impl IntoResponse {
+
+        match guarantee_service::invite_guarantor(&pool, &claims.sub, &loan_id, &payload).await {
+            Ok(_) => ApiResponse::success(Some("Guarantor invited")),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_loan_guarantees(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>) -> impl IntoResponse {
+
+        match guarantee_service::get_loan_guarantees(&pool, &claims.sub, &loan_id).await {
+            Ok(guarantees) => ApiResponse::<Vec<LoanRequestGuarantee>>::success(Some(guarantees)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_my_guarantees(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>) -> impl IntoResponse {
+
+        match guarantee_service::get_user_guarantees(&pool, &claims.sub).await {
+            Ok(guarantees) => ApiResponse::<Vec<LoanRequestGuarantee>>::success(Some(guarantees)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn accept_guarantee(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(guarantee_id): Path<i64>,
+    Json(payload): Json<GuaranteePledgeDto>) -> impl IntoResponse {
+
+        match guarantee_service::accept_guarantee(&pool, &claims.sub, &guarantee_id, &payload).await {
+            Ok(_) => ApiResponse::success(Some("Guarantee pledged")),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn decline_guarantee(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(guarantee_id): Path<i64>) -> impl IntoResponse {
+
+        match guarantee_service::decline_guarantee(&pool, &claims.sub, &guarantee_id).await {
+            Ok(_) => ApiResponse::success(Some("Guarantee declined")),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_loan_schedule(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>) -> impl IntoResponse {
+
+        match schedule_service::get_schedule(&pool, &claims.sub, &loan_id).await {
+            Ok(schedule) => ApiResponse::<Vec<LoanRepaymentSchedule>>::success(Some(schedule)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn make_repayment(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>,
+    Json(payload): Json<LoanRepaymentDto>) -> impl IntoResponse {
+
+        match repayment_service::make_repayment(&pool, &claims.sub, &loan_id, &payload).await {
+            Ok(repayment) => ApiResponse::<LoanRepayment>::success(Some(repayment)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_loan_repayments(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>) -> impl IntoResponse {
+
+        match repayment_service::get_repayments(&pool, &claims.sub, &loan_id).await {
+            Ok(repayments) => ApiResponse::<Vec<LoanRepayment>>::success(Some(repayments)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn disburse_loan(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(loan_id): Path<i64>) -> impl IntoResponse {
+
+        match repayment_service::disburse_loan(&pool, &claims.sub, &loan_id).await {
+            Ok(_) => ApiResponse::success(Some("Loan disbursed")),
+            Err(e) => e.into(),
+        }
+}
+
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/loans", post(apply_for_loan).get(get_loans))
+        .route("/loans/:loan_id", get(get_loan))
+        .route("/loans/:loan_id/cancel", post(cancel_loan))
+        .route("/loans/:loan_id/votes", post(vote_on_loan).get(get_loan_votes))
+        .route("/loans/:loan_id/schedule", get(get_loan_schedule))
+        .route("/loans/:loan_id/repayments", post(make_repayment).get(get_loan_repayments))
+        .route("/loans/:loan_id/disburse", post(disburse_loan))
+        .route("/loans/chama/:chama_id", get(get_chama_loans))
+        .route("/loans/chama/:chama_id/ceiling", get(get_loan_ceiling))
+        .route("/loans/:loan_id/guarantors", post(invite_guarantor).get(get_loan_guarantees))
+        .route("/guarantees", get(get_my_guarantees))
+        .route("/guarantees/:guarantee_id/accept", post(accept_guarantee))
+        .route("/guarantees/:guarantee_id/decline", post(decline_guarantee))
+        .layer(middleware::from_fn(require_auth))
+}
@@ -1,3 +1,6 @@
 pub mod user;
 pub mod auth;
-pub mod chama;
\ No newline at end of file
+pub mod chama;
+pub mod loan;
+pub mod ledger;
+pub mod payment;
@@ -0,0 +1,352 @@
+use std::env;
+
+use axum::routing::{post, get};
+use sqlx::MySqlPool;
+
+use axum::{
+    Router, Json, response::IntoResponse,
+    Extension,
+    extract::{Path, Query},
+    http::StatusCode,
+    middleware
+};
+use tracing::error;
+use crate::dtos::payment::{
+    B2cResultEnvelopeDto,
+    C2bPaymentDto,
+    C2bValidationResponseDto,
+    CallbackAckDto,
+    DepositRequestDto,
+    DepositResponseDto,
+    MockB2cQueryDto,
+    PaymentDiscrepancyDto,
+    PendingSweepDto,
+    ReconciliationQueryDto,
+    ReversePaymentDto,
+    SettleStatementLineDto,
+    StatementImportQueryDto,
+    StatementImportResponseDto,
+    StkCallbackEnvelopeDto,
+    SuspenseReassignDto,
+    WithdrawalRequestDto
+};
+use crate::error::AppError;
+use crate::gateways::{self, SharedGateway};
+use crate::models::transaction::{Deposit, Withdrawal};
+use crate::utils::ApiResponse;
+use crate::middleware::auth::require_auth;
+use crate::dtos::auth::Claims;
+use crate::services::{authentication_service, deposit_service, reconciliation_service, withdrawal_service};
+
+
+pub async fn initiate_deposit(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Extension(gateway): Extension<SharedGateway>,
+    Json(payload): Json<DepositRequestDto>) -> impl IntoResponse {
+
+        match deposit_service::initiate_deposit(&pool, gateway.as_ref(), &claims.sub, &payload).await {
+            Ok(deposit) => ApiResponse::<DepositResponseDto>::success(Some(deposit)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_deposits(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>) -> impl IntoResponse {
+
+        match deposit_service::get_user_deposits(&pool, &claims.sub).await {
+            Ok(deposits) => ApiResponse::<Vec<Deposit>>::success(Some(deposits)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_deposit(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(deposit_id): Path<i64>) -> impl IntoResponse {
+
+        match deposit_service::get_deposit(&pool, &claims.sub, &deposit_id).await {
+            Ok(deposit) => ApiResponse::<Deposit>::success(Some(deposit)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn complete_mock_stk_push(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(checkout_request_id): Path<String>) -> impl IntoResponse {
+
+        match deposit_service::complete_mock_deposit(&pool, &claims.sub, &checkout_request_id).await {
+            Ok(deposit) => ApiResponse::<Deposit>::success(Some(deposit)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn initiate_withdrawal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Extension(gateway): Extension<SharedGateway>,
+    Json(payload): Json<WithdrawalRequestDto>) -> impl IntoResponse {
+
+        match withdrawal_service::initiate_withdrawal(&pool, gateway.as_ref(), &claims.sub, &payload).await {
+            Ok(withdrawal) => ApiResponse::<Withdrawal>::success(Some(withdrawal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_withdrawals(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>) -> impl IntoResponse {
+
+        match withdrawal_service::get_user_withdrawals(&pool, &claims.sub).await {
+            Ok(withdrawals) => ApiResponse::<Vec<Withdrawal>>::success(Some(withdrawals)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_withdrawal(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(withdrawal_id): Path<i64>) -> impl IntoResponse {
+
+        match withdrawal_service::get_withdrawal(&pool, &claims.sub, &withdrawal_id).await {
+            Ok(withdrawal) => ApiResponse::<Withdrawal>::success(Some(withdrawal)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn complete_mock_b2c_payment(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(reference): Path<String>,
+    Query(query): Query<MockB2cQueryDto>) -> impl IntoResponse {
+
+        match withdrawal_service::complete_mock_withdrawal(&pool, &claims.sub, &reference, query.result_code.unwrap_or(0)).await {
+            Ok(withdrawal) => ApiResponse::<Withdrawal>::success(Some(withdrawal)),
+            Err(e) => e.into(),
+        }
+}
+
+fn callback_token_valid(token:&str) -> bool {
+    let expected = env::var("MPESA_CALLBACK_TOKEN").unwrap_or_default();
+    !expected.is_empty() && token == expected
+}
+
+fn callback_ack(result:Result<(), AppError>) -> (StatusCode, Json<CallbackAckDto>) {
+    match result {
+        Ok(()) => (StatusCode::OK, Json(CallbackAckDto {
+            result_code:0,
+            result_desc:"Accepted".to_string(),
+        })),
+        Err(e) => (StatusCode::from_u16(e.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR), Json(CallbackAckDto {
+            result_code:1,
+            result_desc:e.to_string(),
+        })),
+    }
+}
+
+/// Called by M-Pesa, not our users, so it sits outside require_auth. The
+/// secret path token set in MPESA_CALLBACK_TOKEN keeps strangers from
+/// confirming deposits.
+pub async fn stk_callback(
+    Extension(pool): Extension<MySqlPool>,
+    Path(token): Path<String>,
+    Json(payload): Json<StkCallbackEnvelopeDto>) -> impl IntoResponse {
+
+        if !callback_token_valid(&token) {
+            return callback_ack(Err(AppError::Forbidden("Rejected".to_string())));
+        }
+
+        let callback = &payload.body.stk_callback;
+        let result = deposit_service::settle_stk_callback(&pool, callback).await.map(|_| ());
+        if let Err(e) = &result {
+            error!("STK callback {} not applied: {}", callback.checkout_request_id, e);
+        }
+        callback_ack(result)
+}
+
+
+async fn apply_b2c_result(pool:&MySqlPool, token:&str, payload:&B2cResultEnvelopeDto, timed_out:bool) -> (StatusCode, Json<CallbackAckDto>) {
+    if !callback_token_valid(token) {
+        return callback_ack(Err(AppError::Forbidden("Rejected".to_string())));
+    }
+
+    let result = withdrawal_service::settle_b2c_result(pool, &payload.result, timed_out).await.map(|_| ());
+    if let Err(e) = &result {
+        error!("B2C result {} not applied: {}", payload.result.originator_conversation_id, e);
+    }
+    callback_ack(result)
+}
+
+/// Result of a payout, called by M-Pesa on the B2C ResultURL.
+pub async fn b2c_result(
+    Extension(pool): Extension<MySqlPool>,
+    Path(token): Path<String>,
+    Json(payload): Json<B2cResultEnvelopeDto>) -> impl IntoResponse {
+
+        apply_b2c_result(&pool, &token, &payload, false).await
+}
+
+/// Called by M-Pesa on the B2C QueueTimeOutURL when a payout expired unpaid.
+pub async fn b2c_timeout(
+    Extension(pool): Extension<MySqlPool>,
+    Path(token): Path<String>,
+    Json(payload): Json<B2cResultEnvelopeDto>) -> impl IntoResponse {
+
+        apply_b2c_result(&pool, &token, &payload, true).await
+}
+
+/// C2B validation, called by M-Pesa before it accepts a paybill payment.
+pub async fn c2b_validation(
+    Extension(pool): Extension<MySqlPool>,
+    Path(token): Path<String>,
+    Json(payload): Json<C2bPaymentDto>) -> impl IntoResponse {
+
+        let result = if callback_token_valid(&token) {
+            deposit_service::validate_c2b_payment(&pool, &payload).await
+        } else {
+            Err(AppError::Forbidden("Rejected".to_string()))
+        };
+
+        let (result_code, result_desc) = match result {
+            Ok(()) => ("0", "Accepted".to_string()),
+            Err(AppError::NotFound(msg)) => ("C2B00012", msg),
+            Err(AppError::BadRequest(msg)) => ("C2B00013", msg),
+            Err(e) => ("C2B00016", e.to_string()),
+        };
+        Json(C2bValidationResponseDto {
+            result_code:result_code.to_string(),
+            result_desc,
+        })
+}
+
+/// C2B confirmation, called by M-Pesa once a paybill payment has gone through.
+pub async fn c2b_confirmation(
+    Extension(pool): Extension<MySqlPool>,
+    Path(token): Path<String>,
+    Json(payload): Json<C2bPaymentDto>) -> impl IntoResponse {
+
+        if !callback_token_valid(&token) {
+            return callback_ack(Err(AppError::Forbidden("Rejected".to_string())));
+        }
+
+        let result = deposit_service::confirm_c2b_payment(&pool, &payload).await.map(|_| ());
+        if let Err(e) = &result {
+            error!("C2B confirmation {} not applied: {}", payload.trans_id, e);
+        }
+        callback_ack(result)
+}
+
+pub async fn get_suspense_deposits(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>) -> impl IntoResponse {
+
+        match deposit_service::get_suspense_deposits(&pool, &claims.sub).await {
+            Ok(deposits) => ApiResponse::<Vec<Deposit>>::success(Some(deposits)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn reassign_suspense_deposit(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Path(deposit_id): Path<i64>,
+    Json(payload): Json<SuspenseReassignDto>) -> impl IntoResponse {
+
+        match deposit_service::reassign_suspense_deposit(&pool, &claims.sub, &deposit_id, &payload).await {
+            Ok(deposit) => ApiResponse::<Deposit>::success(Some(deposit)),
+            Err(e) => e.into(),
+        }
+}
+
+/// Takes the statement CSV as the raw request body.
+pub async fn import_statement(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Query(query): Query<StatementImportQueryDto>,
+    body: String) -> impl IntoResponse {
+
+        match reconciliation_service::import_statement(&pool, &claims.sub, &query, &body).await {
+            Ok(response) => ApiResponse::<StatementImportResponseDto>::success(Some(response)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn get_discrepancies(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Query(query): Query<ReconciliationQueryDto>) -> impl IntoResponse {
+
+        match reconciliation_service::discrepancy_report(&pool, &claims.sub, &query).await {
+            Ok(report) => ApiResponse::<Vec<PaymentDiscrepancyDto>>::success(Some(report)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn settle_statement_line(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<SettleStatementLineDto>) -> impl IntoResponse {
+
+        match reconciliation_service::settle_statement_line(&pool, &claims.sub, &payload.statement_line_id).await {
+            Ok(record_id) => ApiResponse::<i64>::success(Some(record_id)),
+            Err(e) => e.into(),
+        }
+}
+
+pub async fn reverse_payment(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Json(payload): Json<ReversePaymentDto>) -> impl IntoResponse {
+
+        match reconciliation_service::reverse_payment(&pool, &claims.sub, &payload).await {
+            Ok(record_id) => ApiResponse::<i64>::success(Some(record_id)),
+            Err(e) => e.into(),
+        }
+}
+
+/// Runs the pending payment sweep now rather than waiting for the job.
+pub async fn sweep_pending_payments(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<MySqlPool>,
+    Extension(gateway): Extension<SharedGateway>) -> impl IntoResponse {
+
+        if let Err(e) = authentication_service::require_staff(&pool, &claims.sub).await {
+            return e.into();
+        }
+        match reconciliation_service::sweep_pending(&pool, gateway.as_ref()).await {
+            Ok(sweep) => ApiResponse::<PendingSweepDto>::success(Some(sweep)),
+            Err(e) => e.into(),
+        }
+}
+
+pub fn routes() -> Router {
+    let mut router = Router::new()
+        .route("/payments/deposits", post(initiate_deposit).get(get_deposits))
+        .route("/payments/deposits/:deposit_id", get(get_deposit))
+        .route("/payments/withdrawals", post(initiate_withdrawal).get(get_withdrawals))
+        .route("/payments/withdrawals/:withdrawal_id", get(get_withdrawal));
+
+    // Only on a development machine; see gateways::mock_enabled
+    if gateways::mock_enabled() {
+        router = router
+            .route("/payments/mock/stk/:checkout_request_id", post(complete_mock_stk_push))
+            .route("/payments/mock/b2c/:reference", post(complete_mock_b2c_payment));
+    }
+
+    router
+        .route("/payments/suspense", get(get_suspense_deposits))
+        .route("/payments/suspense/:deposit_id/reassign", post(reassign_suspense_deposit))
+        .route("/payments/statements", post(import_statement))
+        .route("/payments/reconciliation", get(get_discrepancies))
+        .route("/payments/reconciliation/settle", post(settle_statement_line))
+        .route("/payments/reconciliation/reverse", post(reverse_payment))
+        .route("/payments/reconciliation/sweep", post(sweep_pending_payments))
+        .layer(middleware::from_fn(require_auth))
+        .route("/payments/mpesa/stk-callback/:token", post(stk_callback))
+        .route("/payments/mpesa/b2c-result/:token", post(b2c_result))
+        .route("/payments/mpesa/b2c-timeout/:token", post(b2c_timeout))
+        .route("/payments/mpesa/c2b-validation/:token", post(c2b_validation))
+        .route("/payments/mpesa/c2b-confirmation/:token", post(c2b_confirmation))
+}
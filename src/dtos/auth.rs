@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use crate::money::Money;
 
 #[derive(Deserialize)]
 pub struct LoginInfo {
@@ -10,8 +11,8 @@ pub struct LoginInfo {
 #[derive(Serialize)]
 pub struct LoginResponse {
     pub token: String,
-    pub balance: f64,
-    pub credit_balance: f64
+    pub balance: Money,
+    pub credit_balance: Money
 }
 
 #[derive(Serialize, Clone, Deserialize)]
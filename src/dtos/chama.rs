@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use crate::enums::LoanRepaymentFrequecyEnum;
+use chrono::NaiveDateTime;
+use crate::enums::InterestMethodEnum;
+use crate::models::chama::{ChamaPermission, MembershipRequestStatus};
+use crate::money::Money;
 
 #[derive(Debug, Deserialize)]
 pub struct ChamaDto {
@@ -12,13 +15,64 @@ pub struct ChamaDto {
     pub reg_number:Option<String>,       
 }
 
+/// Restricts an invite to one person when `phone_number` or `email` is set.
 #[derive(Debug, Deserialize)]
-pub struct ChamaMemberApproveDto {
-    pub user_id:i64,   
-    pub chama_id:i64,        
+pub struct ChamaInviteDto {
+    pub chama_id:i64,
+    pub phone_number:Option<String>,
+    pub email:Option<String>,
+    // Defaults to a single use
+    pub max_uses:Option<i32>,
+    // Defaults to 7 days
+    pub expiry_days:Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChamaInviteUrlDto {
+    pub invite_id:i64,
+    pub url:String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChamaMembershipSettingDto {
+    pub chama_id:i64,
+    pub joining_fee:Money,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MembershipApproveDto {
     pub position:i64,
-    pub contribution_amount:f64,       
-    pub is_active:i8,     
+    pub contribution_amount:Money,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MembershipRejectDto {
+    pub reason:String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MembershipRequestDetailDto {
+    pub request_id:i64,
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub first_name:String,
+    pub last_name:String,
+    pub phone_number:String,
+    pub email:Option<String>,
+    pub status:MembershipRequestStatus,
+    pub joining_fee:Money,
+    pub fee_paid:bool,
+    pub reason:Option<String>,
+    pub created_at:NaiveDateTime,
+}
+
+/// A new position and agreed contribution for an active member.
+#[derive(Debug, Deserialize)]
+pub struct ChamaMemberUpdateDto {
+    pub member_id:i64,
+    pub chama_id:i64,
+    pub position:i64,
+    pub contribution_amount:Money,
 }
 
 
@@ -29,7 +83,7 @@ pub struct ChamaMemberDto {
     pub user_id:i64,           
     pub chama_id:i64,           
     pub position:i64,      
-    pub contribution_amount:f64,     
+    pub contribution_amount:Money,     
 
 }
 
@@ -41,7 +95,7 @@ pub struct ChamaMemberDetailDto {
     pub first_name:String,      
     pub last_name:String,
     pub position:String,
-    pub contribution_amount:f64,
+    pub contribution_amount:Money,
     pub is_active:i8,
     pub phone_number:String,
     pub email:Option<String>,     
@@ -51,17 +105,21 @@ pub struct ChamaMemberDetailDto {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChamaPositionDetailDto {
     pub id:Option<i64>,                 
-    pub role:String,           
     pub position:String, 
+    pub permissions:Vec<ChamaPermission>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ChamaPositionDto {
-    pub id:Option<i64>,                 
     pub chama_position:String,           
-    pub role_id:i64,     
     pub chama_id:i64,          
+    pub permissions:Vec<ChamaPermission>,
+}
 
+/// Replaces every permission the position carries.
+#[derive(Debug, Deserialize)]
+pub struct ChamaPositionPermissionDto {
+    pub permissions:Vec<ChamaPermission>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,11 +132,26 @@ pub struct ChamaLoanApproverDto {
 }
 
 
+#[derive(Debug, Deserialize)]
+pub struct ChamaLoanApprovalSettingDto {
+    pub chama_id:i64,    
+    pub quorum:i32,               
+
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChamaLoanDefaultSettingDto {
+    pub chama_id:i64,    
+    pub grace_period_days:i32,               
+
+}
+
+
 #[derive(Debug, Deserialize)]
 pub struct ChamaLoadLimitDto {
     pub id:Option<i64>,                 
     pub chama_id:i64,           
-    pub amount:Option<f64>, 
+    pub amount:Option<Money>, 
     pub centage_member_savings:Option<f64>,               
 
 }
@@ -89,6 +162,10 @@ pub struct ChamaLoanRepaymentLimitDto {
     pub chama_id:i64,           
     pub repayment_frequency:String, 
     pub max_repayment_months:i32,  
+    // Monthly interest as a percentage of the principal
+    pub interest_rate:Option<f64>,
+    pub interest_method:Option<InterestMethodEnum>,
+    pub processing_fee_centage:Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
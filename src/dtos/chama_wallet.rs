@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::chama_wallet::{ChamaWithdrawal, ChamaWithdrawalSignature, SignatureDecision};
+use crate::models::transaction::Withdrawal;
+use crate::money::Money;
+
+
+#[derive(Debug, Deserialize)]
+pub struct ChamaWithdrawalSignatoryDto {
+    pub chama_id:i64,
+    pub position_ids:Vec<i64>,
+    // Defaults to every position
+    pub quorum:Option<i32>,
+}
+
+/// Pays a member's wallet when `beneficiary_user_id` is given, otherwise
+/// sends mobile money to `phone_number`.
+#[derive(Debug, Deserialize)]
+pub struct ChamaWithdrawalRequestDto {
+    pub chama_id:i64,
+    pub amount:Money,
+    pub beneficiary_user_id:Option<i64>,
+    pub phone_number:Option<String>,
+    pub narration:String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChamaWithdrawalSignDto {
+    pub decision:SignatureDecision,
+    pub comment:Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChamaWalletDto {
+    pub chama_id:i64,
+    pub balance:Money,
+    // Asked for and still waiting for signatures
+    pub pending_withdrawals:Money,
+    pub available:Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChamaWithdrawalDetailDto {
+    pub withdrawal:ChamaWithdrawal,
+    pub signatures:Vec<ChamaWithdrawalSignature>,
+    pub payout:Option<Withdrawal>,
+}
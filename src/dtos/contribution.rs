@@ -0,0 +1,72 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::models::contribution::ContributionFrequencyEnum;
+use crate::models::fine::Fine;
+use crate::money::Money;
+
+
+#[derive(Debug, Deserialize)]
+pub struct ContributionScheduleDto {
+    pub chama_id:i64,
+    pub frequency:ContributionFrequencyEnum,
+    pub amount:Money,
+    // Defaults to the day after the last cycle, or today for a new chama
+    pub start_date:Option<NaiveDate>,
+}
+
+/// A cash contribution handed to a chama official.
+#[derive(Debug, Deserialize)]
+pub struct ContributionRecordDto {
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub amount:Money,
+    // Defaults to now
+    pub paid_at:Option<NaiveDateTime>,
+    pub narration:Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContributionCycleSummaryDto {
+    pub cycle_id:i64,
+    pub cycle_no:i32,
+    pub start_date:NaiveDate,
+    pub end_date:NaiveDate,
+    pub expected:Money,
+    pub paid:Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberArrearsDto {
+    pub user_id:i64,
+    pub first_name:String,
+    pub last_name:String,
+    // Expected over the cycles that have ended
+    pub expected:Money,
+    pub paid:Money,
+    pub arrears:Money,
+    // Paid ahead of what has fallen due
+    pub advance:Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberStatementLineDto {
+    pub date:NaiveDate,
+    pub description:String,
+    pub expected:Money,
+    pub paid:Money,
+    // Paid less expected so far, negative while the member owes
+    pub balance:Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberStatementDto {
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub lines:Vec<MemberStatementLineDto>,
+    pub total_expected:Money,
+    pub total_paid:Money,
+    pub arrears:Money,
+    pub fines:Vec<Fine>,
+    pub fines_outstanding:Money,
+}
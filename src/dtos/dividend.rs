@@ -0,0 +1,20 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::models::dividend::{DividendPayout, DividendRun};
+
+
+#[derive(Debug, Deserialize)]
+pub struct DividendRunDto {
+    pub chama_id:i64,
+    pub period_start:NaiveDate,
+    pub period_end:NaiveDate,
+    // Percentage of the income kept by the chama, none by default
+    pub retained_rate:Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DividendRunDetailDto {
+    pub run:DividendRun,
+    pub payouts:Vec<DividendPayout>,
+}
@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+use crate::models::fine::{PenaltyCalculationEnum, PenaltyTriggerEnum};
+use crate::money::Money;
+
+
+#[derive(Debug, Deserialize)]
+pub struct PenaltyRuleDto {
+    pub chama_id:i64,
+    pub trigger_type:PenaltyTriggerEnum,
+    pub calculation:PenaltyCalculationEnum,
+    // Required for a FLAT rule
+    pub amount:Option<Money>,
+    // Required for a PERCENTAGE rule
+    pub rate:Option<f64>,
+    pub grace_days:Option<i32>,
+    pub cap:Option<Money>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FineWaiveDto {
+    pub reason:String,
+}
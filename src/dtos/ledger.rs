@@ -0,0 +1,10 @@
+use serde::Serialize;
+use crate::money::Money;
+
+#[derive(Debug, Serialize)]
+pub struct LedgerDiscrepancyDto {
+    pub check:String,
+    pub subject:String,
+    pub expected:Money,
+    pub actual:Money,
+}
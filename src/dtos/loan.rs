@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use crate::money::Money;
+use crate::models::bill::BillFrequencyEnum;
+use crate::models::loan::{LoadRequestStatus, LoanApprovalDecision, LoanRepaymentMethod};
+
+#[derive(Debug, Deserialize)]
+pub struct LoanApplicationDto {
+    pub amount_requested:Money,
+    pub chama_id:Option<i64>,
+    pub credit_profile_id:Option<i64>,
+    pub repayment_months:i32,
+    pub frequency:Option<BillFrequencyEnum>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoanListQueryDto {
+    pub status:Option<LoadRequestStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoanApplicationResponseDto {
+    pub loan_id:i64,
+    pub status:LoadRequestStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoanVoteDto {
+    pub decision:LoanApprovalDecision,
+    pub comment:Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoanVoteResultDto {
+    pub loan_id:i64,
+    pub status:LoadRequestStatus,
+    pub approvals:i64,
+    pub rejections:i64,
+    pub required_approvals:i64,
+    pub guaranteed_amount:Money,
+    pub guarantee_required:Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoanCeilingDto {
+    pub chama_id:i64,
+    pub chama_limit:Option<Money>,
+    pub member_savings:Money,
+    pub locked_savings:Money,
+    pub centage_member_savings:Option<f64>,
+    pub savings_limit:Option<Money>,
+    pub ceiling:Option<Money>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GuarantorInviteDto {
+    pub guarantor_user_id:i64,
+    pub amount_requested:Money,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GuaranteePledgeDto {
+    pub amount:Money,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoanRepaymentDto {
+    pub amount:Money,
+    pub payment_method:LoanRepaymentMethod,
+    pub reference:Option<String>,
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::models::meeting::{
+    AttendanceStatus,
+    Meeting,
+    MeetingAgendaItem,
+    MeetingAttendance,
+    MeetingResolution,
+    ResolutionRecordEnum
+};
+
+
+#[derive(Debug, Deserialize)]
+pub struct MeetingDto {
+    pub chama_id:i64,
+    pub title:String,
+    pub venue:Option<String>,
+    pub scheduled_at:NaiveDateTime,
+    pub agenda:Option<Vec<AgendaItemDto>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AgendaItemDto {
+    pub title:String,
+    pub description:Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttendanceEntryDto {
+    pub user_id:i64,
+    pub status:AttendanceStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MeetingAttendanceDto {
+    pub attendance:Vec<AttendanceEntryDto>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolutionDto {
+    pub agenda_item_id:Option<i64>,
+    pub resolution:String,
+    pub record_type:Option<ResolutionRecordEnum>,
+    pub record_id:Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolutionLinkDto {
+    pub record_type:ResolutionRecordEnum,
+    pub record_id:i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MeetingMinutesDto {
+    pub minutes:String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeetingDetailDto {
+    pub meeting:Meeting,
+    pub agenda:Vec<MeetingAgendaItem>,
+    pub attendance:Vec<MeetingAttendance>,
+    pub resolutions:Vec<MeetingResolution>,
+}
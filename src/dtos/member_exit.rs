@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::fine::Fine;
+use crate::models::loan::{GuaranteeStatus, LoadRequestStatus};
+use crate::models::member_exit::MemberExit;
+use crate::money::Money;
+
+
+/// Members leave on their own request; an admin may open the exit of
+/// another member by giving `user_id`.
+#[derive(Debug, Deserialize)]
+pub struct MemberExitDto {
+    pub chama_id:i64,
+    pub user_id:Option<i64>,
+    pub reason:String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MemberExitRejectDto {
+    pub reason:String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExitLoanDto {
+    pub loan_id:i64,
+    pub status:LoadRequestStatus,
+    pub outstanding:Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExitGuaranteeDto {
+    pub guarantee_id:i64,
+    pub loan_id:i64,
+    pub borrower_id:i64,
+    pub loan_status:LoadRequestStatus,
+    pub status:GuaranteeStatus,
+    pub amount:Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExitStatementDto {
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub savings:Money,
+    pub loans:Vec<ExitLoanDto>,
+    pub loan_balance:Money,
+    pub fines:Vec<Fine>,
+    pub fine_balance:Money,
+    // Pledges the member has given on other members' loans
+    pub guarantees:Vec<ExitGuaranteeDto>,
+    // Negative while the member owes more than they have saved
+    pub net_amount:Money,
+    // Why the exit cannot be settled yet, empty when it can
+    pub blockers:Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberExitDetailDto {
+    pub exit:MemberExit,
+    pub statement:ExitStatementDto,
+}
@@ -1,5 +1,16 @@
 
 pub mod auth; 
 pub mod chama;
+pub mod loan;
+pub mod payment;
+pub mod contribution;
+pub mod rotation;
+pub mod fine;
 
 
+pub mod ledger;
+pub mod dividend;
+pub mod meeting;
+pub mod proposal;
+pub mod chama_wallet;
+pub mod member_exit;
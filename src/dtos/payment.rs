@@ -0,0 +1,251 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use crate::gateways::PaymentDirection;
+use crate::money::Money;
+use crate::models::transaction::DepositStatus;
+
+#[derive(Debug, Deserialize)]
+pub struct DepositRequestDto {
+    pub amount:Money,
+    // Defaults to the phone number the user signed up with
+    pub phone_number:Option<String>,
+    // Pays a contribution to this chama instead of the user's wallet
+    pub chama_id:Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DepositResponseDto {
+    pub deposit_id:i64,
+    pub bill_no:String,
+    pub checkout_request_id:String,
+    pub status:DepositStatus,
+    pub customer_message:String,
+}
+
+/// Body M-Pesa posts to the STK push callback URL.
+#[derive(Debug, Deserialize)]
+pub struct StkCallbackEnvelopeDto {
+    #[serde(rename = "Body")]
+    pub body:StkCallbackBodyDto,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StkCallbackBodyDto {
+    #[serde(rename = "stkCallback")]
+    pub stk_callback:StkCallbackDto,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StkCallbackDto {
+    #[serde(rename = "MerchantRequestID")]
+    pub merchant_request_id:String,
+    #[serde(rename = "CheckoutRequestID")]
+    pub checkout_request_id:String,
+    #[serde(rename = "ResultCode")]
+    pub result_code:i64,
+    #[serde(rename = "ResultDesc")]
+    pub result_desc:String,
+    #[serde(rename = "CallbackMetadata")]
+    pub callback_metadata:Option<CallbackMetadataDto>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CallbackMetadataDto {
+    #[serde(rename = "Item")]
+    pub item:Vec<CallbackItemDto>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CallbackItemDto {
+    #[serde(rename = "Name")]
+    pub name:String,
+    #[serde(rename = "Value")]
+    pub value:Option<JsonValue>,
+}
+
+impl StkCallbackDto {
+    pub fn item(&self, name:&str) -> Option<&JsonValue> {
+        self.callback_metadata
+            .as_ref()?
+            .item
+            .iter()
+            .find(|item| item.name == name)?
+            .value
+            .as_ref()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WithdrawalRequestDto {
+    pub amount:Money,
+    // Defaults to the phone number the user signed up with
+    pub phone_number:Option<String>,
+    pub narration:Option<String>,
+}
+
+/// Body M-Pesa posts to the B2C result and queue timeout URLs.
+#[derive(Debug, Deserialize)]
+pub struct B2cResultEnvelopeDto {
+    #[serde(rename = "Result")]
+    pub result:B2cResultDto,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct B2cResultDto {
+    #[serde(rename = "ResultCode")]
+    pub result_code:i64,
+    #[serde(rename = "ResultDesc")]
+    pub result_desc:String,
+    #[serde(rename = "OriginatorConversationID")]
+    pub originator_conversation_id:String,
+    #[serde(rename = "ConversationID")]
+    pub conversation_id:Option<String>,
+    #[serde(rename = "TransactionID")]
+    pub transaction_id:Option<String>,
+    #[serde(rename = "ResultParameters")]
+    pub result_parameters:Option<B2cResultParametersDto>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct B2cResultParametersDto {
+    #[serde(rename = "ResultParameter")]
+    pub result_parameter:Vec<B2cResultParameterDto>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct B2cResultParameterDto {
+    #[serde(rename = "Key")]
+    pub key:String,
+    #[serde(rename = "Value")]
+    pub value:Option<JsonValue>,
+}
+
+impl B2cResultDto {
+    pub fn parameter(&self, key:&str) -> Option<&JsonValue> {
+        self.result_parameters
+            .as_ref()?
+            .result_parameter
+            .iter()
+            .find(|parameter| parameter.key == key)?
+            .value
+            .as_ref()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MockB2cQueryDto {
+    // Anything but 0 fails the payout, defaults to success
+    pub result_code:Option<i64>,
+}
+
+/// Body M-Pesa posts to the C2B validation and confirmation URLs when a
+/// customer pays to our paybill or till.
+#[derive(Debug, Clone, Deserialize)]
+pub struct C2bPaymentDto {
+    #[serde(rename = "TransactionType")]
+    pub transaction_type:String,
+    #[serde(rename = "TransID")]
+    pub trans_id:String,
+    // yyyyMMddHHmmss
+    #[serde(rename = "TransTime")]
+    pub trans_time:String,
+    #[serde(rename = "TransAmount")]
+    pub trans_amount:Money,
+    #[serde(rename = "BusinessShortCode")]
+    pub business_short_code:String,
+    // The account number the customer typed
+    #[serde(rename = "BillRefNumber")]
+    pub bill_ref_number:String,
+    #[serde(rename = "MSISDN")]
+    pub msisdn:String,
+    #[serde(rename = "FirstName")]
+    pub first_name:Option<String>,
+}
+
+/// What M-Pesa expects back from C2B validation. Any code but "0" cancels
+/// the customer's payment.
+#[derive(Debug, Serialize)]
+pub struct C2bValidationResponseDto {
+    #[serde(rename = "ResultCode")]
+    pub result_code:String,
+    #[serde(rename = "ResultDesc")]
+    pub result_desc:String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuspenseReassignDto {
+    // Credit the user's wallet, or with chama_id record them as the paying member
+    pub user_id:Option<i64>,
+    pub chama_id:Option<i64>,
+    pub reason:String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatementImportQueryDto {
+    pub gateway:String,
+    // Lines completed outside these dates, inclusive, are skipped
+    pub from:NaiveDate,
+    pub to:NaiveDate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatementImportResponseDto {
+    pub statement_id:i64,
+    pub imported:usize,
+    // Outside the period, or already imported from an earlier statement
+    pub skipped:usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReconciliationQueryDto {
+    pub from:NaiveDate,
+    pub to:NaiveDate,
+}
+
+/// Something the provider's statements and our books disagree on.
+#[derive(Debug, Serialize)]
+pub struct PaymentDiscrepancyDto {
+    // MISSING_IN_BOOKS, MISSING_ON_STATEMENT, AMOUNT_MISMATCH, STILL_PENDING, or
+    // RECEIPT_MISSING for a deposit completed before its receipt was known
+    pub kind:String,
+    pub direction:PaymentDirection,
+    pub receipt_no:Option<String>,
+    // Our deposit bill number or withdrawal reference
+    pub reference:Option<String>,
+    // Deposit or withdrawal id
+    pub record_id:Option<i64>,
+    pub statement_line_id:Option<i64>,
+    pub statement_amount:Option<Money>,
+    pub book_amount:Option<Money>,
+    // SETTLE a statement line into the books, or REVERSE a record
+    pub action:Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettleStatementLineDto {
+    pub statement_line_id:i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReversePaymentDto {
+    pub direction:PaymentDirection,
+    // Deposit or withdrawal id
+    pub record_id:i64,
+    pub reason:String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PendingSweepDto {
+    pub checked:usize,
+    pub settled:usize,
+}
+
+/// What M-Pesa expects back from a callback.
+#[derive(Debug, Serialize)]
+pub struct CallbackAckDto {
+    #[serde(rename = "ResultCode")]
+    pub result_code:i64,
+    #[serde(rename = "ResultDesc")]
+    pub result_desc:String,
+}
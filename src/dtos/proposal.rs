@@ -0,0 +1,38 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::models::proposal::{Proposal, ProposalTypeEnum, ProposalVote, VoteChoiceEnum};
+use crate::money::Money;
+
+
+#[derive(Debug, Deserialize)]
+pub struct ProposalDto {
+    pub chama_id:i64,
+    pub proposal_type:ProposalTypeEnum,
+    pub title:String,
+    pub description:Option<String>,
+    // The member a POSITIONCHANGE or MEMBERREMOVAL is about
+    pub target_user_id:Option<i64>,
+    pub position_id:Option<i64>,
+    // The new limit of a LOANLIMIT proposal
+    pub amount:Option<Money>,
+    pub centage_member_savings:Option<f64>,
+    // Defaults to now
+    pub opens_at:Option<NaiveDateTime>,
+    pub closes_at:NaiveDateTime,
+    // Defaults to 50
+    pub quorum_rate:Option<f64>,
+    // Defaults to 50, a tie never passes
+    pub threshold_rate:Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProposalVoteDto {
+    pub choice:VoteChoiceEnum,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProposalDetailDto {
+    pub proposal:Proposal,
+    pub votes:Vec<ProposalVote>,
+}
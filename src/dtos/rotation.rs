@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::rotation::{
+    Rotation,
+    RotationArrearsPolicyEnum,
+    RotationBid,
+    RotationMethodEnum,
+    RotationPayout,
+    RotationSlot
+};
+use crate::money::Money;
+
+
+#[derive(Debug, Deserialize)]
+pub struct RotationDto {
+    pub chama_id:i64,
+    pub method:RotationMethodEnum,
+    pub arrears_policy:RotationArrearsPolicyEnum,
+    // Member user ids in payout order, for a FIXED rotation. Defaults to the
+    // order members joined in
+    pub order:Option<Vec<i64>>,
+    // Defaults to the current cycle
+    pub start_cycle_no:Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RotationBidDto {
+    // How much of the pot the member gives up to the chama
+    pub amount:Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RotationDetailDto {
+    pub rotation:Rotation,
+    pub slots:Vec<RotationSlot>,
+    pub payouts:Vec<RotationPayout>,
+    // Bids on the current cycle
+    pub bids:Vec<RotationBid>,
+}
@@ -1,9 +1,62 @@
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 
+use crate::models::bill::BillFrequencyEnum;
+
 #[derive(Serialize, Deserialize, Clone)]
 #[derive(Debug)]
 pub enum LoanRepaymentFrequecyEnum{
     WEEKLY,
     BIWEEKLY,
     MONTHLY
-  }
\ No newline at end of file
+  }
+
+impl LoanRepaymentFrequecyEnum {
+    pub fn as_bill_frequency(&self) -> BillFrequencyEnum {
+        match self {
+            LoanRepaymentFrequecyEnum::WEEKLY => BillFrequencyEnum::Weekly,
+            LoanRepaymentFrequecyEnum::BIWEEKLY => BillFrequencyEnum::BiWeekly,
+            LoanRepaymentFrequecyEnum::MONTHLY => BillFrequencyEnum::Monthy,
+        }
+    }
+
+    /// Loans outside a chama carry a bill frequency, anything that is not
+    /// weekly or fortnightly is repaid monthly.
+    pub fn from_bill_frequency(frequency:&BillFrequencyEnum) -> Self {
+        match frequency {
+            BillFrequencyEnum::Weekly => LoanRepaymentFrequecyEnum::WEEKLY,
+            BillFrequencyEnum::BiWeekly => LoanRepaymentFrequecyEnum::BIWEEKLY,
+            _ => LoanRepaymentFrequecyEnum::MONTHLY,
+        }
+    }
+
+    /// Installments falling due in one month of the repayment term.
+    pub fn periods_per_month(&self) -> u32 {
+        match self {
+            LoanRepaymentFrequecyEnum::WEEKLY => 4,
+            LoanRepaymentFrequecyEnum::BIWEEKLY => 2,
+            LoanRepaymentFrequecyEnum::MONTHLY => 1,
+        }
+    }
+}
+
+impl FromStr for LoanRepaymentFrequecyEnum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "WEEKLY" => Ok(LoanRepaymentFrequecyEnum::WEEKLY),
+            "BIWEEKLY" => Ok(LoanRepaymentFrequecyEnum::BIWEEKLY),
+            "MONTHLY" => Ok(LoanRepaymentFrequecyEnum::MONTHLY),
+            _ => Err(format!("Unknown repayment frequency {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[derive(Debug)]
+pub enum InterestMethodEnum {
+    FLAT,
+    REDUCINGBALANCE
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+use axum::http::StatusCode;
+use serde::Serialize;
+use tracing::error;
+
+use crate::utils::ApiResponse;
+
+#[derive(Debug)]
+pub enum AppError {
+    NotFound(String),
+    Forbidden(String),
+    BadRequest(String),
+    Conflict(String),
+    // The mobile money provider refused or could not be reached
+    Gateway(String),
+    Database(sqlx::Error),
+}
+
+impl AppError {
+    pub fn status_code(&self) -> u16 {
+        let status = match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Gateway(_) => StatusCode::BAD_GATEWAY,
+            AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        status.as_u16()
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound(msg)
+            | AppError::Forbidden(msg)
+            | AppError::BadRequest(msg)
+            | AppError::Conflict(msg)
+            | AppError::Gateway(msg) => write!(f, "{}", msg),
+            // Never leak query details to API clients
+            AppError::Database(_) => write!(f, "Could not complete request"),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        error!("Database error: {:?}", e);
+        AppError::Database(e)
+    }
+}
+
+impl<T: Serialize> From<AppError> for ApiResponse<T> {
+    fn from(e: AppError) -> Self {
+        ApiResponse::error(&e.to_string(), e.status_code())
+    }
+}
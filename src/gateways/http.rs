@@ -0,0 +1,93 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use reqwest::{Client, Method};
+
+use crate::gateways::GatewayError;
+
+
+/// Short, so a provider that is down is failed over quickly.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Long enough for a slow provider to answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status:u16,
+    pub body:String,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// One client for every provider, so connections and TLS sessions are reused.
+fn client() -> Result<&'static Client, GatewayError> {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+
+    let client = Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| GatewayError::Unavailable(format!("Could not set up the HTTP client: {}", e)))?;
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+/// Builds a provider URL with its query parameters encoded.
+pub fn url_with_params(base:&str, params:&[(&str, &str)]) -> Result<String, GatewayError> {
+    reqwest::Url::parse_with_params(base, params)
+        .map(String::from)
+        .map_err(|_| GatewayError::Rejected(format!("Invalid provider URL {}", base)))
+}
+
+/// Sends a single request to a provider.
+///
+/// Failing to connect and gateway errors (502 to 504) are `Unavailable`, so
+/// the registry can fail over. Losing the connection, timing out or any other
+/// 5xx once the request is sent is `Uncertain`: the provider may have acted
+/// on it. What the provider said otherwise is left to the driver.
+pub async fn send(
+    method:&str,
+    url:&str,
+    headers:&[(&str, &str)],
+    body:Option<(&str, &str)>) -> Result<HttpResponse, GatewayError> {
+
+    let method = Method::from_bytes(method.as_bytes())
+        .map_err(|_| GatewayError::Rejected(format!("Invalid HTTP method {}", method)))?;
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|_| GatewayError::Rejected(format!("Invalid provider URL {}", url)))?;
+    let host = parsed.host_str().unwrap_or_default().to_string();
+
+    let mut request = client()?.request(method, parsed);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    if let Some((content_type, content)) = body {
+        request = request
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(content.to_string());
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        // Nothing has been sent yet, another provider can safely be tried
+        Err(e) if e.is_connect() => return Err(GatewayError::Unavailable(format!("{}: {}", host, e))),
+        Err(e) if e.is_builder() => return Err(GatewayError::Rejected(format!("{}: {}", host, e))),
+        // From here on the provider may have acted on the request
+        Err(e) => return Err(GatewayError::Uncertain(format!("{}: {}", host, e))),
+    };
+
+    let status = response.status().as_u16();
+    let body = response.text().await
+        .map_err(|e| GatewayError::Uncertain(format!("{}: {}", host, e)))?;
+    match status {
+        502..=504 => Err(GatewayError::Unavailable(format!("{} answered {}", host, status))),
+        500.. => Err(GatewayError::Uncertain(format!("{} answered {}", host, status))),
+        _ => Ok(HttpResponse { status, body }),
+    }
+}
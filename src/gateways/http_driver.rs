@@ -0,0 +1,228 @@
+use std::env;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::{json, Value as JsonValue};
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::gateways::http;
+use crate::gateways::{
+    B2cRequest,
+    B2cResponse,
+    GatewayError,
+    MobileMoneyGateway,
+    PaymentDirection,
+    PaymentStatus,
+    StatusQuery,
+    StkPushRequest,
+    StkPushResponse
+};
+use crate::models::mobile_money_provider::MobileMoneyprovider;
+use crate::money::Money;
+use crate::utils;
+
+
+/// Talks to an M-Pesa style REST API (Safaricom Daraja) over HTTPS.
+///
+/// From the provider row: `username` is the shortcode, `password` the Lipa na
+/// M-Pesa passkey and `api_username`/`api_password` the consumer key and
+/// secret. Results are posted to our callback endpoints under
+/// MPESA_CALLBACK_BASE_URL; payouts also need MPESA_INITIATOR_NAME and
+/// MPESA_SECURITY_CREDENTIAL.
+pub struct HttpDriver {
+    provider:MobileMoneyprovider,
+    // Access token and when it stops being valid
+    token:Mutex<Option<(String, Instant)>>,
+}
+
+impl HttpDriver {
+    pub fn new(provider:MobileMoneyprovider) -> Self {
+        HttpDriver { provider, token:Mutex::new(None) }
+    }
+
+    fn url(&self, path:&str) -> String {
+        format!("{}{}", self.provider.endpoint_url.trim_end_matches('/'), path)
+    }
+
+    fn callback_url(&self, path:&str) -> Result<String, GatewayError> {
+        let base = env::var("MPESA_CALLBACK_BASE_URL")
+            .map_err(|_| GatewayError::Rejected("MPESA_CALLBACK_BASE_URL is not set".to_string()))?;
+        let token = env::var("MPESA_CALLBACK_TOKEN")
+            .map_err(|_| GatewayError::Rejected("MPESA_CALLBACK_TOKEN is not set".to_string()))?;
+        Ok(format!("{}{}/{}", base.trim_end_matches('/'), path, token))
+    }
+
+    /// The STK password and the timestamp it was made with.
+    fn stk_password(&self) -> (String, String) {
+        let timestamp = utils::now_eat().format("%Y%m%d%H%M%S").to_string();
+        let password = BASE64.encode(format!("{}{}{}", self.provider.username, self.provider.password, timestamp));
+        (password, timestamp)
+    }
+
+    async fn access_token(&self) -> Result<String, GatewayError> {
+        let mut token = self.token.lock().await;
+        if let Some((value, expires)) = token.as_ref()
+            && Instant::now() < *expires {
+            return Ok(value.clone());
+        }
+
+        let credentials = BASE64.encode(format!("{}:{}", self.provider.api_username, self.provider.api_password));
+        let response = http::send(
+            "GET",
+            &self.url("/oauth/v1/generate?grant_type=client_credentials"),
+            &[("Authorization", &format!("Basic {}", credentials))],
+            None,
+        ).await?;
+        if !response.is_success() {
+            return Err(GatewayError::Unavailable(format!("{} refused our credentials ({})", self.provider.name, response.status)));
+        }
+
+        let body: JsonValue = serde_json::from_str(&response.body)
+            .map_err(|_| GatewayError::Unavailable(format!("{} sent an unreadable token", self.provider.name)))?;
+        let Some(value) = body["access_token"].as_str() else {
+            return Err(GatewayError::Unavailable(format!("{} sent no access token", self.provider.name)));
+        };
+        let lifetime = match &body["expires_in"] {
+            JsonValue::String(secs) => secs.parse().unwrap_or(3599),
+            secs => secs.as_u64().unwrap_or(3599),
+        };
+
+        // Renew a minute early so a token never expires mid request
+        *token = Some((value.to_string(), Instant::now() + Duration::from_secs(lifetime.saturating_sub(60))));
+        Ok(value.to_string())
+    }
+
+    async fn post(&self, path:&str, body:&JsonValue) -> Result<JsonValue, GatewayError> {
+        let token = self.access_token().await?;
+        let response = http::send(
+            "POST",
+            &self.url(path),
+            &[("Authorization", &format!("Bearer {}", token))],
+            Some(("application/json", &body.to_string())),
+        ).await?;
+
+        let body: JsonValue = serde_json::from_str(&response.body).unwrap_or(JsonValue::Null);
+        if !response.is_success() {
+            let message = body["errorMessage"].as_str().unwrap_or(&response.body);
+            return Err(GatewayError::Rejected(format!("{} ({})", message, response.status)));
+        }
+        Ok(body)
+    }
+}
+
+/// M-Pesa only moves whole shillings.
+fn whole_shillings(amount:Money) -> Result<i64, GatewayError> {
+    if amount.minor_units() % 100 != 0 {
+        return Err(GatewayError::Rejected(format!("{} is not a whole shilling amount", amount)));
+    }
+    Ok(amount.minor_units() / 100)
+}
+
+#[async_trait]
+impl MobileMoneyGateway for HttpDriver {
+    fn name(&self) -> &str {
+        &self.provider.name
+    }
+
+    async fn stk_push(&self, request:&StkPushRequest) -> Result<StkPushResponse, GatewayError> {
+        let (password, timestamp) = self.stk_password();
+        let body = self.post("/mpesa/stkpush/v1/processrequest", &json!({
+            "BusinessShortCode": self.provider.username,
+            "Password": password,
+            "Timestamp": timestamp,
+            "TransactionType": "CustomerPayBillOnline",
+            "Amount": whole_shillings(request.amount)?,
+            "PartyA": request.msisdn,
+            "PartyB": self.provider.username,
+            "PhoneNumber": request.msisdn,
+            "CallBackURL": self.callback_url("/payments/mpesa/stk-callback")?,
+            "AccountReference": request.account_reference,
+            "TransactionDesc": request.description,
+        })).await?;
+
+        let Some(checkout_request_id) = body["CheckoutRequestID"].as_str() else {
+            return Err(GatewayError::Rejected(format!("{} did not accept the request", self.provider.name)));
+        };
+        info!("STK push {} sent through {}", checkout_request_id, self.provider.name);
+
+        Ok(StkPushResponse {
+            gateway:self.provider.name.clone(),
+            checkout_request_id:checkout_request_id.to_string(),
+            merchant_request_id:body["MerchantRequestID"].as_str().unwrap_or_default().to_string(),
+            customer_message:body["CustomerMessage"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    async fn b2c_payment(&self, request:&B2cRequest) -> Result<B2cResponse, GatewayError> {
+        let (Ok(initiator), Ok(credential)) = (env::var("MPESA_INITIATOR_NAME"), env::var("MPESA_SECURITY_CREDENTIAL")) else {
+            return Err(GatewayError::Rejected("MPESA_INITIATOR_NAME and MPESA_SECURITY_CREDENTIAL must be set".to_string()));
+        };
+
+        let body = self.post("/mpesa/b2c/v3/paymentrequest", &json!({
+            "OriginatorConversationID": request.reference,
+            "InitiatorName": initiator,
+            "SecurityCredential": credential,
+            "CommandID": "BusinessPayment",
+            "Amount": whole_shillings(request.amount)?,
+            "PartyA": self.provider.username,
+            "PartyB": request.msisdn,
+            "Remarks": request.remarks,
+            "QueueTimeOutURL": self.callback_url("/payments/mpesa/b2c-timeout")?,
+            "ResultURL": self.callback_url("/payments/mpesa/b2c-result")?,
+            "Occasion": request.reference,
+        })).await?;
+
+        let Some(conversation_id) = body["ConversationID"].as_str() else {
+            return Err(GatewayError::Rejected(format!("{} did not accept the payout", self.provider.name)));
+        };
+        info!("B2C payment {} sent through {} as {}", request.reference, self.provider.name, conversation_id);
+
+        Ok(B2cResponse {
+            gateway:self.provider.name.clone(),
+            conversation_id:conversation_id.to_string(),
+            response_description:body["ResponseDescription"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Collections are looked up with the STK push query. Payout lookups are
+    /// answered asynchronously by M-Pesa, so their outcome only ever arrives
+    /// on the result URL.
+    async fn query_status(&self, query:&StatusQuery) -> Result<PaymentStatus, GatewayError> {
+        if query.direction == PaymentDirection::Payout {
+            return Ok(PaymentStatus::Pending);
+        }
+        let Some(checkout_request_id) = query.provider_reference.as_deref() else {
+            return Err(GatewayError::Rejected("A checkout request id is needed to query a collection".to_string()));
+        };
+
+        let (password, timestamp) = self.stk_password();
+        let result = self.post("/mpesa/stkpushquery/v1/query", &json!({
+            "BusinessShortCode": self.provider.username,
+            "Password": password,
+            "Timestamp": timestamp,
+            "CheckoutRequestID": checkout_request_id,
+        })).await;
+
+        let body = match result {
+            Ok(body) => body,
+            // Daraja answers a query for a request still in flight with an error
+            Err(GatewayError::Rejected(message)) if message.contains("being processed") => return Ok(PaymentStatus::Pending),
+            Err(e) => return Err(e),
+        };
+
+        let result_code = match &body["ResultCode"] {
+            JsonValue::String(code) => code.clone(),
+            code => code.to_string(),
+        };
+        let result_desc = body["ResultDesc"].as_str().unwrap_or_default().to_string();
+        Ok(match result_code.as_str() {
+            // The query does not return the receipt, the checkout id stands in for
+            // it until the callback or the statement brings the real one
+            "0" => PaymentStatus::Completed { receipt_no:checkout_request_id.to_string(), amount:None },
+            _ => PaymentStatus::Failed { reason:result_desc },
+        })
+    }
+}
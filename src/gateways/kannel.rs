@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use crate::gateways::http;
+use crate::gateways::{
+    B2cRequest,
+    B2cResponse,
+    GatewayError,
+    MobileMoneyGateway,
+    PaymentDirection,
+    PaymentStatus,
+    StatusQuery,
+    StkPushRequest,
+    StkPushResponse
+};
+use crate::models::mobile_money_provider::MobileMoneyprovider;
+
+
+/// Requests payments by SMS through a Kannel gateway's HTTP `sendsms`
+/// interface, for networks without a push API. Kannel relays the text to the
+/// SMSC over its own SMPP bind; this driver only speaks HTTP to Kannel. The
+/// customer gets the paybill details by text and pays them themselves, so
+/// the money arrives through C2B confirmation like any other paybill payment.
+///
+/// Providers of account type KANNEL use it. From the provider row:
+/// `endpoint_url` is Kannel's sendsms URL, `username`/`password` its sendsms
+/// credentials, `smsc_id` the SMSC to route through and `api_username` the
+/// paybill number customers pay to.
+pub struct KannelHttpDriver {
+    provider:MobileMoneyprovider,
+}
+
+impl KannelHttpDriver {
+    pub fn new(provider:MobileMoneyprovider) -> Self {
+        KannelHttpDriver { provider }
+    }
+
+    async fn send_sms(&self, to:&str, text:&str) -> Result<(), GatewayError> {
+        let smsc = self.provider.smsc_id.to_string();
+        let url = http::url_with_params(&self.provider.endpoint_url, &[
+            ("username", &self.provider.username),
+            ("password", &self.provider.password),
+            ("to", to),
+            ("text", text),
+            ("smsc", &smsc),
+        ])?;
+
+        let response = http::send("GET", &url, &[], None).await?;
+        if !response.is_success() {
+            return Err(GatewayError::Rejected(format!("{} ({})", response.body.trim(), response.status)));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MobileMoneyGateway for KannelHttpDriver {
+    fn name(&self) -> &str {
+        &self.provider.name
+    }
+
+    fn supports(&self, direction:PaymentDirection) -> bool {
+        direction == PaymentDirection::Collection
+    }
+
+    async fn stk_push(&self, request:&StkPushRequest) -> Result<StkPushResponse, GatewayError> {
+        let text = format!(
+            "{}: pay KES {} to paybill {}, account {}.",
+            request.description, request.amount, self.provider.api_username, request.account_reference
+        );
+        self.send_sms(&request.msisdn, &text).await?;
+        info!("Payment request {} sent by SMS through {}", request.account_reference, self.provider.name);
+
+        Ok(StkPushResponse {
+            gateway:self.provider.name.clone(),
+            // The customer quotes our reference when paying, it is all we have
+            checkout_request_id:request.account_reference.clone(),
+            merchant_request_id:request.account_reference.clone(),
+            customer_message:format!("Payment instructions sent to {}", request.msisdn),
+        })
+    }
+
+    async fn b2c_payment(&self, _request:&B2cRequest) -> Result<B2cResponse, GatewayError> {
+        Err(GatewayError::Rejected(format!("{} cannot send payouts", self.provider.name)))
+    }
+
+    /// Nothing to ask an SMS gateway; the payment shows up as a C2B
+    /// confirmation whenever the customer makes it.
+    async fn query_status(&self, _query:&StatusQuery) -> Result<PaymentStatus, GatewayError> {
+        Ok(PaymentStatus::Pending)
+    }
+}
@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use crate::gateways::{
+    B2cRequest,
+    B2cResponse,
+    GatewayError,
+    MobileMoneyGateway,
+    PaymentStatus,
+    StatusQuery,
+    StkPushRequest,
+    StkPushResponse
+};
+use crate::utils;
+
+
+/// Accepts every request without talking to a provider, for local development
+/// and tests. Complete a request by posting a callback for its
+/// checkout_request_id, or through `/payments/mock/stk/:checkout_request_id`;
+/// payouts likewise through `/payments/mock/b2c/:reference`.
+#[derive(Debug, Default)]
+pub struct MockGateway;
+
+#[async_trait]
+impl MobileMoneyGateway for MockGateway {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn stk_push(&self, request:&StkPushRequest) -> Result<StkPushResponse, GatewayError> {
+        if !request.amount.is_positive() {
+            return Err(GatewayError::Rejected("Amount must be greater than zero".to_string()));
+        }
+
+        let checkout_request_id = format!("ws_CO_MOCK_{}", utils::generate_invite_hash_64());
+        info!("Mock STK push of {} to {} as {}", request.amount, request.msisdn, checkout_request_id);
+
+        Ok(StkPushResponse {
+            gateway:self.name().to_string(),
+            checkout_request_id,
+            merchant_request_id:format!("MOCK-{}", utils::generate_invite_hash_64()),
+            customer_message:"Success. Request accepted for processing".to_string(),
+        })
+    }
+
+    async fn b2c_payment(&self, request:&B2cRequest) -> Result<B2cResponse, GatewayError> {
+        if !request.amount.is_positive() {
+            return Err(GatewayError::Rejected("Amount must be greater than zero".to_string()));
+        }
+
+        let conversation_id = format!("AG_MOCK_{}", utils::generate_invite_hash_64());
+        info!("Mock B2C payment of {} to {} for {} as {}", request.amount, request.msisdn, request.reference, conversation_id);
+
+        Ok(B2cResponse {
+            gateway:self.name().to_string(),
+            conversation_id,
+            response_description:"Accept the service request successfully.".to_string(),
+        })
+    }
+
+    /// Mock requests only finish when completed by hand, so they are always
+    /// still pending as far as the provider is concerned.
+    async fn query_status(&self, _query:&StatusQuery) -> Result<PaymentStatus, GatewayError> {
+        Ok(PaymentStatus::Pending)
+    }
+}
@@ -0,0 +1,163 @@
+pub mod http;
+pub mod http_driver;
+pub mod kannel;
+pub mod mock;
+pub mod parlayx;
+pub mod registry;
+
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlPool;
+
+use crate::error::AppError;
+use crate::money::Money;
+
+
+/// Ask the customer's phone to approve a payment to us (Lipa na M-Pesa STK push).
+#[derive(Debug, Clone, Serialize)]
+pub struct StkPushRequest {
+    // 2547XXXXXXXX
+    pub msisdn:String,
+    pub amount:Money,
+    // Shown to the customer and echoed back by the provider, our bill number
+    pub account_reference:String,
+    pub description:String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StkPushResponse {
+    // Name of the provider that took the request
+    pub gateway:String,
+    // The provider's id for the request, quoted again in the callback
+    pub checkout_request_id:String,
+    pub merchant_request_id:String,
+    pub customer_message:String,
+}
+
+/// Pay money out of our float to a customer's phone (B2C).
+#[derive(Debug, Clone, Serialize)]
+pub struct B2cRequest {
+    // 2547XXXXXXXX
+    pub msisdn:String,
+    pub amount:Money,
+    // Our reference, echoed back in the result as the originator conversation id
+    pub reference:String,
+    pub remarks:String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct B2cResponse {
+    // Name of the provider that took the request
+    pub gateway:String,
+    // The provider's id for the payout
+    pub conversation_id:String,
+    pub response_description:String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PaymentDirection {
+    // Money coming in, an STK push
+    Collection,
+    // Money going out, a B2C payout
+    Payout,
+}
+
+/// Ask a provider what became of an earlier request.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusQuery {
+    // Name of the provider that took the request
+    pub gateway:String,
+    pub direction:PaymentDirection,
+    // Our reference, the bill number or withdrawal reference
+    pub reference:String,
+    // The provider's id, the checkout request or conversation id
+    pub provider_reference:Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum PaymentStatus {
+    // Still in flight, ask again later
+    Pending,
+    Completed { receipt_no:String, amount:Option<Money> },
+    Failed { reason:String },
+}
+
+#[derive(Debug)]
+pub enum GatewayError {
+    // The provider refused the request, retrying will not help
+    Rejected(String),
+    // The provider could not be reached or failed, worth retrying elsewhere
+    Unavailable(String),
+    // The request was sent but no answer came back. It may have gone
+    // through, so it must not be repeated; the outcome is found by a status
+    // query or a late callback
+    Uncertain(String),
+}
+
+impl fmt::Display for GatewayError {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GatewayError::Rejected(msg) => write!(f, "Payment request rejected: {}", msg),
+            GatewayError::Unavailable(msg) => write!(f, "Payment provider unavailable: {}", msg),
+            GatewayError::Uncertain(msg) => write!(f, "Payment provider did not answer: {}", msg),
+        }
+    }
+}
+
+impl From<GatewayError> for AppError {
+    fn from(e:GatewayError) -> Self {
+        AppError::Gateway(e.to_string())
+    }
+}
+
+/// A mobile money provider we can collect from and pay out through. Results
+/// of requests arrive later on our callback endpoints, or can be asked for
+/// with `query_status`.
+#[async_trait]
+pub trait MobileMoneyGateway: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Whether the provider can move money in this direction at all.
+    fn supports(&self, _direction:PaymentDirection) -> bool {
+        true
+    }
+
+    async fn stk_push(&self, request:&StkPushRequest) -> Result<StkPushResponse, GatewayError>;
+
+    /// Queues a payout. Acceptance is not payment; the result or a queue
+    /// timeout is reported later against `request.reference`.
+    async fn b2c_payment(&self, request:&B2cRequest) -> Result<B2cResponse, GatewayError>;
+
+    async fn query_status(&self, query:&StatusQuery) -> Result<PaymentStatus, GatewayError>;
+}
+
+pub type SharedGateway = Arc<dyn MobileMoneyGateway>;
+
+/// Whether the mock gateway and its confirmation routes may be used. They let
+/// a user confirm their own deposits, so they are only ever switched on
+/// explicitly with MOBILE_MONEY_MOCK=true on a development machine.
+pub fn mock_enabled() -> bool {
+    env::var("MOBILE_MONEY_MOCK").is_ok_and(|v| v.eq_ignore_ascii_case("true"))
+}
+
+/// Picks the gateway named by MOBILE_MONEY_GATEWAY: "registry" routes
+/// through the active providers in mobile_money_provider, "mock" accepts
+/// everything locally and needs `mock_enabled`. There is no default, so a
+/// deployment cannot end up on the mock by leaving the variable out.
+pub async fn from_env(pool:&MySqlPool) -> SharedGateway {
+    let gateway = env::var("MOBILE_MONEY_GATEWAY").expect("MOBILE_MONEY_GATEWAY not set");
+    match gateway.to_lowercase().as_str() {
+        "mock" if mock_enabled() => Arc::new(mock::MockGateway),
+        "mock" => panic!("MOBILE_MONEY_GATEWAY is mock but MOBILE_MONEY_MOCK is not set to true"),
+        "registry" => Arc::new(
+            registry::ProviderRegistry::load(pool)
+                .await
+                .expect("Could not load mobile money providers")
+        ),
+        other => panic!("Unknown MOBILE_MONEY_GATEWAY {}", other),
+    }
+}
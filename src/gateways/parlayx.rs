@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use crate::gateways::http;
+use crate::gateways::{
+    B2cRequest,
+    B2cResponse,
+    GatewayError,
+    MobileMoneyGateway,
+    PaymentStatus,
+    StatusQuery,
+    StkPushRequest,
+    StkPushResponse
+};
+use crate::models::mobile_money_provider::MobileMoneyprovider;
+use crate::money::Money;
+use crate::utils;
+
+
+/// Charges and refunds through a Parlay X 2.1 Amount Charging SOAP service.
+///
+/// Parlay X answers synchronously: a request that returns without a fault has
+/// moved the money, so there is no callback and a status query for a request
+/// the service accepted is always complete.
+///
+/// From the provider row: `endpoint_url` is the AmountCharging service URL and
+/// `username`/`password` the service provider id and password.
+pub struct ParlayxDriver {
+    provider:MobileMoneyprovider,
+}
+
+fn escape(value:&str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl ParlayxDriver {
+    pub fn new(provider:MobileMoneyprovider) -> Self {
+        ParlayxDriver { provider }
+    }
+
+    /// Calls `chargeAmount` or `refundAmount`, which share their parameters.
+    async fn call(&self, operation:&str, msisdn:&str, amount:Money, description:&str, reference:&str) -> Result<(), GatewayError> {
+        let envelope = format!(
+            r#"<soapenv:Envelope xmlns:soapenv="http://schemas.xmlsoap.org/soap/envelope/" xmlns:v2="http://www.huawei.com.cn/schema/common/v2_1" xmlns:loc="http://www.csapi.org/schema/parlayx/payment/amount_charging/v2_1/local"><soapenv:Header><v2:RequestSOAPHeader><v2:spId>{}</v2:spId><v2:spPassword>{}</v2:spPassword><v2:timeStamp>{}</v2:timeStamp></v2:RequestSOAPHeader></soapenv:Header><soapenv:Body><loc:{op}><loc:endUserIdentifier>tel:{}</loc:endUserIdentifier><loc:charge><description>{}</description><currency>{:?}</currency><amount>{}</amount><code>{}</code></loc:charge><loc:referenceCode>{}</loc:referenceCode></loc:{op}></soapenv:Body></soapenv:Envelope>"#,
+            escape(&self.provider.username),
+            escape(&self.provider.password),
+            utils::now_eat().format("%Y%m%d%H%M%S"),
+            escape(msisdn),
+            escape(description),
+            amount.currency(),
+            amount,
+            escape(reference),
+            escape(reference),
+            op = operation,
+        );
+
+        let response = http::send(
+            "POST",
+            &self.provider.endpoint_url,
+            &[("SOAPAction", "\"\"")],
+            Some(("text/xml; charset=utf-8", &envelope)),
+        ).await?;
+
+        if response.body.contains("Fault>") {
+            let reason = response.body
+                .split("<faultstring>").nth(1)
+                .and_then(|rest| rest.split("</faultstring>").next())
+                .unwrap_or("SOAP fault")
+                .to_string();
+            return Err(GatewayError::Rejected(reason));
+        }
+        if !response.is_success() {
+            return Err(GatewayError::Rejected(format!("{} answered {}", self.provider.name, response.status)));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MobileMoneyGateway for ParlayxDriver {
+    fn name(&self) -> &str {
+        &self.provider.name
+    }
+
+    async fn stk_push(&self, request:&StkPushRequest) -> Result<StkPushResponse, GatewayError> {
+        self.call("chargeAmount", &request.msisdn, request.amount, &request.description, &request.account_reference).await?;
+        info!("Charged {} to {} through {} for {}", request.amount, request.msisdn, self.provider.name, request.account_reference);
+
+        Ok(StkPushResponse {
+            gateway:self.provider.name.clone(),
+            checkout_request_id:request.account_reference.clone(),
+            merchant_request_id:request.account_reference.clone(),
+            customer_message:"Payment charged".to_string(),
+        })
+    }
+
+    async fn b2c_payment(&self, request:&B2cRequest) -> Result<B2cResponse, GatewayError> {
+        self.call("refundAmount", &request.msisdn, request.amount, &request.remarks, &request.reference).await?;
+        info!("Paid {} to {} through {} for {}", request.amount, request.msisdn, self.provider.name, request.reference);
+
+        Ok(B2cResponse {
+            gateway:self.provider.name.clone(),
+            conversation_id:request.reference.clone(),
+            response_description:"Payment sent".to_string(),
+        })
+    }
+
+    /// Only requests the service accepted are given a provider reference.
+    /// Without one the call may have been cut off after the money moved, and
+    /// the service has no lookup to tell, so it stays pending for staff.
+    async fn query_status(&self, query:&StatusQuery) -> Result<PaymentStatus, GatewayError> {
+        Ok(match &query.provider_reference {
+            Some(reference) => PaymentStatus::Completed { receipt_no:reference.clone(), amount:None },
+            None => PaymentStatus::Pending,
+        })
+    }
+}
@@ -0,0 +1,155 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use sqlx::MySqlPool;
+use tracing::{info, warn};
+
+use crate::gateways::http_driver::HttpDriver;
+use crate::gateways::kannel::KannelHttpDriver;
+use crate::gateways::parlayx::ParlayxDriver;
+use crate::gateways::{
+    B2cRequest,
+    B2cResponse,
+    GatewayError,
+    MobileMoneyGateway,
+    PaymentDirection,
+    PaymentStatus,
+    SharedGateway,
+    StatusQuery,
+    StkPushRequest,
+    StkPushResponse
+};
+use crate::models::mobile_money_provider::{AccountTypeEnum, MobileMoneyprovider};
+
+
+/// How long a provider that failed is passed over before it is tried again.
+const FAILOVER_COOLDOWN: Duration = Duration::from_secs(60);
+
+type GatewayFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, GatewayError>> + Send + 'a>>;
+
+struct ProviderSlot {
+    gateway:SharedGateway,
+    down_until:Mutex<Option<Instant>>,
+}
+
+impl ProviderSlot {
+    fn is_up(&self) -> bool {
+        match *self.down_until.lock().unwrap() {
+            Some(until) => Instant::now() >= until,
+            None => true,
+        }
+    }
+
+    fn mark(&self, up:bool) {
+        *self.down_until.lock().unwrap() = if up { None } else { Some(Instant::now() + FAILOVER_COOLDOWN) };
+    }
+}
+
+/// The active rows of mobile_money_provider, each behind the driver for its
+/// account type. New requests go to the first provider by priority that can
+/// handle them; a provider that cannot be reached is skipped for a while and
+/// the request tried on the next one. A provider that refuses a request is
+/// not failed over, the refusal would only be repeated elsewhere. Status
+/// queries go back to the provider that took the request.
+pub struct ProviderRegistry {
+    providers:Vec<ProviderSlot>,
+}
+
+pub fn driver_for(provider:MobileMoneyprovider) -> SharedGateway {
+    match provider.account_type {
+        AccountTypeEnum::HTTP => Arc::new(HttpDriver::new(provider)),
+        AccountTypeEnum::KANNEL | AccountTypeEnum::SMPP => Arc::new(KannelHttpDriver::new(provider)),
+        AccountTypeEnum::PARLAYX => Arc::new(ParlayxDriver::new(provider)),
+    }
+}
+
+impl ProviderRegistry {
+    pub fn new(gateways:Vec<SharedGateway>) -> Self {
+        ProviderRegistry {
+            providers:gateways.into_iter().map(|gateway| ProviderSlot { gateway, down_until:Mutex::new(None) }).collect(),
+        }
+    }
+
+    pub async fn load(pool:&MySqlPool) -> Result<Self, sqlx::Error> {
+        let providers = sqlx::query_as::<_, MobileMoneyprovider>(
+            "SELECT * FROM mobile_money_provider WHERE status = 'ACTIVE' ORDER BY priority, id"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        for provider in &providers {
+            info!("Loaded mobile money provider {} ({:?})", provider.name, provider.account_type);
+            if provider.account_type == AccountTypeEnum::SMPP {
+                warn!("Provider {} has account type SMPP, which is sent through Kannel over HTTP; set it to KANNEL", provider.name);
+            }
+        }
+        if providers.is_empty() {
+            warn!("No active mobile money providers, payments will be refused");
+        }
+
+        Ok(ProviderRegistry::new(providers.into_iter().map(driver_for).collect()))
+    }
+
+    /// Providers able to move money in `direction`, ones that are up first.
+    /// When all are down they are still tried rather than failing outright.
+    fn candidates(&self, direction:PaymentDirection) -> Vec<&ProviderSlot> {
+        let (mut up, down): (Vec<&ProviderSlot>, Vec<&ProviderSlot>) = self.providers
+            .iter()
+            .filter(|slot| slot.gateway.supports(direction))
+            .partition(|slot| slot.is_up());
+        up.extend(down);
+        up
+    }
+
+    async fn route<'a, T>(
+        &'a self,
+        direction:PaymentDirection,
+        call:impl Fn(&'a dyn MobileMoneyGateway) -> GatewayFuture<'a, T>) -> Result<T, GatewayError> {
+
+        let mut last_error = GatewayError::Unavailable("No mobile money provider is configured".to_string());
+        for slot in self.candidates(direction) {
+            match call(slot.gateway.as_ref()).await {
+                Ok(response) => {
+                    slot.mark(true);
+                    return Ok(response);
+                }
+                Err(GatewayError::Unavailable(reason)) => {
+                    warn!("Mobile money provider {} unavailable, failing over: {}", slot.gateway.name(), reason);
+                    slot.mark(false);
+                    last_error = GatewayError::Unavailable(reason);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error)
+    }
+}
+
+#[async_trait]
+impl MobileMoneyGateway for ProviderRegistry {
+    fn name(&self) -> &str {
+        "registry"
+    }
+
+    fn supports(&self, direction:PaymentDirection) -> bool {
+        self.providers.iter().any(|slot| slot.gateway.supports(direction))
+    }
+
+    async fn stk_push(&self, request:&StkPushRequest) -> Result<StkPushResponse, GatewayError> {
+        self.route(PaymentDirection::Collection, |gateway| gateway.stk_push(request)).await
+    }
+
+    async fn b2c_payment(&self, request:&B2cRequest) -> Result<B2cResponse, GatewayError> {
+        self.route(PaymentDirection::Payout, |gateway| gateway.b2c_payment(request)).await
+    }
+
+    async fn query_status(&self, query:&StatusQuery) -> Result<PaymentStatus, GatewayError> {
+        let Some(slot) = self.providers.iter().find(|slot| slot.gateway.name() == query.gateway) else {
+            return Err(GatewayError::Rejected(format!("Provider {} is not active", query.gateway)));
+        };
+        slot.gateway.query_status(query).await
+    }
+}
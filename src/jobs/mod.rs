@@ -0,0 +1,81 @@
+use std::env;
+use std::time::Duration;
+
+use sqlx::MySqlPool;
+use tracing::{info, error};
+
+use crate::gateways::SharedGateway;
+use crate::services::{contribution_service, default_service, fine_service, proposal_service, reconciliation_service, rotation_service};
+
+
+fn interval_from_env(key:&str, default_secs:u64) -> Duration {
+    let secs = env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default_secs);
+    Duration::from_secs(secs.max(1))
+}
+
+/// Starts the background jobs. Each job runs on its own timer for the life of the server.
+pub fn start(pool:MySqlPool, gateway:SharedGateway) {
+    let default_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval_from_env("LOAN_DEFAULT_JOB_INTERVAL_SECS", 3600));
+        loop {
+            ticker.tick().await;
+            match default_service::detect_defaults(&default_pool).await {
+                Ok(count) => info!("Loan default job finished, {} loans defaulted", count),
+                Err(e) => error!("Loan default job failed: {}", e),
+            }
+        }
+    });
+
+    let cycle_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval_from_env("CONTRIBUTION_CYCLE_JOB_INTERVAL_SECS", 3600));
+        loop {
+            ticker.tick().await;
+            match contribution_service::open_due_cycles(&cycle_pool).await {
+                Ok(count) => info!("Contribution cycle job finished, {} cycles opened", count),
+                Err(e) => error!("Contribution cycle job failed: {}", e),
+            }
+            // Pots are paid once their cycle has ended, which the cycle job notices first
+            match rotation_service::run_due_payouts(&cycle_pool).await {
+                Ok(count) => info!("Merry-go-round job finished, {} cycles settled", count),
+                Err(e) => error!("Merry-go-round job failed: {}", e),
+            }
+        }
+    });
+    let fine_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval_from_env("FINE_JOB_INTERVAL_SECS", 3600));
+        loop {
+            ticker.tick().await;
+            match fine_service::assess_fines(&fine_pool).await {
+                Ok(count) => info!("Fine job finished, {} fines charged", count),
+                Err(e) => error!("Fine job failed: {}", e),
+            }
+        }
+    });
+    let proposal_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval_from_env("PROPOSAL_JOB_INTERVAL_SECS", 300));
+        loop {
+            ticker.tick().await;
+            match proposal_service::close_due_proposals(&proposal_pool).await {
+                Ok(count) => info!("Proposal job finished, {} proposals closed", count),
+                Err(e) => error!("Proposal job failed: {}", e),
+            }
+        }
+    });
+    let reconcile_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval_from_env("PAYMENT_RECONCILE_JOB_INTERVAL_SECS", 900));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = reconciliation_service::sweep_pending(&reconcile_pool, gateway.as_ref()).await {
+                error!("Pending payment sweep failed: {}", e);
+            }
+        }
+    });
+}
@@ -15,8 +15,12 @@ pub mod services;
 pub mod repositories;
 pub mod db;
 pub mod utils;
+pub mod money;
 pub mod middleware;
 pub mod enums;
+pub mod error;
+pub mod jobs;
+pub mod gateways;
 
 
 #[tokio::main]
@@ -30,6 +34,13 @@ async fn main() -> Result<()> {
     // Initialize logging to both stdout and a file
     init_tracing()?;
 
+    // Chamas from before positions belonged to a chama need their own copies
+    match services::chama_service::backfill_positions(&dbpool).await {
+        Ok(0) => {},
+        Ok(count) => info!("Gave {} chamas their own positions", count),
+        Err(e) => tracing::error!("Failed to give chamas their own positions: {}", e),
+    }
+
     // Read host and port from env
     let host = env::var("APP_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
     let port: u16 = env::var("APP_PORT")
@@ -46,8 +57,12 @@ async fn main() -> Result<()> {
 
    
 
+    let gateway = gateways::from_env(&dbpool).await;
+    jobs::start(dbpool.clone(), gateway.clone());
+
     // Build Axum app
     let app = routes::routes()
+        .layer(axum::Extension(gateway))
         .layer(axum::Extension(dbpool));
 
     // Start server
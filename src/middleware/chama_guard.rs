@@ -0,0 +1,40 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+};
+use sqlx::MySqlPool;
+
+use crate::dtos::auth::Claims;
+use crate::error::AppError;
+use crate::models::chama::ChamaPermission;
+use crate::services::chama_service;
+use crate::utils::ApiResponse;
+
+/// The signed in user of a chama route, able to check what their position in
+/// a chama allows them to do. Handlers take it in place of the raw claims.
+pub struct ChamaGuard {
+    pub user_id:String,
+    pool:MySqlPool,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ChamaGuard {
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request_parts(parts:&mut Parts, _state:&S) -> Result<Self, Self::Rejection> {
+        // Both are put on the request by `require_auth` and the app's layers
+        let claims = parts.extensions.get::<Claims>()
+            .ok_or_else(|| ApiResponse::error("Unauthorized", StatusCode::UNAUTHORIZED.as_u16()))?;
+        let pool = parts.extensions.get::<MySqlPool>()
+            .ok_or_else(|| ApiResponse::error("Could not complete request", StatusCode::INTERNAL_SERVER_ERROR.as_u16()))?;
+
+        Ok(ChamaGuard { user_id:claims.sub.clone(), pool:pool.clone() })
+    }
+}
+
+impl ChamaGuard {
+    pub async fn require(&self, chama_id:&i64, permission:ChamaPermission) -> Result<(), AppError> {
+        chama_service::require_permission(&self.pool, &self.user_id, chama_id, permission).await
+    }
+}
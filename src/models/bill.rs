@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use sqlx::prelude::FromRow;
 
+use crate::money::Money;
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug)]
 pub enum PaymentModeEnum{
@@ -16,8 +18,8 @@ pub enum AccountTypeEnum {
   TILL
 }
 
-#[derive(Serialize, Deserialize)]
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BillFrequencyEnum {
    Adhoc,
    Daily,
@@ -38,7 +40,7 @@ pub struct Bill {
   pub status:String,
   pub user_id:i64,
   pub frequency:BillFrequencyEnum,
-  pub amount:f64,
+  pub amount:Money,
   pub due_date:NaiveDateTime,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
@@ -65,7 +67,7 @@ pub struct BillHandler {
 pub struct BillPayment {
   pub id:i64,
   pub bill_id:i64,
-  pub amount:f64,
+  pub amount:Money,
   pub pay_date:NaiveDateTime,
   pub payment_mode: PaymentModeEnum,
   pub aggregator_transaction_id:String,
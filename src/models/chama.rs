@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use sqlx::prelude::FromRow;
 
-use crate::enums::LoanRepaymentFrequecyEnum;
+use crate::money::Money;
+
+use crate::enums::InterestMethodEnum;
 
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
@@ -28,7 +30,7 @@ pub struct ChamaMember {
     pub user_id:i64,           
     pub chama_id:i64,           
     pub position:i64,      
-    pub contribution_amount:f64,
+    pub contribution_amount:Money,
     pub created_at: NaiveDateTime,
     pub updated_at:NaiveDateTime,
     pub created_by:i64,
@@ -40,13 +42,58 @@ pub struct ChamaMember {
 #[derive(Debug, FromRow)]
 pub struct ChamaPosition {
     pub id:Option<i64>,                 
+    pub chama_id:i64,
     pub chama_position:String,           
-    pub role_id:i64,           
+    pub created_by:i64,
     pub created_at: NaiveDateTime,
     pub updated_at:NaiveDateTime      
 
 }
 
+/// What holders of a chama position may do. Each chama decides which of
+/// these its positions carry.
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChamaPermission {
+   // Chama details, positions and their permissions
+   MANAGECHAMA,
+   // Invites, membership requests, removals and exits
+   MANAGEMEMBERS,
+   // Loan limits, approvers, guarantee, default and repayment settings
+   MANAGELOANSETTINGS,
+   // Disbursements and repayments received outside the app
+   MANAGELOANS,
+   // Contribution schedules, cash contributions, arrears and rotations
+   MANAGECONTRIBUTIONS,
+   MANAGEFINES,
+   // Meetings, resolutions and other members' proposals
+   MANAGEMEETINGS,
+   // Withdrawal signatories, withdrawal requests and dividend runs
+   MANAGEWALLET
+}
+
+impl ChamaPermission {
+    pub const ALL: [ChamaPermission; 8] = [
+        ChamaPermission::MANAGECHAMA,
+        ChamaPermission::MANAGEMEMBERS,
+        ChamaPermission::MANAGELOANSETTINGS,
+        ChamaPermission::MANAGELOANS,
+        ChamaPermission::MANAGECONTRIBUTIONS,
+        ChamaPermission::MANAGEFINES,
+        ChamaPermission::MANAGEMEETINGS,
+        ChamaPermission::MANAGEWALLET,
+    ];
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaPositionPermission {
+    pub id:Option<i64>,
+    pub position_id:i64,
+    pub permission:ChamaPermission,
+    pub created_at:NaiveDateTime,
+}
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
 pub struct ChamaLoanApprover {
@@ -61,12 +108,38 @@ pub struct ChamaLoanApprover {
 }
 
 
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaLoanApprovalSetting {
+    pub id:Option<i64>,                 
+    pub chama_id:i64,           
+    pub quorum:i32,          
+    pub created_at: NaiveDateTime,
+    pub updated_at:NaiveDateTime,  
+    pub is_active:i8   
+
+}
+
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaLoanDefaultSetting {
+    pub id:Option<i64>,                 
+    pub chama_id:i64,           
+    pub grace_period_days:i32,          
+    pub created_at: NaiveDateTime,
+    pub updated_at:NaiveDateTime,  
+    pub is_active:i8   
+
+}
+
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
 pub struct ChamaLoanLimit {
     pub id:Option<i64>,                 
     pub chama_id:i64,           
-    pub amount:f64, 
+    pub amount:Money, 
     pub centage_member_savings:f64,          
     pub created_at: NaiveDateTime,
     pub updated_at:NaiveDateTime,  
@@ -81,6 +154,9 @@ pub struct ChamaLoanRepaymentLimit {
     pub chama_id:i64,           
     pub repayment_frequency:String, 
     pub max_repayment_months:i32,          
+    pub interest_rate:f64,
+    pub interest_method:InterestMethodEnum,
+    pub processing_fee_centage:f64,
     pub created_at: NaiveDateTime,
     pub updated_at:NaiveDateTime      
 
@@ -107,7 +183,58 @@ pub struct ChamaInvite {
     pub expiry_date:NaiveDateTime,
     pub invite_hash:String,    
     pub invited_by:i64,     
+    // Only the user with this phone number or email can join with it
+    pub invited_phone:Option<String>,
+    pub invited_email:Option<String>,
+    pub max_uses:i32,
+    pub use_count:i32,
+    pub revoked_at:Option<NaiveDateTime>,
+    pub revoked_by:Option<i64>,
     pub created_at: NaiveDateTime,
     pub updated_at:NaiveDateTime      
 
-}
\ No newline at end of file
+}
+/// What a chama asks of people joining it.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaMembershipSetting {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    // Paid before the membership is approved, none when zero
+    pub joining_fee:Money,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+    pub is_active:i8
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MembershipRequestStatus {
+   PENDING,
+   APPROVED,
+   REJECTED,
+   // Withdrawn by the applicant
+   CANCELLED
+}
+
+/// Someone waiting to be let into a chama after following an invite.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaMembershipRequest {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub invite_id:i64,
+    pub status:MembershipRequestStatus,
+    // The chama's joining fee when the request was made
+    pub joining_fee:Money,
+    pub fee_paid_at:Option<NaiveDateTime>,
+    pub reason:Option<String>,
+    pub reviewed_by:Option<i64>,
+    pub reviewed_at:Option<NaiveDateTime>,
+    // The membership created on approval
+    pub chama_member_id:Option<i64>,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
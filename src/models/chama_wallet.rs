@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+/// A position whose holder signs withdrawals from the chama wallet.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaWithdrawalSignatory {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub position_id:i64,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+    pub is_active:i8
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaWithdrawalSetting {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    // Signatory positions that must approve, every one of them when zero
+    pub quorum:i32,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+    pub is_active:i8
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChamaWithdrawalStatus {
+   // Waiting for signatures
+   PENDING,
+   REJECTED,
+   CANCELLED,
+   // Credited to a member's wallet, or paid out to a phone number
+   PAID,
+   // Handed to the mobile money provider, see the linked withdrawal.
+   // Moves on to PAID or FAILED when the provider reports back
+   SUBMITTED,
+   // Approved but the payout could not be made
+   FAILED
+}
+
+/// Money leaving the chama wallet for a member's wallet or a phone number.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaWithdrawal {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub amount:Money,
+    pub beneficiary_user_id:Option<i64>,
+    pub msisdn:Option<String>,
+    pub narration:String,
+    pub status:ChamaWithdrawalStatus,
+    // The mobile money payout made for it
+    pub withdrawal_id:Option<i64>,
+    pub result_desc:Option<String>,
+    pub requested_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureDecision {
+   APPROVE,
+   REJECT
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ChamaWithdrawalSignature {
+    pub id:Option<i64>,
+    pub chama_withdrawal_id:i64,
+    pub user_id:i64,
+    pub position_id:i64,
+    pub decision:SignatureDecision,
+    pub comment:Option<String>,
+    pub created_at:NaiveDateTime,
+}
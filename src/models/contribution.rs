@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use chrono::{Days, Months, NaiveDate, NaiveDateTime};
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContributionFrequencyEnum {
+   WEEKLY,
+   BIWEEKLY,
+   MONTHLY
+}
+
+impl ContributionFrequencyEnum {
+    /// Start of the `n`th cycle of a schedule starting on `start`. Monthly
+    /// cycles are counted from the start date each time, so a schedule
+    /// starting on the 31st keeps returning to the end of the month.
+    pub fn cycle_start(&self, start:NaiveDate, n:u32) -> Option<NaiveDate> {
+        match self {
+            ContributionFrequencyEnum::WEEKLY => start.checked_add_days(Days::new(7 * n as u64)),
+            ContributionFrequencyEnum::BIWEEKLY => start.checked_add_days(Days::new(14 * n as u64)),
+            ContributionFrequencyEnum::MONTHLY => start.checked_add_months(Months::new(n)),
+        }
+    }
+}
+
+/// How often a chama's members contribute and how much. Only one schedule
+/// is active per chama; replacing it leaves the cycles already opened alone.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ContributionSchedule {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub frequency:ContributionFrequencyEnum,
+    // Expected from members without an agreed amount of their own
+    pub amount:Money,
+    pub start_date:NaiveDate,
+    pub is_active:i8,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ContributionCycle {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub schedule_id:i64,
+    pub cycle_no:i32,
+    pub start_date:NaiveDate,
+    // Last day of the cycle, contributions are due by then
+    pub end_date:NaiveDate,
+    pub created_at:NaiveDateTime,
+}
+
+/// What a member was expected to pay in a cycle, fixed when the cycle opened.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ContributionExpectation {
+    pub id:Option<i64>,
+    pub cycle_id:i64,
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub expected_amount:Money,
+    pub created_at:NaiveDateTime,
+}
+
+/// A payment a member made to the chama, either a mobile money deposit or
+/// cash recorded by an official.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct Contribution {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub deposit_id:Option<i64>,
+    pub amount:Money,
+    pub paid_at:NaiveDateTime,
+    pub narration:String,
+    pub recorded_by:i64,
+    // Cleared when the deposit behind it is reversed
+    pub is_active:i8,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
@@ -2,12 +2,14 @@ use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use sqlx::prelude::FromRow;
 
+use crate::money::Money;
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
 pub struct CreditBalance {
   pub id:i64,
   pub user_id:i64,
-  pub balance:f64,
+  pub balance:Money,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
   pub created_by:i64,
@@ -36,7 +38,7 @@ pub struct CreditProfile {
   pub id:i64,
   pub name:String,
   pub narration:String,
-  pub max_limit:f64,
+  pub max_limit:Money,
   pub status:String,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
@@ -62,8 +64,8 @@ pub struct CreditScorehistory {
   pub id:i64,
   pub user_id:i64,
   pub score_time:NaiveDateTime,
-  pub pre_limit:f64,
-  pub current_limit:f64,
+  pub pre_limit:Money,
+  pub current_limit:Money,
   pub narration:String,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
@@ -93,8 +95,8 @@ pub struct CreditOrganizationStaff {
   pub last_name:String,
   pub organization_identifier:String,
   pub msisdn: String,
-  pub income:f64,
-  pub guarantee_value:f64,
+  pub income:Money,
+  pub guarantee_value:Money,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
   pub created_by:i64,
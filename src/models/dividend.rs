@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+/// A year-end share-out of what a chama earned over a period. Loan income is
+/// moved from the income accounts into the chama's savings, the retained part
+/// stays there and the rest is paid to members' wallets.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct DividendRun {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub period_start:NaiveDate,
+    pub period_end:NaiveDate,
+    // Interest, fees and penalties repaid on the chama's loans in the period
+    pub interest_income:Money,
+    pub fee_income:Money,
+    pub penalty_income:Money,
+    // Contribution and meeting fines paid in the period
+    pub fine_income:Money,
+    pub total_income:Money,
+    // Percentage of the income the chama keeps
+    pub retained_rate:f64,
+    pub retained:Money,
+    pub distributed:Money,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct DividendPayout {
+    pub id:Option<i64>,
+    pub run_id:i64,
+    pub user_id:i64,
+    // Savings held on average over the period, the member's weight in the run
+    pub average_balance:Money,
+    pub amount:Money,
+    pub created_at:NaiveDateTime,
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PenaltyTriggerEnum {
+   // A cycle's contribution not paid by its end date
+   LATECONTRIBUTION,
+   MISSEDMEETING,
+   // A chama loan installment not paid by its due date
+   LATEREPAYMENT
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PenaltyCalculationEnum {
+   FLAT,
+   // A percentage of the amount left unpaid
+   PERCENTAGE
+}
+
+/// How a chama fines one kind of lapse. Only one rule per trigger is active
+/// per chama, and it only applies to lapses after it was set.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct PenaltyRule {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub trigger_type:PenaltyTriggerEnum,
+    pub calculation:PenaltyCalculationEnum,
+    // The fine for a FLAT rule
+    pub amount:Money,
+    // Percentage for a PERCENTAGE rule
+    pub rate:f64,
+    // Days after the due date before a fine is charged
+    pub grace_days:i32,
+    // Largest single fine, none when uncapped
+    pub cap:Option<Money>,
+    pub is_active:i8,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FineStatus {
+   OUTSTANDING,
+   PAID,
+   WAIVED
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct Fine {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub rule_id:i64,
+    pub trigger_type:PenaltyTriggerEnum,
+    // The contribution expectation, meeting or loan installment fined for
+    pub source_id:i64,
+    pub amount:Money,
+    pub description:String,
+    pub status:FineStatus,
+    pub waived_by:Option<i64>,
+    pub waive_reason:Option<String>,
+    pub settled_at:Option<NaiveDateTime>,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
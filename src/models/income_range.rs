@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 
+use crate::money::Money;
+
 #[derive(Serialize, Deserialize)]
 pub struct IncomeRange {
   pub id:i32,
-  pub min_amount:f64,
-  pub max_amount:f64,
+  pub min_amount:Money,
+  pub max_amount:Money,
   pub status:String,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
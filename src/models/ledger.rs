@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LedgerAccountType {
+   ASSET,
+   LIABILITY,
+   EQUITY,
+   INCOME,
+   EXPENSE
+}
+
+impl LedgerAccountType {
+    /// Assets and expenses grow with debits, everything else with credits.
+    pub fn is_debit_normal(&self) -> bool {
+        matches!(self, LedgerAccountType::ASSET | LedgerAccountType::EXPENSE)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct LedgerAccount {
+  pub id:Option<i64>,
+  pub code:String,
+  pub name:String,
+  pub account_type:LedgerAccountType,
+  pub user_id:Option<i64>,
+  pub chama_id:Option<i64>,
+  pub balance:Money,
+  pub created_at:NaiveDateTime,
+  pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct JournalEntry {
+  pub id:Option<i64>,
+  pub reference:String,
+  pub entry_type:String,
+  pub narration:String,
+  pub created_by:Option<i64>,
+  pub posted_at:NaiveDateTime,
+  pub created_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct LedgerPosting {
+  pub id:Option<i64>,
+  pub journal_entry_id:i64,
+  pub ledger_account_id:i64,
+  pub debit:Money,
+  pub credit:Money,
+  pub created_at:NaiveDateTime,
+}
@@ -1,6 +1,13 @@
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
-#[derive(Serialize, Deserialize)]
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+use crate::models::bill::BillFrequencyEnum;
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LoadRequestStatus {
    PENDING,
    PARTIALYAPPROVED,
@@ -8,56 +15,189 @@ pub enum LoadRequestStatus {
    REJECTED,
    DISBURSED,
    REPAID,
-   DEFAULTED
+   DEFAULTED,
+   CANCELLED
+}
+
+impl LoadRequestStatus {
+    /// The only moves a loan may make. Anything not listed here is refused,
+    /// so a loan can never skip e.g. from PENDING straight to REPAID.
+    pub fn can_transition_to(&self, next: LoadRequestStatus) -> bool {
+        use LoadRequestStatus::*;
+        matches!(
+            (self, next),
+            (PENDING, PARTIALYAPPROVED)
+                | (PENDING, APPROVED)
+                | (PENDING, REJECTED)
+                | (PENDING, CANCELLED)
+                | (PARTIALYAPPROVED, APPROVED)
+                | (PARTIALYAPPROVED, REJECTED)
+                | (PARTIALYAPPROVED, CANCELLED)
+                | (APPROVED, DISBURSED)
+                | (APPROVED, CANCELLED)
+                | (DISBURSED, REPAID)
+                | (DISBURSED, DEFAULTED)
+                | (DEFAULTED, REPAID)
+        )
+    }
+
+    /// Loans that still tie up the borrower: awaiting a decision or money outstanding.
+    pub fn is_open(&self) -> bool {
+        use LoadRequestStatus::*;
+        matches!(self, PENDING | PARTIALYAPPROVED | APPROVED | DISBURSED | DEFAULTED)
+    }
 }
 
 
 #[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
 pub struct LoanRequest {
-  pub id:i64,
+  pub id:Option<i64>,
   pub user_id:i64,
-  pub amount_requested:f64,
-  pub amount_approved:f64,
-  pub credit_profile_id:i64,
+  pub chama_id:Option<i64>,
+  pub amount_requested:Money,
+  pub amount_approved:Money,
+  pub credit_profile_id:Option<i64>,
   pub status:LoadRequestStatus,
   pub frequency:BillFrequencyEnum,
+  pub repayment_months:i32,
   pub due_date:NaiveDateTime,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
  
 } 
 
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoanApprovalDecision {
+   APPROVE,
+   REJECT
+}
+
 #[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct LoanRequestApproval {
+  pub id:Option<i64>,
+  pub load_request_id:i64,
+  pub user_id:i64,
+  pub approver_position_id:i64,
+  pub decision:LoanApprovalDecision,
+  pub comment:Option<String>,
+  pub created_at:NaiveDateTime,
+  pub updated_at:NaiveDateTime,
+
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GuaranteeStatus {
+   INVITED,
+   ACCEPTED,
+   DECLINED,
+   RELEASED
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
 pub struct LoanRequestGuarantee {
-  pub id:i64,
+  pub id:Option<i64>,
   pub load_request_id:i64,
   pub loan_quaranter_id:i64,
-  pub amount_quaranteed:f64,
+  pub amount_requested:Money,
+  pub amount_quaranteed:Money,
+  pub status:GuaranteeStatus,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
  
 } 
 
 
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstallmentStatus {
+   PENDING,
+   PARTIAL,
+   PAID
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct LoanRepaymentSchedule {
+  pub id:Option<i64>,
+  pub load_request_id:i64,
+  pub installment_no:i32,
+  pub due_date:NaiveDateTime,
+  pub principal:Money,
+  pub interest:Money,
+  pub fees:Money,
+  pub penalty:Money,
+  pub amount_due:Money,
+  pub remaining_balance:Money,
+  pub principal_paid:Money,
+  pub interest_paid:Money,
+  pub fees_paid:Money,
+  pub penalty_paid:Money,
+  pub status:InstallmentStatus,
+  pub created_at:NaiveDateTime,
+  pub updated_at:NaiveDateTime,
+
+}
+
+impl LoanRepaymentSchedule {
+    pub fn outstanding(&self) -> Money {
+        (self.principal - self.principal_paid)
+            + (self.interest - self.interest_paid)
+            + (self.fees - self.fees_paid)
+            + (self.penalty - self.penalty_paid)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, PartialEq)]
+pub enum LoanRepaymentMethod {
+   ACCOUNT,
+   CASH,
+   MPESA,
+   BANK,
+   GUARANTEE,
+   // Taken from the borrower's own chama savings
+   SAVINGS
+}
+
 #[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
 pub struct LoanRepayment {
-  pub id:i64,
+  pub id:Option<i64>,
   pub load_request_id:i64,
-  pub amount:f64,
-  pub loan_balance:f64,
+  pub amount:Money,
+  pub penalty_paid:Money,
+  pub fees_paid:Money,
+  pub interest_paid:Money,
+  pub principal_paid:Money,
+  pub overpayment:Money,
+  pub loan_balance:Money,
   pub payment_mothod:String,
-  paid_at:NaiveDateTime,
+  pub reference:Option<String>,
+  pub paid_at:NaiveDateTime,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
  
 } 
 
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoanDefaultStatus {
+   OUTSTANDING,
+   RECOVERED
+}
+
 #[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
 pub struct LoanDafault {
-  pub id:i64,
+  pub id:Option<i64>,
   pub load_request_id:i64,
-  pub loan_balance:f64,
-  pub status:String,
+  pub loan_balance:Money,
+  pub status:LoanDefaultStatus,
   pub default_at:NaiveDateTime,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
@@ -65,11 +205,13 @@ pub struct LoanDafault {
 } 
 
 #[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
 pub struct LoanDafaultReclaim {
-  pub id:i64,
+  pub id:Option<i64>,
+  pub loan_dafault_id:i64,
   pub quarantee_type:String,
   pub quaranter_id:i64,
-  pub amount_quaranteed:f64,
+  pub amount_quaranteed:Money,
   pub chama_id:Option<i64>,
   pub default_at:NaiveDateTime,
   pub created_at:NaiveDateTime,
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use sqlx::prelude::FromRow;
+
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MeetingStatus {
+   SCHEDULED,
+   // Attendance has been taken
+   HELD,
+   CANCELLED
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct Meeting {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub title:String,
+    pub venue:Option<String>,
+    pub scheduled_at:NaiveDateTime,
+    pub status:MeetingStatus,
+    pub minutes:Option<String>,
+    pub minutes_recorded_by:Option<i64>,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct MeetingAgendaItem {
+    pub id:Option<i64>,
+    pub meeting_id:i64,
+    pub position:i32,
+    pub title:String,
+    pub description:Option<String>,
+    pub created_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttendanceStatus {
+   PRESENT,
+   // Sent an apology the chama accepted, never fined
+   EXCUSED,
+   ABSENT
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct MeetingAttendance {
+    pub id:Option<i64>,
+    pub meeting_id:i64,
+    pub user_id:i64,
+    pub status:AttendanceStatus,
+    pub recorded_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+/// The kinds of chama records a resolution can authorise.
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolutionRecordEnum {
+   MEMBER,
+   LOAN,
+   LOANLIMIT,
+   LOANREPAYMENTLIMIT,
+   LOANAPPROVALSETTING,
+   LOANDEFAULTSETTING,
+   GUARANTEESETTING,
+   CONTRIBUTIONSCHEDULE,
+   PENALTYRULE,
+   ROTATION,
+   DIVIDENDRUN
+}
+
+impl ResolutionRecordEnum {
+    /// The table the linked record lives in. Every one of them has a chama_id.
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            ResolutionRecordEnum::MEMBER => "chama_member",
+            ResolutionRecordEnum::LOAN => "loan_request",
+            ResolutionRecordEnum::LOANLIMIT => "chama_loan_limit",
+            ResolutionRecordEnum::LOANREPAYMENTLIMIT => "chama_loan_repayment_limit",
+            ResolutionRecordEnum::LOANAPPROVALSETTING => "chama_loan_approval_setting",
+            ResolutionRecordEnum::LOANDEFAULTSETTING => "chama_loan_default_setting",
+            ResolutionRecordEnum::GUARANTEESETTING => "chama_loan_quarantee_setting",
+            ResolutionRecordEnum::CONTRIBUTIONSCHEDULE => "contribution_schedule",
+            ResolutionRecordEnum::PENALTYRULE => "penalty_rule",
+            ResolutionRecordEnum::ROTATION => "rotation",
+            ResolutionRecordEnum::DIVIDENDRUN => "dividend_run",
+        }
+    }
+}
+
+/// A decision taken at a meeting, optionally linked to the record it
+/// authorised, e.g. the membership of an admitted member.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct MeetingResolution {
+    pub id:Option<i64>,
+    pub meeting_id:i64,
+    pub chama_id:i64,
+    pub agenda_item_id:Option<i64>,
+    pub resolution:String,
+    pub record_type:Option<ResolutionRecordEnum>,
+    pub record_id:Option<i64>,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberExitStatus {
+   PENDING,
+   // Paid out and membership ended
+   SETTLED,
+   REJECTED,
+   // Withdrawn by the member
+   CANCELLED
+}
+
+/// A member leaving a chama. The amounts are those of the statement the exit
+/// was settled on, or of the latest statement while it is pending.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct MemberExit {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub user_id:i64,
+    pub status:MemberExitStatus,
+    pub reason:String,
+    pub savings:Money,
+    pub loan_balance:Money,
+    pub fine_balance:Money,
+    // Savings less loans and fines, paid to the member's wallet
+    pub net_amount:Money,
+    pub requested_by:i64,
+    pub reviewed_by:Option<i64>,
+    pub review_note:Option<String>,
+    pub settled_at:Option<NaiveDateTime>,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
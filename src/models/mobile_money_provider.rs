@@ -1,15 +1,21 @@
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
+use sqlx::prelude::FromRow;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
  pub enum AccountTypeEnum {
+  // Rows set up before KANNEL, sent through Kannel the same way
   SMPP,
   HTTP,
-  PARLAYX
+  PARLAYX,
+  // SMS payment requests through a Kannel gateway's HTTP sendsms interface
+  KANNEL
 
 }
 
 #[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, FromRow)]
 pub struct MobileMoneyprovider {
   pub id:i32,
   pub name:String,
@@ -17,11 +23,16 @@ pub struct MobileMoneyprovider {
   pub description:String,
   pub account_type:AccountTypeEnum,
   pub password:String,
+  // ACTIVE providers are loaded into the registry
   pub status:String,
   pub api_username:String,
   pub api_password:String,
   pub smsc_id:i32,
+  // Base URL of the provider's API or SMS gateway
+  pub endpoint_url:String,
+  // Lower is tried first
+  pub priority:i32,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
   pub created_by:i32,
-} 
+}
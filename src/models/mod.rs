@@ -7,4 +7,15 @@ pub mod bill;
 pub mod credit;
 pub mod income_range;
 pub mod transaction;
+pub mod loan;
 pub mod email;
+pub mod ledger;
+pub mod statement;
+pub mod contribution;
+pub mod rotation;
+pub mod fine;
+pub mod dividend;
+pub mod meeting;
+pub mod proposal;
+pub mod chama_wallet;
+pub mod member_exit;
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+/// What a proposal changes once it passes.
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProposalTypeEnum {
+   // A decision with no effect in the app
+   GENERAL,
+   // Sets a new chama loan limit from `amount` and `centage_member_savings`
+   LOANLIMIT,
+   // Moves `target_user_id` to position `position_id`
+   POSITIONCHANGE,
+   // Opens the exit of `target_user_id` for settlement
+   MEMBERREMOVAL
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProposalStatus {
+   OPEN,
+   PASSED,
+   REJECTED,
+   // Too few members voted for the result to count
+   NOQUORUM,
+   CANCELLED
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct Proposal {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub proposal_type:ProposalTypeEnum,
+    pub title:String,
+    pub description:Option<String>,
+    pub target_user_id:Option<i64>,
+    pub position_id:Option<i64>,
+    pub amount:Option<Money>,
+    pub centage_member_savings:Option<f64>,
+    pub opens_at:NaiveDateTime,
+    pub closes_at:NaiveDateTime,
+    // Percentage of active members who must vote
+    pub quorum_rate:f64,
+    // Percentage of yes and no votes that must be yes
+    pub threshold_rate:f64,
+    pub status:ProposalStatus,
+    // Active members when voting closed
+    pub eligible_voters:Option<i32>,
+    pub yes_votes:i32,
+    pub no_votes:i32,
+    pub abstain_votes:i32,
+    pub applied_at:Option<NaiveDateTime>,
+    // Why a passed proposal's effect could not be applied
+    pub apply_error:Option<String>,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoteChoiceEnum {
+   YES,
+   NO,
+   ABSTAIN
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ProposalVote {
+    pub id:Option<i64>,
+    pub proposal_id:i64,
+    pub user_id:i64,
+    pub choice:VoteChoiceEnum,
+    pub created_at:NaiveDateTime,
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationMethodEnum {
+   // Members are paid in the order the officials set
+   FIXED,
+   // The order is drawn at random when the rotation starts
+   RANDOM,
+   // Each cycle's pot goes to the member offering the chama the most of it
+   BIDDING
+}
+
+/// What happens when the member whose turn it is owes contributions.
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationArrearsPolicyEnum {
+   // The next member without arrears is paid, the skipped member keeps their place
+   SKIP,
+   // The member is paid, less what they owe
+   DEDUCT,
+   // The pot is held until the member clears their arrears
+   HOLD
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationStatus {
+   ACTIVE,
+   COMPLETED,
+   CANCELLED
+}
+
+/// A merry-go-round: every member receives the pooled contributions of one
+/// cycle, starting with cycle `start_cycle_no`.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct Rotation {
+    pub id:Option<i64>,
+    pub chama_id:i64,
+    pub method:RotationMethodEnum,
+    pub arrears_policy:RotationArrearsPolicyEnum,
+    pub start_cycle_no:i32,
+    pub status:RotationStatus,
+    pub created_by:i64,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationSlotStatus {
+   WAITING,
+   PAID
+}
+
+/// A member's place in the rotation.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct RotationSlot {
+    pub id:Option<i64>,
+    pub rotation_id:i64,
+    pub user_id:i64,
+    pub position:i32,
+    pub status:RotationSlotStatus,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationPayoutStatus {
+   // Waiting for the member to clear their arrears, or for the chama's
+   // savings to cover the pot, see hold_reason
+   HELD,
+   PAID
+}
+
+/// One cycle's pot and who received it.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct RotationPayout {
+    pub id:Option<i64>,
+    pub rotation_id:i64,
+    pub cycle_id:i64,
+    pub user_id:i64,
+    // Contributions paid during the cycle
+    pub pot:Money,
+    // Given up by the winning bidder, stays with the chama
+    pub bid:Money,
+    // Kept back against the member's arrears
+    pub withheld:Money,
+    // Credited to the member's wallet
+    pub amount:Money,
+    pub status:RotationPayoutStatus,
+    pub hold_reason:Option<String>,
+    pub paid_at:Option<NaiveDateTime>,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
+
+/// What one member put into a payout's pot, fixed when the pot is settled.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct RotationPayoutShare {
+    pub id:Option<i64>,
+    pub rotation_payout_id:i64,
+    pub user_id:i64,
+    pub amount:Money,
+    pub created_at:NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct RotationBid {
+    pub id:Option<i64>,
+    pub rotation_id:i64,
+    pub cycle_id:i64,
+    pub user_id:i64,
+    pub amount:Money,
+    pub created_at:NaiveDateTime,
+    pub updated_at:NaiveDateTime,
+}
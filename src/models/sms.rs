@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use sqlx::prelude::FromRow;
 
+use crate::money::Money;
+
 
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
@@ -10,7 +12,7 @@ pub struct Message {
   pub user_id:i32,
   pub date:NaiveDateTime,
   pub organization:String,
-  pub amount:f64,
+  pub amount:Money,
   pub transaction_type:String,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::prelude::FromRow;
+
+use crate::money::Money;
+
+
+/// A statement file a provider gave us, imported for reconciliation.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ProviderStatement {
+  pub id:Option<i64>,
+  pub gateway:String,
+  pub period_start:NaiveDate,
+  pub period_end:NaiveDate,
+  pub line_count:i32,
+  pub imported_by:i64,
+  pub created_at:NaiveDateTime,
+}
+
+/// One transaction on a provider statement.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, FromRow)]
+pub struct ProviderStatementLine {
+  pub id:Option<i64>,
+  pub provider_statement_id:i64,
+  pub receipt_no:String,
+  pub completed_at:NaiveDateTime,
+  pub details:String,
+  pub transaction_status:String,
+  pub paid_in:Money,
+  pub withdrawn:Money,
+  // Phone number and name of the customer, "2547XXXXXXXX - JANE DOE"
+  pub other_party:String,
+  // The account number the customer quoted
+  pub account_no:String,
+  pub created_at:NaiveDateTime,
+}
@@ -2,53 +2,93 @@ use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use sqlx::prelude::FromRow;
 
+use crate::money::Money;
+
 
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
 pub struct Transaction {
   pub id:i64,
   pub user_id:i64,
-  pub amount:f64,
+  pub amount:Money,
   pub transaction_type:String,
   pub trx_time:NaiveDateTime,
   pub cr_dr:i8,
   pub reference:String,
   pub status:String,
   pub narration:String,
-  pub pre_balance:f64,
-  pub balance:f64,
+  pub pre_balance:Money,
+  pub balance:Money,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
   pub created_by:i32,
 } 
 
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepositStatus {
+   PENDING,
+   COMPLETED,
+   FAILED,
+   // Paid but not matched to a user or chama, waiting for staff to reassign
+   SUSPENSE,
+   // Credited, then taken back because the provider never received the money
+   REVERSED
+}
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
 pub struct Deposit {
-  pub id:i64,
-  pub user_id:i64,
+  pub id:Option<i64>,
+  // The paying member, None for unmatched payments
+  pub user_id:Option<i64>,
+  // Set when the payment was made to a chama's account
+  pub chama_id:Option<i64>,
   pub paying_number:String,
   pub bill_no:String,
   pub account_no:String,
   pub vendor_reference:String,
+  pub amount:Money,
+  pub receipt_no:Option<String>,
+  pub gateway:String,
   pub bill_date:NaiveDateTime,
-  pub status:String,
+  pub status:DepositStatus,
   pub created_at:NaiveDateTime,
   pub updated_at:NaiveDateTime,
   pub created_by:i64,
 } 
 
 
+#[derive(Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WithdrawalStatus {
+   // Funds held, not yet accepted by the provider
+   PENDING,
+   // Accepted by the provider, waiting for the result
+   SUBMITTED,
+   COMPLETED,
+   // Payout failed or timed out and the hold went back to the wallet it came from
+   REVERSED
+}
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
 pub struct Withdrawal {
-  pub id:i64,         
-  pub user_id:i64,    
-  pub amount:f64,     
-  pub msisdn:String,     
-  pub reference:String,  
-  pub narration:String,  
-  pub status:String,     
+  pub id:Option<i64>,
+  pub user_id:i64,
+  // Set when the money comes out of a chama's wallet, user_id then being
+  // the official who asked for it
+  pub chama_id:Option<i64>,
+  pub amount:Money,
+  pub msisdn:String,
+  // Ours, sent to the provider as the originator conversation id
+  pub reference:String,
+  pub narration:String,
+  pub status:WithdrawalStatus,
+  pub gateway:String,
+  pub conversation_id:Option<String>,
+  pub receipt_no:Option<String>,
+  pub result_desc:Option<String>,
   pub created_at: NaiveDateTime,
   pub updated_at :NaiveDateTime
 }
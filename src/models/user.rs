@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use sqlx::prelude::FromRow;
 
+use crate::money::Money;
+
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, FromRow)]
 pub struct UserCreditorganization {
@@ -73,7 +75,7 @@ pub struct IncomeHistory {
 pub struct AcccountBalance {
     pub id: i32,
     pub user_id: i32,
-    pub balance: f64,
+    pub balance: Money,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime
 }
\ No newline at end of file
@@ -0,0 +1,456 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::decode::Decode;
+use sqlx::encode::{Encode, IsNull};
+use sqlx::error::BoxDynError;
+use sqlx::mysql::{MySql, MySqlTypeInfo, MySqlValueRef};
+
+
+/// Currencies amounts can be held in. Everything is stored in the platform
+/// currency, KES, and the database columns carry no currency of their own.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Currency {
+    #[default]
+    KES
+}
+
+impl Currency {
+    /// Digits after the decimal point in the currency's minor unit.
+    pub fn scale(&self) -> u32 {
+        match self {
+            Currency::KES => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoneyError {
+    // More decimal places than the currency's minor unit, e.g. 10.005 KES
+    Precision(String),
+    CurrencyMismatch(Currency, Currency),
+    Overflow,
+    Invalid(String),
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::Precision(amount) => write!(f, "{} has more decimal places than the currency allows", amount),
+            MoneyError::CurrencyMismatch(a, b) => write!(f, "Cannot combine {:?} with {:?}", a, b),
+            MoneyError::Overflow => write!(f, "Amount is too large"),
+            MoneyError::Invalid(amount) => write!(f, "{} is not a valid amount", amount),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// An exact amount of money held as a whole number of minor units (cents).
+///
+/// Adding and subtracting amounts is always exact. Anything that can produce
+/// fractions of a cent, like taking a percentage or splitting an amount, either
+/// says how it rounds in its name or hands back every cent of the original so
+/// nothing is ever rounded away silently. Amounts with more decimals than the
+/// currency allows are rejected rather than rounded when parsed, deserialized
+/// or read from the database.
+///
+/// In JSON an amount is a string with exactly the currency's decimals, e.g.
+/// `"1250.50"`, so clients never see binary floating point. Numbers are also
+/// accepted on input, and anything beyond `REQUEST_LIMIT` is refused.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Money {
+    minor:i64,
+    currency:Currency,
+}
+
+impl Money {
+    pub const ZERO:Money = Money { minor:0, currency:Currency::KES };
+
+    /// The largest amount, either way, taken from a request: a trillion
+    /// shillings. Amounts this far inside the i64 range can be added up and
+    /// subtracted by the services without overflowing, so a bad amount in a
+    /// request is refused instead of crashing the arithmetic.
+    pub const REQUEST_LIMIT:Money = Money { minor:100_000_000_000_000, currency:Currency::KES };
+
+    pub fn from_minor(minor:i64) -> Self {
+        Money { minor, currency:Currency::default() }
+    }
+
+    pub fn from_major(major:i64) -> Self {
+        Money::from_minor(major * 10_i64.pow(Currency::default().scale()))
+    }
+
+    /// Exact conversion, failing when the amount has sub-cent digits.
+    pub fn from_decimal(amount:Decimal) -> Result<Self, MoneyError> {
+        let currency = Currency::default();
+        let minor = amount
+            .checked_mul(Decimal::from(10_i64.pow(currency.scale())))
+            .ok_or(MoneyError::Overflow)?;
+        if !minor.fract().is_zero() {
+            return Err(MoneyError::Precision(amount.normalize().to_string()));
+        }
+        let minor = minor.to_i64().ok_or(MoneyError::Overflow)?;
+        Ok(Money { minor, currency })
+    }
+
+    pub fn to_decimal(&self) -> Decimal {
+        Decimal::new(self.minor, self.currency.scale())
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        self.minor
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor == 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.minor > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor < 0
+    }
+
+    pub fn abs(&self) -> Self {
+        Money { minor:self.minor.abs(), ..*self }
+    }
+
+    pub fn checked_add(&self, other:Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        let minor = self.minor.checked_add(other.minor).ok_or(MoneyError::Overflow)?;
+        Ok(Money { minor, ..*self })
+    }
+
+    pub fn checked_sub(&self, other:Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        let minor = self.minor.checked_sub(other.minor).ok_or(MoneyError::Overflow)?;
+        Ok(Money { minor, ..*self })
+    }
+
+    /// `rate` percent of the amount, only when it comes to whole cents.
+    pub fn percent(&self, rate:Decimal) -> Result<Money, MoneyError> {
+        Money::from_decimal(self.to_decimal() * rate / Decimal::ONE_HUNDRED)
+    }
+
+    /// `rate` percent of the amount rounded half away from zero to the cent,
+    /// for charges like interest and fees where a rounded figure is expected.
+    pub fn percent_rounded(&self, rate:Decimal) -> Money {
+        self.mul_rounded(rate / Decimal::ONE_HUNDRED)
+    }
+
+    /// The amount scaled by `factor`, rounded half away from zero to the cent.
+    pub fn mul_rounded(&self, factor:Decimal) -> Money {
+        let value = (self.to_decimal() * factor)
+            .round_dp_with_strategy(self.currency.scale(), RoundingStrategy::MidpointAwayFromZero);
+        Money::from_decimal(value).expect("Rounded amount fits the currency scale")
+    }
+
+    /// Splits the amount into `parts` near-equal amounts that add back up to it
+    /// exactly, the leftover cents going to the last parts.
+    pub fn split(&self, parts:u32) -> Vec<Money> {
+        if parts == 0 {
+            return Vec::new();
+        }
+        let parts = parts as i64;
+        let base = self.minor.div_euclid(parts);
+        let leftover = self.minor.rem_euclid(parts);
+        (0..parts)
+            .map(|n| Money { minor:base + if n >= parts - leftover { 1 } else { 0 }, ..*self })
+            .collect()
+    }
+
+    /// Shares the amount out in proportion to `weights`. The shares always add
+    /// up to the amount exactly; cents lost to rounding go to the largest
+    /// remainders. All weights zero gives every share zero.
+    pub fn allocate(&self, weights:&[Money]) -> Vec<Money> {
+        let total:i128 = weights.iter().map(|w| w.minor.max(0) as i128).sum();
+        if total == 0 {
+            return vec![Money { minor:0, ..*self }; weights.len()];
+        }
+
+        let mut shares: Vec<(i64, i128)> = weights.iter()
+            .map(|w| {
+                let exact = self.minor as i128 * w.minor.max(0) as i128;
+                ((exact / total) as i64, exact % total)
+            })
+            .collect();
+
+        let mut leftover = self.minor - shares.iter().map(|s| s.0).sum::<i64>();
+        let mut order: Vec<usize> = (0..shares.len()).collect();
+        order.sort_by(|a, b| shares[*b].1.cmp(&shares[*a].1).then(a.cmp(b)));
+        for index in order {
+            if leftover <= 0 {
+                break;
+            }
+            if weights[index].minor > 0 {
+                shares[index].0 += 1;
+                leftover -= 1;
+            }
+        }
+
+        shares.into_iter().map(|(minor, _)| Money { minor, ..*self }).collect()
+    }
+
+    /// Fails when the amount is beyond what a request may carry.
+    pub fn within_request_limit(self) -> Result<Money, MoneyError> {
+        if self.minor.unsigned_abs() > Money::REQUEST_LIMIT.minor.unsigned_abs() {
+            return Err(MoneyError::Overflow);
+        }
+        Ok(self)
+    }
+
+    fn same_currency(&self, other:&Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch(self.currency, other.currency));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_decimal())
+    }
+}
+
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    fn from_str(s:&str) -> Result<Self, Self::Err> {
+        let amount = Decimal::from_str(s.trim()).map_err(|_| MoneyError::Invalid(s.to_string()))?;
+        Money::from_decimal(amount)
+    }
+}
+
+impl TryFrom<f64> for Money {
+    type Error = MoneyError;
+
+    /// Uses the shortest decimal that prints as the float, so 0.1 is ten cents,
+    /// and still refuses anything finer than a cent.
+    fn try_from(amount:f64) -> Result<Self, Self::Error> {
+        if !amount.is_finite() {
+            return Err(MoneyError::Invalid(amount.to_string()));
+        }
+        Money::from_str(&amount.to_string())
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other:Money) -> Money {
+        self.checked_add(other).expect("Money addition")
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other:Money) -> Money {
+        self.checked_sub(other).expect("Money subtraction")
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other:Money) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other:Money) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money { minor:-self.minor, ..self }
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter:I) -> Money {
+        iter.fold(Money::ZERO, |total, amount| total + amount)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter:I) -> Money {
+        iter.fold(Money::ZERO, |total, amount| total + *amount)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer:S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+struct MoneyVisitor;
+
+impl Visitor<'_> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an amount with at most two decimal places")
+    }
+
+    fn visit_str<E: de::Error>(self, value:&str) -> Result<Money, E> {
+        Money::from_str(value).and_then(Money::within_request_limit).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, value:i64) -> Result<Money, E> {
+        Money::from_decimal(Decimal::from(value)).and_then(Money::within_request_limit).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, value:u64) -> Result<Money, E> {
+        Money::from_decimal(Decimal::from(value)).and_then(Money::within_request_limit).map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, value:f64) -> Result<Money, E> {
+        Money::try_from(value).and_then(Money::within_request_limit).map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer:D) -> Result<Money, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+/// Rates and percentages are configured as floats; this turns one into the
+/// decimal it was written as so money maths stays exact.
+pub fn rate(value:f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or_default()
+}
+
+impl sqlx::Type<MySql> for Money {
+    fn type_info() -> MySqlTypeInfo {
+        <Decimal as sqlx::Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty:&MySqlTypeInfo) -> bool {
+        <Decimal as sqlx::Type<MySql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, MySql> for Money {
+    fn encode_by_ref(&self, buf:&mut Vec<u8>) -> Result<IsNull, BoxDynError> {
+        <Decimal as Encode<MySql>>::encode_by_ref(&self.to_decimal(), buf)
+    }
+}
+
+impl Decode<'_, MySql> for Money {
+    fn decode(value:MySqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let amount = <Decimal as Decode<MySql>>::decode(value)?;
+        Ok(Money::from_decimal(amount)?)
+    }
+}
+
+/// Shorthand for amounts in tests, `Money::kes("10.50")`.
+#[cfg(test)]
+impl Money {
+    pub fn kes(amount:&str) -> Money {
+        Money::from_str(amount).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_decimal_rejects_sub_cent_amounts() {
+        assert_eq!(Money::from_decimal(Decimal::new(1000, 2)), Ok(Money::from_minor(1000)));
+        assert_eq!(Money::from_decimal(Decimal::new(10005, 3)), Err(MoneyError::Precision("10.005".to_string())));
+        // Trailing zeros beyond the cent are not extra precision
+        assert_eq!(Money::from_decimal(Decimal::new(10500, 3)), Ok(Money::from_minor(1050)));
+        assert_eq!(Money::from_decimal(Decimal::MAX), Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn split_hands_leftover_cents_to_the_last_parts() {
+        assert_eq!(Money::kes("100.00").split(3), vec![Money::kes("33.33"), Money::kes("33.33"), Money::kes("33.34")]);
+        assert_eq!(Money::kes("0.05").split(3), vec![Money::kes("0.01"), Money::kes("0.02"), Money::kes("0.02")]);
+        assert_eq!(Money::kes("-1.00").split(3), vec![Money::kes("-0.34"), Money::kes("-0.33"), Money::kes("-0.33")]);
+        assert!(Money::kes("10.00").split(0).is_empty());
+    }
+
+    #[test]
+    fn allocate_keeps_every_cent() {
+        let shares = Money::kes("100.00").allocate(&[Money::kes("1.00"), Money::kes("1.00"), Money::kes("1.00")]);
+        assert_eq!(shares.iter().sum::<Money>(), Money::kes("100.00"));
+        assert_eq!(shares, vec![Money::kes("33.34"), Money::kes("33.33"), Money::kes("33.33")]);
+
+        let shares = Money::kes("10.00").allocate(&[Money::kes("300.00"), Money::kes("100.00")]);
+        assert_eq!(shares, vec![Money::kes("7.50"), Money::kes("2.50")]);
+    }
+
+    #[test]
+    fn allocate_skips_zero_and_negative_weights() {
+        let shares = Money::kes("0.03").allocate(&[Money::kes("1.00"), Money::ZERO, Money::kes("-5.00"), Money::kes("1.00")]);
+        assert_eq!(shares, vec![Money::kes("0.02"), Money::ZERO, Money::ZERO, Money::kes("0.01")]);
+        assert_eq!(Money::kes("5.00").allocate(&[Money::ZERO, Money::ZERO]), vec![Money::ZERO, Money::ZERO]);
+    }
+
+    #[test]
+    fn percent_rounded_rounds_half_away_from_zero() {
+        assert_eq!(Money::kes("10.05").percent_rounded(Decimal::from(50)), Money::kes("5.03"));
+        assert_eq!(Money::kes("-10.05").percent_rounded(Decimal::from(50)), Money::kes("-5.03"));
+        assert_eq!(Money::kes("1000.00").percent_rounded(rate(1.5)), Money::kes("15.00"));
+        assert_eq!(Money::kes("0.01").percent_rounded(Decimal::from(10)), Money::ZERO);
+    }
+
+    #[test]
+    fn percent_refuses_fractions_of_a_cent() {
+        assert_eq!(Money::kes("100.00").percent(Decimal::from(15)), Ok(Money::kes("15.00")));
+        assert!(matches!(Money::kes("0.01").percent(Decimal::from(50)), Err(MoneyError::Precision(_))));
+    }
+
+    #[test]
+    fn serde_round_trips_through_a_string() {
+        let amount = Money::kes("1250.50");
+        let json = serde_json::to_string(&amount).unwrap();
+        assert_eq!(json, "\"1250.50\"");
+        assert_eq!(serde_json::from_str::<Money>(&json).unwrap(), amount);
+
+        assert_eq!(serde_json::from_str::<Money>("12").unwrap(), Money::kes("12.00"));
+        assert_eq!(serde_json::from_str::<Money>("0.1").unwrap(), Money::kes("0.10"));
+        assert!(serde_json::from_str::<Money>("\"10.005\"").is_err());
+        assert!(serde_json::from_str::<Money>("\"ten\"").is_err());
+    }
+
+    #[test]
+    fn requests_cannot_carry_amounts_past_the_limit() {
+        assert_eq!(serde_json::from_str::<Money>("\"1000000000000.00\"").unwrap(), Money::REQUEST_LIMIT);
+        assert!(serde_json::from_str::<Money>("\"1000000000000.01\"").is_err());
+        assert!(serde_json::from_str::<Money>("\"-1000000000000.01\"").is_err());
+        assert!(serde_json::from_str::<Money>("9223372036854775807").is_err());
+        assert!(serde_json::from_str::<Money>("\"79228162514264337593543950335\"").is_err());
+    }
+
+    #[test]
+    fn checked_arithmetic_reports_overflow() {
+        let max = Money::from_minor(i64::MAX);
+        assert_eq!(max.checked_add(Money::from_minor(1)), Err(MoneyError::Overflow));
+        assert_eq!(Money::from_minor(i64::MIN).checked_sub(Money::from_minor(1)), Err(MoneyError::Overflow));
+        assert_eq!(Money::kes("1.50").checked_add(Money::kes("2.25")), Ok(Money::kes("3.75")));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::api::{user, auth, chama}; 
+use crate::api::{user, auth, chama, loan, ledger, payment}; 
 
 use axum::Router;
 
@@ -7,4 +7,7 @@ pub fn routes() -> Router {
         .merge(user::routes())
         .merge(auth::routes())
         .merge(chama::routes())
+        .merge(loan::routes())
+        .merge(ledger::routes())
+        .merge(payment::routes())
 }
\ No newline at end of file
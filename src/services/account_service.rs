@@ -1,8 +1,12 @@
-use sqlx::MySqlPool;
+use sqlx::{MySqlConnection, MySqlPool};
 use tracing::{info, error};
 use sqlx::Row;
 
-pub async fn get_user_balance(pool:&MySqlPool, user_id:&i64) -> Result<(Option<f64>, Option<f64>), sqlx::Error>{
+use crate::error::AppError;
+use crate::money::Money;
+use crate::utils;
+
+pub async fn get_user_balance(pool:&MySqlPool, user_id:&i64) -> Result<(Option<Money>, Option<Money>), sqlx::Error>{
 
    
     let results = sqlx::query(
@@ -18,7 +22,112 @@ pub async fn get_user_balance(pool:&MySqlPool, user_id:&i64) -> Result<(Option<f
             Ok((row.try_get("account_balance")?, row.try_get("credit_balance")?))
     } else {
         error!("Could not find account balance information");
-        Ok((Some(0 as f64), Some(0 as f64)))
+        Ok((Some(Money::ZERO), Some(Money::ZERO)))
     }
         
-}
\ No newline at end of file
+}
+
+pub const CREDIT: i8 = 1;
+pub const DEBIT: i8 = 0;
+
+/// Mirrors a ledger movement on a user's wallet into account_balance and the
+/// `transaction` history. Only called from `ledger_service::post_journal`, which
+/// holds the ledger lock; the balance row is locked as well so direct readers
+/// never see a half-applied posting.
+pub async fn apply_wallet_posting(
+    conn:&mut MySqlConnection,
+    user_id:&i64,
+    delta:Money,
+    transaction_type:&str,
+    reference:&str,
+    narration:&str) -> Result<Money, AppError> {
+
+    let current: Option<(Money,)> = sqlx::query_as(
+        "SELECT balance FROM account_balance WHERE user_id = ? FOR UPDATE"
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let now_eat = utils::now_eat();
+    let pre_balance = match current {
+        Some((balance,)) => balance,
+        None => {
+            sqlx::query("INSERT INTO account_balance (user_id, balance, created_at, updated_at) VALUES (?, 0, ?, ?)")
+                .bind(user_id)
+                .bind(now_eat)
+                .bind(now_eat)
+                .execute(&mut *conn)
+                .await?;
+            Money::ZERO
+        }
+    };
+
+    let balance = pre_balance + delta;
+    if balance.is_negative() {
+        return Err(AppError::BadRequest(format!("Insufficient balance, available {}", pre_balance)));
+    }
+
+    sqlx::query("UPDATE account_balance SET balance = ?, updated_at = ? WHERE user_id = ?")
+        .bind(balance)
+        .bind(now_eat)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let cr_dr = if delta.is_negative() { DEBIT } else { CREDIT };
+    sqlx::query(
+        "INSERT INTO `transaction` (user_id, amount, transaction_type, trx_time, cr_dr, reference, status,
+            narration, pre_balance, balance, created_at, updated_at, created_by)
+        VALUES (?, ?, ?, ?, ?, ?, 'SUCCESS', ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(user_id)
+    .bind(delta.abs())
+    .bind(transaction_type)
+    .bind(now_eat)
+    .bind(cr_dr)
+    .bind(reference)
+    .bind(narration)
+    .bind(pre_balance)
+    .bind(balance)
+    .bind(now_eat)
+    .bind(now_eat)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    info!("Posted {} {} to account of user {}", delta.abs(), if cr_dr == CREDIT { "CR" } else { "DR" }, user_id);
+    Ok(balance)
+}
+
+/// Mirrors a movement on a user's loan receivable into credit_balance.
+pub async fn apply_credit_posting(conn:&mut MySqlConnection, user_id:&i64, delta:Money) -> Result<Money, AppError> {
+    let current: Option<(Money,)> = sqlx::query_as(
+        "SELECT balance FROM credit_balance WHERE user_id = ? FOR UPDATE"
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let now_eat = utils::now_eat();
+    let balance = current.map(|c| c.0).unwrap_or(Money::ZERO) + delta;
+
+    if current.is_some() {
+        sqlx::query("UPDATE credit_balance SET balance = ?, updated_at = ? WHERE user_id = ?")
+            .bind(balance)
+            .bind(now_eat)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    } else {
+        sqlx::query("INSERT INTO credit_balance (user_id, balance, created_at, updated_at) VALUES (?, ?, ?, ?)")
+            .bind(user_id)
+            .bind(balance)
+            .bind(now_eat)
+            .bind(now_eat)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(balance)
+}
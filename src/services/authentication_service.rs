@@ -15,6 +15,7 @@ use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
 use crate::models::auth;
 use crate::utils;
 use crate::services::email_service;
+use crate::error::AppError;
 
 
 
@@ -442,3 +443,10 @@ pub fn validate_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error>
     Ok(token_data.claims)
 }
 
+/// Back-office operations are limited to staff accounts.
+pub async fn require_staff(pool:&MySqlPool, user_id:&str) -> Result<(), AppError> {
+    match get_auth_user_by_id(pool, user_id).await {
+        Some(user) if user.is_staff == 1 => Ok(()),
+        _ => Err(AppError::Forbidden("User not allowed to perform this action".to_string())),
+    }
+}
@@ -1,6 +1,7 @@
 use std::env;
 
 use chrono::{Duration, NaiveDateTime};
+use sqlx::Executor;
 use sqlx::MySql;
 use sqlx::MySqlPool;
 use sqlx::Transaction;
@@ -8,14 +9,21 @@ use tracing::{info, error};
 use crate::dtos::chama::ChamaLoadLimitDto;
 use crate::dtos::chama::ChamaLoanQuaranteeSettingDto;
 use crate::dtos::chama::ChamaLoanApproverDto;
+use crate::dtos::chama::ChamaLoanApprovalSettingDto;
+use crate::dtos::chama::ChamaLoanDefaultSettingDto;
 use crate::dtos::chama::ChamaLoanRepaymentLimitDto;
 use crate::dtos::chama::ChamaMemberDetailDto;
 use crate::dtos::chama::ChamaPositionDetailDto;
 use crate::dtos::chama::ChamaPositionDto;
+use crate::dtos::chama::ChamaPositionPermissionDto;
 use crate::models::chama;
-use crate::dtos::chama::{ChamaDto, ChamaMemberApproveDto};
+use crate::enums::InterestMethodEnum;
+use crate::error::AppError;
+use crate::dtos::chama::{ChamaDto, ChamaInviteDto, ChamaInviteUrlDto, ChamaMemberUpdateDto};
 use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
 use crate::repositories::data_repository;
+use crate::services::{authentication_service, contribution_service, exit_service, loan_service, membership_service};
+use crate::money::Money;
 use crate::utils;
 use sqlx::Row;
 
@@ -73,12 +81,19 @@ pub async fn create_new_chama(pool:&MySqlPool, user_id:&str, payload:&ChamaDto)
     };
     let chama_id = result.unwrap();
 
+    let chairperson = match seed_positions(pool, &mut tx, &chama_id, chama.created_by).await {
+        Ok(position_id) => position_id,
+        Err(e) => {
+            error!("Failed to create chama positions: {:?}", e);
+            return 0;
+        }
+    };
     let chama_member =  chama::ChamaMember {
         id:None,                 
         user_id:user_id.parse::<i64>().unwrap(),         
         chama_id:chama_id.clone(),
-        position:1,
-        contribution_amount:0.0,           
+        position:chairperson,
+        contribution_amount:Money::ZERO,         
         created_at: now_eat,
         updated_at:now_eat,
         created_by:user_id.parse::<i64>().unwrap(),
@@ -146,136 +161,195 @@ pub async fn update_chama(pool:&MySqlPool, user_id:&str, payload:&ChamaDto) -> i
 
 }
 
-pub async fn get_invite(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> String{
-
-    let chama_repository = data_repository::DataRepository::<chama::Chama> {
-        pool,
-        table_name: "chama",
-        pk_column: "id",
-        phantom: std::marker::PhantomData,
-    };
-
-    let result:i64 = match chama_repository.record_exists(&"id", &chama_id.to_string()).await {
-        Ok(exists) => { if exists { 1 } else { -1 } },
-        Err(_) => 0,
+/// Creates an invite link for the chama. Invites are single use and last 7
+/// days unless asked otherwise, and can be restricted to one phone number or
+/// email.
+pub async fn create_invite(pool:&MySqlPool, user_id:&str, payload:&ChamaInviteDto) -> Result<ChamaInviteUrlDto, AppError> {
+    require_permission(pool, user_id, &payload.chama_id, chama::ChamaPermission::MANAGEMEMBERS).await?;
+    let invited_by = loan_service::parse_user_id(user_id)?;
+
+    let invited_phone = match payload.phone_number.as_deref() {
+        Some(phone) => match utils::is_valid_phone(phone) {
+            Some(phone) => Some(phone),
+            None => return Err(AppError::BadRequest("Phone number not valid".to_string())),
+        },
+        None => None,
     };
-
-    if result == -1 || result == 0{
-        error!("Failed to create new chama: {:?}", result);
-        return result.to_string()
+    let invited_email = payload.email.as_deref().map(|email| email.trim().to_lowercase());
+    if invited_email.as_deref().is_some_and(|email| !email.contains('@')) {
+        return Err(AppError::BadRequest("Email not valid".to_string()));
+    }
+    let max_uses = payload.max_uses.unwrap_or(1);
+    if max_uses < 1 {
+        return Err(AppError::BadRequest("An invite must allow at least one use".to_string()));
+    }
+    if (invited_phone.is_some() || invited_email.is_some()) && max_uses > 1 {
+        return Err(AppError::BadRequest("An invite for one person can only be used once".to_string()));
+    }
+    let expiry_days = payload.expiry_days.unwrap_or(7);
+    if !(1..=90).contains(&expiry_days) {
+        return Err(AppError::BadRequest("Invites last between 1 and 90 days".to_string()));
     }
-
-    let chama_invite_repository = data_repository::DataRepository::<chama::ChamaInvite> {
-        pool,
-        table_name: "chama_invite",
-        pk_column: "id",
-        phantom: std::marker::PhantomData,
-    };
 
     let now_eat: NaiveDateTime = utils::now_eat();
     let hash_string = utils::generate_invite_hash_64();
-
-    let chama_invite =  chama::ChamaInvite {
+    let chama_invite = chama::ChamaInvite {
         id:None,
-        chama_id:chama_id.clone(),
-        invited_by:user_id.parse::<i64>().unwrap(),
-        expiry_date: now_eat + Duration::days(7),
+        chama_id:payload.chama_id,
+        invited_by,
+        expiry_date: now_eat + Duration::days(expiry_days),
         invite_hash:hash_string.clone(),
+        invited_phone,
+        invited_email,
+        max_uses,
+        use_count:0,
+        revoked_at:None,
+        revoked_by:None,
         created_at: now_eat,
         updated_at:now_eat,
-       
     };
-    let result = chama_invite_repository.insert(&chama_invite).await;
-    if result.is_err() {
-        error!("Failed to create new chama: {:?}", result);
-        return "0".to_string();
-    }
-
-    let vurl:String = env::var("CHAMA_INVITE_URL").unwrap();
-    format!("{}/invite/{}", vurl, hash_string)
-}
-
-
-pub async fn join_chama(pool:&MySqlPool, user_id:&str, invite_hash:&String) -> i64{
-
     let chama_invite_repository = data_repository::DataRepository::<chama::ChamaInvite> {
         pool,
         table_name: "chama_invite",
         pk_column: "id",
         phantom: std::marker::PhantomData,
     };
+    let invite_id = chama_invite_repository.insert(&chama_invite).await?;
+
+    info!("Invite {} to chama {} created by {}", invite_id, payload.chama_id, invited_by);
+    let vurl:String = env::var("CHAMA_INVITE_URL").unwrap_or_default();
+    Ok(ChamaInviteUrlDto {
+        invite_id,
+        url:format!("{}/invite/{}", vurl, hash_string),
+    })
+}
 
-    let result = match chama_invite_repository.find_by(&"invite_hash", &invite_hash).await {
-        Ok(mut result) => result.pop(), 
-        Err(_) => None,
-    };
+/// Every invite of the chama with how often it has been used.
+pub async fn get_invites(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<chama::ChamaInvite>, AppError> {
+    require_permission(pool, user_id, chama_id, chama::ChamaPermission::MANAGEMEMBERS).await?;
 
-    if let Some(chama_invite) = result {
+    let invites = sqlx::query_as::<_, chama::ChamaInvite>("SELECT * FROM chama_invite WHERE chama_id = ? ORDER BY id DESC")
+        .bind(chama_id)
+        .fetch_all(pool)
+        .await?;
 
-        let chama_member_repository = data_repository::DataRepository::<chama::ChamaMember> {
-            pool,
-            table_name: "chama_member",
-            pk_column: "id",
-            phantom: std::marker::PhantomData,
-        };
+    Ok(invites)
+}
 
-        let now_eat: NaiveDateTime = utils::now_eat();
-        let chama_member =  chama::ChamaMember {
-            id:None,                 
-            user_id:user_id.parse::<i64>().unwrap(),         
-            chama_id:chama_invite.chama_id,
-            position:0,
-            contribution_amount:0.0,           
-            created_at: now_eat,
-            updated_at:now_eat,
-            created_by:chama_invite.invited_by,
-            is_active:0
-        };
-        let result = chama_member_repository.insert(&chama_member).await;
-        if result.is_err() {
-            error!("Failed to create new chama: {:?}", result);
-            return 0;
-        }
-        return result.unwrap() as i64;
+pub async fn revoke_invite(pool:&MySqlPool, user_id:&str, invite_id:&i64) -> Result<chama::ChamaInvite, AppError> {
+    let invite = sqlx::query_as::<_, chama::ChamaInvite>("SELECT * FROM chama_invite WHERE id = ?")
+        .bind(invite_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such invite".to_string()))?;
+    require_permission(pool, user_id, &invite.chama_id, chama::ChamaPermission::MANAGEMEMBERS).await?;
+    let revoked_by = loan_service::parse_user_id(user_id)?;
+
+    let now_eat = utils::now_eat();
+    let updated = sqlx::query("UPDATE chama_invite SET revoked_at = ?, revoked_by = ?, updated_at = ? WHERE id = ? AND revoked_at IS NULL")
+        .bind(now_eat)
+        .bind(revoked_by)
+        .bind(now_eat)
+        .bind(invite_id)
+        .execute(pool)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict("Invite is already revoked".to_string()));
     }
-    return 0;
+
+    info!("Invite {} to chama {} revoked by {}", invite_id, invite.chama_id, revoked_by);
+    Ok(chama::ChamaInvite { revoked_at:Some(now_eat), revoked_by:Some(revoked_by), updated_at:now_eat, ..invite })
 }
 
-pub async fn approve_member(pool:&MySqlPool, user_id:&str, payload:&ChamaMemberApproveDto) -> i64{
+/// Joins the chama through an invite, queueing a membership request for an
+/// admin to approve. The invite must be live, have uses left and, when it was
+/// sent to someone, be used by them.
+pub async fn join_chama(pool:&MySqlPool, user_id:&str, invite_hash:&String) -> Result<chama::ChamaMembershipRequest, AppError> {
+    let Some(user) = authentication_service::get_auth_user_by_id(pool, user_id).await else {
+        return Err(AppError::Forbidden("Invalid user".to_string()));
+    };
+    let member_id = loan_service::parse_user_id(user_id)?;
 
-    let chama_member_repository = data_repository::DataRepository::<chama::ChamaMember> {
-        pool,
-        table_name: "chama_member",
-        pk_column: "id",
-        phantom: std::marker::PhantomData,
+    let mut tx = pool.begin().await?;
+    let chama_invite = sqlx::query_as::<_, chama::ChamaInvite>("SELECT * FROM chama_invite WHERE invite_hash = ? FOR UPDATE")
+        .bind(invite_hash)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such invite".to_string()))?;
+
+    let now_eat: NaiveDateTime = utils::now_eat();
+    if chama_invite.revoked_at.is_some() {
+        return Err(AppError::Conflict("This invite has been revoked".to_string()));
+    }
+    if chama_invite.expiry_date < now_eat {
+        return Err(AppError::BadRequest(format!("This invite expired on {}", chama_invite.expiry_date.format("%d/%m/%Y"))));
+    }
+    if chama_invite.use_count >= chama_invite.max_uses {
+        return Err(AppError::Conflict("This invite has already been used".to_string()));
+    }
+    let phone_matches = chama_invite.invited_phone.as_ref()
+        .is_none_or(|phone| utils::is_valid_phone(&user.username).as_ref() == Some(phone));
+    let email_matches = chama_invite.invited_email.as_ref()
+        .is_none_or(|email| user.email.as_ref().map(|e| e.trim().to_lowercase()).as_ref() == Some(email));
+    if !phone_matches || !email_matches {
+        return Err(AppError::Forbidden("This invite was sent to someone else".to_string()));
+    }
+    let Some(invite_id) = chama_invite.id else {
+        return Err(AppError::NotFound("No such invite".to_string()));
     };
+    let request = membership_service::open_request(pool, &mut tx, &chama_invite.chama_id, &member_id, &invite_id).await?;
+    sqlx::query("UPDATE chama_invite SET use_count = use_count + 1, updated_at = ? WHERE id = ?")
+        .bind(now_eat)
+        .bind(chama_invite.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("User {} asked to join chama {} with invite {}", member_id, chama_invite.chama_id, invite_id);
+    Ok(request)
+}
 
-    match chama_member_repository.find_by(&"id", &payload.user_id.to_string()).await {
-        Ok(mut result) => {
-            if let Some(mut chama_member) = result.pop() {
-                chama_member.is_active = payload.is_active.clone();
-                chama_member.position = payload.position.clone();
-                chama_member.contribution_amount = payload.contribution_amount.clone();
-                chama_member.updated_at = utils::now_eat();
-                chama_member.created_by = user_id.parse::<i64>().unwrap();
-                let result = chama_member_repository.update_by_id(&payload.user_id, &chama_member).await;
-                if result.is_err() {
-                    error!("Failed to approve member: {:?}", result);
-                    return 0;
-                }
-                return result.unwrap() as i64;
-            }
-            0
-        },
-        Err(_) => {
-            error!("Failed to find chama member by id");
-            0
-        },
+/// Changes an active member's position and agreed contribution. People join
+/// through a membership request and leave through an exit, so this never
+/// makes anyone a member or ends a membership. See
+/// `require_assignable_position` for who may move whom.
+pub async fn update_member(pool:&MySqlPool, user_id:&str, payload:&ChamaMemberUpdateDto) -> Result<chama::ChamaMember, AppError> {
+    if payload.contribution_amount.is_negative() {
+        return Err(AppError::BadRequest("Contribution amount cannot be negative".to_string()));
+    }
+
+    let mut member = sqlx::query_as::<_, chama::ChamaMember>("SELECT * FROM chama_member WHERE id = ? AND chama_id = ?")
+        .bind(payload.member_id)
+        .bind(payload.chama_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such member".to_string()))?;
+    if member.is_active != 1 {
+        return Err(AppError::Conflict("Only active members can be updated, new members join through a membership request".to_string()));
+    }
+    // Both the position they leave and the one they take must be the caller's to give
+    require_assignable_position(pool, user_id, &payload.chama_id, &member.position).await?;
+    require_assignable_position(pool, user_id, &payload.chama_id, &payload.position).await?;
+
+    member.position = payload.position;
+    member.contribution_amount = payload.contribution_amount;
+    member.updated_at = utils::now_eat();
+    let updated = sqlx::query("UPDATE chama_member SET position = ?, contribution_amount = ?, updated_at = ? WHERE id = ? AND is_active = 1")
+        .bind(member.position)
+        .bind(member.contribution_amount)
+        .bind(member.updated_at)
+        .bind(payload.member_id)
+        .execute(pool)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict("Only active members can be updated, new members join through a membership request".to_string()));
     }
 
+    info!("Member {} of chama {} moved to position {} contributing {}", payload.member_id, payload.chama_id, member.position, member.contribution_amount);
+    Ok(member)
 }
 
-pub async fn remove_member(pool:&MySqlPool, member_id:&i64) -> i64{
+pub async fn remove_member(pool:&MySqlPool, chama_id:&i64, member_id:&i64) -> i64{
 
     let chama_member_repository = data_repository::DataRepository::<chama::ChamaMember> {
         pool,
@@ -287,6 +361,14 @@ pub async fn remove_member(pool:&MySqlPool, member_id:&i64) -> i64{
     match chama_member_repository.find_by(&"id", &member_id.to_string()).await {
         Ok(mut result) => {
             if let Some(mut chama_member) = result.pop() {
+                if chama_member.chama_id != *chama_id {
+                    return -1;
+                }
+                match exit_service::has_open_balances(pool, &chama_member.chama_id, &chama_member.user_id).await {
+                    Ok(false) => {},
+                    Ok(true) => return -2,
+                    Err(_) => return 0,
+                }
                 chama_member.is_active = 0;
                 chama_member.updated_at = utils::now_eat();
                 let result = chama_member_repository.update_by_id(&member_id, &chama_member).await;
@@ -306,31 +388,38 @@ pub async fn remove_member(pool:&MySqlPool, member_id:&i64) -> i64{
 
 }
 
-pub async fn get_chama_roles(pool:&MySqlPool, user_id:&str, chama_id:&str) -> Result<Vec<String>, sqlx::Error> {
+/// Moves an active member to another position in the chama.
+pub async fn set_member_position(pool:&MySqlPool, chama_id:&i64, user_id:&i64, position_id:&i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("UPDATE chama_member SET position = ?, updated_at = ? WHERE chama_id = ? AND user_id = ? AND is_active = 1")
+        .bind(position_id)
+        .bind(utils::now_eat())
+        .bind(chama_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
 
-    let results = sqlx::query(
-        "select name from  chama_member cm 
-        inner join chama_position cp on cm.position = cp.id 
-        inner join auth_group ag on cp.role_id = ag.id 
-        where cm.chama_id = ? and cm.user_id = ?"
+/// The permissions a member holds in a chama through their position there.
+pub async fn get_member_permissions(pool:&MySqlPool, chama_id:&i64, user_id:&i64) -> Result<Vec<chama::ChamaPermission>, sqlx::Error> {
+    let permissions: Vec<(chama::ChamaPermission,)> = sqlx::query_as(
+        "SELECT DISTINCT pp.permission FROM chama_member cm
+        INNER JOIN chama_position cp ON cp.id = cm.position AND cp.chama_id = cm.chama_id
+        INNER JOIN chama_position_permission pp ON pp.position_id = cp.id
+        WHERE cm.chama_id = ? AND cm.user_id = ? AND cm.is_active = 1"
     )
     .bind(chama_id)
     .bind(user_id)
     .fetch_all(pool)
     .await?;
 
-    if !results.is_empty() {
-        let mut roles = Vec::new();
-        for row in results {
-            roles.push(row.try_get::<String, _>("name")?);
-        }
-        info!("Found results from query");
-        return Ok(roles);
-    } else {
-        error!("Could not find user roles");
-        return Ok(Vec::new());
-    }
+    Ok(permissions.into_iter().map(|(permission,)| permission).collect())
+}
 
+pub async fn get_my_permissions(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<chama::ChamaPermission>, AppError> {
+    let member_id = loan_service::parse_user_id(user_id)?;
+    Ok(get_member_permissions(pool, chama_id, &member_id).await?)
 }
 
 pub async fn get_members(pool:&MySqlPool, user_id:&str, chama_id:&str) -> Result<Vec<ChamaMemberDetailDto>, sqlx::Error> {
@@ -359,7 +448,7 @@ pub async fn get_members(pool:&MySqlPool, user_id:&str, chama_id:&str) -> Result
                 first_name: row.try_get::<String, _>("first_name").unwrap(),
                 last_name: row.try_get::<String, _>("last_name").unwrap(),
                 position: row.try_get::<String, _>("chama_position").unwrap(),
-                contribution_amount: row.try_get::<f64, _>("contribution_amount").unwrap(),
+                contribution_amount: row.try_get::<Money, _>("contribution_amount").unwrap(),
                 is_active: row.try_get::<i8, _>("is_active").unwrap(),
                 phone_number: row.try_get::<String, _>("username").unwrap(),
                 email: Some(row.try_get::<String, _>("email").unwrap()),
@@ -383,6 +472,7 @@ pub async fn get_loan_approvers(pool:&MySqlPool, user_id:&str, chama_id:&str) ->
         inner join auth_user au on au.id = cm.user_id 
         inner join chama_position cp on cp.id = cm.position 
         inner join chama_loan_approver cla on cp.id = cla.approver_position_id
+            and cla.chama_id = cm.chama_id and cla.is_active = 1
         where cm.chama_id=? and 
         exists (select 1 from chama_member where user_id = ?)"
     )
@@ -401,7 +491,7 @@ pub async fn get_loan_approvers(pool:&MySqlPool, user_id:&str, chama_id:&str) ->
                 first_name: row.try_get::<String, _>("first_name").unwrap(),
                 last_name: row.try_get::<String, _>("last_name").unwrap(),
                 position: row.try_get::<String, _>("chama_position").unwrap(),
-                contribution_amount: row.try_get::<f64, _>("contribution_amount").unwrap(),
+                contribution_amount: row.try_get::<Money, _>("contribution_amount").unwrap(),
                 is_active: row.try_get::<i8, _>("is_active").unwrap(),
                 phone_number: row.try_get::<String, _>("username").unwrap(),
                 email: Some(row.try_get::<String, _>("email").unwrap()),
@@ -416,33 +506,36 @@ pub async fn get_loan_approvers(pool:&MySqlPool, user_id:&str, chama_id:&str) ->
     }
 }
 
-pub async fn get_chama_positions(pool:&MySqlPool) -> Result<Vec<ChamaPositionDetailDto>, sqlx::Error> {
+async fn get_position_permissions(pool:&MySqlPool, position_id:&i64) -> Result<Vec<chama::ChamaPermission>, sqlx::Error> {
+    let permissions: Vec<(chama::ChamaPermission,)> = sqlx::query_as(
+        "SELECT permission FROM chama_position_permission WHERE position_id = ? ORDER BY id"
+    )
+    .bind(position_id)
+    .fetch_all(pool)
+    .await?;
 
-    let results = sqlx::query(
-        "select cp.id, cp.chama_position, au.name
-        from chama_position cp
-        inner join auth_group au on cp.role_id = ag.id"
+    Ok(permissions.into_iter().map(|(permission,)| permission).collect())
+}
+
+pub async fn get_chama_positions(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<ChamaPositionDetailDto>, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let positions = sqlx::query_as::<_, chama::ChamaPosition>(
+        "SELECT * FROM chama_position WHERE chama_id = ? ORDER BY id"
     )
+    .bind(chama_id)
     .fetch_all(pool)
     .await?;
 
-    if !results.is_empty() {
-        let mut positions: Vec<ChamaPositionDetailDto> = Vec::new();
-        for row in results {
-            let pos = ChamaPositionDetailDto {
-                id: Some(row.try_get::<i64, _>("id").unwrap()),
-                position: row.try_get::<String, _>("chama_position").unwrap(),
-                role: row.try_get::<String, _>("name").unwrap()
-                
-            };
-            positions.push(pos);
-        }
-        info!("Found results from query");
-        return Ok(positions);
-    } else {
-        error!("Could not find user roles");
-        return Ok(Vec::new());
+    let mut details = Vec::with_capacity(positions.len());
+    for position in positions {
+        let permissions = match position.id {
+            Some(position_id) => get_position_permissions(pool, &position_id).await?,
+            None => Vec::new(),
+        };
+        details.push(ChamaPositionDetailDto { id:position.id, position:position.chama_position, permissions });
     }
+    Ok(details)
 }
 
 
@@ -483,6 +576,16 @@ pub async fn add_loan_approver(pool:&MySqlPool, user_id:&str, payload:&ChamaLoan
         phantom: std::marker::PhantomData,
     };
 
+    let (position,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chama_position WHERE id = ? AND chama_id = ?")
+        .bind(payload.approver_position_id)
+        .bind(payload.chama_id)
+        .fetch_one(pool)
+        .await?;
+    if position == 0 {
+        error!("Position {} is not one of chama {}", payload.approver_position_id, payload.chama_id);
+        return Ok(0);
+    }
+
     let now_eat: NaiveDateTime = utils::now_eat();
     let chama_loan_approver =  chama::ChamaLoanApprover {
         id:None,                 
@@ -567,7 +670,10 @@ pub async fn add_loan_repayment_limit(pool:&MySqlPool, payload:&ChamaLoanRepayme
         id:None,                 
         chama_id:payload.chama_id.clone(),
         max_repayment_months:payload.max_repayment_months.clone(),
-        repayment_frequency:payload.repayment_frequency.clone(),          
+        repayment_frequency:payload.repayment_frequency.to_uppercase(),          
+        interest_rate:payload.interest_rate.unwrap_or(0.0),
+        interest_method:payload.interest_method.unwrap_or(InterestMethodEnum::FLAT),
+        processing_fee_centage:payload.processing_fee_centage.unwrap_or(0.0),
         created_at: now_eat,
         updated_at:now_eat
         
@@ -580,7 +686,14 @@ pub async fn add_loan_repayment_limit(pool:&MySqlPool, payload:&ChamaLoanRepayme
     Ok(result.unwrap() as i64)
 }
 
-pub async fn create_chama_position(pool:&MySqlPool, payload:&ChamaPositionDto) -> Result<i64, sqlx::Error> {
+async fn insert_position(
+    pool:&MySqlPool,
+    tx:&mut Transaction<'_, MySql>,
+    chama_id:&i64,
+    name:&str,
+    permissions:&[chama::ChamaPermission],
+    created_by:i64) -> Result<i64, sqlx::Error> {
+
     let chama_position_repository = data_repository::DataRepository::<chama::ChamaPosition> {
         pool,
         table_name: "chama_position",
@@ -591,21 +704,234 @@ pub async fn create_chama_position(pool:&MySqlPool, payload:&ChamaPositionDto) -
     let now_eat: NaiveDateTime = utils::now_eat();
     let chama_position =  chama::ChamaPosition {
         id:None,                 
-        chama_position:payload.chama_position.clone(),
-        role_id:payload.role_id.clone(),           
+        chama_id:*chama_id,
+        chama_position:name.to_string(),
+        created_by,
         created_at: now_eat,
         updated_at:now_eat
     };
-    let result = chama_position_repository.insert(&chama_position).await;
-    if result.is_err() {
-        error!("Failed to create new chama loan approver: {:?}", result);
-        return Err(result.err().unwrap());
+    let position_id = chama_position_repository.insert_trx(tx, &chama_position).await?;
+    insert_position_permissions(pool, tx, &position_id, permissions).await?;
+    Ok(position_id)
+}
+
+async fn insert_position_permissions(
+    pool:&MySqlPool,
+    tx:&mut Transaction<'_, MySql>,
+    position_id:&i64,
+    permissions:&[chama::ChamaPermission]) -> Result<(), sqlx::Error> {
+
+    let permission_repository = data_repository::DataRepository::<chama::ChamaPositionPermission> {
+        pool,
+        table_name: "chama_position_permission",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let now_eat = utils::now_eat();
+    for permission in chama::ChamaPermission::ALL.iter().filter(|p| permissions.contains(p)) {
+        let position_permission = chama::ChamaPositionPermission {
+            id:None,
+            position_id:*position_id,
+            permission:*permission,
+            created_at:now_eat,
+        };
+        permission_repository.insert_trx(tx, &position_permission).await?;
     }
-    Ok(result.unwrap() as i64)
+    Ok(())
 }
 
+/// Gives a new chama the usual officials and returns the chairperson's
+/// position, which its founder takes. The chama can rename or rework them.
+async fn seed_positions(pool:&MySqlPool, tx:&mut Transaction<'_, MySql>, chama_id:&i64, created_by:i64) -> Result<i64, sqlx::Error> {
+    use chama::ChamaPermission::*;
+
+    let chairperson = insert_position(pool, tx, chama_id, "Chairperson", &chama::ChamaPermission::ALL, created_by).await?;
+    insert_position(pool, tx, chama_id, "Treasurer", &[MANAGELOANS, MANAGECONTRIBUTIONS, MANAGEFINES, MANAGEWALLET], created_by).await?;
+    insert_position(pool, tx, chama_id, "Secretary", &[MANAGEMEMBERS, MANAGEMEETINGS], created_by).await?;
+    insert_position(pool, tx, chama_id, "Member", &[], created_by).await?;
+    Ok(chairperson)
+}
 
-pub async fn remove_from_loan_approver(pool:&MySqlPool, position_id:&i64) -> Result<i64, sqlx::Error> {
+pub async fn create_chama_position(pool:&MySqlPool, user_id:&str, payload:&ChamaPositionDto) -> Result<ChamaPositionDetailDto, AppError> {
+    require_permission(pool, user_id, &payload.chama_id, chama::ChamaPermission::MANAGECHAMA).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+    let name = payload.chama_position.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Position name is required".to_string()));
+    }
+
+    let (existing,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chama_position WHERE chama_id = ? AND chama_position = ?")
+        .bind(payload.chama_id)
+        .bind(name)
+        .fetch_one(pool)
+        .await?;
+    if existing > 0 {
+        return Err(AppError::Conflict(format!("The chama already has a {}", name)));
+    }
+
+    let mut tx = pool.begin().await?;
+    let position_id = insert_position(pool, &mut tx, &payload.chama_id, name, &payload.permissions, created_by).await?;
+    tx.commit().await?;
+
+    info!("Position {} ({}) created in chama {} by {}", position_id, name, payload.chama_id, created_by);
+    Ok(ChamaPositionDetailDto {
+        id:Some(position_id),
+        position:name.to_string(),
+        permissions:get_position_permissions(pool, &position_id).await?,
+    })
+}
+
+/// Replaces what a position may do. Refused when it would leave no active
+/// member able to manage the chama's positions.
+pub async fn set_position_permissions(pool:&MySqlPool, user_id:&str, position_id:&i64, payload:&ChamaPositionPermissionDto) -> Result<ChamaPositionDetailDto, AppError> {
+    let position = sqlx::query_as::<_, chama::ChamaPosition>("SELECT * FROM chama_position WHERE id = ?")
+        .bind(position_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such position".to_string()))?;
+    require_permission(pool, user_id, &position.chama_id, chama::ChamaPermission::MANAGECHAMA).await?;
+
+    let mut tx = pool.begin().await?;
+    // Changes to one chama's positions are made one at a time
+    sqlx::query("SELECT id FROM chama WHERE id = ? FOR UPDATE")
+        .bind(position.chama_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM chama_position_permission WHERE position_id = ?")
+        .bind(position_id)
+        .execute(&mut *tx)
+        .await?;
+    insert_position_permissions(pool, &mut tx, position_id, &payload.permissions).await?;
+
+    let (managers,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM chama_member cm
+        INNER JOIN chama_position_permission pp ON pp.position_id = cm.position
+        WHERE cm.chama_id = ? AND cm.is_active = 1 AND pp.permission = ?"
+    )
+    .bind(position.chama_id)
+    .bind(chama::ChamaPermission::MANAGECHAMA)
+    .fetch_one(&mut *tx)
+    .await?;
+    if managers == 0 {
+        return Err(AppError::Conflict("No active member would be left able to manage the chama".to_string()));
+    }
+    tx.commit().await?;
+
+    info!("Permissions of position {} in chama {} set to {:?}", position_id, position.chama_id, payload.permissions);
+    Ok(ChamaPositionDetailDto {
+        id:position.id,
+        position:position.chama_position,
+        permissions:get_position_permissions(pool, position_id).await?,
+    })
+}
+
+/// Positions used to be shared by every chama, with what a holder could do
+/// coming from the `auth_group` of the position's `role_id`. This gives each
+/// chama that has no positions of its own a copy of every shared position and
+/// points its members, loan approvers, withdrawal signatories and proposals at
+/// the copies. Copies of positions in the `chama-admin` group carry every
+/// permission and the rest carry none, so whoever ran a chama still does. A
+/// chama where that leaves no active member able to manage it gets an
+/// Administrator position holding every permission, taken by its founder.
+///
+/// Runs at startup and does nothing once every chama has its own positions.
+/// The shared positions are left in place for the record.
+pub async fn backfill_positions(pool:&MySqlPool) -> Result<u64, AppError> {
+    let shared: Vec<(i64, String, Option<String>)> = sqlx::query_as(
+        "SELECT cp.id, cp.chama_position, ag.name FROM chama_position cp
+        LEFT JOIN auth_group ag ON ag.id = cp.role_id
+        WHERE cp.chama_id IS NULL OR cp.chama_id = 0
+        ORDER BY cp.id"
+    )
+    .fetch_all(pool)
+    .await?;
+    if shared.is_empty() {
+        return Ok(0);
+    }
+
+    let chamas: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT c.id, c.created_by FROM chama c
+        WHERE NOT EXISTS (SELECT 1 FROM chama_position cp WHERE cp.chama_id = c.id)
+        ORDER BY c.id"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut migrated = 0;
+    for (chama_id, founder) in chamas {
+        let mut tx = pool.begin().await?;
+        // Another instance starting at the same time may have got here first
+        sqlx::query("SELECT id FROM chama WHERE id = ? FOR UPDATE")
+            .bind(chama_id)
+            .execute(&mut *tx)
+            .await?;
+        let (own,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chama_position WHERE chama_id = ?")
+            .bind(chama_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if own > 0 {
+            continue;
+        }
+
+        for (shared_id, name, group) in &shared {
+            let permissions: &[chama::ChamaPermission] = if group.as_deref() == Some("chama-admin") {
+                &chama::ChamaPermission::ALL
+            } else {
+                &[]
+            };
+            let position_id = insert_position(pool, &mut tx, &chama_id, name, permissions, founder).await?;
+
+            for statement in [
+                "UPDATE chama_member SET position = ? WHERE chama_id = ? AND position = ?",
+                "UPDATE chama_loan_approver SET approver_position_id = ? WHERE chama_id = ? AND approver_position_id = ?",
+                "UPDATE loan_request_approval a INNER JOIN loan_request lr ON lr.id = a.load_request_id
+                    SET a.approver_position_id = ? WHERE lr.chama_id = ? AND a.approver_position_id = ?",
+                "UPDATE chama_withdrawal_signatory SET position_id = ? WHERE chama_id = ? AND position_id = ?",
+                "UPDATE chama_withdrawal_signature s INNER JOIN chama_withdrawal w ON w.id = s.chama_withdrawal_id
+                    SET s.position_id = ? WHERE w.chama_id = ? AND s.position_id = ?",
+                "UPDATE proposal SET position_id = ? WHERE chama_id = ? AND position_id = ?",
+            ] {
+                sqlx::query(statement)
+                    .bind(position_id)
+                    .bind(chama_id)
+                    .bind(shared_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        let (managers,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM chama_member cm
+            INNER JOIN chama_position_permission pp ON pp.position_id = cm.position
+            WHERE cm.chama_id = ? AND cm.is_active = 1 AND pp.permission = ?"
+        )
+        .bind(chama_id)
+        .bind(chama::ChamaPermission::MANAGECHAMA)
+        .fetch_one(&mut *tx)
+        .await?;
+        if managers == 0 {
+            let position_id = insert_position(pool, &mut tx, &chama_id, "Administrator", &chama::ChamaPermission::ALL, founder).await?;
+            let moved = sqlx::query("UPDATE chama_member SET position = ?, updated_at = ? WHERE chama_id = ? AND user_id = ? AND is_active = 1")
+                .bind(position_id)
+                .bind(utils::now_eat())
+                .bind(chama_id)
+                .bind(founder)
+                .execute(&mut *tx)
+                .await?;
+            if moved.rows_affected() == 0 {
+                error!("Chama {} has no admin and its founder {} has left, no one can manage it", chama_id, founder);
+            }
+        }
+
+        tx.commit().await?;
+        info!("Chama {} given its own copy of {} shared positions", chama_id, shared.len());
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+
+pub async fn remove_from_loan_approver(pool:&MySqlPool, chama_id:&i64, position_id:&i64) -> Result<i64, sqlx::Error> {
     let chama_loan_approver_repository = data_repository::DataRepository::<chama::ChamaLoanApprover> {
         pool,
         table_name: "chama_loan_approver",
@@ -615,7 +941,7 @@ pub async fn remove_from_loan_approver(pool:&MySqlPool, position_id:&i64) -> Res
 
     let now_eat: NaiveDateTime = utils::now_eat();
     let result = match chama_loan_approver_repository.find_by(&"id", &position_id.to_string()).await {
-        Ok(mut result) => result.pop(), 
+        Ok(mut result) => result.pop().filter(|approver| approver.chama_id == *chama_id), 
         Err(_) => None,
     };
     if result.is_none() {
@@ -636,7 +962,7 @@ pub async fn remove_from_loan_approver(pool:&MySqlPool, position_id:&i64) -> Res
 }
 
 
-pub async fn remove_guarantee_setting(pool:&MySqlPool, g_id:&i64) -> Result<i64, sqlx::Error> {
+pub async fn remove_guarantee_setting(pool:&MySqlPool, chama_id:&i64, g_id:&i64) -> Result<i64, sqlx::Error> {
     let chama_loan_guarantee_repository = data_repository::DataRepository::<chama::ChamaLoanQuaranteeSetting> {
         pool,
         table_name: "chama_loan_quarantee_setting",
@@ -646,7 +972,7 @@ pub async fn remove_guarantee_setting(pool:&MySqlPool, g_id:&i64) -> Result<i64,
 
     let now_eat: NaiveDateTime = utils::now_eat();
     let result = match chama_loan_guarantee_repository.find_by(&"id", &g_id.to_string()).await {
-        Ok(mut result) => result.pop(), 
+        Ok(mut result) => result.pop().filter(|setting| setting.chama_id == *chama_id), 
         Err(_) => None,
     };
     if result.is_none() {
@@ -664,4 +990,243 @@ pub async fn remove_guarantee_setting(pool:&MySqlPool, g_id:&i64) -> Result<i64,
         return Err(result.err().unwrap());
     }
     Ok(result.unwrap() as i64)
-}
\ No newline at end of file
+}
+pub async fn is_active_member(pool:&MySqlPool, chama_id:&i64, user_id:&i64) -> Result<bool, sqlx::Error> {
+    let exists: (i64,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM chama_member WHERE chama_id = ? AND user_id = ? AND is_active = 1)"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists.0 == 1)
+}
+
+/// Users with an active membership in the chama.
+pub async fn active_member_ids(pool:&MySqlPool, chama_id:&i64) -> Result<Vec<i64>, sqlx::Error> {
+    let members: Vec<(i64,)> = sqlx::query_as(
+        "SELECT DISTINCT user_id FROM chama_member WHERE chama_id = ? AND is_active = 1"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(members.into_iter().map(|m| m.0).collect())
+}
+
+pub async fn add_loan_approval_setting(pool:&MySqlPool, payload:&ChamaLoanApprovalSettingDto) -> Result<i64, sqlx::Error> {
+    let chama_loan_approval_setting_repository = data_repository::DataRepository::<chama::ChamaLoanApprovalSetting> {
+        pool,
+        table_name: "chama_loan_approval_setting",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let mut tx: Transaction<'_, MySql> = pool.begin().await?;
+    let now_eat: NaiveDateTime = utils::now_eat();
+
+    // Only one quorum applies at a time, retire the previous one
+    sqlx::query("UPDATE chama_loan_approval_setting SET is_active = 0, updated_at = ? WHERE chama_id = ? AND is_active = 1")
+        .bind(now_eat)
+        .bind(payload.chama_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let setting = chama::ChamaLoanApprovalSetting {
+        id:None,
+        chama_id:payload.chama_id,
+        quorum:payload.quorum,
+        created_at: now_eat,
+        updated_at:now_eat,
+        is_active:1
+    };
+    let result = chama_loan_approval_setting_repository.insert_trx(&mut tx, &setting).await;
+    if result.is_err() {
+        error!("Failed to create chama loan approval setting: {:?}", result);
+        return Err(result.err().unwrap());
+    }
+    tx.commit().await?;
+    Ok(result.unwrap())
+}
+
+pub async fn add_loan_default_setting(pool:&MySqlPool, payload:&ChamaLoanDefaultSettingDto) -> Result<i64, sqlx::Error> {
+    let chama_loan_default_setting_repository = data_repository::DataRepository::<chama::ChamaLoanDefaultSetting> {
+        pool,
+        table_name: "chama_loan_default_setting",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let mut tx: Transaction<'_, MySql> = pool.begin().await?;
+    let now_eat: NaiveDateTime = utils::now_eat();
+
+    sqlx::query("UPDATE chama_loan_default_setting SET is_active = 0, updated_at = ? WHERE chama_id = ? AND is_active = 1")
+        .bind(now_eat)
+        .bind(payload.chama_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let setting = chama::ChamaLoanDefaultSetting {
+        id:None,
+        chama_id:payload.chama_id,
+        grace_period_days:payload.grace_period_days,
+        created_at: now_eat,
+        updated_at:now_eat,
+        is_active:1
+    };
+    let result = chama_loan_default_setting_repository.insert_trx(&mut tx, &setting).await;
+    if result.is_err() {
+        error!("Failed to create chama loan default setting: {:?}", result);
+        return Err(result.err().unwrap());
+    }
+    tx.commit().await?;
+    Ok(result.unwrap())
+}
+
+pub async fn get_approver_position_ids(pool:&MySqlPool, chama_id:&i64) -> Result<Vec<i64>, sqlx::Error> {
+    let positions: Vec<(i64,)> = sqlx::query_as(
+        "SELECT DISTINCT approver_position_id FROM chama_loan_approver WHERE chama_id = ? AND is_active = 1"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(positions.into_iter().map(|p| p.0).collect())
+}
+
+/// Number of approver positions that must approve a loan: the configured
+/// quorum when there is one, otherwise every active approver position.
+pub async fn get_required_approvals(pool:&MySqlPool, chama_id:&i64) -> Result<i64, sqlx::Error> {
+    let positions = get_approver_position_ids(pool, chama_id).await?.len() as i64;
+
+    let quorum: Option<(i32,)> = sqlx::query_as(
+        "SELECT quorum FROM chama_loan_approval_setting WHERE chama_id = ? AND is_active = 1
+        ORDER BY id DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match quorum {
+        Some((quorum,)) if quorum > 0 => Ok((quorum as i64).min(positions)),
+        _ => Ok(positions),
+    }
+}
+
+pub async fn get_member_approver_position(pool:&MySqlPool, chama_id:&i64, user_id:&i64) -> Result<Option<i64>, sqlx::Error> {
+    let position: Option<(i64,)> = sqlx::query_as(
+        "SELECT cm.position FROM chama_member cm
+        INNER JOIN chama_loan_approver cla on cla.approver_position_id = cm.position
+            AND cla.chama_id = cm.chama_id AND cla.is_active = 1
+        WHERE cm.chama_id = ? AND cm.user_id = ? AND cm.is_active = 1
+        LIMIT 1"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(position.map(|p| p.0))
+}
+
+pub async fn get_active_loan_limit(pool:&MySqlPool, chama_id:&i64) -> Result<Option<chama::ChamaLoanLimit>, sqlx::Error> {
+    let limit = sqlx::query_as::<_, chama::ChamaLoanLimit>(
+        "SELECT * FROM chama_loan_limit WHERE chama_id = ? AND is_active = 1 ORDER BY id DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(limit)
+}
+
+/// What a member has saved with the chama so far: the contributions they
+/// have actually paid.
+pub async fn get_member_savings<'c, E>(executor:E, chama_id:&i64, user_id:&i64) -> Result<Money, sqlx::Error>
+where
+    E: Executor<'c, Database = MySql>,
+{
+    let savings: (Money,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount), 0) FROM contribution WHERE chama_id = ? AND user_id = ? AND is_active = 1"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(savings.0)
+}
+
+pub async fn get_active_guarantee_setting(pool:&MySqlPool, chama_id:&i64) -> Result<Option<chama::ChamaLoanQuaranteeSetting>, sqlx::Error> {
+    let setting = sqlx::query_as::<_, chama::ChamaLoanQuaranteeSetting>(
+        "SELECT * FROM chama_loan_quarantee_setting WHERE chama_id = ? AND is_active = 1 ORDER BY id DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(setting)
+}
+
+pub async fn get_active_repayment_limit(pool:&MySqlPool, chama_id:&i64) -> Result<Option<chama::ChamaLoanRepaymentLimit>, sqlx::Error> {
+    let limit = sqlx::query_as::<_, chama::ChamaLoanRepaymentLimit>(
+        "SELECT * FROM chama_loan_repayment_limit WHERE chama_id = ? ORDER BY id DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(limit)
+}
+
+/// Fails unless the user may move a member into or out of `position_id`. It
+/// must be one of the chama's positions, and a user who cannot manage the
+/// chama may only hand out positions whose permissions they hold themselves
+/// and which neither sign withdrawals nor approve loans.
+pub async fn require_assignable_position(pool:&MySqlPool, user_id:&str, chama_id:&i64, position_id:&i64) -> Result<(), AppError> {
+    let (position,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chama_position WHERE id = ? AND chama_id = ?")
+        .bind(position_id)
+        .bind(chama_id)
+        .fetch_one(pool)
+        .await?;
+    if position == 0 {
+        return Err(AppError::NotFound("No such position in this chama".to_string()));
+    }
+
+    let member_id = loan_service::parse_user_id(user_id)?;
+    let held = get_member_permissions(pool, chama_id, &member_id).await?;
+    if held.contains(&chama::ChamaPermission::MANAGECHAMA) {
+        return Ok(());
+    }
+
+    let granted = get_position_permissions(pool, position_id).await?;
+    if granted.iter().any(|permission| !held.contains(permission)) {
+        return Err(AppError::Forbidden("Only a member who can manage the chama can assign a position with permissions you do not hold".to_string()));
+    }
+    let (approves,): (i64,) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM chama_withdrawal_signatory WHERE chama_id = ? AND position_id = ? AND is_active = 1)
+            + (SELECT COUNT(*) FROM chama_loan_approver WHERE chama_id = ? AND approver_position_id = ? AND is_active = 1)"
+    )
+    .bind(chama_id)
+    .bind(position_id)
+    .bind(chama_id)
+    .bind(position_id)
+    .fetch_one(pool)
+    .await?;
+    if approves > 0 {
+        return Err(AppError::Forbidden("Only a member who can manage the chama can assign withdrawal signatories or loan approvers".to_string()));
+    }
+    Ok(())
+}
+
+/// Fails unless the user's position in the chama carries `permission`.
+pub async fn require_permission(pool:&MySqlPool, user_id:&str, chama_id:&i64, permission:chama::ChamaPermission) -> Result<(), AppError> {
+    let member_id = loan_service::parse_user_id(user_id)?;
+    let permissions = get_member_permissions(pool, chama_id, &member_id).await?;
+
+    if !permissions.contains(&permission) {
+        return Err(AppError::Forbidden("User not allowed to perform this action".to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,609 @@
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::{info, error};
+
+use crate::dtos::chama_wallet::{
+    ChamaWalletDto,
+    ChamaWithdrawalDetailDto,
+    ChamaWithdrawalRequestDto,
+    ChamaWithdrawalSignDto,
+    ChamaWithdrawalSignatoryDto
+};
+use crate::error::AppError;
+use crate::gateways::MobileMoneyGateway;
+use crate::models::chama::ChamaPermission;
+use crate::models::chama_wallet::{
+    ChamaWithdrawal,
+    ChamaWithdrawalSetting,
+    ChamaWithdrawalSignatory,
+    ChamaWithdrawalSignature,
+    ChamaWithdrawalStatus,
+    SignatureDecision
+};
+use crate::models::transaction::{Withdrawal, WithdrawalStatus};
+use crate::money::Money;
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{authentication_service, chama_service, contribution_service, ledger_service, loan_service, notification_service, withdrawal_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+/// Sets which positions sign chama wallet withdrawals and how many of them
+/// must approve, replacing the previous signatories.
+pub async fn set_signatories(pool:&MySqlPool, user_id:&str, payload:&ChamaWithdrawalSignatoryDto) -> Result<Vec<ChamaWithdrawalSignatory>, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGEWALLET).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+
+    let mut position_ids = payload.position_ids.clone();
+    position_ids.sort();
+    position_ids.dedup();
+    if position_ids.is_empty() {
+        return Err(AppError::BadRequest("At least one signatory position is required".to_string()));
+    }
+    for position_id in &position_ids {
+        let found: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chama_position WHERE id = ? AND chama_id = ?")
+            .bind(position_id)
+            .bind(payload.chama_id)
+            .fetch_one(pool)
+            .await?;
+        if found.0 == 0 {
+            return Err(AppError::NotFound(format!("No such position {} in this chama", position_id)));
+        }
+    }
+    let quorum = payload.quorum.unwrap_or(0);
+    if quorum < 0 || quorum as usize > position_ids.len() {
+        return Err(AppError::BadRequest("Quorum cannot be more than the number of signatory positions".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE chama_withdrawal_signatory SET is_active = 0, updated_at = ? WHERE chama_id = ? AND is_active = 1")
+        .bind(now_eat)
+        .bind(payload.chama_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE chama_withdrawal_setting SET is_active = 0, updated_at = ? WHERE chama_id = ? AND is_active = 1")
+        .bind(now_eat)
+        .bind(payload.chama_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let signatory_repository = data_repository::DataRepository::<ChamaWithdrawalSignatory> {
+        pool,
+        table_name: "chama_withdrawal_signatory",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let mut signatories = Vec::new();
+    for position_id in position_ids {
+        let mut signatory = ChamaWithdrawalSignatory {
+            id:None,
+            chama_id:payload.chama_id,
+            position_id,
+            created_by,
+            created_at:now_eat,
+            updated_at:now_eat,
+            is_active:1,
+        };
+        signatory.id = Some(signatory_repository.insert_trx(&mut tx, &signatory).await?);
+        signatories.push(signatory);
+    }
+    let setting = ChamaWithdrawalSetting {
+        id:None,
+        chama_id:payload.chama_id,
+        quorum,
+        created_at:now_eat,
+        updated_at:now_eat,
+        is_active:1,
+    };
+    let setting_repository = data_repository::DataRepository::<ChamaWithdrawalSetting> {
+        pool,
+        table_name: "chama_withdrawal_setting",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    setting_repository.insert_trx(&mut tx, &setting).await?;
+    tx.commit().await?;
+
+    info!("Chama {} withdrawals now signed by positions {:?}, quorum {}", payload.chama_id,
+        signatories.iter().map(|s| s.position_id).collect::<Vec<_>>(), quorum);
+    Ok(signatories)
+}
+
+pub async fn get_signatories(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<ChamaWithdrawalSignatory>, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let signatories = sqlx::query_as::<_, ChamaWithdrawalSignatory>(
+        "SELECT * FROM chama_withdrawal_signatory WHERE chama_id = ? AND is_active = 1 ORDER BY position_id"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(signatories)
+}
+
+async fn get_signatory_position_ids(pool:&MySqlPool, chama_id:&i64) -> Result<Vec<i64>, sqlx::Error> {
+    let positions: Vec<(i64,)> = sqlx::query_as(
+        "SELECT DISTINCT position_id FROM chama_withdrawal_signatory WHERE chama_id = ? AND is_active = 1"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(positions.into_iter().map(|p| p.0).collect())
+}
+
+/// Number of signatory positions that must approve: the configured quorum
+/// when there is one, otherwise every signatory position.
+async fn get_required_signatures(pool:&MySqlPool, chama_id:&i64) -> Result<i64, sqlx::Error> {
+    let positions = get_signatory_position_ids(pool, chama_id).await?.len() as i64;
+
+    let quorum: Option<(i32,)> = sqlx::query_as(
+        "SELECT quorum FROM chama_withdrawal_setting WHERE chama_id = ? AND is_active = 1 ORDER BY id DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match quorum {
+        Some((quorum,)) if quorum > 0 => Ok((quorum as i64).min(positions)),
+        _ => Ok(positions),
+    }
+}
+
+async fn get_member_signatory_position(pool:&MySqlPool, chama_id:&i64, user_id:&i64) -> Result<Option<i64>, sqlx::Error> {
+    let position: Option<(i64,)> = sqlx::query_as(
+        "SELECT cm.position FROM chama_member cm
+        INNER JOIN chama_withdrawal_signatory cws on cws.position_id = cm.position
+            AND cws.chama_id = cm.chama_id AND cws.is_active = 1
+        WHERE cm.chama_id = ? AND cm.user_id = ? AND cm.is_active = 1
+        LIMIT 1"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(position.map(|p| p.0))
+}
+
+async fn pending_total(pool:&MySqlPool, chama_id:&i64) -> Result<Money, sqlx::Error> {
+    let pending: (Money,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount), 0) FROM chama_withdrawal WHERE chama_id = ? AND status = ?"
+    )
+    .bind(chama_id)
+    .bind(ChamaWithdrawalStatus::PENDING)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(pending.0)
+}
+
+/// The chama's own money: its savings account in the ledger, which every
+/// contribution is credited to.
+pub async fn get_wallet(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<ChamaWalletDto, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let balance = ledger_service::get_balance(pool, &LedgerAccountKey::ChamaSavings(*chama_id)).await?;
+    let pending_withdrawals = pending_total(pool, chama_id).await?;
+
+    Ok(ChamaWalletDto {
+        chama_id:*chama_id,
+        balance,
+        pending_withdrawals,
+        available:balance - pending_withdrawals,
+    })
+}
+
+async fn lock_withdrawal(conn:&mut MySqlConnection, withdrawal_id:&i64) -> Result<ChamaWithdrawal, AppError> {
+    let withdrawal = sqlx::query_as::<_, ChamaWithdrawal>("SELECT * FROM chama_withdrawal WHERE id = ? FOR UPDATE")
+        .bind(withdrawal_id)
+        .fetch_optional(conn)
+        .await?;
+
+    withdrawal.ok_or_else(|| AppError::NotFound("No such chama withdrawal".to_string()))
+}
+
+/// Whether a withdrawal pays the user, either as its member beneficiary or to
+/// the phone number they are registered with.
+async fn is_paid_to(pool:&MySqlPool, beneficiary_user_id:Option<i64>, msisdn:Option<&str>, user_id:&i64) -> bool {
+    if beneficiary_user_id == Some(*user_id) {
+        return true;
+    }
+    let Some(msisdn) = msisdn else {
+        return false;
+    };
+    authentication_service::get_auth_user_by_id(pool, &user_id.to_string()).await
+        .and_then(|user| utils::is_valid_phone(&user.username))
+        .is_some_and(|phone| phone == msisdn)
+}
+
+/// Asks for money out of the chama wallet. Only signatories and chama admins
+/// can ask, and a signatory's request counts as their position's approval
+/// unless the money is for themselves.
+pub async fn request_withdrawal(
+    pool:&MySqlPool,
+    gateway:&dyn MobileMoneyGateway,
+    user_id:&str,
+    payload:&ChamaWithdrawalRequestDto) -> Result<ChamaWithdrawalDetailDto, AppError> {
+
+    let requested_by = loan_service::parse_user_id(user_id)?;
+    let position_id = get_member_signatory_position(pool, &payload.chama_id, &requested_by).await?;
+    if position_id.is_none() {
+        chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGEWALLET).await?;
+    }
+    if get_signatory_position_ids(pool, &payload.chama_id).await?.is_empty() {
+        return Err(AppError::BadRequest("Set who signs chama withdrawals first".to_string()));
+    }
+    if !payload.amount.is_positive() {
+        return Err(AppError::BadRequest("Withdrawal amount must be greater than zero".to_string()));
+    }
+    let narration = payload.narration.trim();
+    if narration.is_empty() {
+        return Err(AppError::BadRequest("Say what the withdrawal is for".to_string()));
+    }
+    let msisdn = match (payload.beneficiary_user_id, payload.phone_number.as_deref()) {
+        (Some(beneficiary_id), None) => {
+            if !chama_service::is_active_member(pool, &payload.chama_id, &beneficiary_id).await? {
+                return Err(AppError::BadRequest(format!("User {} is not a member of the chama", beneficiary_id)));
+            }
+            None
+        }
+        (None, Some(phone)) => match utils::is_valid_phone(phone) {
+            Some(msisdn) => Some(msisdn),
+            None => return Err(AppError::BadRequest("Phone number not valid".to_string())),
+        },
+        _ => return Err(AppError::BadRequest("Give either a member or a phone number to pay".to_string())),
+    };
+    let wallet = get_wallet(pool, user_id, &payload.chama_id).await?;
+    if payload.amount > wallet.available {
+        return Err(AppError::BadRequest(format!("The chama wallet only has {} available", wallet.available)));
+    }
+
+    let now_eat = utils::now_eat();
+    let mut withdrawal = ChamaWithdrawal {
+        id:None,
+        chama_id:payload.chama_id,
+        amount:payload.amount,
+        beneficiary_user_id:payload.beneficiary_user_id,
+        msisdn,
+        narration:narration.to_string(),
+        status:ChamaWithdrawalStatus::PENDING,
+        withdrawal_id:None,
+        result_desc:None,
+        requested_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let withdrawal_repository = data_repository::DataRepository::<ChamaWithdrawal> {
+        pool,
+        table_name: "chama_withdrawal",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let mut tx = pool.begin().await?;
+    let withdrawal_id = withdrawal_repository.insert_trx(&mut tx, &withdrawal).await?;
+    withdrawal.id = Some(withdrawal_id);
+    if let Some(position_id) = position_id
+        && !is_paid_to(pool, withdrawal.beneficiary_user_id, withdrawal.msisdn.as_deref(), &requested_by).await {
+        insert_signature(&mut tx, &withdrawal_id, &requested_by, &position_id, SignatureDecision::APPROVE, None).await?;
+    }
+    let status = settle_signatures(pool, &mut tx, &withdrawal).await?;
+    tx.commit().await?;
+
+    info!("Chama {} withdrawal {} of {} requested by {}", withdrawal.chama_id, withdrawal_id, withdrawal.amount, requested_by);
+    if status == ChamaWithdrawalStatus::PENDING {
+        let message = format!("A withdrawal of {} from the chama wallet for \"{}\" is waiting for your signature.",
+            withdrawal.amount, withdrawal.narration);
+        notify_signatories(pool, &withdrawal.chama_id, &requested_by, &message).await?;
+    }
+    after_settle(pool, gateway, &withdrawal_id, status).await
+}
+
+async fn notify_signatories(pool:&MySqlPool, chama_id:&i64, except:&i64, message:&str) -> Result<(), AppError> {
+    let signatories: Vec<(i64,)> = sqlx::query_as(
+        "SELECT DISTINCT cm.user_id FROM chama_member cm
+        INNER JOIN chama_withdrawal_signatory cws on cws.position_id = cm.position
+            AND cws.chama_id = cm.chama_id AND cws.is_active = 1
+        WHERE cm.chama_id = ? AND cm.is_active = 1"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    for (signatory_id,) in signatories.into_iter().filter(|s| s.0 != *except) {
+        notification_service::notify_user(pool, &signatory_id, "Chama withdrawal", message).await;
+    }
+    Ok(())
+}
+
+async fn insert_signature(
+    conn:&mut MySqlConnection,
+    withdrawal_id:&i64,
+    user_id:&i64,
+    position_id:&i64,
+    decision:SignatureDecision,
+    comment:Option<String>) -> Result<(), AppError> {
+
+    sqlx::query(
+        "INSERT INTO chama_withdrawal_signature (chama_withdrawal_id, user_id, position_id, decision, comment, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(withdrawal_id)
+    .bind(user_id)
+    .bind(position_id)
+    .bind(decision)
+    .bind(comment)
+    .bind(utils::now_eat())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Records a signatory's decision on a withdrawal. Each signatory position
+/// signs once, and nobody signs for money paid to themselves.
+pub async fn sign_withdrawal(
+    pool:&MySqlPool,
+    gateway:&dyn MobileMoneyGateway,
+    user_id:&str,
+    withdrawal_id:&i64,
+    payload:&ChamaWithdrawalSignDto) -> Result<ChamaWithdrawalDetailDto, AppError> {
+
+    let signer_id = loan_service::parse_user_id(user_id)?;
+
+    let mut tx = pool.begin().await?;
+    let withdrawal = lock_withdrawal(&mut tx, withdrawal_id).await?;
+    let Some(position_id) = get_member_signatory_position(pool, &withdrawal.chama_id, &signer_id).await? else {
+        return Err(AppError::Forbidden("Only members holding a signatory position can sign".to_string()));
+    };
+    if is_paid_to(pool, withdrawal.beneficiary_user_id, withdrawal.msisdn.as_deref(), &signer_id).await {
+        return Err(AppError::Forbidden("You cannot sign a withdrawal paid to you".to_string()));
+    }
+    if withdrawal.status != ChamaWithdrawalStatus::PENDING {
+        return Err(AppError::Conflict(format!("Withdrawal is {:?} and no longer open for signatures", withdrawal.status)));
+    }
+    let signed: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM chama_withdrawal_signature WHERE chama_withdrawal_id = ? AND position_id = ?"
+    )
+    .bind(withdrawal_id)
+    .bind(position_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if signed.0 > 0 {
+        return Err(AppError::Conflict("Your position has already signed this withdrawal".to_string()));
+    }
+
+    insert_signature(&mut tx, withdrawal_id, &signer_id, &position_id, payload.decision, payload.comment.clone()).await?;
+    let status = settle_signatures(pool, &mut tx, &withdrawal).await?;
+    tx.commit().await?;
+
+    info!("Chama withdrawal {} signed by {}: {:?}, now {:?}", withdrawal_id, signer_id, payload.decision, status);
+    after_settle(pool, gateway, withdrawal_id, status).await
+}
+
+/// Tallies the signatures on a withdrawal the caller holds locked, the way
+/// loan approvals are tallied. Once enough positions approve, money for a
+/// member is moved to their wallet here and money for a phone number is
+/// marked SUBMITTED for `after_settle` to send.
+async fn settle_signatures(pool:&MySqlPool, conn:&mut MySqlConnection, withdrawal:&ChamaWithdrawal) -> Result<ChamaWithdrawalStatus, AppError> {
+    let withdrawal_id = withdrawal.id.unwrap_or_default();
+    let positions = get_signatory_position_ids(pool, &withdrawal.chama_id).await?;
+    let required = get_required_signatures(pool, &withdrawal.chama_id).await?;
+
+    let signatures: Vec<(i64, SignatureDecision)> = sqlx::query_as(
+        "SELECT position_id, decision FROM chama_withdrawal_signature WHERE chama_withdrawal_id = ?"
+    )
+    .bind(withdrawal_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    // Signatures from positions that no longer sign do not count
+    let counted = |decision: SignatureDecision| {
+        signatures.iter().filter(|(position, d)| *d == decision && positions.contains(position)).count() as i64
+    };
+    let approvals = counted(SignatureDecision::APPROVE);
+    let rejections = counted(SignatureDecision::REJECT);
+
+    let status = if positions.is_empty() {
+        ChamaWithdrawalStatus::PENDING
+    } else if approvals >= required {
+        match withdrawal.beneficiary_user_id {
+            Some(_) => ChamaWithdrawalStatus::PAID,
+            None => ChamaWithdrawalStatus::SUBMITTED,
+        }
+    } else if rejections > positions.len() as i64 - required {
+        ChamaWithdrawalStatus::REJECTED
+    } else {
+        ChamaWithdrawalStatus::PENDING
+    };
+    if status == ChamaWithdrawalStatus::PENDING {
+        return Ok(status);
+    }
+
+    if let (ChamaWithdrawalStatus::PAID, Some(beneficiary_id)) = (status, withdrawal.beneficiary_user_id) {
+        let source = LedgerAccountKey::ChamaSavings(withdrawal.chama_id);
+        if ledger_service::lock_balance(&mut *conn, &source).await? < withdrawal.amount {
+            return Err(AppError::BadRequest("Insufficient balance in the chama wallet".to_string()));
+        }
+        ledger_service::post_journal(
+            &mut *conn,
+            &JournalRequest {
+                reference:&format!("CHAMA-WITHDRAWAL-{}", withdrawal_id),
+                entry_type:"CHAMA_WITHDRAWAL",
+                narration:&withdrawal.narration,
+                created_by:Some(withdrawal.requested_by),
+            },
+            &[
+                PostingLine::debit(source, withdrawal.amount),
+                PostingLine::credit(LedgerAccountKey::Wallet(beneficiary_id), withdrawal.amount),
+            ],
+        ).await?;
+    }
+    sqlx::query("UPDATE chama_withdrawal SET status = ?, updated_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(utils::now_eat())
+        .bind(withdrawal_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(status)
+}
+
+/// Sends an approved phone payout and tells the people involved how the
+/// withdrawal ended.
+async fn after_settle(
+    pool:&MySqlPool,
+    gateway:&dyn MobileMoneyGateway,
+    withdrawal_id:&i64,
+    status:ChamaWithdrawalStatus) -> Result<ChamaWithdrawalDetailDto, AppError> {
+
+    let withdrawal = find_withdrawal(pool, withdrawal_id).await?;
+    match status {
+        ChamaWithdrawalStatus::SUBMITTED => {
+            let now_eat = utils::now_eat();
+            let payout = Withdrawal {
+                id:None,
+                user_id:withdrawal.requested_by,
+                chama_id:Some(withdrawal.chama_id),
+                amount:withdrawal.amount,
+                msisdn:withdrawal.msisdn.clone().unwrap_or_default(),
+                reference:format!("CWD{}", utils::generate_invite_hash_64().to_uppercase()),
+                narration:withdrawal.narration.clone(),
+                status:WithdrawalStatus::PENDING,
+                gateway:gateway.name().to_string(),
+                conversation_id:None,
+                receipt_no:None,
+                result_desc:None,
+                created_at:now_eat,
+                updated_at:now_eat,
+            };
+            match withdrawal_service::submit_payout(pool, gateway, payout).await {
+                // Later results reach this request through its withdrawal_id. One
+                // may already have arrived, so the status is taken from the payout
+                Ok(payout_id) => {
+                    sqlx::query(
+                        "UPDATE chama_withdrawal cw INNER JOIN withdrawal w ON w.id = ?
+                        SET cw.withdrawal_id = w.id,
+                            cw.status = CASE w.status WHEN ? THEN ? WHEN ? THEN ? ELSE ? END,
+                            cw.result_desc = w.result_desc, cw.updated_at = ?
+                        WHERE cw.id = ?"
+                    )
+                    .bind(payout_id)
+                    .bind(WithdrawalStatus::COMPLETED)
+                    .bind(ChamaWithdrawalStatus::PAID)
+                    .bind(WithdrawalStatus::REVERSED)
+                    .bind(ChamaWithdrawalStatus::FAILED)
+                    .bind(ChamaWithdrawalStatus::SUBMITTED)
+                    .bind(utils::now_eat())
+                    .bind(withdrawal_id)
+                    .execute(pool)
+                    .await?;
+                }
+                Err(e) => {
+                    error!("Could not pay out chama withdrawal {}: {}", withdrawal_id, e);
+                    sqlx::query("UPDATE chama_withdrawal SET status = ?, result_desc = ?, updated_at = ? WHERE id = ?")
+                        .bind(ChamaWithdrawalStatus::FAILED)
+                        .bind(e.to_string())
+                        .bind(utils::now_eat())
+                        .bind(withdrawal_id)
+                        .execute(pool)
+                        .await?;
+                }
+            }
+            let status = find_withdrawal(pool, withdrawal_id).await?.status;
+            let message = match status {
+                ChamaWithdrawalStatus::SUBMITTED | ChamaWithdrawalStatus::PAID => format!("The chama withdrawal of {} to {} has been approved and sent.",
+                    withdrawal.amount, withdrawal.msisdn.as_deref().unwrap_or_default()),
+                _ => format!("The chama withdrawal of {} was approved but could not be paid out.", withdrawal.amount),
+            };
+            notification_service::notify_user(pool, &withdrawal.requested_by, "Chama withdrawal", &message).await;
+        }
+        ChamaWithdrawalStatus::PAID => {
+            if let Some(beneficiary_id) = withdrawal.beneficiary_user_id {
+                let message = format!("{} from the chama wallet has been credited to your account: {}.",
+                    withdrawal.amount, withdrawal.narration);
+                notification_service::notify_user(pool, &beneficiary_id, "Chama withdrawal", &message).await;
+            }
+        }
+        ChamaWithdrawalStatus::REJECTED => {
+            let message = format!("The chama withdrawal of {} for \"{}\" was rejected by the signatories.",
+                withdrawal.amount, withdrawal.narration);
+            notification_service::notify_user(pool, &withdrawal.requested_by, "Chama withdrawal", &message).await;
+        }
+        _ => {}
+    }
+
+    withdrawal_detail(pool, find_withdrawal(pool, withdrawal_id).await?).await
+}
+
+/// Withdraws a request still waiting for signatures. Only whoever asked for
+/// it or a chama admin can.
+pub async fn cancel_withdrawal(pool:&MySqlPool, user_id:&str, withdrawal_id:&i64) -> Result<ChamaWithdrawalDetailDto, AppError> {
+    let caller = loan_service::parse_user_id(user_id)?;
+    let withdrawal = find_withdrawal(pool, withdrawal_id).await?;
+    if withdrawal.requested_by != caller {
+        chama_service::require_permission(pool, user_id, &withdrawal.chama_id, ChamaPermission::MANAGEWALLET).await?;
+    }
+
+    let updated = sqlx::query("UPDATE chama_withdrawal SET status = ?, updated_at = ? WHERE id = ? AND status = ?")
+        .bind(ChamaWithdrawalStatus::CANCELLED)
+        .bind(utils::now_eat())
+        .bind(withdrawal_id)
+        .bind(ChamaWithdrawalStatus::PENDING)
+        .execute(pool)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("Withdrawal is {:?} and cannot be cancelled", withdrawal.status)));
+    }
+
+    info!("Chama withdrawal {} cancelled by {}", withdrawal_id, caller);
+    withdrawal_detail(pool, find_withdrawal(pool, withdrawal_id).await?).await
+}
+
+async fn find_withdrawal(pool:&MySqlPool, withdrawal_id:&i64) -> Result<ChamaWithdrawal, AppError> {
+    let withdrawal = sqlx::query_as::<_, ChamaWithdrawal>("SELECT * FROM chama_withdrawal WHERE id = ?")
+        .bind(withdrawal_id)
+        .fetch_optional(pool)
+        .await?;
+
+    withdrawal.ok_or_else(|| AppError::NotFound("No such chama withdrawal".to_string()))
+}
+
+async fn withdrawal_detail(pool:&MySqlPool, withdrawal:ChamaWithdrawal) -> Result<ChamaWithdrawalDetailDto, AppError> {
+    let signatures = sqlx::query_as::<_, ChamaWithdrawalSignature>(
+        "SELECT * FROM chama_withdrawal_signature WHERE chama_withdrawal_id = ? ORDER BY id"
+    )
+    .bind(withdrawal.id)
+    .fetch_all(pool)
+    .await?;
+    let payout = match withdrawal.withdrawal_id {
+        Some(payout_id) => sqlx::query_as::<_, Withdrawal>("SELECT * FROM withdrawal WHERE id = ?")
+            .bind(payout_id)
+            .fetch_optional(pool)
+            .await?,
+        None => None,
+    };
+
+    Ok(ChamaWithdrawalDetailDto { withdrawal, signatures, payout })
+}
+
+pub async fn get_withdrawal(pool:&MySqlPool, user_id:&str, withdrawal_id:&i64) -> Result<ChamaWithdrawalDetailDto, AppError> {
+    let withdrawal = find_withdrawal(pool, withdrawal_id).await?;
+    contribution_service::require_member(pool, user_id, &withdrawal.chama_id).await?;
+
+    withdrawal_detail(pool, withdrawal).await
+}
+
+pub async fn get_withdrawals(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<ChamaWithdrawal>, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let withdrawals = sqlx::query_as::<_, ChamaWithdrawal>(
+        "SELECT * FROM chama_withdrawal WHERE chama_id = ? ORDER BY id DESC"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(withdrawals)
+}
@@ -0,0 +1,529 @@
+use chrono::{Days, NaiveDate};
+use sqlx::{MySqlConnection, MySqlPool, Row};
+use tracing::{info, error};
+
+use crate::dtos::contribution::{
+    ContributionCycleSummaryDto,
+    ContributionRecordDto,
+    ContributionScheduleDto,
+    MemberArrearsDto,
+    MemberStatementDto,
+    MemberStatementLineDto
+};
+use crate::error::AppError;
+use crate::models::chama::ChamaPermission;
+use crate::models::contribution::{Contribution, ContributionCycle, ContributionExpectation, ContributionSchedule};
+use crate::models::fine::FineStatus;
+use crate::models::transaction::Deposit;
+use crate::money::Money;
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, fine_service, ledger_service, loan_service, notification_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+async fn get_active_schedule(conn:&mut MySqlConnection, chama_id:&i64) -> Result<Option<ContributionSchedule>, sqlx::Error> {
+    let schedule = sqlx::query_as::<_, ContributionSchedule>(
+        "SELECT * FROM contribution_schedule WHERE chama_id = ? AND is_active = 1 ORDER BY id DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(schedule)
+}
+
+async fn get_last_cycle(conn:&mut MySqlConnection, chama_id:&i64) -> Result<Option<ContributionCycle>, sqlx::Error> {
+    let cycle = sqlx::query_as::<_, ContributionCycle>(
+        "SELECT * FROM contribution_cycle WHERE chama_id = ? ORDER BY cycle_no DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(cycle)
+}
+
+/// Locks the chama so that its schedule and cycles change one caller at a
+/// time.
+async fn lock_chama(conn:&mut MySqlConnection, chama_id:&i64) -> Result<(), AppError> {
+    sqlx::query_as::<_, (i64,)>("SELECT id FROM chama WHERE id = ? FOR UPDATE")
+        .bind(chama_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such chama".to_string()))?;
+    Ok(())
+}
+
+/// Replaces the chama's contribution schedule. Cycles already opened keep
+/// the expectations they were opened with; the new schedule starts after
+/// the last of them.
+pub async fn set_schedule(pool:&MySqlPool, user_id:&str, payload:&ContributionScheduleDto) -> Result<ContributionSchedule, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGECONTRIBUTIONS).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+
+    if !payload.amount.is_positive() {
+        return Err(AppError::BadRequest("Contribution amount must be greater than zero".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let mut tx = pool.begin().await?;
+    lock_chama(&mut tx, &payload.chama_id).await?;
+    let first_free_day = match get_last_cycle(&mut tx, &payload.chama_id).await? {
+        Some(cycle) => cycle.end_date.checked_add_days(Days::new(1)).unwrap_or(cycle.end_date),
+        None => now_eat.date(),
+    };
+    let start_date = payload.start_date.unwrap_or(first_free_day);
+    if start_date < first_free_day {
+        return Err(AppError::BadRequest(format!("Schedule cannot start before {}, earlier cycles are already open", first_free_day)));
+    }
+
+    sqlx::query("UPDATE contribution_schedule SET is_active = 0, updated_at = ? WHERE chama_id = ? AND is_active = 1")
+        .bind(now_eat)
+        .bind(payload.chama_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut schedule = ContributionSchedule {
+        id:None,
+        chama_id:payload.chama_id,
+        frequency:payload.frequency,
+        amount:payload.amount,
+        start_date,
+        is_active:1,
+        created_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let schedule_repository = data_repository::DataRepository::<ContributionSchedule> {
+        pool,
+        table_name: "contribution_schedule",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    schedule.id = Some(schedule_repository.insert_trx(&mut tx, &schedule).await?);
+    tx.commit().await?;
+
+    info!("Chama {} contributes {:?} {} from {}", payload.chama_id, schedule.frequency, schedule.amount, start_date);
+    open_cycles(pool, &payload.chama_id).await?;
+    Ok(schedule)
+}
+
+/// Opens every cycle of the chama's schedule that has started, fixing what
+/// each active member is expected to pay in it: their agreed contribution
+/// amount, or the schedule's amount when they have none. Returns how many
+/// cycles were opened.
+///
+/// Each cycle is opened under a lock on the chama, with the schedule and
+/// the cycles already open read again inside it, so callers racing each
+/// other never open the same cycle twice. Readers leave this to the cycle
+/// job and to the writes that need the current cycle.
+pub async fn open_cycles(pool:&MySqlPool, chama_id:&i64) -> Result<u32, AppError> {
+    let today = utils::now_eat().date();
+
+    let mut opened = 0;
+    loop {
+        let mut tx = pool.begin().await?;
+        lock_chama(&mut tx, chama_id).await?;
+        let Some(schedule) = get_active_schedule(&mut tx, chama_id).await? else {
+            break;
+        };
+        let schedule_id = schedule.id.unwrap_or_default();
+        let last_cycle = get_last_cycle(&mut tx, chama_id).await?;
+        let schedule_cycles: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM contribution_cycle WHERE schedule_id = ?")
+            .bind(schedule_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let n = schedule_cycles.0 as u32;
+
+        let (Some(start_date), Some(next_start)) = (
+            schedule.frequency.cycle_start(schedule.start_date, n),
+            schedule.frequency.cycle_start(schedule.start_date, n + 1),
+        ) else {
+            break;
+        };
+        if start_date > today {
+            break;
+        }
+        let end_date = next_start.pred_opt().unwrap_or(start_date);
+        let cycle_no = last_cycle.map(|c| c.cycle_no + 1).unwrap_or(1);
+
+        let now_eat = utils::now_eat();
+        let cycle = sqlx::query(
+            "INSERT INTO contribution_cycle (chama_id, schedule_id, cycle_no, start_date, end_date, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(chama_id)
+        .bind(schedule_id)
+        .bind(cycle_no)
+        .bind(start_date)
+        .bind(end_date)
+        .bind(now_eat)
+        .execute(&mut *tx)
+        .await?;
+        let cycle_id = cycle.last_insert_id() as i64;
+
+        sqlx::query(
+            "INSERT INTO contribution_expectation (cycle_id, chama_id, user_id, expected_amount, created_at)
+            SELECT ?, chama_id, user_id, IF(contribution_amount > 0, contribution_amount, ?), ?
+            FROM chama_member WHERE chama_id = ? AND is_active = 1"
+        )
+        .bind(cycle_id)
+        .bind(schedule.amount)
+        .bind(now_eat)
+        .bind(chama_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        info!("Opened contribution cycle {} of chama {}, {} to {}", cycle_no, chama_id, start_date, end_date);
+        opened += 1;
+    }
+
+    Ok(opened)
+}
+
+/// Opens due cycles for every chama with a contribution schedule.
+pub async fn open_due_cycles(pool:&MySqlPool) -> Result<u32, AppError> {
+    let chamas: Vec<(i64,)> = sqlx::query_as("SELECT DISTINCT chama_id FROM contribution_schedule WHERE is_active = 1")
+        .fetch_all(pool)
+        .await?;
+
+    let mut opened = 0;
+    for (chama_id,) in chamas {
+        match open_cycles(pool, &chama_id).await {
+            Ok(count) => opened += count,
+            Err(e) => error!("Could not open contribution cycles for chama {}: {}", chama_id, e),
+        }
+    }
+    Ok(opened)
+}
+
+pub async fn insert_contribution(conn:&mut MySqlConnection, contribution:&Contribution) -> Result<i64, AppError> {
+    let result = sqlx::query(
+        "INSERT INTO contribution (chama_id, user_id, deposit_id, amount, paid_at, narration, recorded_by, is_active, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(contribution.chama_id)
+    .bind(contribution.user_id)
+    .bind(contribution.deposit_id)
+    .bind(contribution.amount)
+    .bind(contribution.paid_at)
+    .bind(&contribution.narration)
+    .bind(contribution.recorded_by)
+    .bind(contribution.is_active)
+    .bind(contribution.created_at)
+    .bind(contribution.updated_at)
+    .execute(conn)
+    .await?;
+
+    Ok(result.last_insert_id() as i64)
+}
+
+/// Counts a completed deposit to a chama as the paying member's
+/// contribution. Deposits not made by a member are left to the chama.
+pub async fn record_deposit_contribution(conn:&mut MySqlConnection, deposit:&Deposit, receipt_no:&str) -> Result<(), AppError> {
+    let (Some(chama_id), Some(user_id)) = (deposit.chama_id, deposit.user_id) else {
+        return Ok(());
+    };
+
+    let now_eat = utils::now_eat();
+    insert_contribution(conn, &Contribution {
+        id:None,
+        chama_id,
+        user_id,
+        deposit_id:deposit.id,
+        amount:deposit.amount,
+        paid_at:deposit.bill_date,
+        narration:format!("Mobile money {}", receipt_no),
+        recorded_by:user_id,
+        is_active:1,
+        created_at:now_eat,
+        updated_at:now_eat,
+    }).await?;
+    Ok(())
+}
+
+/// Takes savings back from a member, e.g. to cover a loan they guaranteed.
+/// Kept as a negative contribution so savings drop while the member's
+/// contribution record stays as paid.
+pub async fn record_savings_deduction(conn:&mut MySqlConnection, chama_id:&i64, user_id:&i64, amount:Money, narration:&str) -> Result<(), AppError> {
+    let now_eat = utils::now_eat();
+    insert_contribution(conn, &Contribution {
+        id:None,
+        chama_id:*chama_id,
+        user_id:*user_id,
+        deposit_id:None,
+        amount:-amount,
+        paid_at:now_eat,
+        narration:narration.to_string(),
+        recorded_by:0,
+        is_active:1,
+        created_at:now_eat,
+        updated_at:now_eat,
+    }).await?;
+    Ok(())
+}
+
+/// Stops counting the contribution made by a deposit that has been reversed.
+pub async fn reverse_deposit_contribution(conn:&mut MySqlConnection, deposit_id:&i64) -> Result<(), AppError> {
+    sqlx::query("UPDATE contribution SET is_active = 0, updated_at = ? WHERE deposit_id = ?")
+        .bind(utils::now_eat())
+        .bind(deposit_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Records cash a member handed to a chama official and puts it in the
+/// chama's savings.
+pub async fn record_contribution(pool:&MySqlPool, user_id:&str, payload:&ContributionRecordDto) -> Result<Contribution, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGECONTRIBUTIONS).await?;
+    let recorded_by = loan_service::parse_user_id(user_id)?;
+
+    if !payload.amount.is_positive() {
+        return Err(AppError::BadRequest("Contribution amount must be greater than zero".to_string()));
+    }
+    if !chama_service::is_active_member(pool, &payload.chama_id, &payload.user_id).await? {
+        return Err(AppError::BadRequest("User is not an active member of the chama".to_string()));
+    }
+    let now_eat = utils::now_eat();
+    let paid_at = payload.paid_at.unwrap_or(now_eat);
+    if paid_at > now_eat {
+        return Err(AppError::BadRequest("Payment date is in the future".to_string()));
+    }
+
+    let mut contribution = Contribution {
+        id:None,
+        chama_id:payload.chama_id,
+        user_id:payload.user_id,
+        deposit_id:None,
+        amount:payload.amount,
+        paid_at,
+        narration:payload.narration.clone().unwrap_or_else(|| "Cash contribution".to_string()),
+        recorded_by,
+        is_active:1,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+
+    let mut tx = pool.begin().await?;
+    let contribution_id = insert_contribution(&mut tx, &contribution).await?;
+    contribution.id = Some(contribution_id);
+
+    ledger_service::post_journal(
+        &mut tx,
+        &JournalRequest {
+            reference:&format!("CONTRIBUTION-{}", contribution_id),
+            entry_type:"CONTRIBUTION",
+            narration:&format!("{} from member {} to chama {}", contribution.narration, payload.user_id, payload.chama_id),
+            created_by:Some(recorded_by),
+        },
+        &[
+            PostingLine::debit(LedgerAccountKey::Cash, contribution.amount),
+            PostingLine::credit(LedgerAccountKey::ChamaSavings(payload.chama_id), contribution.amount),
+        ],
+    ).await?;
+    tx.commit().await?;
+
+    info!("Contribution {} of {} recorded for member {} of chama {} by {}", contribution_id, contribution.amount, payload.user_id, payload.chama_id, recorded_by);
+    notification_service::notify_user(
+        pool,
+        &payload.user_id,
+        "Contribution received",
+        &format!("Your contribution of KES {} has been recorded.", contribution.amount),
+    ).await;
+
+    Ok(contribution)
+}
+
+/// Chama records are open to its active members, officials included.
+pub async fn require_member(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<(), AppError> {
+    let caller = loan_service::parse_user_id(user_id)?;
+    if !chama_service::is_active_member(pool, chama_id, &caller).await? {
+        return Err(AppError::Forbidden("User not allowed to perform this action".to_string()));
+    }
+    Ok(())
+}
+
+/// Each cycle with what all members were expected to pay and what was paid
+/// during it.
+pub async fn get_cycles(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<ContributionCycleSummaryDto>, AppError> {
+    require_member(pool, user_id, chama_id).await?;
+
+    let rows = sqlx::query(
+        "SELECT cc.id, cc.cycle_no, cc.start_date, cc.end_date,
+            (SELECT COALESCE(SUM(ce.expected_amount), 0) FROM contribution_expectation ce WHERE ce.cycle_id = cc.id) AS expected,
+            (SELECT COALESCE(SUM(c.amount), 0) FROM contribution c
+                WHERE c.chama_id = cc.chama_id AND c.is_active = 1 AND c.amount > 0
+                AND DATE(c.paid_at) BETWEEN cc.start_date AND cc.end_date) AS paid
+        FROM contribution_cycle cc
+        WHERE cc.chama_id = ?
+        ORDER BY cc.cycle_no DESC"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut cycles = Vec::new();
+    for row in rows {
+        cycles.push(ContributionCycleSummaryDto {
+            cycle_id:row.try_get("id")?,
+            cycle_no:row.try_get("cycle_no")?,
+            start_date:row.try_get("start_date")?,
+            end_date:row.try_get("end_date")?,
+            expected:row.try_get("expected")?,
+            paid:row.try_get("paid")?,
+        });
+    }
+    Ok(cycles)
+}
+
+/// Splits the gap between what has fallen due and what was paid into
+/// arrears and advance.
+fn arrears_and_advance(expected:Money, paid:Money) -> (Money, Money) {
+    if expected > paid {
+        (expected - paid, Money::ZERO)
+    } else {
+        (Money::ZERO, paid - expected)
+    }
+}
+
+/// What a member owes for the cycles that have ended.
+pub async fn member_arrears(pool:&MySqlPool, chama_id:&i64, user_id:&i64) -> Result<Money, AppError> {
+    let (expected, paid): (Money, Money) = sqlx::query_as(
+        "SELECT
+            (SELECT COALESCE(SUM(ce.expected_amount), 0) FROM contribution_expectation ce
+                INNER JOIN contribution_cycle cc ON cc.id = ce.cycle_id
+                WHERE ce.chama_id = ? AND ce.user_id = ? AND cc.end_date < ?),
+            (SELECT COALESCE(SUM(c.amount), 0) FROM contribution c
+                WHERE c.chama_id = ? AND c.user_id = ? AND c.is_active = 1 AND c.amount > 0)"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .bind(utils::now_eat().date())
+    .bind(chama_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(arrears_and_advance(expected, paid).0)
+}
+
+/// Every active member's arrears, the members owing most first.
+pub async fn get_arrears(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<MemberArrearsDto>, AppError> {
+    chama_service::require_permission(pool, user_id, chama_id, ChamaPermission::MANAGECONTRIBUTIONS).await?;
+
+    let rows = sqlx::query(
+        "SELECT cm.user_id, au.first_name, au.last_name,
+            (SELECT COALESCE(SUM(ce.expected_amount), 0) FROM contribution_expectation ce
+                INNER JOIN contribution_cycle cc ON cc.id = ce.cycle_id
+                WHERE ce.chama_id = cm.chama_id AND ce.user_id = cm.user_id AND cc.end_date < ?) AS expected,
+            (SELECT COALESCE(SUM(c.amount), 0) FROM contribution c
+                WHERE c.chama_id = cm.chama_id AND c.user_id = cm.user_id AND c.is_active = 1 AND c.amount > 0) AS paid
+        FROM chama_member cm
+        INNER JOIN auth_user au ON au.id = cm.user_id
+        WHERE cm.chama_id = ? AND cm.is_active = 1"
+    )
+    .bind(utils::now_eat().date())
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut members = Vec::new();
+    for row in rows {
+        let expected: Money = row.try_get("expected")?;
+        let paid: Money = row.try_get("paid")?;
+        let (arrears, advance) = arrears_and_advance(expected, paid);
+        members.push(MemberArrearsDto {
+            user_id:row.try_get("user_id")?,
+            first_name:row.try_get("first_name")?,
+            last_name:row.try_get("last_name")?,
+            expected,
+            paid,
+            arrears,
+            advance,
+        });
+    }
+    members.sort_by_key(|m| std::cmp::Reverse(m.arrears));
+    Ok(members)
+}
+
+/// A member's contributions as a running account: each cycle's expected
+/// contribution on its due date and each payment on the day it was made,
+/// followed by the member's fines.
+/// Members may read their own statement; officials may read anyone's.
+pub async fn get_member_statement(pool:&MySqlPool, user_id:&str, chama_id:&i64, member_id:&i64) -> Result<MemberStatementDto, AppError> {
+    if loan_service::parse_user_id(user_id)? != *member_id {
+        chama_service::require_permission(pool, user_id, chama_id, ChamaPermission::MANAGECONTRIBUTIONS).await?;
+    }
+
+    let expectations = sqlx::query_as::<_, ContributionExpectation>(
+        "SELECT * FROM contribution_expectation WHERE chama_id = ? AND user_id = ?"
+    )
+    .bind(chama_id)
+    .bind(member_id)
+    .fetch_all(pool)
+    .await?;
+    let cycles = sqlx::query_as::<_, ContributionCycle>("SELECT * FROM contribution_cycle WHERE chama_id = ?")
+        .bind(chama_id)
+        .fetch_all(pool)
+        .await?;
+    let contributions = sqlx::query_as::<_, Contribution>(
+        "SELECT * FROM contribution WHERE chama_id = ? AND user_id = ? AND is_active = 1 AND amount > 0"
+    )
+    .bind(chama_id)
+    .bind(member_id)
+    .fetch_all(pool)
+    .await?;
+
+    // (date, payments before dues on the same day, line)
+    let mut entries: Vec<(NaiveDate, u8, String, Money, Money)> = Vec::new();
+    for expectation in &expectations {
+        let Some(cycle) = cycles.iter().find(|c| c.id == Some(expectation.cycle_id)) else {
+            continue;
+        };
+        entries.push((
+            cycle.end_date,
+            1,
+            format!("Cycle {} contribution ({} to {})", cycle.cycle_no, cycle.start_date, cycle.end_date),
+            expectation.expected_amount,
+            Money::ZERO,
+        ));
+    }
+    for contribution in &contributions {
+        entries.push((contribution.paid_at.date(), 0, contribution.narration.clone(), Money::ZERO, contribution.amount));
+    }
+    entries.sort_by_key(|e| (e.0, e.1));
+
+    let today = utils::now_eat().date();
+    let mut balance = Money::ZERO;
+    let mut total_expected = Money::ZERO;
+    let mut due = Money::ZERO;
+    let mut lines = Vec::new();
+    for (date, _, description, expected, paid) in entries {
+        balance = balance + paid - expected;
+        total_expected += expected;
+        if date < today {
+            due += expected;
+        }
+        lines.push(MemberStatementLineDto { date, description, expected, paid, balance });
+    }
+    let total_paid: Money = contributions.iter().map(|c| c.amount).sum();
+    let (arrears, _) = arrears_and_advance(due, total_paid);
+
+    let fines = fine_service::get_member_fines(pool, chama_id, member_id).await?;
+    let fines_outstanding = fines.iter().filter(|f| f.status == FineStatus::OUTSTANDING).map(|f| f.amount).sum();
+
+    Ok(MemberStatementDto {
+        chama_id:*chama_id,
+        user_id:*member_id,
+        lines,
+        total_expected,
+        total_paid,
+        arrears,
+        fines,
+        fines_outstanding,
+    })
+}
@@ -0,0 +1,243 @@
+use sqlx::{MySql, MySqlConnection, MySqlPool, Transaction};
+use tracing::{info, error};
+
+use crate::error::AppError;
+use crate::money::Money;
+use crate::models::loan::{
+    LoadRequestStatus,
+    LoanDafault,
+    LoanDafaultReclaim,
+    LoanDefaultStatus,
+    LoanRepaymentMethod,
+    LoanRequest
+};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, contribution_service, guarantee_service, loan_service, notification_service, repayment_service};
+use crate::utils;
+
+
+/// Days an installment may stay unpaid past its due date before the loan is
+/// defaulted, for chamas that have not set their own grace period.
+pub const DEFAULT_GRACE_PERIOD_DAYS: i32 = 30;
+
+pub struct Reclaim {
+    pub guarantor_id:i64,
+    pub amount:Money,
+}
+
+/// Defaults every disbursed loan with an installment overdue beyond its
+/// chama's grace period. Loans are handled one transaction each so one bad
+/// loan does not hold back the rest. Returns the number of loans defaulted.
+pub async fn detect_defaults(pool:&MySqlPool) -> Result<usize, AppError> {
+    let overdue: Vec<(i64,)> = sqlx::query_as(
+        "SELECT DISTINCT lr.id FROM loan_request lr
+        INNER JOIN loan_repayment_schedule s on s.load_request_id = lr.id AND s.status <> 'PAID'
+        LEFT JOIN chama_loan_default_setting ds on ds.chama_id = lr.chama_id AND ds.is_active = 1
+        WHERE lr.status = 'DISBURSED'
+            AND DATE_ADD(s.due_date, INTERVAL COALESCE(ds.grace_period_days, ?) DAY) < ?"
+    )
+    .bind(DEFAULT_GRACE_PERIOD_DAYS)
+    .bind(utils::now_eat())
+    .fetch_all(pool)
+    .await?;
+
+    let mut defaulted = 0;
+    for (loan_id,) in overdue {
+        match default_loan(pool, &loan_id).await {
+            Ok(true) => defaulted += 1,
+            Ok(false) => {},
+            Err(e) => error!("Failed to default loan {}: {}", loan_id, e),
+        }
+    }
+
+    Ok(defaulted)
+}
+
+async fn is_overdue(conn:&mut MySqlConnection, loan:&LoanRequest) -> Result<bool, AppError> {
+    let overdue: (i64,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM loan_repayment_schedule s
+        LEFT JOIN chama_loan_default_setting ds on ds.chama_id = ? AND ds.is_active = 1
+        WHERE s.load_request_id = ? AND s.status <> 'PAID'
+            AND DATE_ADD(s.due_date, INTERVAL COALESCE(ds.grace_period_days, ?) DAY) < ?)"
+    )
+    .bind(loan.chama_id)
+    .bind(loan.id)
+    .bind(DEFAULT_GRACE_PERIOD_DAYS)
+    .bind(utils::now_eat())
+    .fetch_one(conn)
+    .await?;
+
+    Ok(overdue.0 == 1)
+}
+
+/// Marks a loan DEFAULTED, records the default and recovers what it can from
+/// the guarantors' pledges. Returns false when a repayment got in first and
+/// the loan is no longer overdue.
+pub async fn default_loan(pool:&MySqlPool, loan_id:&i64) -> Result<bool, AppError> {
+    let loan = loan_service::find_loan(pool, loan_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let status = loan_service::lock_loan_status(&mut tx, loan_id).await?;
+    if status != LoadRequestStatus::DISBURSED || !is_overdue(&mut tx, &loan).await? {
+        return Ok(false);
+    }
+
+    loan_service::transition_loan_status(&mut tx, loan_id, status, LoadRequestStatus::DEFAULTED).await?;
+    let balance = repayment_service::get_outstanding_balance(&mut tx, loan_id).await?;
+
+    let now_eat = utils::now_eat();
+    let default = LoanDafault {
+        id:None,
+        load_request_id:*loan_id,
+        loan_balance:balance,
+        status:LoanDefaultStatus::OUTSTANDING,
+        default_at:now_eat,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let default_repository = data_repository::DataRepository::<LoanDafault> {
+        pool,
+        table_name: "loan_dafault",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let default_id = default_repository.insert_trx(&mut tx, &default).await?;
+
+    let reclaims = reclaim_from_guarantors(pool, &mut tx, &loan, &default_id, balance).await?;
+    let recovered: Money = reclaims.iter().map(|r| r.amount).sum();
+
+    let mut remaining = balance;
+    if recovered.is_positive() {
+        let repayment = repayment_service::post_repayment(
+            pool,
+            &mut tx,
+            loan_id,
+            recovered,
+            LoanRepaymentMethod::GUARANTEE,
+            Some(format!("DEFAULT-{}-RECLAIM", default_id)),
+        ).await?;
+        remaining = repayment.loan_balance;
+    }
+
+    if !remaining.is_positive() {
+        sqlx::query("UPDATE loan_dafault SET status = ?, updated_at = ? WHERE id = ?")
+            .bind(LoanDefaultStatus::RECOVERED)
+            .bind(utils::now_eat())
+            .bind(default_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Pledges have been called in, they no longer secure anything
+    guarantee_service::release_guarantees(&mut tx, loan_id).await?;
+
+    tx.commit().await?;
+    info!("Loan {} defaulted with {} outstanding, {} recovered from guarantors", loan_id, balance, recovered);
+
+    notification_service::notify_user(
+        pool,
+        &loan.user_id,
+        "Loan defaulted",
+        &format!(
+            "Your loan #{} has been marked as defaulted with KES {} outstanding. KES {} was recovered from your guarantors and KES {} remains due.",
+            loan_id, balance, recovered, remaining
+        ),
+    ).await;
+    for reclaim in &reclaims {
+        notification_service::notify_user(
+            pool,
+            &reclaim.guarantor_id,
+            "Guarantee called in",
+            &format!(
+                "Loan #{} that you guaranteed has defaulted. KES {} has been recovered from your savings in line with your pledge.",
+                loan_id, reclaim.amount
+            ),
+        ).await;
+    }
+
+    Ok(true)
+}
+
+/// Recovers up to `balance` from the accepted pledges on a loan, each
+/// guarantor paying in proportion to what they pledged and never more than
+/// their pledge or their current savings. The amounts come off the
+/// guarantors' chama savings and are recorded as reclaims against the default.
+pub async fn reclaim_from_guarantors(
+    pool:&MySqlPool,
+    tx:&mut Transaction<'_, MySql>,
+    loan:&LoanRequest,
+    default_id:&i64,
+    balance:Money) -> Result<Vec<Reclaim>, AppError> {
+
+    let Some(chama_id) = loan.chama_id else {
+        return Ok(Vec::new());
+    };
+
+    let pledges: Vec<(i64, Money)> = sqlx::query_as(
+        "SELECT loan_quaranter_id, amount_quaranteed FROM loan_request_guarantee
+        WHERE load_request_id = ? AND status = 'ACCEPTED' AND amount_quaranteed > 0
+        ORDER BY id"
+    )
+    .bind(loan.id)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let total_pledged: Money = pledges.iter().map(|p| p.1).sum();
+    if !total_pledged.is_positive() || !balance.is_positive() {
+        return Ok(Vec::new());
+    }
+
+    let to_recover = balance.min(total_pledged);
+    let weights: Vec<Money> = pledges.iter().map(|p| p.1).collect();
+    let shares = to_recover.allocate(&weights);
+    let reclaim_repository = data_repository::DataRepository::<LoanDafaultReclaim> {
+        pool,
+        table_name: "loan_dafault_reclaim",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let mut reclaims = Vec::with_capacity(pledges.len());
+    for ((guarantor_id, pledged), share) in pledges.iter().zip(shares) {
+        // Serialises reclaims against the same guarantor
+        sqlx::query("SELECT id FROM chama_member WHERE chama_id = ? AND user_id = ? FOR UPDATE")
+            .bind(chama_id)
+            .bind(guarantor_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+        let savings = chama_service::get_member_savings(&mut **tx, &chama_id, guarantor_id).await?;
+
+        let amount = share.min(*pledged).min(savings);
+        if !amount.is_positive() {
+            continue;
+        }
+
+        contribution_service::record_savings_deduction(
+            tx,
+            &chama_id,
+            guarantor_id,
+            amount,
+            &format!("Reclaimed for defaulted loan {}", loan.id.unwrap_or_default()),
+        ).await?;
+
+        let now_eat = utils::now_eat();
+        let reclaim = LoanDafaultReclaim {
+            id:None,
+            loan_dafault_id:*default_id,
+            quarantee_type:"SAVINGS".to_string(),
+            quaranter_id:*guarantor_id,
+            amount_quaranteed:amount,
+            chama_id:Some(chama_id),
+            default_at:now_eat,
+            created_at:now_eat,
+            updated_at:now_eat,
+        };
+        reclaim_repository.insert_trx(tx, &reclaim).await?;
+
+        reclaims.push(Reclaim { guarantor_id:*guarantor_id, amount });
+    }
+
+    Ok(reclaims)
+}
@@ -0,0 +1,717 @@
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::{info, error};
+
+use chrono::NaiveDateTime;
+
+use crate::dtos::payment::{
+    C2bPaymentDto,
+    CallbackItemDto,
+    CallbackMetadataDto,
+    DepositRequestDto,
+    DepositResponseDto,
+    StkCallbackDto,
+    SuspenseReassignDto
+};
+use crate::error::AppError;
+use crate::gateways::{MobileMoneyGateway, PaymentStatus, StkPushRequest};
+use crate::money::Money;
+use crate::models::transaction::{Deposit, DepositStatus};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{authentication_service, chama_service, contribution_service, ledger_service, loan_service, notification_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+/// Gateway name of deposits recorded from paybill and till payments.
+const C2B_GATEWAY: &str = "mpesa-c2b";
+
+/// Starts a deposit: asks the gateway to push a payment prompt to the phone
+/// and records the deposit as PENDING until the provider calls back.
+pub async fn initiate_deposit(
+    pool:&MySqlPool,
+    gateway:&dyn MobileMoneyGateway,
+    user_id:&str,
+    payload:&DepositRequestDto) -> Result<DepositResponseDto, AppError> {
+
+    let Some(user) = authentication_service::get_auth_user_by_id(pool, user_id).await else {
+        return Err(AppError::Forbidden("Invalid user".to_string()));
+    };
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    if !payload.amount.is_positive() {
+        return Err(AppError::BadRequest("Deposit amount must be greater than zero".to_string()));
+    }
+    if let Some(chama_id) = payload.chama_id
+        && !chama_service::is_active_member(pool, &chama_id, &user_id).await? {
+        return Err(AppError::BadRequest("You are not an active member of this chama".to_string()));
+    }
+    let phone = payload.phone_number.as_deref().unwrap_or(&user.username);
+    let Some(msisdn) = utils::is_valid_phone(phone) else {
+        return Err(AppError::BadRequest("Phone number not valid".to_string()));
+    };
+
+    let bill_no = format!("DEP{}", utils::generate_invite_hash_64().to_uppercase());
+    let response = gateway.stk_push(&StkPushRequest {
+        msisdn:msisdn.clone(),
+        amount:payload.amount,
+        account_reference:bill_no.clone(),
+        description:if payload.chama_id.is_some() { "Chama contribution" } else { "Wallet deposit" }.to_string(),
+    }).await?;
+
+    let now_eat = utils::now_eat();
+    let deposit = Deposit {
+        id:None,
+        user_id:Some(user_id),
+        chama_id:payload.chama_id,
+        paying_number:msisdn,
+        bill_no:bill_no.clone(),
+        account_no:user_id.to_string(),
+        vendor_reference:response.checkout_request_id.clone(),
+        amount:payload.amount,
+        receipt_no:None,
+        gateway:response.gateway.clone(),
+        bill_date:now_eat,
+        status:DepositStatus::PENDING,
+        created_at:now_eat,
+        updated_at:now_eat,
+        created_by:user_id,
+    };
+    let deposit_repository = data_repository::DataRepository::<Deposit> {
+        pool,
+        table_name: "deposit",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let deposit_id = deposit_repository.insert(&deposit).await?;
+
+    info!("Deposit {} of {} started for user {} via {}", deposit_id, payload.amount, user_id, response.gateway);
+    Ok(DepositResponseDto {
+        deposit_id,
+        bill_no,
+        checkout_request_id:response.checkout_request_id,
+        status:DepositStatus::PENDING,
+        customer_message:response.customer_message,
+    })
+}
+
+async fn lock_deposit_by_checkout(conn:&mut MySqlConnection, checkout_request_id:&str) -> Result<Deposit, AppError> {
+    let deposit = sqlx::query_as::<_, Deposit>(
+        "SELECT * FROM deposit WHERE vendor_reference = ? FOR UPDATE"
+    )
+    .bind(checkout_request_id)
+    .fetch_optional(conn)
+    .await?;
+
+    deposit.ok_or_else(|| AppError::NotFound("No such deposit".to_string()))
+}
+
+/// The ledger account a received deposit belongs in: the chama's savings for
+/// payments to a chama, the user's wallet, or suspense when unmatched.
+fn deposit_account(deposit:&Deposit) -> LedgerAccountKey {
+    match (deposit.chama_id, deposit.user_id) {
+        (Some(chama_id), _) => LedgerAccountKey::ChamaSavings(chama_id),
+        (None, Some(user_id)) => LedgerAccountKey::Wallet(user_id),
+        (None, None) => LedgerAccountKey::Suspense,
+    }
+}
+
+/// Credits a deposit to the user's wallet, or the chama it was paid to, once
+/// its payment is confirmed.
+/// Callers must hold the deposit row lock.
+pub async fn complete_deposit(conn:&mut MySqlConnection, deposit:&Deposit, receipt_no:&str) -> Result<(), AppError> {
+    let deposit_id = deposit.id.unwrap_or_default();
+
+    let existing: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM deposit WHERE receipt_no = ? AND id <> ?")
+        .bind(receipt_no)
+        .bind(deposit_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if existing.0 > 0 {
+        return Err(AppError::Conflict(format!("Receipt {} has already been used", receipt_no)));
+    }
+
+    sqlx::query("UPDATE deposit SET status = ?, receipt_no = ?, updated_at = ? WHERE id = ?")
+        .bind(DepositStatus::COMPLETED)
+        .bind(receipt_no)
+        .bind(utils::now_eat())
+        .bind(deposit_id)
+        .execute(&mut *conn)
+        .await?;
+
+    ledger_service::post_journal(
+        conn,
+        &JournalRequest {
+            reference:&format!("DEPOSIT-{}", deposit_id),
+            entry_type:"DEPOSIT",
+            narration:&format!("Mobile money deposit {} from {}", receipt_no, deposit.paying_number),
+            created_by:None,
+        },
+        &[
+            PostingLine::debit(LedgerAccountKey::Cash, deposit.amount),
+            PostingLine::credit(deposit_account(deposit), deposit.amount),
+        ],
+    ).await?;
+    contribution_service::record_deposit_contribution(conn, deposit, receipt_no).await?;
+
+    Ok(())
+}
+
+/// A push deposit completed from a status query, which does not return the
+/// M-Pesa receipt, carries its checkout id in place of one until the
+/// callback or the provider statement supplies the real receipt.
+fn has_placeholder_receipt(deposit:&Deposit) -> bool {
+    deposit.status == DepositStatus::COMPLETED
+        && deposit.gateway != C2B_GATEWAY
+        && deposit.receipt_no.as_deref() == Some(deposit.vendor_reference.as_str())
+}
+
+/// Puts the real M-Pesa receipt on a deposit completed under a placeholder.
+/// The money was credited when it completed, so nothing is posted.
+/// Callers must hold the deposit row lock.
+async fn replace_placeholder_receipt(conn:&mut MySqlConnection, deposit:&mut Deposit, receipt_no:&str) -> Result<(), AppError> {
+    let existing: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM deposit WHERE receipt_no = ? AND id <> ?")
+        .bind(receipt_no)
+        .bind(deposit.id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if existing.0 > 0 {
+        return Err(AppError::Conflict(format!("Receipt {} has already been used", receipt_no)));
+    }
+
+    sqlx::query("UPDATE deposit SET receipt_no = ?, updated_at = ? WHERE id = ?")
+        .bind(receipt_no)
+        .bind(utils::now_eat())
+        .bind(deposit.id)
+        .execute(&mut *conn)
+        .await?;
+
+    info!("Deposit {:?} receipt {} replaced with {}", deposit.id, deposit.vendor_reference, receipt_no);
+    deposit.receipt_no = Some(receipt_no.to_string());
+    Ok(())
+}
+
+/// The deposit a provider statement line paid for, found by the bill number
+/// the customer quoted and the amount: one still pending, or one completed
+/// under a placeholder receipt.
+pub async fn find_by_statement_line(pool:&MySqlPool, bill_no:&str, amount:Money) -> Result<Option<Deposit>, AppError> {
+    let deposit = sqlx::query_as::<_, Deposit>(
+        "SELECT * FROM deposit WHERE bill_no = ? AND amount = ? AND gateway <> ?
+            AND (status = ? OR (status = ? AND receipt_no = vendor_reference))
+        ORDER BY id LIMIT 1"
+    )
+    .bind(bill_no.trim())
+    .bind(amount)
+    .bind(C2B_GATEWAY)
+    .bind(DepositStatus::PENDING)
+    .bind(DepositStatus::COMPLETED)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(deposit)
+}
+
+/// Records the receipt a provider statement shows for a deposit completed
+/// under a placeholder.
+pub async fn settle_placeholder_receipt(pool:&MySqlPool, deposit_id:&i64, receipt_no:&str) -> Result<Deposit, AppError> {
+    let mut tx = pool.begin().await?;
+    let deposit = sqlx::query_as::<_, Deposit>("SELECT * FROM deposit WHERE id = ? FOR UPDATE")
+        .bind(deposit_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(mut deposit) = deposit.filter(has_placeholder_receipt) else {
+        return Err(AppError::Conflict("Deposit already has its receipt".to_string()));
+    };
+    replace_placeholder_receipt(&mut tx, &mut deposit, receipt_no).await?;
+    tx.commit().await?;
+
+    Ok(deposit)
+}
+
+fn received_message(deposit:&Deposit, receipt_no:&str) -> String {
+    match deposit.chama_id {
+        Some(_) => format!("Your contribution of KES {} has been received. M-Pesa receipt {}.", deposit.amount, receipt_no),
+        None => format!("KES {} has been credited to your wallet. M-Pesa receipt {}.", deposit.amount, receipt_no),
+    }
+}
+
+/// Applies a provider's verdict on a deposit. Deposits that are no longer
+/// pending are returned untouched, so repeated callbacks and status queries
+/// are harmless, except that a completed one still waiting for its real
+/// receipt takes it. A completed payment must match the deposit's amount when
+/// the provider reports one and carry a receipt that has not been seen before.
+pub async fn settle_deposit(pool:&MySqlPool, vendor_reference:&str, outcome:&PaymentStatus) -> Result<Deposit, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let mut deposit = lock_deposit_by_checkout(&mut tx, vendor_reference).await?;
+    if deposit.status != DepositStatus::PENDING {
+        // A late callback brings the receipt a status query could not
+        if let PaymentStatus::Completed { receipt_no, .. } = outcome
+            && has_placeholder_receipt(&deposit)
+            && *receipt_no != deposit.vendor_reference {
+            replace_placeholder_receipt(&mut tx, &mut deposit, receipt_no).await?;
+            tx.commit().await?;
+            return Ok(deposit);
+        }
+        info!("Deposit {:?} already {:?}, ignoring repeated result", deposit.id, deposit.status);
+        return Ok(deposit);
+    }
+
+    let (receipt_no, amount) = match outcome {
+        PaymentStatus::Pending => return Ok(deposit),
+        PaymentStatus::Failed { reason } => {
+            sqlx::query("UPDATE deposit SET status = ?, updated_at = ? WHERE id = ?")
+                .bind(DepositStatus::FAILED)
+                .bind(utils::now_eat())
+                .bind(deposit.id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            info!("Deposit {:?} failed: {}", deposit.id, reason);
+            deposit.status = DepositStatus::FAILED;
+            return Ok(deposit);
+        }
+        PaymentStatus::Completed { receipt_no, amount } => (receipt_no, amount),
+    };
+
+    if let Some(amount) = amount
+        && *amount != deposit.amount {
+        error!("Deposit {:?} paid amount {} does not match {}", deposit.id, amount, deposit.amount);
+        return Err(AppError::BadRequest("Paid amount does not match the deposit".to_string()));
+    }
+
+    complete_deposit(&mut tx, &deposit, receipt_no).await?;
+    tx.commit().await?;
+
+    info!("Deposit {:?} of {} completed with receipt {}", deposit.id, deposit.amount, receipt_no);
+    if let Some(user_id) = deposit.user_id {
+        notification_service::notify_user(
+            pool,
+            &user_id,
+            "Deposit received",
+            &received_message(&deposit, receipt_no),
+        ).await;
+    }
+
+    deposit.status = DepositStatus::COMPLETED;
+    deposit.receipt_no = Some(receipt_no.clone());
+    Ok(deposit)
+}
+
+/// Applies the STK push callback. A successful callback must state the amount
+/// paid and the M-Pesa receipt.
+pub async fn settle_stk_callback(pool:&MySqlPool, callback:&StkCallbackDto) -> Result<Deposit, AppError> {
+    let outcome = if callback.result_code != 0 {
+        PaymentStatus::Failed { reason:callback.result_desc.clone() }
+    } else {
+        let Some(amount) = callback.item("Amount").and_then(|value| serde_json::from_value::<Money>(value.clone()).ok()) else {
+            return Err(AppError::BadRequest("Callback carries no amount".to_string()));
+        };
+        let Some(receipt_no) = callback.item("MpesaReceiptNumber").and_then(|value| value.as_str()) else {
+            return Err(AppError::BadRequest("Callback carries no receipt number".to_string()));
+        };
+        PaymentStatus::Completed { receipt_no:receipt_no.to_string(), amount:Some(amount) }
+    };
+
+    settle_deposit(pool, &callback.checkout_request_id, &outcome).await
+}
+
+/// Takes back a deposit the provider's statement does not show, or gives up
+/// on one still pending. The reason is kept on the journal entry.
+pub async fn reverse_deposit(pool:&MySqlPool, staff_id:&i64, deposit_id:&i64, reason:&str) -> Result<Deposit, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let deposit = sqlx::query_as::<_, Deposit>("SELECT * FROM deposit WHERE id = ? FOR UPDATE")
+        .bind(deposit_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(mut deposit) = deposit else {
+        return Err(AppError::NotFound("No such deposit".to_string()));
+    };
+
+    let status = match deposit.status {
+        // Nothing was credited yet
+        DepositStatus::PENDING => DepositStatus::FAILED,
+        DepositStatus::COMPLETED | DepositStatus::SUSPENSE => {
+            ledger_service::post_journal(
+                &mut tx,
+                &JournalRequest {
+                    reference:&format!("DEPOSIT-{}-REVERSAL", deposit_id),
+                    entry_type:"DEPOSIT_REVERSAL",
+                    narration:&format!("Reversal of deposit {}: {}", deposit.vendor_reference, reason),
+                    created_by:Some(*staff_id),
+                },
+                &[
+                    PostingLine::debit(deposit_account(&deposit), deposit.amount),
+                    PostingLine::credit(LedgerAccountKey::Cash, deposit.amount),
+                ],
+            ).await?;
+            contribution_service::reverse_deposit_contribution(&mut tx, deposit_id).await?;
+            DepositStatus::REVERSED
+        }
+        DepositStatus::FAILED | DepositStatus::REVERSED => {
+            return Err(AppError::Conflict(format!("Deposit is already {:?}", deposit.status)));
+        }
+    };
+
+    sqlx::query("UPDATE deposit SET status = ?, updated_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(utils::now_eat())
+        .bind(deposit_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("Deposit {} reversed by {} from {:?}: {}", deposit_id, staff_id, deposit.status, reason);
+    deposit.status = status;
+    Ok(deposit)
+}
+
+/// Who a paybill or till payment is for, from the account number the
+/// customer typed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum C2bTarget {
+    // A phone number, paid into that user's wallet
+    User(i64),
+    // A chama's registration number, paid into its savings. The member is
+    // the user paying from their registered phone, when they belong to it
+    Chama { chama_id:i64, member_id:Option<i64> },
+}
+
+async fn find_user_by_phone(pool:&MySqlPool, phone:&str) -> Option<i64> {
+    let msisdn = utils::is_valid_phone(phone.trim())?;
+    authentication_service::get_auth_user(pool, &msisdn).await?.id
+}
+
+pub async fn match_c2b_target(pool:&MySqlPool, bill_ref_number:&str, msisdn:&str) -> Result<Option<C2bTarget>, AppError> {
+    if let Some(user_id) = find_user_by_phone(pool, bill_ref_number).await {
+        return Ok(Some(C2bTarget::User(user_id)));
+    }
+
+    let chama: Option<(i64,)> = sqlx::query_as("SELECT id FROM chama WHERE reg_number = ?")
+        .bind(bill_ref_number.trim())
+        .fetch_optional(pool)
+        .await?;
+    let Some((chama_id,)) = chama else {
+        return Ok(None);
+    };
+
+    let member_id = match find_user_by_phone(pool, msisdn).await {
+        Some(user_id) if chama_service::is_active_member(pool, &chama_id, &user_id).await? => Some(user_id),
+        _ => None,
+    };
+    Ok(Some(C2bTarget::Chama { chama_id, member_id }))
+}
+
+/// Answers M-Pesa's C2B validation: payments are only let through when the
+/// amount is sensible and the account number matches a user or chama.
+/// Payments still reach confirmation unvalidated when validation is off for
+/// the shortcode or our answer is late, so confirmation must cope with
+/// unmatched references too.
+pub async fn validate_c2b_payment(pool:&MySqlPool, payment:&C2bPaymentDto) -> Result<(), AppError> {
+    if !payment.trans_amount.is_positive() {
+        return Err(AppError::BadRequest("Invalid amount".to_string()));
+    }
+    if find_pending_by_bill_no(pool, &payment.bill_ref_number).await?.is_some() {
+        return Ok(());
+    }
+    if match_c2b_target(pool, &payment.bill_ref_number, &payment.msisdn).await?.is_none() {
+        return Err(AppError::NotFound(format!("Unknown account number {}", payment.bill_ref_number)));
+    }
+    Ok(())
+}
+
+/// A pending deposit the customer was asked to pay by paybill, quoting its
+/// bill number as the account, as drivers without a push prompt do.
+async fn find_pending_by_bill_no<'c, E>(executor:E, bill_no:&str) -> Result<Option<Deposit>, AppError>
+where
+    E: sqlx::Executor<'c, Database = sqlx::MySql>,
+{
+    let deposit = sqlx::query_as::<_, Deposit>(
+        "SELECT * FROM deposit WHERE bill_no = ? AND status = ? FOR UPDATE"
+    )
+    .bind(bill_no.trim())
+    .bind(DepositStatus::PENDING)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(deposit)
+}
+
+/// Records a confirmed paybill or till payment as a deposit and credits the
+/// matched wallet or chama, or parks it in suspense when nothing matches. A
+/// payment quoting the bill number of a pending deposit for the same amount
+/// completes that deposit instead. Repeated confirmations of the same M-Pesa
+/// transaction return the deposit already recorded.
+pub async fn confirm_c2b_payment(pool:&MySqlPool, payment:&C2bPaymentDto) -> Result<Deposit, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let existing = sqlx::query_as::<_, Deposit>(
+        "SELECT * FROM deposit WHERE vendor_reference = ? OR receipt_no = ? FOR UPDATE"
+    )
+    .bind(&payment.trans_id)
+    .bind(&payment.trans_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(deposit) = existing {
+        info!("C2B payment {} already recorded as deposit {:?}", payment.trans_id, deposit.id);
+        return Ok(deposit);
+    }
+    if !payment.trans_amount.is_positive() {
+        return Err(AppError::BadRequest("Invalid amount".to_string()));
+    }
+
+    if let Some(mut deposit) = find_pending_by_bill_no(&mut *tx, &payment.bill_ref_number).await?
+        && deposit.amount == payment.trans_amount {
+        complete_deposit(&mut tx, &deposit, &payment.trans_id).await?;
+        tx.commit().await?;
+
+        info!("C2B payment {} completed pending deposit {:?}", payment.trans_id, deposit.id);
+        if let Some(user_id) = deposit.user_id {
+            notification_service::notify_user(
+                pool,
+                &user_id,
+                "Deposit received",
+                &received_message(&deposit, &payment.trans_id),
+            ).await;
+        }
+
+        deposit.status = DepositStatus::COMPLETED;
+        deposit.receipt_no = Some(payment.trans_id.clone());
+        return Ok(deposit);
+    }
+
+    let target = match_c2b_target(pool, &payment.bill_ref_number, &payment.msisdn).await?;
+    let (user_id, chama_id, status) = match target {
+        Some(C2bTarget::User(user_id)) => (Some(user_id), None, DepositStatus::COMPLETED),
+        Some(C2bTarget::Chama { chama_id, member_id }) => (member_id, Some(chama_id), DepositStatus::COMPLETED),
+        None => (None, None, DepositStatus::SUSPENSE),
+    };
+
+    let now_eat = utils::now_eat();
+    let mut deposit = Deposit {
+        id:None,
+        user_id,
+        chama_id,
+        paying_number:payment.msisdn.clone(),
+        bill_no:payment.bill_ref_number.clone(),
+        account_no:payment.business_short_code.clone(),
+        vendor_reference:payment.trans_id.clone(),
+        amount:payment.trans_amount,
+        receipt_no:Some(payment.trans_id.clone()),
+        gateway:C2B_GATEWAY.to_string(),
+        bill_date:NaiveDateTime::parse_from_str(&payment.trans_time, "%Y%m%d%H%M%S").unwrap_or(now_eat),
+        status,
+        created_at:now_eat,
+        updated_at:now_eat,
+        created_by:user_id.unwrap_or_default(),
+    };
+    let deposit_repository = data_repository::DataRepository::<Deposit> {
+        pool,
+        table_name: "deposit",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let deposit_id = deposit_repository.insert_trx(&mut tx, &deposit).await?;
+    deposit.id = Some(deposit_id);
+
+    // Keyed on the M-Pesa transaction so a racing duplicate fails here
+    ledger_service::post_journal(
+        &mut tx,
+        &JournalRequest {
+            reference:&format!("C2B-{}", payment.trans_id),
+            entry_type:"DEPOSIT",
+            narration:&format!("Paybill payment {} from {} for {}", payment.trans_id, payment.msisdn, payment.bill_ref_number),
+            created_by:None,
+        },
+        &[
+            PostingLine::debit(LedgerAccountKey::Cash, deposit.amount),
+            PostingLine::credit(deposit_account(&deposit), deposit.amount),
+        ],
+    ).await?;
+    contribution_service::record_deposit_contribution(&mut tx, &deposit, &payment.trans_id).await?;
+    tx.commit().await?;
+
+    info!("C2B payment {} of {} recorded as deposit {} ({:?})", payment.trans_id, deposit.amount, deposit_id, deposit.status);
+    if let Some(user_id) = deposit.user_id {
+        notification_service::notify_user(
+            pool,
+            &user_id,
+            "Payment received",
+            &format!("We have received KES {} for account {}. M-Pesa receipt {}.", deposit.amount, deposit.bill_no, payment.trans_id),
+        ).await;
+    }
+
+    Ok(deposit)
+}
+
+pub async fn get_suspense_deposits(pool:&MySqlPool, user_id:&str) -> Result<Vec<Deposit>, AppError> {
+    authentication_service::require_staff(pool, user_id).await?;
+
+    let deposits = sqlx::query_as::<_, Deposit>(
+        "SELECT * FROM deposit WHERE status = ? ORDER BY created_at"
+    )
+    .bind(DepositStatus::SUSPENSE)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(deposits)
+}
+
+/// Moves an unmatched payment out of suspense into the account staff have
+/// identified it belongs to. The reason is kept on the journal entry.
+pub async fn reassign_suspense_deposit(
+    pool:&MySqlPool,
+    user_id:&str,
+    deposit_id:&i64,
+    payload:&SuspenseReassignDto) -> Result<Deposit, AppError> {
+
+    authentication_service::require_staff(pool, user_id).await?;
+    let staff_id = loan_service::parse_user_id(user_id)?;
+
+    if payload.reason.trim().is_empty() {
+        return Err(AppError::BadRequest("A reason is required".to_string()));
+    }
+    match (payload.user_id, payload.chama_id) {
+        (None, None) => return Err(AppError::BadRequest("Choose a user or a chama".to_string())),
+        (Some(member_id), Some(chama_id)) => {
+            if !chama_service::is_active_member(pool, &chama_id, &member_id).await? {
+                return Err(AppError::BadRequest("User is not an active member of the chama".to_string()));
+            }
+        }
+        (Some(member_id), None) => {
+            if authentication_service::get_auth_user_by_id(pool, &member_id.to_string()).await.is_none() {
+                return Err(AppError::NotFound("No such user".to_string()));
+            }
+        }
+        (None, Some(chama_id)) => {
+            let chama: Option<(i64,)> = sqlx::query_as("SELECT id FROM chama WHERE id = ?")
+                .bind(chama_id)
+                .fetch_optional(pool)
+                .await?;
+            if chama.is_none() {
+                return Err(AppError::NotFound("No such chama".to_string()));
+            }
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let deposit = sqlx::query_as::<_, Deposit>("SELECT * FROM deposit WHERE id = ? FOR UPDATE")
+        .bind(deposit_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(mut deposit) = deposit else {
+        return Err(AppError::NotFound("No such deposit".to_string()));
+    };
+    if deposit.status != DepositStatus::SUSPENSE {
+        return Err(AppError::Conflict("Deposit is not in suspense".to_string()));
+    }
+
+    deposit.user_id = payload.user_id;
+    deposit.chama_id = payload.chama_id;
+    deposit.status = DepositStatus::COMPLETED;
+
+    sqlx::query("UPDATE deposit SET user_id = ?, chama_id = ?, status = ?, updated_at = ? WHERE id = ?")
+        .bind(deposit.user_id)
+        .bind(deposit.chama_id)
+        .bind(deposit.status)
+        .bind(utils::now_eat())
+        .bind(deposit_id)
+        .execute(&mut *tx)
+        .await?;
+
+    ledger_service::post_journal(
+        &mut tx,
+        &JournalRequest {
+            reference:&format!("DEPOSIT-{}-REASSIGN", deposit_id),
+            entry_type:"SUSPENSE_REASSIGN",
+            narration:&format!("Suspense payment {} reassigned: {}", deposit.vendor_reference, payload.reason.trim()),
+            created_by:Some(staff_id),
+        },
+        &[
+            PostingLine::debit(LedgerAccountKey::Suspense, deposit.amount),
+            PostingLine::credit(deposit_account(&deposit), deposit.amount),
+        ],
+    ).await?;
+    contribution_service::record_deposit_contribution(&mut tx, &deposit, &deposit.vendor_reference).await?;
+    tx.commit().await?;
+
+    info!("Suspense deposit {} reassigned by {} to user {:?} chama {:?}", deposit_id, staff_id, deposit.user_id, deposit.chama_id);
+    if let Some(member_id) = deposit.user_id {
+        notification_service::notify_user(
+            pool,
+            &member_id,
+            "Payment received",
+            &format!("Your payment of KES {} with receipt {} has been credited.", deposit.amount, deposit.vendor_reference),
+        ).await;
+    }
+
+    Ok(deposit)
+}
+
+pub async fn get_user_deposits(pool:&MySqlPool, user_id:&str) -> Result<Vec<Deposit>, AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    let deposits = sqlx::query_as::<_, Deposit>(
+        "SELECT * FROM deposit WHERE user_id = ? ORDER BY created_at DESC"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(deposits)
+}
+
+pub async fn get_deposit(pool:&MySqlPool, user_id:&str, deposit_id:&i64) -> Result<Deposit, AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    let deposit_repository = data_repository::DataRepository::<Deposit> {
+        pool,
+        table_name: "deposit",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    match deposit_repository.find_by_id(deposit_id).await? {
+        Some(deposit) if deposit.user_id == Some(user_id) => Ok(deposit),
+        _ => Err(AppError::NotFound("No such deposit".to_string())),
+    }
+}
+
+/// Stands in for the provider when the mock gateway is in use: confirms the
+/// caller's pending deposit exactly as a successful STK callback would.
+pub async fn complete_mock_deposit(pool:&MySqlPool, user_id:&str, checkout_request_id:&str) -> Result<Deposit, AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    let deposit = sqlx::query_as::<_, Deposit>("SELECT * FROM deposit WHERE vendor_reference = ?")
+        .bind(checkout_request_id)
+        .fetch_optional(pool)
+        .await?;
+    let Some(deposit) = deposit.filter(|deposit| deposit.user_id == Some(user_id)) else {
+        return Err(AppError::NotFound("No such deposit".to_string()));
+    };
+    if deposit.gateway != "mock" {
+        return Err(AppError::BadRequest("Deposit was not made through the mock gateway".to_string()));
+    }
+
+    let callback = StkCallbackDto {
+        merchant_request_id:format!("MOCK-{}", deposit.bill_no),
+        checkout_request_id:checkout_request_id.to_string(),
+        result_code:0,
+        result_desc:"The service request is processed successfully.".to_string(),
+        callback_metadata:Some(CallbackMetadataDto {
+            item:vec![
+                CallbackItemDto { name:"Amount".to_string(), value:Some(serde_json::json!(deposit.amount)) },
+                CallbackItemDto {
+                    name:"MpesaReceiptNumber".to_string(),
+                    value:Some(serde_json::json!(format!("MOCK{}", utils::generate_invite_hash_64().to_uppercase()))),
+                },
+                CallbackItemDto { name:"PhoneNumber".to_string(), value:Some(serde_json::json!(deposit.paying_number)) },
+            ],
+        }),
+    };
+
+    settle_stk_callback(pool, &callback).await
+}
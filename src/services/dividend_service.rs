@@ -0,0 +1,260 @@
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::info;
+
+use crate::dtos::dividend::{DividendRunDetailDto, DividendRunDto};
+use crate::error::AppError;
+use crate::models::chama::ChamaPermission;
+use crate::models::dividend::{DividendPayout, DividendRun};
+use crate::models::fine::{FineStatus, PenaltyTriggerEnum};
+use crate::money::{self, Money};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, contribution_service, ledger_service, loan_service, notification_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+/// Works a dividend run out without saving anything.
+///
+/// Income is what the chama's loans earned in interest, fees and penalties
+/// over the period plus the fines members paid; loan fines are left out as
+/// they come in with the penalties. Each active member's share of what is not
+/// retained is weighted by their savings balance on every day of the period,
+/// so money saved early earns more than money saved just before year end.
+async fn compute_run(conn:&mut MySqlConnection, created_by:i64, payload:&DividendRunDto) -> Result<DividendRunDetailDto, AppError> {
+    if payload.period_start > payload.period_end {
+        return Err(AppError::BadRequest("Period cannot end before it starts".to_string()));
+    }
+    let retained_rate = payload.retained_rate.unwrap_or(0.0);
+    if !(0.0..=100.0).contains(&retained_rate) {
+        return Err(AppError::BadRequest("Retained percentage must be between 0 and 100".to_string()));
+    }
+
+    let overlapping: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM dividend_run WHERE chama_id = ? AND period_start <= ? AND period_end >= ?"
+    )
+    .bind(payload.chama_id)
+    .bind(payload.period_end)
+    .bind(payload.period_start)
+    .fetch_one(&mut *conn)
+    .await?;
+    if overlapping.0 > 0 {
+        return Err(AppError::Conflict("Dividends were already shared out for part of this period".to_string()));
+    }
+
+    let (interest_income, fee_income, penalty_income): (Money, Money, Money) = sqlx::query_as(
+        "SELECT COALESCE(SUM(r.interest_paid), 0), COALESCE(SUM(r.fees_paid), 0), COALESCE(SUM(r.penalty_paid), 0)
+        FROM loan_repayment r
+        INNER JOIN loan_request lr ON lr.id = r.load_request_id
+        WHERE lr.chama_id = ? AND DATE(r.paid_at) BETWEEN ? AND ?"
+    )
+    .bind(payload.chama_id)
+    .bind(payload.period_start)
+    .bind(payload.period_end)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let (fine_income,): (Money,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount), 0) FROM fine
+        WHERE chama_id = ? AND status = ? AND trigger_type <> ? AND DATE(settled_at) BETWEEN ? AND ?"
+    )
+    .bind(payload.chama_id)
+    .bind(FineStatus::PAID)
+    .bind(PenaltyTriggerEnum::LATEREPAYMENT)
+    .bind(payload.period_start)
+    .bind(payload.period_end)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let total_income = interest_income + fee_income + penalty_income + fine_income;
+    let retained = total_income.percent_rounded(money::rate(retained_rate));
+    let distributed = total_income - retained;
+
+    let members: Vec<(i64,)> = sqlx::query_as(
+        "SELECT DISTINCT user_id FROM chama_member WHERE chama_id = ? AND is_active = 1 ORDER BY user_id"
+    )
+    .bind(payload.chama_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let days = (payload.period_end - payload.period_start).num_days() + 1;
+    let mut balance_days = Vec::with_capacity(members.len());
+    for (user_id,) in &members {
+        let (weight,): (Money,) = sqlx::query_as(
+            "SELECT COALESCE(SUM(amount * (DATEDIFF(?, GREATEST(DATE(paid_at), ?)) + 1)), 0) FROM contribution
+            WHERE chama_id = ? AND user_id = ? AND is_active = 1 AND DATE(paid_at) <= ?"
+        )
+        .bind(payload.period_end)
+        .bind(payload.period_start)
+        .bind(payload.chama_id)
+        .bind(user_id)
+        .bind(payload.period_end)
+        .fetch_one(&mut *conn)
+        .await?;
+        balance_days.push(weight);
+    }
+    let shares = distributed.allocate(&balance_days);
+
+    let now_eat = utils::now_eat();
+    let payouts = members.iter().zip(balance_days.iter().zip(shares))
+        .map(|((user_id,), (weight, amount))| DividendPayout {
+            id:None,
+            run_id:0,
+            user_id:*user_id,
+            average_balance:Money::from_minor(weight.minor_units() / days),
+            amount,
+            created_at:now_eat,
+        })
+        .collect();
+
+    // Nobody to pay means the chama keeps the lot
+    let (retained, distributed) = if balance_days.iter().any(|w| w.is_positive()) {
+        (retained, distributed)
+    } else {
+        (total_income, Money::ZERO)
+    };
+
+    let run = DividendRun {
+        id:None,
+        chama_id:payload.chama_id,
+        period_start:payload.period_start,
+        period_end:payload.period_end,
+        interest_income,
+        fee_income,
+        penalty_income,
+        fine_income,
+        total_income,
+        retained_rate,
+        retained,
+        distributed,
+        created_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    Ok(DividendRunDetailDto { run, payouts })
+}
+
+pub async fn preview_run(pool:&MySqlPool, user_id:&str, payload:&DividendRunDto) -> Result<DividendRunDetailDto, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGEWALLET).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+
+    let mut conn = pool.acquire().await?;
+    compute_run(&mut conn, created_by, payload).await
+}
+
+/// Computes the run again and posts it: the chama's loan income is moved into
+/// its savings and each member's share is credited to their wallet.
+pub async fn post_run(pool:&MySqlPool, user_id:&str, payload:&DividendRunDto) -> Result<DividendRunDetailDto, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGEWALLET).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+    if payload.period_end >= utils::now_eat().date() {
+        return Err(AppError::BadRequest("Dividends can only be posted once the period has ended".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    // Runs for the same chama are posted one at a time
+    sqlx::query("SELECT id FROM chama WHERE id = ? FOR UPDATE")
+        .bind(payload.chama_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let DividendRunDetailDto { mut run, payouts } = compute_run(&mut tx, created_by, payload).await?;
+    if !run.total_income.is_positive() {
+        return Err(AppError::BadRequest("The chama earned nothing to share out in this period".to_string()));
+    }
+
+    let run_repository = data_repository::DataRepository::<DividendRun> {
+        pool,
+        table_name: "dividend_run",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let run_id = run_repository.insert_trx(&mut tx, &run).await?;
+    run.id = Some(run_id);
+
+    let loan_income = run.interest_income + run.fee_income + run.penalty_income;
+    if loan_income.is_positive() {
+        ledger_service::post_journal(
+            &mut tx,
+            &JournalRequest {
+                reference:&format!("DIVIDEND-INCOME-{}", run_id),
+                entry_type:"DIVIDEND_INCOME",
+                narration:&format!("Loan income of chama {} for {} to {}", run.chama_id, run.period_start, run.period_end),
+                created_by:Some(created_by),
+            },
+            &[
+                PostingLine::debit(LedgerAccountKey::InterestIncome, run.interest_income),
+                PostingLine::debit(LedgerAccountKey::FeeIncome, run.fee_income),
+                PostingLine::debit(LedgerAccountKey::PenaltyIncome, run.penalty_income),
+                PostingLine::credit(LedgerAccountKey::ChamaSavings(run.chama_id), loan_income),
+            ],
+        ).await?;
+    }
+
+    let payout_repository = data_repository::DataRepository::<DividendPayout> {
+        pool,
+        table_name: "dividend_payout",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let mut posted = Vec::new();
+    let mut lines = vec![PostingLine::debit(LedgerAccountKey::ChamaSavings(run.chama_id), run.distributed)];
+    for payout in payouts.into_iter().filter(|p| p.amount.is_positive()) {
+        let payout = DividendPayout { run_id, ..payout };
+        let payout_id = payout_repository.insert_trx(&mut tx, &payout).await?;
+        lines.push(PostingLine::credit(LedgerAccountKey::Wallet(payout.user_id), payout.amount));
+        posted.push(DividendPayout { id:Some(payout_id), ..payout });
+    }
+    if run.distributed.is_positive() {
+        ledger_service::post_journal(
+            &mut tx,
+            &JournalRequest {
+                reference:&format!("DIVIDEND-{}", run_id),
+                entry_type:"DIVIDEND",
+                narration:&format!("Dividends of chama {} for {} to {}", run.chama_id, run.period_start, run.period_end),
+                created_by:Some(created_by),
+            },
+            &lines,
+        ).await?;
+    }
+    tx.commit().await?;
+
+    info!("Dividend run {} of chama {} shared {} and retained {}", run_id, run.chama_id, run.distributed, run.retained);
+    for payout in &posted {
+        let message = format!("You have received a dividend of {} for {} to {}, credited to your account.",
+            payout.amount, run.period_start, run.period_end);
+        notification_service::notify_user(pool, &payout.user_id, "Chama dividend", &message).await;
+    }
+    Ok(DividendRunDetailDto { run, payouts:posted })
+}
+
+pub async fn get_runs(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<DividendRun>, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let runs = sqlx::query_as::<_, DividendRun>(
+        "SELECT * FROM dividend_run WHERE chama_id = ? ORDER BY period_end DESC"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(runs)
+}
+
+pub async fn get_run(pool:&MySqlPool, user_id:&str, run_id:&i64) -> Result<DividendRunDetailDto, AppError> {
+    let run = sqlx::query_as::<_, DividendRun>("SELECT * FROM dividend_run WHERE id = ?")
+        .bind(run_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such dividend run".to_string()))?;
+    contribution_service::require_member(pool, user_id, &run.chama_id).await?;
+
+    let payouts = sqlx::query_as::<_, DividendPayout>(
+        "SELECT * FROM dividend_payout WHERE run_id = ? ORDER BY amount DESC"
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(DividendRunDetailDto { run, payouts })
+}
@@ -0,0 +1,465 @@
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::info;
+
+use crate::dtos::member_exit::{
+    ExitGuaranteeDto,
+    ExitLoanDto,
+    ExitStatementDto,
+    MemberExitDetailDto,
+    MemberExitDto,
+    MemberExitRejectDto
+};
+use crate::error::AppError;
+use crate::models::chama::ChamaPermission;
+use crate::models::fine::{Fine, FineStatus, PenaltyTriggerEnum};
+use crate::models::loan::{GuaranteeStatus, LoadRequestStatus, LoanRepaymentMethod};
+use crate::models::member_exit::{MemberExit, MemberExitStatus};
+use crate::money::Money;
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, contribution_service, ledger_service, loan_service, notification_service, repayment_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+/// Works out what a member would leave the chama with: their savings less
+/// what they still owe on loans and fines. Anything that stops the exit from
+/// being settled, such as a loan they guarantee that is still being repaid,
+/// is listed in `blockers`.
+async fn compute_statement(conn:&mut MySqlConnection, chama_id:&i64, user_id:&i64) -> Result<ExitStatementDto, AppError> {
+    let savings = chama_service::get_member_savings(&mut *conn, chama_id, user_id).await?;
+    let mut blockers = Vec::new();
+
+    let loan_rows: Vec<(i64, LoadRequestStatus, Money)> = sqlx::query_as(
+        "SELECT lr.id, lr.status, COALESCE(SUM(s.principal - s.principal_paid + s.interest - s.interest_paid
+            + s.fees - s.fees_paid + s.penalty - s.penalty_paid), 0)
+        FROM loan_request lr
+        LEFT JOIN loan_repayment_schedule s ON s.load_request_id = lr.id
+        WHERE lr.chama_id = ? AND lr.user_id = ? AND lr.status IN (?, ?, ?, ?, ?)
+        GROUP BY lr.id, lr.status
+        ORDER BY lr.id"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .bind(LoadRequestStatus::PENDING)
+    .bind(LoadRequestStatus::PARTIALYAPPROVED)
+    .bind(LoadRequestStatus::APPROVED)
+    .bind(LoadRequestStatus::DISBURSED)
+    .bind(LoadRequestStatus::DEFAULTED)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut loans = Vec::with_capacity(loan_rows.len());
+    for (loan_id, status, outstanding) in loan_rows {
+        if !matches!(status, LoadRequestStatus::DISBURSED | LoadRequestStatus::DEFAULTED) {
+            blockers.push(format!("Loan {} is still {:?} and must be cancelled first", loan_id, status));
+        }
+        loans.push(ExitLoanDto { loan_id, status, outstanding });
+    }
+    let loan_balance: Money = loans.iter().map(|l| l.outstanding).sum();
+
+    // Loan fines are part of the loan balance
+    let fines = sqlx::query_as::<_, Fine>(
+        "SELECT * FROM fine WHERE chama_id = ? AND user_id = ? AND status = ? AND trigger_type <> ? ORDER BY id"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .bind(FineStatus::OUTSTANDING)
+    .bind(PenaltyTriggerEnum::LATEREPAYMENT)
+    .fetch_all(&mut *conn)
+    .await?;
+    let fine_balance: Money = fines.iter().map(|f| f.amount).sum();
+
+    let guarantee_rows: Vec<(i64, i64, i64, LoadRequestStatus, GuaranteeStatus, Money)> = sqlx::query_as(
+        "SELECT g.id, g.load_request_id, lr.user_id, lr.status, g.status, g.amount_quaranteed
+        FROM loan_request_guarantee g
+        INNER JOIN loan_request lr ON lr.id = g.load_request_id
+        WHERE lr.chama_id = ? AND g.loan_quaranter_id = ? AND g.status IN (?, ?)
+        ORDER BY g.id"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .bind(GuaranteeStatus::INVITED)
+    .bind(GuaranteeStatus::ACCEPTED)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut guarantees = Vec::with_capacity(guarantee_rows.len());
+    for (guarantee_id, loan_id, borrower_id, loan_status, status, amount) in guarantee_rows {
+        if status == GuaranteeStatus::ACCEPTED && matches!(loan_status, LoadRequestStatus::DISBURSED | LoadRequestStatus::DEFAULTED) {
+            blockers.push(format!("Guarantees {} on loan {} of member {}, which is still {:?}", amount, loan_id, borrower_id, loan_status));
+        }
+        guarantees.push(ExitGuaranteeDto { guarantee_id, loan_id, borrower_id, loan_status, status, amount });
+    }
+
+    let net_amount = savings - loan_balance - fine_balance;
+    if net_amount.is_negative() {
+        blockers.push(format!("Savings fall {} short of the loans and fines owed", -net_amount));
+    }
+
+    Ok(ExitStatementDto {
+        chama_id:*chama_id,
+        user_id:*user_id,
+        savings,
+        loans,
+        loan_balance,
+        fines,
+        fine_balance,
+        guarantees,
+        net_amount,
+        blockers,
+    })
+}
+
+/// True while a member still has savings, loans, fines or pledges in the
+/// chama, in which case they can only leave through an exit settlement.
+pub async fn has_open_balances(pool:&MySqlPool, chama_id:&i64, user_id:&i64) -> Result<bool, AppError> {
+    let mut conn = pool.acquire().await?;
+    let statement = compute_statement(&mut conn, chama_id, user_id).await?;
+
+    Ok(!statement.savings.is_zero()
+        || !statement.loans.is_empty()
+        || !statement.fines.is_empty()
+        || !statement.guarantees.is_empty())
+}
+
+/// The exit statement of a member as it stands, for the member or an admin.
+pub async fn get_statement(pool:&MySqlPool, user_id:&str, chama_id:&i64, member_id:&i64) -> Result<ExitStatementDto, AppError> {
+    if loan_service::parse_user_id(user_id)? != *member_id {
+        chama_service::require_permission(pool, user_id, chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+    }
+
+    let mut conn = pool.acquire().await?;
+    compute_statement(&mut conn, chama_id, member_id).await
+}
+
+/// Opens the exit of an active member. Nothing is paid out or closed until an
+/// admin settles it.
+pub async fn open_exit(pool:&MySqlPool, chama_id:&i64, member_id:&i64, requested_by:i64, reason:&str) -> Result<MemberExitDetailDto, AppError> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::BadRequest("A reason is required".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let active: Option<(i64,)> = sqlx::query_as(
+        "SELECT id FROM chama_member WHERE chama_id = ? AND user_id = ? AND is_active = 1 LIMIT 1 FOR UPDATE"
+    )
+    .bind(chama_id)
+    .bind(member_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if active.is_none() {
+        return Err(AppError::NotFound(format!("User {} is not a member of this chama", member_id)));
+    }
+    let (pending,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM member_exit WHERE chama_id = ? AND user_id = ? AND status = ?")
+        .bind(chama_id)
+        .bind(member_id)
+        .bind(MemberExitStatus::PENDING)
+        .fetch_one(&mut *tx)
+        .await?;
+    if pending > 0 {
+        return Err(AppError::Conflict("This member is already on their way out".to_string()));
+    }
+
+    let statement = compute_statement(&mut tx, chama_id, member_id).await?;
+    let now_eat = utils::now_eat();
+    let mut exit = MemberExit {
+        id:None,
+        chama_id:*chama_id,
+        user_id:*member_id,
+        status:MemberExitStatus::PENDING,
+        reason:reason.to_string(),
+        savings:statement.savings,
+        loan_balance:statement.loan_balance,
+        fine_balance:statement.fine_balance,
+        net_amount:statement.net_amount,
+        requested_by,
+        reviewed_by:None,
+        review_note:None,
+        settled_at:None,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let exit_repository = data_repository::DataRepository::<MemberExit> {
+        pool,
+        table_name: "member_exit",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    exit.id = Some(exit_repository.insert_trx(&mut tx, &exit).await?);
+    tx.commit().await?;
+
+    info!("Exit {:?} of user {} from chama {} opened by {}", exit.id, member_id, chama_id, requested_by);
+    Ok(MemberExitDetailDto { exit, statement })
+}
+
+pub async fn request_exit(pool:&MySqlPool, user_id:&str, payload:&MemberExitDto) -> Result<MemberExitDetailDto, AppError> {
+    let requested_by = loan_service::parse_user_id(user_id)?;
+    let member_id = payload.user_id.unwrap_or(requested_by);
+    if member_id != requested_by {
+        chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+    }
+
+    let detail = open_exit(pool, &payload.chama_id, &member_id, requested_by, &payload.reason).await?;
+    if member_id != requested_by {
+        let message = format!("Your exit from the chama has been started: {}. You will be told once it is settled.", detail.exit.reason);
+        notification_service::notify_user(pool, &member_id, "Leaving the chama", &message).await;
+    }
+    Ok(detail)
+}
+
+async fn lock_exit(conn:&mut MySqlConnection, exit_id:&i64) -> Result<MemberExit, AppError> {
+    let exit = sqlx::query_as::<_, MemberExit>("SELECT * FROM member_exit WHERE id = ? FOR UPDATE")
+        .bind(exit_id)
+        .fetch_optional(conn)
+        .await?;
+
+    exit.ok_or_else(|| AppError::NotFound("No such exit".to_string()))
+}
+
+/// Settles a pending exit against a fresh statement. Loans are repaid and
+/// fines cleared out of the member's savings, the rest goes to their wallet,
+/// pledges on loans not yet paid out are released and the membership ends.
+pub async fn settle_exit(pool:&MySqlPool, user_id:&str, exit_id:&i64) -> Result<MemberExitDetailDto, AppError> {
+    let settled_by = loan_service::parse_user_id(user_id)?;
+
+    let mut tx = pool.begin().await?;
+    let exit = lock_exit(&mut tx, exit_id).await?;
+    chama_service::require_permission(pool, user_id, &exit.chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+    if exit.status != MemberExitStatus::PENDING {
+        return Err(AppError::Conflict(format!("Exit is already {:?}", exit.status)));
+    }
+
+    // Holds off contributions and guarantee reclaims on the member meanwhile
+    sqlx::query("SELECT id FROM chama_member WHERE chama_id = ? AND user_id = ? FOR UPDATE")
+        .bind(exit.chama_id)
+        .bind(exit.user_id)
+        .execute(&mut *tx)
+        .await?;
+    let statement = compute_statement(&mut tx, &exit.chama_id, &exit.user_id).await?;
+    if !statement.blockers.is_empty() {
+        return Err(AppError::Conflict(statement.blockers.join("; ")));
+    }
+    for loan in statement.loans.iter().filter(|l| l.outstanding.is_positive()) {
+        repayment_service::post_repayment(
+            pool,
+            &mut tx,
+            &loan.loan_id,
+            loan.outstanding,
+            LoanRepaymentMethod::SAVINGS,
+            Some(format!("EXIT-{}", exit_id)),
+        ).await?;
+        contribution_service::record_savings_deduction(
+            &mut tx,
+            &exit.chama_id,
+            &exit.user_id,
+            loan.outstanding,
+            &format!("Loan {} repaid on leaving the chama", loan.loan_id),
+        ).await?;
+    }
+
+    // Recovering the loans also draws on the chama wallet, so the payout is
+    // checked against what they leave in it
+    let chama_account = LedgerAccountKey::ChamaSavings(exit.chama_id);
+    let available = ledger_service::lock_balance(&mut tx, &chama_account).await?;
+    if statement.net_amount > available {
+        return Err(AppError::Conflict(format!(
+            "The chama wallet holds {} once the member's loans are repaid, short of the {} due to the member",
+            available, statement.net_amount
+        )));
+    }
+
+    let now_eat = utils::now_eat();
+    for fine in &statement.fines {
+        sqlx::query("UPDATE fine SET status = ?, settled_at = ?, updated_at = ? WHERE id = ? AND status = ?")
+            .bind(FineStatus::PAID)
+            .bind(now_eat)
+            .bind(now_eat)
+            .bind(fine.id)
+            .bind(FineStatus::OUTSTANDING)
+            .execute(&mut *tx)
+            .await?;
+    }
+    if statement.fine_balance.is_positive() {
+        contribution_service::record_savings_deduction(
+            &mut tx,
+            &exit.chama_id,
+            &exit.user_id,
+            statement.fine_balance,
+            "Fines paid on leaving the chama",
+        ).await?;
+    }
+
+    if statement.net_amount.is_positive() {
+        contribution_service::record_savings_deduction(
+            &mut tx,
+            &exit.chama_id,
+            &exit.user_id,
+            statement.net_amount,
+            "Savings paid out on leaving the chama",
+        ).await?;
+        ledger_service::post_journal(
+            &mut tx,
+            &JournalRequest {
+                reference:&format!("MEMBER-EXIT-{}", exit_id),
+                entry_type:"MEMBER_EXIT",
+                narration:&format!("Exit settlement of user {} from chama {}", exit.user_id, exit.chama_id),
+                created_by:Some(settled_by),
+            },
+            &[
+                PostingLine::debit(chama_account, statement.net_amount),
+                PostingLine::credit(LedgerAccountKey::Wallet(exit.user_id), statement.net_amount),
+            ],
+        ).await?;
+    }
+
+    // Only pledges on loans still awaiting disbursement are left by now
+    sqlx::query(
+        "UPDATE loan_request_guarantee g
+        INNER JOIN loan_request lr ON lr.id = g.load_request_id
+        SET g.status = ?, g.updated_at = ?
+        WHERE lr.chama_id = ? AND g.loan_quaranter_id = ? AND g.status IN (?, ?)"
+    )
+    .bind(GuaranteeStatus::RELEASED)
+    .bind(now_eat)
+    .bind(exit.chama_id)
+    .bind(exit.user_id)
+    .bind(GuaranteeStatus::INVITED)
+    .bind(GuaranteeStatus::ACCEPTED)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE chama_member SET is_active = 0, updated_at = ? WHERE chama_id = ? AND user_id = ? AND is_active = 1")
+        .bind(now_eat)
+        .bind(exit.chama_id)
+        .bind(exit.user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "UPDATE member_exit SET status = ?, savings = ?, loan_balance = ?, fine_balance = ?, net_amount = ?,
+            reviewed_by = ?, settled_at = ?, updated_at = ?
+        WHERE id = ?"
+    )
+    .bind(MemberExitStatus::SETTLED)
+    .bind(statement.savings)
+    .bind(statement.loan_balance)
+    .bind(statement.fine_balance)
+    .bind(statement.net_amount)
+    .bind(settled_by)
+    .bind(now_eat)
+    .bind(now_eat)
+    .bind(exit_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    info!("Exit {} of user {} from chama {} settled, {} paid out", exit_id, exit.user_id, exit.chama_id, statement.net_amount);
+    let message = format!(
+        "You have left the chama. Of your savings of {}, {} went to your loans and {} to your fines; {} has been credited to your account.",
+        statement.savings, statement.loan_balance, statement.fine_balance, statement.net_amount
+    );
+    notification_service::notify_user(pool, &exit.user_id, "Leaving the chama", &message).await;
+
+    let exit = MemberExit {
+        status:MemberExitStatus::SETTLED,
+        savings:statement.savings,
+        loan_balance:statement.loan_balance,
+        fine_balance:statement.fine_balance,
+        net_amount:statement.net_amount,
+        reviewed_by:Some(settled_by),
+        settled_at:Some(now_eat),
+        updated_at:now_eat,
+        ..exit
+    };
+    Ok(MemberExitDetailDto { exit, statement })
+}
+
+/// Turns down a pending exit, keeping the admin's reason on it.
+pub async fn reject_exit(pool:&MySqlPool, user_id:&str, exit_id:&i64, payload:&MemberExitRejectDto) -> Result<MemberExit, AppError> {
+    let reviewer_id = loan_service::parse_user_id(user_id)?;
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::BadRequest("A reason is required".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let exit = lock_exit(&mut tx, exit_id).await?;
+    chama_service::require_permission(pool, user_id, &exit.chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+    if exit.status != MemberExitStatus::PENDING {
+        return Err(AppError::Conflict(format!("Exit is already {:?}", exit.status)));
+    }
+
+    let now_eat = utils::now_eat();
+    sqlx::query("UPDATE member_exit SET status = ?, reviewed_by = ?, review_note = ?, updated_at = ? WHERE id = ?")
+        .bind(MemberExitStatus::REJECTED)
+        .bind(reviewer_id)
+        .bind(reason)
+        .bind(now_eat)
+        .bind(exit_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let message = format!("Your exit from the chama was not approved: {}", reason);
+    notification_service::notify_user(pool, &exit.user_id, "Leaving the chama", &message).await;
+    Ok(MemberExit {
+        status:MemberExitStatus::REJECTED,
+        reviewed_by:Some(reviewer_id),
+        review_note:Some(reason.to_string()),
+        updated_at:now_eat,
+        ..exit
+    })
+}
+
+/// Lets a member change their mind while their exit is still pending.
+pub async fn cancel_exit(pool:&MySqlPool, user_id:&str, exit_id:&i64) -> Result<MemberExit, AppError> {
+    let member_id = loan_service::parse_user_id(user_id)?;
+
+    let mut tx = pool.begin().await?;
+    let exit = lock_exit(&mut tx, exit_id).await?;
+    if exit.user_id != member_id {
+        return Err(AppError::Forbidden("Only the member leaving can cancel this exit".to_string()));
+    }
+    if exit.status != MemberExitStatus::PENDING {
+        return Err(AppError::Conflict(format!("Exit is already {:?}", exit.status)));
+    }
+
+    let now_eat = utils::now_eat();
+    sqlx::query("UPDATE member_exit SET status = ?, updated_at = ? WHERE id = ?")
+        .bind(MemberExitStatus::CANCELLED)
+        .bind(now_eat)
+        .bind(exit_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(MemberExit { status:MemberExitStatus::CANCELLED, updated_at:now_eat, ..exit })
+}
+
+/// An exit with the member's statement as it stands now. A settled exit keeps
+/// the amounts it was settled on.
+pub async fn get_exit(pool:&MySqlPool, user_id:&str, exit_id:&i64) -> Result<MemberExitDetailDto, AppError> {
+    let exit = sqlx::query_as::<_, MemberExit>("SELECT * FROM member_exit WHERE id = ?")
+        .bind(exit_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such exit".to_string()))?;
+    let statement = get_statement(pool, user_id, &exit.chama_id, &exit.user_id).await?;
+
+    Ok(MemberExitDetailDto { exit, statement })
+}
+
+pub async fn get_exits(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<MemberExit>, AppError> {
+    chama_service::require_permission(pool, user_id, chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+
+    let exits = sqlx::query_as::<_, MemberExit>(
+        "SELECT * FROM member_exit WHERE chama_id = ? ORDER BY created_at DESC"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(exits)
+}
@@ -0,0 +1,524 @@
+use chrono::{Days, NaiveDate};
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::{info, error};
+
+use crate::dtos::fine::{FineWaiveDto, PenaltyRuleDto};
+use crate::error::AppError;
+use crate::models::chama::ChamaPermission;
+use crate::models::contribution::ContributionExpectation;
+use crate::models::fine::{Fine, FineStatus, PenaltyCalculationEnum, PenaltyRule, PenaltyTriggerEnum};
+use crate::models::loan::LoanRepaymentSchedule;
+use crate::money::{self, Money};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, contribution_service, ledger_service, loan_service, notification_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+/// Sets how the chama fines one kind of lapse, replacing the rule it had.
+pub async fn set_rule(pool:&MySqlPool, user_id:&str, payload:&PenaltyRuleDto) -> Result<PenaltyRule, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGEFINES).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+    if payload.trigger_type == PenaltyTriggerEnum::MISSEDMEETING && payload.calculation != PenaltyCalculationEnum::FLAT {
+        return Err(AppError::BadRequest("Missed meetings can only be fined a flat amount".to_string()));
+    }
+
+    let (amount, rate) = match payload.calculation {
+        PenaltyCalculationEnum::FLAT => match payload.amount {
+            Some(amount) if amount.is_positive() => (amount, 0.0),
+            _ => return Err(AppError::BadRequest("A flat fine needs an amount greater than zero".to_string())),
+        },
+        PenaltyCalculationEnum::PERCENTAGE => match payload.rate {
+            Some(rate) if rate > 0.0 && rate <= 100.0 => (Money::ZERO, rate),
+            _ => return Err(AppError::BadRequest("A percentage fine needs a rate between 0 and 100".to_string())),
+        },
+    };
+    let grace_days = payload.grace_days.unwrap_or(0);
+    if grace_days < 0 {
+        return Err(AppError::BadRequest("Grace days cannot be negative".to_string()));
+    }
+    if let Some(cap) = payload.cap
+        && !cap.is_positive() {
+        return Err(AppError::BadRequest("Cap must be greater than zero".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE penalty_rule SET is_active = 0, updated_at = ? WHERE chama_id = ? AND trigger_type = ? AND is_active = 1")
+        .bind(now_eat)
+        .bind(payload.chama_id)
+        .bind(payload.trigger_type)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut rule = PenaltyRule {
+        id:None,
+        chama_id:payload.chama_id,
+        trigger_type:payload.trigger_type,
+        calculation:payload.calculation,
+        amount,
+        rate,
+        grace_days,
+        cap:payload.cap,
+        is_active:1,
+        created_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let rule_repository = data_repository::DataRepository::<PenaltyRule> {
+        pool,
+        table_name: "penalty_rule",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    rule.id = Some(rule_repository.insert_trx(&mut tx, &rule).await?);
+    tx.commit().await?;
+
+    info!("Chama {} fines {:?} with rule {:?}", rule.chama_id, rule.trigger_type, rule.id);
+    Ok(rule)
+}
+
+pub async fn get_rules(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<PenaltyRule>, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let rules = sqlx::query_as::<_, PenaltyRule>(
+        "SELECT * FROM penalty_rule WHERE chama_id = ? AND is_active = 1 ORDER BY trigger_type"
+    )
+    .bind(chama_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rules)
+}
+
+pub async fn get_active_rule(pool:&MySqlPool, chama_id:&i64, trigger_type:PenaltyTriggerEnum) -> Result<Option<PenaltyRule>, sqlx::Error> {
+    let rule = sqlx::query_as::<_, PenaltyRule>(
+        "SELECT * FROM penalty_rule WHERE chama_id = ? AND trigger_type = ? AND is_active = 1 ORDER BY id DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .bind(trigger_type)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(rule)
+}
+
+/// The fine a rule charges on `unpaid`, after its cap.
+pub fn fine_amount(rule:&PenaltyRule, unpaid:Money) -> Money {
+    let amount = match rule.calculation {
+        PenaltyCalculationEnum::FLAT => rule.amount,
+        PenaltyCalculationEnum::PERCENTAGE => unpaid.percent_rounded(money::rate(rule.rate)),
+    };
+    match rule.cap {
+        Some(cap) => amount.min(cap),
+        None => amount,
+    }
+}
+
+/// The first day a lapse due on `due` can be fined under the rule.
+fn fineable_from(rule:&PenaltyRule, due:NaiveDate) -> NaiveDate {
+    due.checked_add_days(Days::new(rule.grace_days as u64 + 1)).unwrap_or(due)
+}
+
+/// Records a fine unless the member was already fined for the same lapse.
+/// Returns the new fine's id.
+pub async fn create_fine(
+    conn:&mut MySqlConnection,
+    rule:&PenaltyRule,
+    user_id:&i64,
+    source_id:&i64,
+    amount:Money,
+    description:&str) -> Result<Option<i64>, AppError> {
+
+    if !amount.is_positive() {
+        return Ok(None);
+    }
+    let existing: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM fine WHERE chama_id = ? AND trigger_type = ? AND source_id = ? AND user_id = ?")
+        .bind(rule.chama_id)
+        .bind(rule.trigger_type)
+        .bind(source_id)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if existing.0 > 0 {
+        return Ok(None);
+    }
+
+    let now_eat = utils::now_eat();
+    let result = sqlx::query(
+        "INSERT INTO fine (chama_id, user_id, rule_id, trigger_type, source_id, amount, description, status, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(rule.chama_id)
+    .bind(user_id)
+    .bind(rule.id)
+    .bind(rule.trigger_type)
+    .bind(source_id)
+    .bind(amount)
+    .bind(description)
+    .bind(FineStatus::OUTSTANDING)
+    .bind(now_eat)
+    .bind(now_eat)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Some(result.last_insert_id() as i64))
+}
+
+/// Fines members who had not paid a cycle's contribution by its end date
+/// plus the grace days. Earlier overpayments count towards the cycle; a
+/// percentage fine is charged on what was still missing.
+async fn assess_late_contributions(pool:&MySqlPool, rule:&PenaltyRule) -> Result<Vec<(i64, Money)>, AppError> {
+    let today = utils::now_eat().date();
+    let expectations = sqlx::query_as::<_, ContributionExpectation>(
+        "SELECT ce.* FROM contribution_expectation ce
+        INNER JOIN contribution_cycle cc ON cc.id = ce.cycle_id
+        WHERE ce.chama_id = ? AND DATE_ADD(cc.end_date, INTERVAL ? DAY) < ? AND cc.end_date >= DATE(?)
+        AND NOT EXISTS (SELECT 1 FROM fine f WHERE f.trigger_type = ? AND f.source_id = ce.id AND f.user_id = ce.user_id)"
+    )
+    .bind(rule.chama_id)
+    .bind(rule.grace_days)
+    .bind(today)
+    .bind(rule.created_at)
+    .bind(PenaltyTriggerEnum::LATECONTRIBUTION)
+    .fetch_all(pool)
+    .await?;
+
+    let mut fined = Vec::new();
+    for expectation in expectations {
+        let (end_date, cycle_no): (NaiveDate, i32) = sqlx::query_as("SELECT end_date, cycle_no FROM contribution_cycle WHERE id = ?")
+            .bind(expectation.cycle_id)
+            .fetch_one(pool)
+            .await?;
+        let (expected_so_far, paid_in_time): (Money, Money) = sqlx::query_as(
+            "SELECT
+                (SELECT COALESCE(SUM(ce.expected_amount), 0) FROM contribution_expectation ce
+                    INNER JOIN contribution_cycle cc ON cc.id = ce.cycle_id
+                    WHERE ce.chama_id = ? AND ce.user_id = ? AND cc.end_date <= ?),
+                (SELECT COALESCE(SUM(c.amount), 0) FROM contribution c
+                    WHERE c.chama_id = ? AND c.user_id = ? AND c.is_active = 1 AND c.amount > 0 AND c.paid_at < ?)"
+        )
+        .bind(rule.chama_id)
+        .bind(expectation.user_id)
+        .bind(end_date)
+        .bind(rule.chama_id)
+        .bind(expectation.user_id)
+        .bind(fineable_from(rule, end_date))
+        .fetch_one(pool)
+        .await?;
+
+        let unpaid = (expected_so_far - paid_in_time).min(expectation.expected_amount);
+        if !unpaid.is_positive() {
+            continue;
+        }
+
+        let amount = fine_amount(rule, unpaid);
+        let mut conn = pool.acquire().await?;
+        let description = format!("Late contribution for cycle {}, {} unpaid", cycle_no, unpaid);
+        if create_fine(&mut conn, rule, &expectation.user_id, &expectation.id.unwrap_or_default(), amount, &description).await?.is_some() {
+            fined.push((expectation.user_id, amount));
+        }
+    }
+
+    Ok(fined)
+}
+
+/// Fines borrowers of chama loans for installments still unpaid after their
+/// due date plus the grace days. The fine is added to the installment's
+/// penalty, so it is collected with the loan's repayments.
+async fn assess_late_repayments(pool:&MySqlPool, rule:&PenaltyRule) -> Result<Vec<(i64, Money)>, AppError> {
+    let installments: Vec<(i64, i64, i32)> = sqlx::query_as(
+        "SELECT lrs.id, lr.user_id, lrs.installment_no FROM loan_repayment_schedule lrs
+        INNER JOIN loan_request lr ON lr.id = lrs.load_request_id
+        WHERE lr.chama_id = ? AND lr.status IN ('DISBURSED', 'DEFAULTED') AND lrs.status <> 'PAID'
+        AND DATE_ADD(DATE(lrs.due_date), INTERVAL ? DAY) < ? AND lrs.due_date >= ?
+        AND NOT EXISTS (SELECT 1 FROM fine f WHERE f.trigger_type = ? AND f.source_id = lrs.id)"
+    )
+    .bind(rule.chama_id)
+    .bind(rule.grace_days)
+    .bind(utils::now_eat().date())
+    .bind(rule.created_at)
+    .bind(PenaltyTriggerEnum::LATEREPAYMENT)
+    .fetch_all(pool)
+    .await?;
+
+    let mut fined = Vec::new();
+    for (installment_id, borrower_id, installment_no) in installments {
+        let mut tx = pool.begin().await?;
+        let installment = sqlx::query_as::<_, LoanRepaymentSchedule>("SELECT * FROM loan_repayment_schedule WHERE id = ? FOR UPDATE")
+            .bind(installment_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let unpaid = (installment.principal - installment.principal_paid)
+            + (installment.interest - installment.interest_paid)
+            + (installment.fees - installment.fees_paid);
+        if !unpaid.is_positive() {
+            continue;
+        }
+
+        let amount = fine_amount(rule, unpaid);
+        let description = format!("Late repayment of installment {} of loan {}, {} unpaid", installment_no, installment.load_request_id, unpaid);
+        if create_fine(&mut tx, rule, &borrower_id, &installment_id, amount, &description).await?.is_none() {
+            continue;
+        }
+        sqlx::query(
+            "UPDATE loan_repayment_schedule SET penalty = penalty + ?, amount_due = amount_due + ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(amount)
+        .bind(amount)
+        .bind(utils::now_eat())
+        .bind(installment_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        fined.push((borrower_id, amount));
+    }
+
+    Ok(fined)
+}
+
+/// Runs every active rule against contributions and loan installments.
+/// Missed meetings are fined when attendance is recorded. Returns how many
+/// fines were charged.
+pub async fn assess_fines(pool:&MySqlPool) -> Result<u32, AppError> {
+    let rules = sqlx::query_as::<_, PenaltyRule>(
+        "SELECT * FROM penalty_rule WHERE is_active = 1 AND trigger_type IN (?, ?)"
+    )
+    .bind(PenaltyTriggerEnum::LATECONTRIBUTION)
+    .bind(PenaltyTriggerEnum::LATEREPAYMENT)
+    .fetch_all(pool)
+    .await?;
+
+    let mut charged = 0;
+    for rule in &rules {
+        let fined = match rule.trigger_type {
+            PenaltyTriggerEnum::LATECONTRIBUTION => {
+                contribution_service::open_cycles(pool, &rule.chama_id).await?;
+                assess_late_contributions(pool, rule).await
+            }
+            PenaltyTriggerEnum::LATEREPAYMENT => assess_late_repayments(pool, rule).await,
+            PenaltyTriggerEnum::MISSEDMEETING => continue,
+        };
+        let fined = match fined {
+            Ok(fined) => fined,
+            Err(e) => {
+                error!("Could not apply penalty rule {:?} of chama {}: {}", rule.id, rule.chama_id, e);
+                continue;
+            }
+        };
+
+        for (member_id, amount) in &fined {
+            notification_service::notify_user(
+                pool,
+                member_id,
+                "Chama fine",
+                &format!("You have been fined KES {} for a {}.", amount, describe_trigger(rule.trigger_type)),
+            ).await;
+        }
+        charged += fined.len() as u32;
+    }
+
+    Ok(charged)
+}
+
+pub fn describe_trigger(trigger_type:PenaltyTriggerEnum) -> &'static str {
+    match trigger_type {
+        PenaltyTriggerEnum::LATECONTRIBUTION => "late contribution",
+        PenaltyTriggerEnum::MISSEDMEETING => "missed meeting",
+        PenaltyTriggerEnum::LATEREPAYMENT => "late loan repayment",
+    }
+}
+
+/// A member's fines in a chama, newest first.
+pub async fn get_member_fines(pool:&MySqlPool, chama_id:&i64, user_id:&i64) -> Result<Vec<Fine>, sqlx::Error> {
+    let fines = sqlx::query_as::<_, Fine>("SELECT * FROM fine WHERE chama_id = ? AND user_id = ? ORDER BY created_at DESC")
+        .bind(chama_id)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(fines)
+}
+
+/// Officials see every fine in the chama, members only their own.
+pub async fn get_fines(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<Fine>, AppError> {
+    let member_id = loan_service::parse_user_id(user_id)?;
+    if chama_service::require_permission(pool, user_id, chama_id, ChamaPermission::MANAGEFINES).await.is_err() {
+        if !chama_service::is_active_member(pool, chama_id, &member_id).await? {
+            return Err(AppError::Forbidden("User not allowed to perform this action".to_string()));
+        }
+        return Ok(get_member_fines(pool, chama_id, &member_id).await?);
+    }
+
+    let fines = sqlx::query_as::<_, Fine>("SELECT * FROM fine WHERE chama_id = ? ORDER BY created_at DESC")
+        .bind(chama_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(fines)
+}
+
+async fn lock_fine(conn:&mut MySqlConnection, fine_id:&i64) -> Result<Fine, AppError> {
+    let fine = sqlx::query_as::<_, Fine>("SELECT * FROM fine WHERE id = ? FOR UPDATE")
+        .bind(fine_id)
+        .fetch_optional(conn)
+        .await?;
+
+    fine.ok_or_else(|| AppError::NotFound("No such fine".to_string()))
+}
+
+/// Cancels an unpaid fine. The official and their reason are kept on the
+/// fine. A loan fine is also taken off the installment's penalty.
+pub async fn waive_fine(pool:&MySqlPool, user_id:&str, fine_id:&i64, payload:&FineWaiveDto) -> Result<Fine, AppError> {
+    let staff_id = loan_service::parse_user_id(user_id)?;
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::BadRequest("A reason is required".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut fine = lock_fine(&mut tx, fine_id).await?;
+    chama_service::require_permission(pool, user_id, &fine.chama_id, ChamaPermission::MANAGEFINES).await?;
+    if fine.status != FineStatus::OUTSTANDING {
+        return Err(AppError::Conflict(format!("Fine is already {:?}", fine.status)));
+    }
+
+    if fine.trigger_type == PenaltyTriggerEnum::LATEREPAYMENT {
+        let installment = sqlx::query_as::<_, LoanRepaymentSchedule>("SELECT * FROM loan_repayment_schedule WHERE id = ? FOR UPDATE")
+            .bind(fine.source_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if installment.penalty - installment.penalty_paid < fine.amount {
+            return Err(AppError::Conflict("The fine has already been paid with the loan".to_string()));
+        }
+        sqlx::query(
+            "UPDATE loan_repayment_schedule SET penalty = penalty - ?, amount_due = amount_due - ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(fine.amount)
+        .bind(fine.amount)
+        .bind(utils::now_eat())
+        .bind(fine.source_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let now_eat = utils::now_eat();
+    sqlx::query("UPDATE fine SET status = ?, waived_by = ?, waive_reason = ?, settled_at = ?, updated_at = ? WHERE id = ?")
+        .bind(FineStatus::WAIVED)
+        .bind(staff_id)
+        .bind(reason)
+        .bind(now_eat)
+        .bind(now_eat)
+        .bind(fine_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("Fine {} of {} for member {} waived by {}: {}", fine_id, fine.amount, fine.user_id, staff_id, reason);
+    fine.status = FineStatus::WAIVED;
+    fine.waived_by = Some(staff_id);
+    fine.waive_reason = Some(reason.to_string());
+    fine.settled_at = Some(now_eat);
+    Ok(fine)
+}
+
+/// Waives a member's unpaid fine for a lapse that turned out not to be one,
+/// such as an absence corrected after the register was taken.
+pub async fn waive_source_fine(
+    conn:&mut MySqlConnection,
+    trigger_type:PenaltyTriggerEnum,
+    source_id:&i64,
+    user_id:&i64,
+    waived_by:&i64,
+    reason:&str) -> Result<(), AppError> {
+
+    let now_eat = utils::now_eat();
+    sqlx::query(
+        "UPDATE fine SET status = ?, waived_by = ?, waive_reason = ?, settled_at = ?, updated_at = ?
+        WHERE trigger_type = ? AND source_id = ? AND user_id = ? AND status = ?"
+    )
+    .bind(FineStatus::WAIVED)
+    .bind(waived_by)
+    .bind(reason)
+    .bind(now_eat)
+    .bind(now_eat)
+    .bind(trigger_type)
+    .bind(source_id)
+    .bind(user_id)
+    .bind(FineStatus::OUTSTANDING)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Pays a fine into the chama's savings. Members pay their own fines from
+/// their wallet; officials record fines paid to them in cash. Loan fines are
+/// paid with the loan's repayments instead.
+pub async fn pay_fine(pool:&MySqlPool, user_id:&str, fine_id:&i64) -> Result<Fine, AppError> {
+    let payer_id = loan_service::parse_user_id(user_id)?;
+
+    let mut tx = pool.begin().await?;
+    let mut fine = lock_fine(&mut tx, fine_id).await?;
+    let source = if fine.user_id == payer_id {
+        LedgerAccountKey::Wallet(payer_id)
+    } else {
+        chama_service::require_permission(pool, user_id, &fine.chama_id, ChamaPermission::MANAGEFINES).await?;
+        LedgerAccountKey::Cash
+    };
+    if fine.status != FineStatus::OUTSTANDING {
+        return Err(AppError::Conflict(format!("Fine is already {:?}", fine.status)));
+    }
+    if fine.trigger_type == PenaltyTriggerEnum::LATEREPAYMENT {
+        return Err(AppError::BadRequest("Loan fines are paid with the loan's repayments".to_string()));
+    }
+
+    ledger_service::post_journal(
+        &mut tx,
+        &JournalRequest {
+            reference:&format!("FINE-{}", fine_id),
+            entry_type:"FINE",
+            narration:&format!("Fine paid by member {}: {}", fine.user_id, fine.description),
+            created_by:Some(payer_id),
+        },
+        &[
+            PostingLine::debit(source, fine.amount),
+            PostingLine::credit(LedgerAccountKey::ChamaSavings(fine.chama_id), fine.amount),
+        ],
+    ).await?;
+
+    let now_eat = utils::now_eat();
+    sqlx::query("UPDATE fine SET status = ?, settled_at = ?, updated_at = ? WHERE id = ?")
+        .bind(FineStatus::PAID)
+        .bind(now_eat)
+        .bind(now_eat)
+        .bind(fine_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("Fine {} of {} paid from {:?}", fine_id, fine.amount, source);
+    fine.status = FineStatus::PAID;
+    fine.settled_at = Some(now_eat);
+    Ok(fine)
+}
+
+/// Marks the fines on an installment paid once its penalty has been repaid.
+pub async fn settle_installment_fines(conn:&mut MySqlConnection, installment:&LoanRepaymentSchedule) -> Result<(), AppError> {
+    if installment.penalty_paid < installment.penalty || !installment.penalty.is_positive() {
+        return Ok(());
+    }
+
+    let now_eat = utils::now_eat();
+    sqlx::query("UPDATE fine SET status = ?, settled_at = ?, updated_at = ? WHERE trigger_type = ? AND source_id = ? AND status = ?")
+        .bind(FineStatus::PAID)
+        .bind(now_eat)
+        .bind(now_eat)
+        .bind(PenaltyTriggerEnum::LATEREPAYMENT)
+        .bind(installment.id)
+        .bind(FineStatus::OUTSTANDING)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
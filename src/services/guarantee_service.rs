@@ -0,0 +1,253 @@
+use sqlx::{Executor, MySql, MySqlConnection, MySqlPool};
+use tracing::info;
+
+use crate::dtos::loan::{GuaranteePledgeDto, GuarantorInviteDto};
+use crate::error::AppError;
+use crate::money::{self, Money};
+use crate::models::loan::{GuaranteeStatus, LoadRequestStatus, LoanRequest, LoanRequestGuarantee};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, loan_service};
+use crate::utils;
+
+
+pub async fn invite_guarantor(pool:&MySqlPool, user_id:&str, loan_id:&i64, payload:&GuarantorInviteDto) -> Result<i64, AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+    let loan = loan_service::find_loan(pool, loan_id).await?;
+
+    if loan.user_id != user_id {
+        return Err(AppError::NotFound("No such loan".to_string()));
+    }
+    let Some(chama_id) = loan.chama_id else {
+        return Err(AppError::BadRequest("Only chama loans take guarantors".to_string()));
+    };
+    if !matches!(loan.status, LoadRequestStatus::PENDING | LoadRequestStatus::PARTIALYAPPROVED) {
+        return Err(AppError::Conflict(format!("Loan is {:?} and no longer takes guarantors", loan.status)));
+    }
+    if payload.guarantor_user_id == user_id {
+        return Err(AppError::BadRequest("You cannot guarantee your own loan".to_string()));
+    }
+    if !payload.amount_requested.is_positive() {
+        return Err(AppError::BadRequest("Guarantee amount must be greater than zero".to_string()));
+    }
+    if !chama_service::is_active_member(pool, &chama_id, &payload.guarantor_user_id).await? {
+        return Err(AppError::BadRequest("Guarantor must be an active member of the chama".to_string()));
+    }
+
+    let existing: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM loan_request_guarantee
+        WHERE load_request_id = ? AND loan_quaranter_id = ? AND status IN ('INVITED', 'ACCEPTED')"
+    )
+    .bind(loan_id)
+    .bind(payload.guarantor_user_id)
+    .fetch_one(pool)
+    .await?;
+
+    if existing.0 > 0 {
+        return Err(AppError::Conflict("Member has already been asked to guarantee this loan".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let guarantee = LoanRequestGuarantee {
+        id:None,
+        load_request_id:*loan_id,
+        loan_quaranter_id:payload.guarantor_user_id,
+        amount_requested:payload.amount_requested,
+        amount_quaranteed:Money::ZERO,
+        status:GuaranteeStatus::INVITED,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let guarantee_repository = data_repository::DataRepository::<LoanRequestGuarantee> {
+        pool,
+        table_name: "loan_request_guarantee",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let guarantee_id = guarantee_repository.insert(&guarantee).await?;
+    info!("User {} invited to guarantee loan {}", payload.guarantor_user_id, loan_id);
+    Ok(guarantee_id)
+}
+
+pub async fn get_loan_guarantees(pool:&MySqlPool, user_id:&str, loan_id:&i64) -> Result<Vec<LoanRequestGuarantee>, AppError> {
+    // Same visibility as the loan itself
+    loan_service::get_loan(pool, user_id, loan_id).await?;
+
+    let guarantees = sqlx::query_as::<_, LoanRequestGuarantee>(
+        "SELECT * FROM loan_request_guarantee WHERE load_request_id = ? ORDER BY created_at"
+    )
+    .bind(loan_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(guarantees)
+}
+
+pub async fn get_user_guarantees(pool:&MySqlPool, user_id:&str) -> Result<Vec<LoanRequestGuarantee>, AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    let guarantees = sqlx::query_as::<_, LoanRequestGuarantee>(
+        "SELECT * FROM loan_request_guarantee WHERE loan_quaranter_id = ? ORDER BY created_at DESC"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(guarantees)
+}
+
+/// Guarantor accepts an invitation and pledges part of their savings. The pledge
+/// is locked against those savings until the loan is repaid or falls through,
+/// and the loan is re-evaluated since the pledge may complete its approval.
+pub async fn accept_guarantee(pool:&MySqlPool, user_id:&str, guarantee_id:&i64, payload:&GuaranteePledgeDto) -> Result<(), AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    if !payload.amount.is_positive() {
+        return Err(AppError::BadRequest("Pledge amount must be greater than zero".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let guarantee = lock_guarantee(&mut tx, guarantee_id, &user_id).await?;
+    if guarantee.status != GuaranteeStatus::INVITED {
+        return Err(AppError::Conflict(format!("Guarantee is already {:?}", guarantee.status)));
+    }
+
+    let loan = loan_service::find_loan(pool, &guarantee.load_request_id).await?;
+    let chama_id = loan.chama_id.unwrap_or_default();
+    let status = loan_service::lock_loan_status(&mut tx, &guarantee.load_request_id).await?;
+    if !matches!(status, LoadRequestStatus::PENDING | LoadRequestStatus::PARTIALYAPPROVED) {
+        return Err(AppError::Conflict(format!("Loan is {:?} and no longer takes guarantors", status)));
+    }
+
+    // Lock the guarantor's membership so concurrent pledges cannot overcommit the same savings
+    let member: Option<(i64,)> = sqlx::query_as(
+        "SELECT id FROM chama_member WHERE chama_id = ? AND user_id = ? AND is_active = 1 FOR UPDATE"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if member.is_none() {
+        return Err(AppError::Forbidden("Only active chama members can guarantee loans".to_string()));
+    }
+
+    let savings = chama_service::get_member_savings(&mut *tx, &chama_id, &user_id).await?;
+    let locked = get_locked_savings(&mut *tx, &chama_id, &user_id).await?;
+    let available = (savings - locked).max(Money::ZERO);
+
+    if payload.amount > available {
+        return Err(AppError::BadRequest(format!(
+            "Pledge exceeds your free savings, you can pledge at most {}", available
+        )));
+    }
+
+    sqlx::query(
+        "UPDATE loan_request_guarantee SET status = ?, amount_quaranteed = ?, updated_at = ? WHERE id = ?"
+    )
+    .bind(GuaranteeStatus::ACCEPTED)
+    .bind(payload.amount)
+    .bind(utils::now_eat())
+    .bind(guarantee_id)
+    .execute(&mut *tx)
+    .await?;
+
+    loan_service::settle_loan_approval(pool, &mut tx, &loan, status).await?;
+
+    tx.commit().await?;
+    info!("User {} pledged {} on loan {}", user_id, payload.amount, guarantee.load_request_id);
+    Ok(())
+}
+
+pub async fn decline_guarantee(pool:&MySqlPool, user_id:&str, guarantee_id:&i64) -> Result<(), AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    let mut tx = pool.begin().await?;
+
+    let guarantee = lock_guarantee(&mut tx, guarantee_id, &user_id).await?;
+    if guarantee.status != GuaranteeStatus::INVITED {
+        return Err(AppError::Conflict(format!("Guarantee is already {:?}", guarantee.status)));
+    }
+
+    sqlx::query("UPDATE loan_request_guarantee SET status = ?, updated_at = ? WHERE id = ?")
+        .bind(GuaranteeStatus::DECLINED)
+        .bind(utils::now_eat())
+        .bind(guarantee_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    info!("User {} declined to guarantee loan {}", user_id, guarantee.load_request_id);
+    Ok(())
+}
+
+async fn lock_guarantee(conn:&mut MySqlConnection, guarantee_id:&i64, user_id:&i64) -> Result<LoanRequestGuarantee, AppError> {
+    let guarantee = sqlx::query_as::<_, LoanRequestGuarantee>(
+        "SELECT * FROM loan_request_guarantee WHERE id = ? FOR UPDATE"
+    )
+    .bind(guarantee_id)
+    .fetch_optional(conn)
+    .await?;
+
+    match guarantee {
+        Some(guarantee) if guarantee.loan_quaranter_id == *user_id => Ok(guarantee),
+        _ => Err(AppError::NotFound("No such guarantee".to_string())),
+    }
+}
+
+/// Savings a member has pledged on loans in the chama that are still outstanding.
+pub async fn get_locked_savings<'c, E>(executor:E, chama_id:&i64, user_id:&i64) -> Result<Money, sqlx::Error>
+where
+    E: Executor<'c, Database = MySql>,
+{
+    let locked: (Money,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(g.amount_quaranteed), 0) FROM loan_request_guarantee g
+        INNER JOIN loan_request lr on lr.id = g.load_request_id
+        WHERE lr.chama_id = ? AND g.loan_quaranter_id = ? AND g.status = 'ACCEPTED'"
+    )
+    .bind(chama_id)
+    .bind(user_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(locked.0)
+}
+
+/// Returns the accepted pledges on a loan and the amount the chama requires
+/// them to cover.
+pub async fn get_guarantee_coverage(pool:&MySqlPool, conn:&mut MySqlConnection, loan:&LoanRequest) -> Result<(Money, Money), AppError> {
+    let required = match loan.chama_id {
+        Some(chama_id) => chama_service::get_active_guarantee_setting(pool, &chama_id)
+            .await?
+            .map(|setting| loan.amount_requested.percent_rounded(money::rate(setting.centage_required)))
+            .unwrap_or(Money::ZERO),
+        None => Money::ZERO,
+    };
+
+    let guaranteed: (Money,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount_quaranteed), 0) FROM loan_request_guarantee
+        WHERE load_request_id = ? AND status = 'ACCEPTED'"
+    )
+    .bind(loan.id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok((guaranteed.0, required))
+}
+
+/// Frees every pledge on a loan, used once the loan no longer needs securing.
+pub async fn release_guarantees(conn:&mut MySqlConnection, loan_id:&i64) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE loan_request_guarantee SET status = ?, updated_at = ?
+        WHERE load_request_id = ? AND status IN ('INVITED', 'ACCEPTED')"
+    )
+    .bind(GuaranteeStatus::RELEASED)
+    .bind(utils::now_eat())
+    .bind(loan_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::info;
+
+use crate::dtos::ledger::LedgerDiscrepancyDto;
+use crate::error::AppError;
+use crate::money::Money;
+use crate::models::ledger::{LedgerAccountType, LedgerPosting};
+use crate::services::account_service;
+use crate::utils;
+
+
+/// The ledger accounts money moves between. Member-facing accounts are keyed
+/// by the user or chama they belong to, the rest are single system accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LedgerAccountKey {
+    // What the platform owes a user, mirrored in account_balance
+    Wallet(i64),
+    // Principal a user owes on loans not lent out of chama savings, mirrored in credit_balance
+    LoanReceivable(i64),
+    // Member savings held by a chama
+    ChamaSavings(i64),
+    // Money held at the bank and mobile money float
+    Cash,
+    // Wallet money held for payouts the provider has not confirmed yet
+    PayoutClearing,
+    // Payments received that we could not match to an account yet
+    Suspense,
+    InterestIncome,
+    FeeIncome,
+    PenaltyIncome,
+}
+
+impl LedgerAccountKey {
+    pub fn code(&self) -> String {
+        match self {
+            LedgerAccountKey::Wallet(user_id) => format!("WALLET-{}", user_id),
+            LedgerAccountKey::LoanReceivable(user_id) => format!("LOAN-{}", user_id),
+            LedgerAccountKey::ChamaSavings(chama_id) => format!("CHAMA-SAVINGS-{}", chama_id),
+            LedgerAccountKey::Cash => "CASH".to_string(),
+            LedgerAccountKey::PayoutClearing => "PAYOUT-CLEARING".to_string(),
+            LedgerAccountKey::Suspense => "SUSPENSE".to_string(),
+            LedgerAccountKey::InterestIncome => "INTEREST-INCOME".to_string(),
+            LedgerAccountKey::FeeIncome => "FEE-INCOME".to_string(),
+            LedgerAccountKey::PenaltyIncome => "PENALTY-INCOME".to_string(),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            LedgerAccountKey::Wallet(user_id) => format!("Wallet of user {}", user_id),
+            LedgerAccountKey::LoanReceivable(user_id) => format!("Loans receivable from user {}", user_id),
+            LedgerAccountKey::ChamaSavings(chama_id) => format!("Member savings of chama {}", chama_id),
+            LedgerAccountKey::Cash => "Cash and mobile money float".to_string(),
+            LedgerAccountKey::PayoutClearing => "Payouts awaiting provider confirmation".to_string(),
+            LedgerAccountKey::Suspense => "Unmatched payments awaiting assignment".to_string(),
+            LedgerAccountKey::InterestIncome => "Interest income".to_string(),
+            LedgerAccountKey::FeeIncome => "Fee income".to_string(),
+            LedgerAccountKey::PenaltyIncome => "Penalty income".to_string(),
+        }
+    }
+
+    pub fn account_type(&self) -> LedgerAccountType {
+        match self {
+            LedgerAccountKey::Wallet(_)
+            | LedgerAccountKey::ChamaSavings(_)
+            | LedgerAccountKey::PayoutClearing
+            | LedgerAccountKey::Suspense => LedgerAccountType::LIABILITY,
+            LedgerAccountKey::LoanReceivable(_) | LedgerAccountKey::Cash => LedgerAccountType::ASSET,
+            LedgerAccountKey::InterestIncome | LedgerAccountKey::FeeIncome | LedgerAccountKey::PenaltyIncome => LedgerAccountType::INCOME,
+        }
+    }
+
+    fn user_id(&self) -> Option<i64> {
+        match self {
+            LedgerAccountKey::Wallet(user_id) | LedgerAccountKey::LoanReceivable(user_id) => Some(*user_id),
+            _ => None,
+        }
+    }
+
+    fn chama_id(&self) -> Option<i64> {
+        match self {
+            LedgerAccountKey::ChamaSavings(chama_id) => Some(*chama_id),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PostingLine {
+    pub account:LedgerAccountKey,
+    pub debit:Money,
+    pub credit:Money,
+}
+
+impl PostingLine {
+    pub fn debit(account:LedgerAccountKey, amount:Money) -> Self {
+        PostingLine { account, debit:amount, credit:Money::ZERO }
+    }
+
+    pub fn credit(account:LedgerAccountKey, amount:Money) -> Self {
+        PostingLine { account, debit:Money::ZERO, credit:amount }
+    }
+}
+
+pub struct JournalRequest<'a> {
+    pub reference:&'a str,
+    pub entry_type:&'a str,
+    pub narration:&'a str,
+    pub created_by:Option<i64>,
+}
+
+/// Returns the id of a ledger account, opening it on first use.
+async fn ensure_account(conn:&mut MySqlConnection, key:&LedgerAccountKey) -> Result<i64, AppError> {
+    let now_eat = utils::now_eat();
+    sqlx::query(
+        "INSERT IGNORE INTO ledger_account (code, name, account_type, user_id, chama_id, balance, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, 0, ?, ?)"
+    )
+    .bind(key.code())
+    .bind(key.name())
+    .bind(key.account_type())
+    .bind(key.user_id())
+    .bind(key.chama_id())
+    .bind(now_eat)
+    .bind(now_eat)
+    .execute(&mut *conn)
+    .await?;
+
+    let (id,): (i64,) = sqlx::query_as("SELECT id FROM ledger_account WHERE code = ?")
+        .bind(key.code())
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(id)
+}
+
+/// Records a balanced journal entry and moves the balances of every account it
+/// touches, inside the caller's database transaction.
+///
+/// Debits must equal credits and every line must be one-sided and
+/// non-negative. Account rows are locked in id order so concurrent entries over
+/// the same accounts serialise instead of deadlocking. Wallet and loan
+/// receivable movements are mirrored into account_balance and credit_balance.
+pub async fn post_journal(conn:&mut MySqlConnection, request:&JournalRequest<'_>, lines:&[PostingLine]) -> Result<i64, AppError> {
+    let lines: Vec<&PostingLine> = lines.iter().filter(|l| !l.debit.is_zero() || !l.credit.is_zero()).collect();
+
+    if lines.iter().any(|l| l.debit.is_negative() || l.credit.is_negative() || (!l.debit.is_zero() && !l.credit.is_zero())) {
+        return Err(AppError::BadRequest("Journal lines must carry a single positive debit or credit".to_string()));
+    }
+    let debits: Money = lines.iter().map(|l| l.debit).sum();
+    let credits: Money = lines.iter().map(|l| l.credit).sum();
+    if debits.is_zero() || debits != credits {
+        return Err(AppError::BadRequest(format!(
+            "Journal {} does not balance: debits {}, credits {}",
+            request.reference, debits, credits
+        )));
+    }
+
+    let existing: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM journal_entry WHERE reference = ?")
+        .bind(request.reference)
+        .fetch_one(&mut *conn)
+        .await?;
+    if existing.0 > 0 {
+        return Err(AppError::Conflict(format!("Journal {} is already posted", request.reference)));
+    }
+
+    // Net movement per account, debit positive
+    let mut movements: BTreeMap<LedgerAccountKey, Money> = BTreeMap::new();
+    for line in &lines {
+        *movements.entry(line.account).or_default() += line.debit - line.credit;
+    }
+
+    let mut account_ids = BTreeMap::new();
+    for key in movements.keys() {
+        account_ids.insert(*key, ensure_account(conn, key).await?);
+    }
+    let mut locked: Vec<(i64, LedgerAccountKey)> = account_ids.iter().map(|(key, id)| (*id, *key)).collect();
+    locked.sort();
+    for (id, _) in &locked {
+        sqlx::query("SELECT id FROM ledger_account WHERE id = ? FOR UPDATE")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let now_eat = utils::now_eat();
+    let entry = sqlx::query(
+        "INSERT INTO journal_entry (reference, entry_type, narration, created_by, posted_at, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(request.reference)
+    .bind(request.entry_type)
+    .bind(request.narration)
+    .bind(request.created_by)
+    .bind(now_eat)
+    .bind(now_eat)
+    .execute(&mut *conn)
+    .await?;
+    let entry_id = entry.last_insert_id() as i64;
+
+    for line in &lines {
+        sqlx::query(
+            "INSERT INTO ledger_posting (journal_entry_id, ledger_account_id, debit, credit, created_at)
+            VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(entry_id)
+        .bind(account_ids[&line.account])
+        .bind(line.debit)
+        .bind(line.credit)
+        .bind(now_eat)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    for (key, movement) in &movements {
+        let delta = if key.account_type().is_debit_normal() { *movement } else { -*movement };
+
+        sqlx::query("UPDATE ledger_account SET balance = balance + ?, updated_at = ? WHERE id = ?")
+            .bind(delta)
+            .bind(now_eat)
+            .bind(account_ids[key])
+            .execute(&mut *conn)
+            .await?;
+
+        match key {
+            LedgerAccountKey::Wallet(user_id) if !delta.is_zero() => {
+                account_service::apply_wallet_posting(
+                    conn, user_id, delta, request.entry_type, request.reference, request.narration
+                ).await?;
+            }
+            LedgerAccountKey::LoanReceivable(user_id) if !delta.is_zero() => {
+                account_service::apply_credit_posting(conn, user_id, delta).await?;
+            }
+            _ => {}
+        }
+    }
+
+    info!("Posted journal {} ({}) for {}", request.reference, request.entry_type, debits);
+    Ok(entry_id)
+}
+
+/// An account's balance, zero while it has not been opened.
+pub async fn get_balance(pool:&MySqlPool, key:&LedgerAccountKey) -> Result<Money, AppError> {
+    let balance: Option<(Money,)> = sqlx::query_as("SELECT balance FROM ledger_account WHERE code = ?")
+        .bind(key.code())
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(balance.map(|b| b.0).unwrap_or(Money::ZERO))
+}
+
+/// Like `get_balance`, holding the account's row lock for the rest of the
+/// caller's transaction so the balance cannot change before they post.
+pub async fn lock_balance(conn:&mut MySqlConnection, key:&LedgerAccountKey) -> Result<Money, AppError> {
+    let balance: Option<(Money,)> = sqlx::query_as("SELECT balance FROM ledger_account WHERE code = ? FOR UPDATE")
+        .bind(key.code())
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(balance.map(|b| b.0).unwrap_or(Money::ZERO))
+}
+
+pub async fn get_account_postings(pool:&MySqlPool, key:&LedgerAccountKey) -> Result<Vec<LedgerPosting>, AppError> {
+    let postings = sqlx::query_as::<_, LedgerPosting>(
+        "SELECT p.* FROM ledger_posting p
+        INNER JOIN ledger_account a on a.id = p.ledger_account_id
+        WHERE a.code = ? ORDER BY p.id"
+    )
+    .bind(key.code())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(postings)
+}
+
+/// Checks the books hang together: every journal balances, every account
+/// balance equals the sum of its postings and the account_balance and
+/// credit_balance mirrors agree with the wallet and loan accounts.
+pub async fn reconcile(pool:&MySqlPool) -> Result<Vec<LedgerDiscrepancyDto>, AppError> {
+    let mut discrepancies = Vec::new();
+
+    let unbalanced: Vec<(String, Money, Money)> = sqlx::query_as(
+        "SELECT j.reference, SUM(p.debit), SUM(p.credit)
+        FROM journal_entry j INNER JOIN ledger_posting p on p.journal_entry_id = j.id
+        GROUP BY j.id, j.reference
+        HAVING SUM(p.debit) <> SUM(p.credit)"
+    )
+    .fetch_all(pool)
+    .await?;
+    for (reference, debits, credits) in unbalanced {
+        discrepancies.push(LedgerDiscrepancyDto {
+            check:"UNBALANCED_JOURNAL".to_string(),
+            subject:reference,
+            expected:debits,
+            actual:credits,
+        });
+    }
+
+    let accounts: Vec<(String, LedgerAccountType, Money, Money, Money)> = sqlx::query_as(
+        "SELECT a.code, a.account_type, a.balance, COALESCE(SUM(p.debit), 0), COALESCE(SUM(p.credit), 0)
+        FROM ledger_account a LEFT JOIN ledger_posting p on p.ledger_account_id = a.id
+        GROUP BY a.id, a.code, a.account_type, a.balance"
+    )
+    .fetch_all(pool)
+    .await?;
+    for (code, account_type, balance, debits, credits) in accounts {
+        let expected = if account_type.is_debit_normal() { debits - credits } else { credits - debits };
+        if expected != balance {
+            discrepancies.push(LedgerDiscrepancyDto {
+                check:"ACCOUNT_BALANCE".to_string(),
+                subject:code,
+                expected,
+                actual:balance,
+            });
+        }
+    }
+
+    let wallets: Vec<(i64, Money, Option<Money>)> = sqlx::query_as(
+        "SELECT ab.user_id, ab.balance, la.balance FROM account_balance ab
+        LEFT JOIN ledger_account la on la.code = CONCAT('WALLET-', ab.user_id)"
+    )
+    .fetch_all(pool)
+    .await?;
+    for (user_id, balance, ledger_balance) in wallets {
+        let ledger_balance = ledger_balance.unwrap_or(Money::ZERO);
+        if ledger_balance != balance {
+            discrepancies.push(LedgerDiscrepancyDto {
+                check:"ACCOUNT_BALANCE_MIRROR".to_string(),
+                subject:LedgerAccountKey::Wallet(user_id).code(),
+                expected:ledger_balance,
+                actual:balance,
+            });
+        }
+    }
+
+    let credits: Vec<(i64, Money, Option<Money>)> = sqlx::query_as(
+        "SELECT cb.user_id, cb.balance, la.balance FROM credit_balance cb
+        LEFT JOIN ledger_account la on la.code = CONCAT('LOAN-', cb.user_id)"
+    )
+    .fetch_all(pool)
+    .await?;
+    for (user_id, balance, ledger_balance) in credits {
+        let ledger_balance = ledger_balance.unwrap_or(Money::ZERO);
+        if ledger_balance != balance {
+            discrepancies.push(LedgerDiscrepancyDto {
+                check:"CREDIT_BALANCE_MIRROR".to_string(),
+                subject:LedgerAccountKey::LoanReceivable(user_id).code(),
+                expected:ledger_balance,
+                actual:balance,
+            });
+        }
+    }
+
+    info!("Ledger reconciliation found {} discrepancies", discrepancies.len());
+    Ok(discrepancies)
+}
@@ -0,0 +1,474 @@
+use std::str::FromStr;
+
+use chrono::Months;
+use sqlx::{MySql, MySqlConnection, MySqlPool, Transaction};
+use tracing::{info, error};
+
+use crate::dtos::loan::{LoanApplicationDto, LoanCeilingDto, LoanVoteDto, LoanVoteResultDto};
+use crate::enums::LoanRepaymentFrequecyEnum;
+use crate::error::AppError;
+use crate::money::{self, Money};
+use crate::models::bill::BillFrequencyEnum;
+use crate::models::credit::CreditProfile;
+use crate::models::loan::{LoadRequestStatus, LoanApprovalDecision, LoanRequest, LoanRequestApproval};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, guarantee_service, schedule_service};
+use crate::utils;
+
+
+pub fn parse_user_id(user_id:&str) -> Result<i64, AppError> {
+    user_id
+        .parse::<i64>()
+        .map_err(|_| AppError::Forbidden("Invalid user".to_string()))
+}
+
+pub async fn apply_for_loan(pool:&MySqlPool, user_id:&str, payload:&LoanApplicationDto) -> Result<i64, AppError> {
+    let user_id = parse_user_id(user_id)?;
+
+    if !payload.amount_requested.is_positive() {
+        return Err(AppError::BadRequest("Requested amount must be greater than zero".to_string()));
+    }
+    if payload.repayment_months <= 0 {
+        return Err(AppError::BadRequest("Repayment period must be at least one month".to_string()));
+    }
+
+    // Only chama positions can approve and disburse a loan so far, a loan
+    // without a chama would never leave PENDING
+    let Some(chama_id) = payload.chama_id else {
+        return Err(AppError::BadRequest("Loans are only offered through a chama, say which chama to borrow from".to_string()));
+    };
+    if !chama_service::is_active_member(pool, &chama_id, &user_id).await? {
+        return Err(AppError::Forbidden("Only active chama members can apply for a chama loan".to_string()));
+    }
+    check_chama_loan_limit(pool, &chama_id, &user_id, payload.amount_requested).await?;
+
+    let mut chama_frequency = None;
+    if let Some(repayment_limit) = chama_service::get_active_repayment_limit(pool, &chama_id).await? {
+        if payload.repayment_months > repayment_limit.max_repayment_months {
+            return Err(AppError::BadRequest(format!(
+                "Repayment period cannot exceed {} months for this chama", repayment_limit.max_repayment_months
+            )));
+        }
+        // Chama loans are repaid on the chama's schedule, not the applicant's
+        chama_frequency = LoanRepaymentFrequecyEnum::from_str(&repayment_limit.repayment_frequency)
+            .ok()
+            .map(|f| f.as_bill_frequency());
+    }
+
+    if let Some(profile_id) = payload.credit_profile_id {
+        let credit_profile_repository = data_repository::DataRepository::<CreditProfile> {
+            pool,
+            table_name: "credit_profile",
+            pk_column: "id",
+            phantom: std::marker::PhantomData,
+        };
+        let Some(profile) = credit_profile_repository.find_by_id(&profile_id).await? else {
+            return Err(AppError::BadRequest("No such credit profile".to_string()));
+        };
+        if payload.amount_requested > profile.max_limit {
+            return Err(AppError::BadRequest(format!(
+                "Requested amount exceeds the credit profile limit of {}", profile.max_limit
+            )));
+        }
+    }
+
+    let open_loans: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM loan_request
+        WHERE user_id = ? AND chama_id <=> ?
+        AND status IN ('PENDING', 'PARTIALYAPPROVED', 'APPROVED', 'DISBURSED', 'DEFAULTED')"
+    )
+    .bind(user_id)
+    .bind(payload.chama_id)
+    .fetch_one(pool)
+    .await?;
+
+    if open_loans.0 > 0 {
+        return Err(AppError::Conflict("You already have an open loan, clear it before applying again".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let due_date = now_eat
+        .checked_add_months(Months::new(payload.repayment_months as u32))
+        .ok_or_else(|| AppError::BadRequest("Repayment period is too long".to_string()))?;
+
+    let loan_request = LoanRequest {
+        id:None,
+        user_id,
+        chama_id:payload.chama_id,
+        amount_requested:payload.amount_requested,
+        amount_approved:Money::ZERO,
+        credit_profile_id:payload.credit_profile_id,
+        status:LoadRequestStatus::PENDING,
+        frequency:chama_frequency.or(payload.frequency).unwrap_or(BillFrequencyEnum::Monthy),
+        repayment_months:payload.repayment_months,
+        due_date,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+
+    let loan_repository = data_repository::DataRepository::<LoanRequest> {
+        pool,
+        table_name: "loan_request",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let loan_id = loan_repository.insert(&loan_request).await?;
+    info!("Loan request {} created for user {}", loan_id, user_id);
+    Ok(loan_id)
+}
+
+/// The most a member may borrow from a chama: the lower of the chama's absolute
+/// limit and the configured percentage of the member's unpledged savings. `None` means
+/// the chama has not configured any limit.
+pub async fn get_loan_ceiling(pool:&MySqlPool, chama_id:&i64, user_id:&i64) -> Result<LoanCeilingDto, AppError> {
+    let limit = chama_service::get_active_loan_limit(pool, chama_id).await?;
+    let member_savings = chama_service::get_member_savings(pool, chama_id, user_id).await?;
+    // Savings pledged as guarantees for other members cannot back a new loan
+    let locked_savings = guarantee_service::get_locked_savings(pool, chama_id, user_id).await?;
+    let free_savings = (member_savings - locked_savings).max(Money::ZERO);
+
+    let chama_limit = limit.as_ref().map(|l| l.amount);
+    let centage_member_savings = limit.as_ref().map(|l| l.centage_member_savings);
+    let savings_limit = centage_member_savings.map(|centage| free_savings.percent_rounded(money::rate(centage)));
+
+    let ceiling = match (chama_limit, savings_limit) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+
+    Ok(LoanCeilingDto {
+        chama_id:*chama_id,
+        chama_limit,
+        member_savings,
+        locked_savings,
+        centage_member_savings,
+        savings_limit,
+        ceiling,
+    })
+}
+
+pub async fn get_member_loan_ceiling(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<LoanCeilingDto, AppError> {
+    let user_id = parse_user_id(user_id)?;
+
+    if !chama_service::is_active_member(pool, chama_id, &user_id).await? {
+        return Err(AppError::Forbidden("User not allowed to perform this action".to_string()));
+    }
+    get_loan_ceiling(pool, chama_id, &user_id).await
+}
+
+async fn check_chama_loan_limit(pool:&MySqlPool, chama_id:&i64, user_id:&i64, amount_requested:Money) -> Result<(), AppError> {
+    let ceiling = get_loan_ceiling(pool, chama_id, user_id).await?;
+
+    let Some(max_amount) = ceiling.ceiling else {
+        return Ok(());
+    };
+    if amount_requested <= max_amount {
+        return Ok(());
+    }
+
+    // Name whichever limit is the binding one
+    let reason = match (ceiling.savings_limit, ceiling.centage_member_savings) {
+        (Some(savings_limit), Some(centage)) if savings_limit <= max_amount => format!(
+            "Requested amount {} exceeds {}% of your free savings of {}",
+            amount_requested, centage, ceiling.member_savings - ceiling.locked_savings
+        ),
+        _ => format!(
+            "Requested amount {} exceeds the chama loan limit of {}",
+            amount_requested, ceiling.chama_limit.unwrap_or(max_amount)
+        ),
+    };
+
+    Err(AppError::BadRequest(format!("{}. Your current ceiling is {}", reason, max_amount)))
+}
+
+pub async fn get_user_loans(pool:&MySqlPool, user_id:&str, status:Option<LoadRequestStatus>) -> Result<Vec<LoanRequest>, AppError> {
+    let user_id = parse_user_id(user_id)?;
+
+    let loans = sqlx::query_as::<_, LoanRequest>(
+        "SELECT * FROM loan_request WHERE user_id = ? AND (? IS NULL OR status = ?)
+        ORDER BY created_at DESC"
+    )
+    .bind(user_id)
+    .bind(status)
+    .bind(status)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(loans)
+}
+
+pub async fn find_loan(pool:&MySqlPool, loan_id:&i64) -> Result<LoanRequest, AppError> {
+    let loan_repository = data_repository::DataRepository::<LoanRequest> {
+        pool,
+        table_name: "loan_request",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    loan_repository
+        .find_by_id(loan_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such loan".to_string()))
+}
+
+/// Borrowers can see their own loans, and fellow members can see the loans of
+/// the chama they belong to so that approvers can review them.
+pub async fn get_loan(pool:&MySqlPool, user_id:&str, loan_id:&i64) -> Result<LoanRequest, AppError> {
+    let user_id = parse_user_id(user_id)?;
+    let loan = find_loan(pool, loan_id).await?;
+
+    if loan.user_id == user_id {
+        return Ok(loan);
+    }
+    if let Some(chama_id) = loan.chama_id
+        && chama_service::is_active_member(pool, &chama_id, &user_id).await? {
+        return Ok(loan);
+    }
+    Err(AppError::NotFound("No such loan".to_string()))
+}
+
+pub async fn get_chama_loans(pool:&MySqlPool, user_id:&str, chama_id:&i64, status:Option<LoadRequestStatus>) -> Result<Vec<LoanRequest>, AppError> {
+    let user_id = parse_user_id(user_id)?;
+
+    if !chama_service::is_active_member(pool, chama_id, &user_id).await? {
+        return Err(AppError::Forbidden("User not allowed to perform this action".to_string()));
+    }
+
+    let loans = sqlx::query_as::<_, LoanRequest>(
+        "SELECT * FROM loan_request WHERE chama_id = ? AND (? IS NULL OR status = ?)
+        ORDER BY created_at DESC"
+    )
+    .bind(chama_id)
+    .bind(status)
+    .bind(status)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(loans)
+}
+
+pub async fn cancel_loan(pool:&MySqlPool, user_id:&str, loan_id:&i64) -> Result<(), AppError> {
+    let user_id = parse_user_id(user_id)?;
+    let loan = find_loan(pool, loan_id).await?;
+
+    if loan.user_id != user_id {
+        return Err(AppError::NotFound("No such loan".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    transition_loan_status(&mut tx, loan_id, loan.status, LoadRequestStatus::CANCELLED).await?;
+    guarantee_service::release_guarantees(&mut tx, loan_id).await?;
+    tx.commit().await?;
+    info!("Loan request {} cancelled by borrower", loan_id);
+    Ok(())
+}
+
+/// Moves a loan between states. The update is guarded on the status the caller
+/// last saw, so two concurrent transitions cannot both succeed.
+pub async fn transition_loan_status(
+    conn:&mut MySqlConnection,
+    loan_id:&i64,
+    from:LoadRequestStatus,
+    to:LoadRequestStatus) -> Result<(), AppError> {
+
+    if !from.can_transition_to(to) {
+        return Err(AppError::Conflict(format!("Loan cannot move from {:?} to {:?}", from, to)));
+    }
+
+    let result = sqlx::query(
+        "UPDATE loan_request SET status = ?, updated_at = ? WHERE id = ? AND status = ?"
+    )
+    .bind(to)
+    .bind(utils::now_eat())
+    .bind(loan_id)
+    .bind(from)
+    .execute(conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        error!("Loan {} was no longer {:?} when moving to {:?}", loan_id, from, to);
+        return Err(AppError::Conflict("Loan status changed, please retry".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn get_loan_votes(pool:&MySqlPool, user_id:&str, loan_id:&i64) -> Result<Vec<LoanRequestApproval>, AppError> {
+    // Same visibility as the loan itself
+    get_loan(pool, user_id, loan_id).await?;
+
+    let votes = sqlx::query_as::<_, LoanRequestApproval>(
+        "SELECT * FROM loan_request_approval WHERE load_request_id = ? ORDER BY created_at"
+    )
+    .bind(loan_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(votes)
+}
+
+/// Records an approver's vote on a chama loan and moves the loan along,
+/// see `settle_loan_approval` for how votes are counted.
+pub async fn vote_on_loan(pool:&MySqlPool, user_id:&str, loan_id:&i64, payload:&LoanVoteDto) -> Result<LoanVoteResultDto, AppError> {
+    let user_id = parse_user_id(user_id)?;
+    let loan = find_loan(pool, loan_id).await?;
+
+    let Some(chama_id) = loan.chama_id else {
+        return Err(AppError::BadRequest("Only chama loans are approved by vote".to_string()));
+    };
+    if loan.user_id == user_id {
+        return Err(AppError::Forbidden("You cannot vote on your own loan".to_string()));
+    }
+    let Some(position_id) = chama_service::get_member_approver_position(pool, &chama_id, &user_id).await? else {
+        return Err(AppError::Forbidden("Only members holding a loan approver position can vote".to_string()));
+    };
+
+    let mut tx = pool.begin().await?;
+
+    // Serialise votes on the same loan so the tally below sees every earlier vote
+    let status = lock_loan_status(&mut tx, loan_id).await?;
+
+    if !matches!(status, LoadRequestStatus::PENDING | LoadRequestStatus::PARTIALYAPPROVED) {
+        return Err(AppError::Conflict(format!("Loan is {:?} and no longer open for votes", status)));
+    }
+
+    let already_voted: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM loan_request_approval WHERE load_request_id = ? AND approver_position_id = ?"
+    )
+    .bind(loan_id)
+    .bind(position_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if already_voted.0 > 0 {
+        return Err(AppError::Conflict("Your position has already voted on this loan".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let vote = LoanRequestApproval {
+        id:None,
+        load_request_id:*loan_id,
+        user_id,
+        approver_position_id:position_id,
+        decision:payload.decision,
+        comment:payload.comment.clone(),
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let vote_repository = data_repository::DataRepository::<LoanRequestApproval> {
+        pool,
+        table_name: "loan_request_approval",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    vote_repository.insert_trx(&mut tx, &vote).await?;
+
+    let result = settle_loan_approval(pool, &mut tx, &loan, status).await?;
+
+    tx.commit().await?;
+    info!("Loan {} vote by user {}: {:?}, loan now {:?}", loan_id, user_id, payload.decision, result.status);
+
+    Ok(result)
+}
+
+/// Locks the loan row for the rest of the transaction and returns its current status.
+pub async fn lock_loan_status(conn:&mut MySqlConnection, loan_id:&i64) -> Result<LoadRequestStatus, AppError> {
+    let (status,): (LoadRequestStatus,) = sqlx::query_as(
+        "SELECT status FROM loan_request WHERE id = ? FOR UPDATE"
+    )
+    .bind(loan_id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(status)
+}
+
+/// Tallies the votes cast on a chama loan and applies the outcome.
+///
+/// Each approver position votes once. The first approval makes the loan
+/// PARTIALYAPPROVED, and enough rejections that the quorum can no longer be
+/// reached make it REJECTED. The loan only becomes APPROVED once the required
+/// approvals are in and accepted guarantor pledges cover the chama's guarantee
+/// percentage, so this is re-run whenever either side changes.
+pub async fn settle_loan_approval(
+    pool:&MySqlPool,
+    tx:&mut Transaction<'_, MySql>,
+    loan:&LoanRequest,
+    status:LoadRequestStatus) -> Result<LoanVoteResultDto, AppError> {
+
+    let loan_id = loan.id.unwrap_or_default();
+    let chama_id = loan.chama_id.unwrap_or_default();
+
+    let approver_positions = chama_service::get_approver_position_ids(pool, &chama_id).await?;
+    let required_approvals = chama_service::get_required_approvals(pool, &chama_id).await?;
+    let (guaranteed_amount, guarantee_required) = guarantee_service::get_guarantee_coverage(pool, tx, loan).await?;
+
+    let votes: Vec<(i64, LoanApprovalDecision)> = sqlx::query_as(
+        "SELECT approver_position_id, decision FROM loan_request_approval WHERE load_request_id = ?"
+    )
+    .bind(loan_id)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    // Votes from positions that have since been removed as approvers no longer count
+    let counted = |decision: LoanApprovalDecision| {
+        votes
+            .iter()
+            .filter(|(position, d)| *d == decision && approver_positions.contains(position))
+            .count() as i64
+    };
+    let approvals = counted(LoanApprovalDecision::APPROVE);
+    let rejections = counted(LoanApprovalDecision::REJECT);
+    let total_positions = approver_positions.len() as i64;
+
+    let next_status = if total_positions == 0 {
+        // Nobody can vote, leave the loan for the chama to configure approvers
+        status
+    } else if approvals >= required_approvals && guaranteed_amount >= guarantee_required {
+        LoadRequestStatus::APPROVED
+    } else if rejections > total_positions - required_approvals {
+        LoadRequestStatus::REJECTED
+    } else if approvals > 0 {
+        LoadRequestStatus::PARTIALYAPPROVED
+    } else {
+        status
+    };
+
+    if next_status == LoadRequestStatus::APPROVED {
+        approve_loan(pool, tx, loan, status).await?;
+    } else if next_status != status {
+        transition_loan_status(tx, &loan_id, status, next_status).await?;
+        if next_status == LoadRequestStatus::REJECTED {
+            guarantee_service::release_guarantees(tx, &loan_id).await?;
+        }
+    }
+
+    Ok(LoanVoteResultDto {
+        loan_id,
+        status:next_status,
+        approvals,
+        rejections,
+        required_approvals,
+        guaranteed_amount,
+        guarantee_required,
+    })
+}
+
+/// Final approval: grants the requested amount, marks the loan APPROVED and
+/// lays out its repayment schedule.
+async fn approve_loan(pool:&MySqlPool, tx:&mut Transaction<'_, MySql>, loan:&LoanRequest, from:LoadRequestStatus) -> Result<(), AppError> {
+    let loan_id = loan.id.unwrap_or_default();
+
+    transition_loan_status(tx, &loan_id, from, LoadRequestStatus::APPROVED).await?;
+
+    sqlx::query("UPDATE loan_request SET amount_approved = ? WHERE id = ?")
+        .bind(loan.amount_requested)
+        .bind(loan_id)
+        .execute(&mut **tx)
+        .await?;
+
+    schedule_service::generate_schedule(pool, tx, loan, loan.amount_requested).await?;
+
+    Ok(())
+}
@@ -0,0 +1,434 @@
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::info;
+
+use crate::dtos::meeting::{
+    AgendaItemDto,
+    MeetingAttendanceDto,
+    MeetingDetailDto,
+    MeetingDto,
+    MeetingMinutesDto,
+    ResolutionDto,
+    ResolutionLinkDto
+};
+use crate::error::AppError;
+use crate::models::chama::ChamaPermission;
+use crate::models::fine::PenaltyTriggerEnum;
+use crate::models::meeting::{
+    AttendanceStatus,
+    Meeting,
+    MeetingAgendaItem,
+    MeetingAttendance,
+    MeetingResolution,
+    MeetingStatus,
+    ResolutionRecordEnum
+};
+use crate::money::Money;
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, contribution_service, fine_service, loan_service, notification_service};
+use crate::utils;
+
+
+async fn find_meeting(pool:&MySqlPool, meeting_id:&i64) -> Result<Meeting, AppError> {
+    let meeting = sqlx::query_as::<_, Meeting>("SELECT * FROM meeting WHERE id = ?")
+        .bind(meeting_id)
+        .fetch_optional(pool)
+        .await?;
+
+    meeting.ok_or_else(|| AppError::NotFound("No such meeting".to_string()))
+}
+
+async fn insert_agenda_item(
+    conn:&mut MySqlConnection,
+    meeting_id:&i64,
+    item:&AgendaItemDto) -> Result<MeetingAgendaItem, AppError> {
+
+    let title = item.title.trim();
+    if title.is_empty() {
+        return Err(AppError::BadRequest("Agenda items need a title".to_string()));
+    }
+    let (position,): (i32,) = sqlx::query_as("SELECT COALESCE(MAX(position), 0) + 1 FROM meeting_agenda_item WHERE meeting_id = ?")
+        .bind(meeting_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let mut agenda_item = MeetingAgendaItem {
+        id:None,
+        meeting_id:*meeting_id,
+        position,
+        title:title.to_string(),
+        description:item.description.clone(),
+        created_at:utils::now_eat(),
+    };
+    let result = sqlx::query(
+        "INSERT INTO meeting_agenda_item (meeting_id, position, title, description, created_at) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(agenda_item.meeting_id)
+    .bind(agenda_item.position)
+    .bind(&agenda_item.title)
+    .bind(&agenda_item.description)
+    .bind(agenda_item.created_at)
+    .execute(&mut *conn)
+    .await?;
+    agenda_item.id = Some(result.last_insert_id() as i64);
+
+    Ok(agenda_item)
+}
+
+/// Schedules a meeting with its agenda and lets the members know.
+pub async fn schedule_meeting(pool:&MySqlPool, user_id:&str, payload:&MeetingDto) -> Result<MeetingDetailDto, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGEMEETINGS).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+    let title = payload.title.trim();
+    if title.is_empty() {
+        return Err(AppError::BadRequest("Meetings need a title".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let mut meeting = Meeting {
+        id:None,
+        chama_id:payload.chama_id,
+        title:title.to_string(),
+        venue:payload.venue.clone(),
+        scheduled_at:payload.scheduled_at,
+        status:MeetingStatus::SCHEDULED,
+        minutes:None,
+        minutes_recorded_by:None,
+        created_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let meeting_repository = data_repository::DataRepository::<Meeting> {
+        pool,
+        table_name: "meeting",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let mut tx = pool.begin().await?;
+    let meeting_id = meeting_repository.insert_trx(&mut tx, &meeting).await?;
+    meeting.id = Some(meeting_id);
+    let mut agenda = Vec::new();
+    for item in payload.agenda.iter().flatten() {
+        agenda.push(insert_agenda_item(&mut tx, &meeting_id, item).await?);
+    }
+    tx.commit().await?;
+
+    info!("Meeting {} of chama {} scheduled for {}", meeting_id, meeting.chama_id, meeting.scheduled_at);
+    let message = format!("{} is scheduled for {}{}.", meeting.title, meeting.scheduled_at.format("%d/%m/%Y %H:%M"),
+        meeting.venue.as_ref().map(|v| format!(" at {}", v)).unwrap_or_default());
+    for member_id in chama_service::active_member_ids(pool, &meeting.chama_id).await? {
+        notification_service::notify_user(pool, &member_id, "Chama meeting", &message).await;
+    }
+
+    Ok(MeetingDetailDto { meeting, agenda, attendance:Vec::new(), resolutions:Vec::new() })
+}
+
+pub async fn get_meetings(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<Meeting>, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let meetings = sqlx::query_as::<_, Meeting>("SELECT * FROM meeting WHERE chama_id = ? ORDER BY scheduled_at DESC")
+        .bind(chama_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(meetings)
+}
+
+async fn meeting_detail(pool:&MySqlPool, meeting:Meeting) -> Result<MeetingDetailDto, AppError> {
+    let agenda = sqlx::query_as::<_, MeetingAgendaItem>("SELECT * FROM meeting_agenda_item WHERE meeting_id = ? ORDER BY position")
+        .bind(meeting.id)
+        .fetch_all(pool)
+        .await?;
+    let attendance = sqlx::query_as::<_, MeetingAttendance>("SELECT * FROM meeting_attendance WHERE meeting_id = ? ORDER BY user_id")
+        .bind(meeting.id)
+        .fetch_all(pool)
+        .await?;
+    let resolutions = sqlx::query_as::<_, MeetingResolution>("SELECT * FROM meeting_resolution WHERE meeting_id = ? ORDER BY id")
+        .bind(meeting.id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(MeetingDetailDto { meeting, agenda, attendance, resolutions })
+}
+
+pub async fn get_meeting(pool:&MySqlPool, user_id:&str, meeting_id:&i64) -> Result<MeetingDetailDto, AppError> {
+    let meeting = find_meeting(pool, meeting_id).await?;
+    contribution_service::require_member(pool, user_id, &meeting.chama_id).await?;
+
+    meeting_detail(pool, meeting).await
+}
+
+pub async fn add_agenda_item(pool:&MySqlPool, user_id:&str, meeting_id:&i64, payload:&AgendaItemDto) -> Result<MeetingAgendaItem, AppError> {
+    let meeting = find_meeting(pool, meeting_id).await?;
+    chama_service::require_permission(pool, user_id, &meeting.chama_id, ChamaPermission::MANAGEMEETINGS).await?;
+    if meeting.status == MeetingStatus::CANCELLED {
+        return Err(AppError::Conflict("Meeting was cancelled".to_string()));
+    }
+
+    let mut conn = pool.acquire().await?;
+    insert_agenda_item(&mut conn, meeting_id, payload).await
+}
+
+/// Takes the register and marks the meeting held. Members can be recorded
+/// again to correct the register. Under the chama's missed meeting rule an
+/// absent member is fined, and the fine is waived if the absence is later
+/// corrected to present or excused. Meetings scheduled before the rule was set
+/// are not fined.
+pub async fn record_attendance(pool:&MySqlPool, user_id:&str, meeting_id:&i64, payload:&MeetingAttendanceDto) -> Result<MeetingDetailDto, AppError> {
+    let meeting = find_meeting(pool, meeting_id).await?;
+    chama_service::require_permission(pool, user_id, &meeting.chama_id, ChamaPermission::MANAGEMEETINGS).await?;
+    let recorded_by = loan_service::parse_user_id(user_id)?;
+    if meeting.status == MeetingStatus::CANCELLED {
+        return Err(AppError::Conflict("Meeting was cancelled".to_string()));
+    }
+    let now_eat = utils::now_eat();
+    if meeting.scheduled_at.date() > now_eat.date() {
+        return Err(AppError::BadRequest("Attendance is taken on the day of the meeting".to_string()));
+    }
+    for entry in &payload.attendance {
+        if !chama_service::is_active_member(pool, &meeting.chama_id, &entry.user_id).await? {
+            return Err(AppError::BadRequest(format!("User {} is not a member of the chama", entry.user_id)));
+        }
+    }
+
+    let rule = fine_service::get_active_rule(pool, &meeting.chama_id, PenaltyTriggerEnum::MISSEDMEETING).await?
+        .filter(|rule| rule.created_at <= meeting.scheduled_at);
+
+    let mut tx = pool.begin().await?;
+    let mut fined = Vec::new();
+    for entry in &payload.attendance {
+        let updated = sqlx::query("UPDATE meeting_attendance SET status = ?, recorded_by = ?, updated_at = ? WHERE meeting_id = ? AND user_id = ?")
+            .bind(entry.status)
+            .bind(recorded_by)
+            .bind(now_eat)
+            .bind(meeting_id)
+            .bind(entry.user_id)
+            .execute(&mut *tx)
+            .await?;
+        if updated.rows_affected() == 0 {
+            sqlx::query(
+                "INSERT INTO meeting_attendance (meeting_id, user_id, status, recorded_by, created_at, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?)"
+            )
+            .bind(meeting_id)
+            .bind(entry.user_id)
+            .bind(entry.status)
+            .bind(recorded_by)
+            .bind(now_eat)
+            .bind(now_eat)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        match entry.status {
+            AttendanceStatus::ABSENT => {
+                if let Some(rule) = &rule {
+                    let amount = fine_service::fine_amount(rule, Money::ZERO);
+                    let description = format!("Missed meeting: {} on {}", meeting.title, meeting.scheduled_at.date());
+                    if fine_service::create_fine(&mut tx, rule, &entry.user_id, meeting_id, amount, &description).await?.is_some() {
+                        fined.push((entry.user_id, amount));
+                    }
+                }
+            }
+            AttendanceStatus::PRESENT | AttendanceStatus::EXCUSED => {
+                fine_service::waive_source_fine(
+                    &mut tx,
+                    PenaltyTriggerEnum::MISSEDMEETING,
+                    meeting_id,
+                    &entry.user_id,
+                    &recorded_by,
+                    &format!("Attendance corrected to {:?}", entry.status),
+                ).await?;
+            }
+        }
+    }
+    sqlx::query("UPDATE meeting SET status = ?, updated_at = ? WHERE id = ?")
+        .bind(MeetingStatus::HELD)
+        .bind(now_eat)
+        .bind(meeting_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("Attendance of {} members recorded for meeting {}, {} fined", payload.attendance.len(), meeting_id, fined.len());
+    for (member_id, amount) in fined {
+        let message = format!("You have been fined {} for missing {} on {}.", amount, meeting.title, meeting.scheduled_at.date());
+        notification_service::notify_user(pool, &member_id, "Chama fine", &message).await;
+    }
+
+    let meeting = find_meeting(pool, meeting_id).await?;
+    meeting_detail(pool, meeting).await
+}
+
+/// Checks the record a resolution is linked to exists in the same chama.
+async fn check_record(pool:&MySqlPool, chama_id:&i64, record_type:ResolutionRecordEnum, record_id:&i64) -> Result<(), AppError> {
+    let query = format!("SELECT COUNT(*) FROM {} WHERE id = ? AND chama_id = ?", record_type.table_name());
+    let found: (i64,) = sqlx::query_as(&query)
+        .bind(record_id)
+        .bind(chama_id)
+        .fetch_one(pool)
+        .await?;
+
+    if found.0 == 0 {
+        return Err(AppError::NotFound(format!("No {:?} record {} in this chama", record_type, record_id)));
+    }
+    Ok(())
+}
+
+/// Records a decision taken at a meeting, linked to the record it authorised
+/// when that record already exists.
+pub async fn add_resolution(pool:&MySqlPool, user_id:&str, meeting_id:&i64, payload:&ResolutionDto) -> Result<MeetingResolution, AppError> {
+    let meeting = find_meeting(pool, meeting_id).await?;
+    chama_service::require_permission(pool, user_id, &meeting.chama_id, ChamaPermission::MANAGEMEETINGS).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+    if meeting.status == MeetingStatus::CANCELLED {
+        return Err(AppError::Conflict("Meeting was cancelled".to_string()));
+    }
+    let now_eat = utils::now_eat();
+    if meeting.scheduled_at > now_eat {
+        return Err(AppError::BadRequest("Resolutions are recorded once the meeting has started".to_string()));
+    }
+    let resolution = payload.resolution.trim();
+    if resolution.is_empty() {
+        return Err(AppError::BadRequest("Resolution text is required".to_string()));
+    }
+    if let Some(agenda_item_id) = payload.agenda_item_id {
+        let found: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM meeting_agenda_item WHERE id = ? AND meeting_id = ?")
+            .bind(agenda_item_id)
+            .bind(meeting_id)
+            .fetch_one(pool)
+            .await?;
+        if found.0 == 0 {
+            return Err(AppError::NotFound("No such agenda item in this meeting".to_string()));
+        }
+    }
+    match (payload.record_type, payload.record_id) {
+        (Some(record_type), Some(record_id)) => check_record(pool, &meeting.chama_id, record_type, &record_id).await?,
+        (None, None) => {}
+        _ => return Err(AppError::BadRequest("Both the record type and id are needed to link a record".to_string())),
+    }
+
+    let mut resolution = MeetingResolution {
+        id:None,
+        meeting_id:*meeting_id,
+        chama_id:meeting.chama_id,
+        agenda_item_id:payload.agenda_item_id,
+        resolution:resolution.to_string(),
+        record_type:payload.record_type,
+        record_id:payload.record_id,
+        created_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let resolution_repository = data_repository::DataRepository::<MeetingResolution> {
+        pool,
+        table_name: "meeting_resolution",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    resolution.id = Some(resolution_repository.insert(&resolution).await?);
+
+    info!("Resolution {:?} recorded at meeting {}", resolution.id, meeting_id);
+    Ok(resolution)
+}
+
+/// Links a resolution to the record created on its authority afterwards.
+pub async fn link_resolution(pool:&MySqlPool, user_id:&str, resolution_id:&i64, payload:&ResolutionLinkDto) -> Result<MeetingResolution, AppError> {
+    let mut resolution = sqlx::query_as::<_, MeetingResolution>("SELECT * FROM meeting_resolution WHERE id = ?")
+        .bind(resolution_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such resolution".to_string()))?;
+    chama_service::require_permission(pool, user_id, &resolution.chama_id, ChamaPermission::MANAGEMEETINGS).await?;
+    if resolution.record_id.is_some() {
+        return Err(AppError::Conflict("Resolution is already linked to a record".to_string()));
+    }
+    check_record(pool, &resolution.chama_id, payload.record_type, &payload.record_id).await?;
+
+    let now_eat = utils::now_eat();
+    let updated = sqlx::query(
+        "UPDATE meeting_resolution SET record_type = ?, record_id = ?, updated_at = ? WHERE id = ? AND record_id IS NULL"
+    )
+    .bind(payload.record_type)
+    .bind(payload.record_id)
+    .bind(now_eat)
+    .bind(resolution_id)
+    .execute(pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict("Resolution is already linked to a record".to_string()));
+    }
+
+    resolution.record_type = Some(payload.record_type);
+    resolution.record_id = Some(payload.record_id);
+    resolution.updated_at = now_eat;
+    Ok(resolution)
+}
+
+/// The chama's resolutions, newest first, so officials can trace what
+/// authorised a record.
+pub async fn get_resolutions(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<MeetingResolution>, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let resolutions = sqlx::query_as::<_, MeetingResolution>("SELECT * FROM meeting_resolution WHERE chama_id = ? ORDER BY id DESC")
+        .bind(chama_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(resolutions)
+}
+
+pub async fn record_minutes(pool:&MySqlPool, user_id:&str, meeting_id:&i64, payload:&MeetingMinutesDto) -> Result<Meeting, AppError> {
+    let mut meeting = find_meeting(pool, meeting_id).await?;
+    chama_service::require_permission(pool, user_id, &meeting.chama_id, ChamaPermission::MANAGEMEETINGS).await?;
+    let recorded_by = loan_service::parse_user_id(user_id)?;
+    if meeting.status != MeetingStatus::HELD {
+        return Err(AppError::Conflict("Minutes are recorded once attendance has been taken".to_string()));
+    }
+    let minutes = payload.minutes.trim();
+    if minutes.is_empty() {
+        return Err(AppError::BadRequest("Minutes cannot be empty".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    sqlx::query("UPDATE meeting SET minutes = ?, minutes_recorded_by = ?, updated_at = ? WHERE id = ?")
+        .bind(minutes)
+        .bind(recorded_by)
+        .bind(now_eat)
+        .bind(meeting_id)
+        .execute(pool)
+        .await?;
+
+    meeting.minutes = Some(minutes.to_string());
+    meeting.minutes_recorded_by = Some(recorded_by);
+    meeting.updated_at = now_eat;
+    Ok(meeting)
+}
+
+pub async fn cancel_meeting(pool:&MySqlPool, user_id:&str, meeting_id:&i64) -> Result<Meeting, AppError> {
+    let mut meeting = find_meeting(pool, meeting_id).await?;
+    chama_service::require_permission(pool, user_id, &meeting.chama_id, ChamaPermission::MANAGEMEETINGS).await?;
+
+    let now_eat = utils::now_eat();
+    let updated = sqlx::query("UPDATE meeting SET status = ?, updated_at = ? WHERE id = ? AND status = ?")
+        .bind(MeetingStatus::CANCELLED)
+        .bind(now_eat)
+        .bind(meeting_id)
+        .bind(MeetingStatus::SCHEDULED)
+        .execute(pool)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("Meeting is {:?} and cannot be cancelled", meeting.status)));
+    }
+
+    info!("Meeting {} of chama {} cancelled", meeting_id, meeting.chama_id);
+    let message = format!("{} scheduled for {} has been cancelled.", meeting.title, meeting.scheduled_at.format("%d/%m/%Y %H:%M"));
+    for member_id in chama_service::active_member_ids(pool, &meeting.chama_id).await? {
+        notification_service::notify_user(pool, &member_id, "Chama meeting cancelled", &message).await;
+    }
+
+    meeting.status = MeetingStatus::CANCELLED;
+    meeting.updated_at = now_eat;
+    Ok(meeting)
+}
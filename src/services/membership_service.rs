@@ -0,0 +1,404 @@
+use chrono::NaiveDateTime;
+use sqlx::{MySql, MySqlConnection, MySqlPool, Row, Transaction};
+use tracing::info;
+
+use crate::dtos::chama::{
+    ChamaMembershipSettingDto,
+    MembershipApproveDto,
+    MembershipRejectDto,
+    MembershipRequestDetailDto
+};
+use crate::error::AppError;
+use crate::models::chama::{ChamaMember, ChamaMembershipRequest, ChamaMembershipSetting, ChamaPermission, MembershipRequestStatus};
+use crate::money::Money;
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, ledger_service, loan_service, notification_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+/// Sets the fee new members pay before they are let in, replacing the
+/// previous setting. Requests already in the queue keep the fee they were
+/// opened with.
+pub async fn set_setting(pool:&MySqlPool, user_id:&str, payload:&ChamaMembershipSettingDto) -> Result<ChamaMembershipSetting, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+    if payload.joining_fee.is_negative() {
+        return Err(AppError::BadRequest("Joining fee cannot be negative".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE chama_membership_setting SET is_active = 0, updated_at = ? WHERE chama_id = ? AND is_active = 1")
+        .bind(now_eat)
+        .bind(payload.chama_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let setting_repository = data_repository::DataRepository::<ChamaMembershipSetting> {
+        pool,
+        table_name: "chama_membership_setting",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let mut setting = ChamaMembershipSetting {
+        id:None,
+        chama_id:payload.chama_id,
+        joining_fee:payload.joining_fee,
+        created_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+        is_active:1
+    };
+    setting.id = Some(setting_repository.insert_trx(&mut tx, &setting).await?);
+    tx.commit().await?;
+
+    Ok(setting)
+}
+
+async fn get_joining_fee(conn:&mut MySqlConnection, chama_id:&i64) -> Result<Money, sqlx::Error> {
+    let fee: Option<(Money,)> = sqlx::query_as(
+        "SELECT joining_fee FROM chama_membership_setting WHERE chama_id = ? AND is_active = 1 ORDER BY id DESC LIMIT 1"
+    )
+    .bind(chama_id)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(fee.map_or(Money::ZERO, |(fee,)| fee))
+}
+
+/// Locks the chama and fails when it already has as many active members as
+/// its size allows. A size of zero or less leaves the chama uncapped.
+pub async fn require_capacity(conn:&mut MySqlConnection, chama_id:&i64) -> Result<(), AppError> {
+    let (size,): (i32,) = sqlx::query_as("SELECT size FROM chama WHERE id = ? FOR UPDATE")
+        .bind(chama_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No such chama".to_string()))?;
+    if size <= 0 {
+        return Ok(());
+    }
+
+    let (members,): (i64,) = sqlx::query_as("SELECT COUNT(DISTINCT user_id) FROM chama_member WHERE chama_id = ? AND is_active = 1")
+        .bind(chama_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if members >= i64::from(size) {
+        return Err(AppError::Conflict(format!("The chama is full at {} members", size)));
+    }
+    Ok(())
+}
+
+/// Queues a request to join the chama behind an invite. Members and people
+/// already waiting cannot queue again, and nobody can queue for a full chama.
+pub async fn open_request(pool:&MySqlPool, tx:&mut Transaction<'_, MySql>, chama_id:&i64, user_id:&i64, invite_id:&i64) -> Result<ChamaMembershipRequest, AppError> {
+    require_capacity(tx, chama_id).await?;
+
+    let (active,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chama_member WHERE chama_id = ? AND user_id = ? AND is_active = 1")
+        .bind(chama_id)
+        .bind(user_id)
+        .fetch_one(&mut **tx)
+        .await?;
+    if active > 0 {
+        return Err(AppError::Conflict("You are already a member of this chama".to_string()));
+    }
+    let (pending,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chama_membership_request WHERE chama_id = ? AND user_id = ? AND status = ?")
+        .bind(chama_id)
+        .bind(user_id)
+        .bind(MembershipRequestStatus::PENDING)
+        .fetch_one(&mut **tx)
+        .await?;
+    if pending > 0 {
+        return Err(AppError::Conflict("You have already asked to join this chama".to_string()));
+    }
+
+    let now_eat = utils::now_eat();
+    let mut request = ChamaMembershipRequest {
+        id:None,
+        chama_id:*chama_id,
+        user_id:*user_id,
+        invite_id:*invite_id,
+        status:MembershipRequestStatus::PENDING,
+        joining_fee:get_joining_fee(tx, chama_id).await?,
+        fee_paid_at:None,
+        reason:None,
+        reviewed_by:None,
+        reviewed_at:None,
+        chama_member_id:None,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let request_repository = data_repository::DataRepository::<ChamaMembershipRequest> {
+        pool,
+        table_name: "chama_membership_request",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    request.id = Some(request_repository.insert_trx(tx, &request).await?);
+
+    Ok(request)
+}
+
+async fn lock_request(conn:&mut MySqlConnection, request_id:&i64) -> Result<ChamaMembershipRequest, AppError> {
+    let request = sqlx::query_as::<_, ChamaMembershipRequest>("SELECT * FROM chama_membership_request WHERE id = ? FOR UPDATE")
+        .bind(request_id)
+        .fetch_optional(conn)
+        .await?;
+
+    request.ok_or_else(|| AppError::NotFound("No such membership request".to_string()))
+}
+
+/// Lists the requests waiting on a chama's officials, oldest first.
+pub async fn get_pending_requests(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<MembershipRequestDetailDto>, AppError> {
+    chama_service::require_permission(pool, user_id, chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+
+    let rows = sqlx::query(
+        "SELECT r.id, r.chama_id, r.user_id, au.first_name, au.last_name, au.username, au.email,
+            r.status, r.joining_fee, r.fee_paid_at, r.reason, r.created_at
+        FROM chama_membership_request r
+        INNER JOIN auth_user au ON au.id = r.user_id
+        WHERE r.chama_id = ? AND r.status = ?
+        ORDER BY r.created_at"
+    )
+    .bind(chama_id)
+    .bind(MembershipRequestStatus::PENDING)
+    .fetch_all(pool)
+    .await?;
+
+    let mut requests = Vec::with_capacity(rows.len());
+    for row in rows {
+        requests.push(MembershipRequestDetailDto {
+            request_id:row.try_get("id")?,
+            chama_id:row.try_get("chama_id")?,
+            user_id:row.try_get("user_id")?,
+            first_name:row.try_get("first_name")?,
+            last_name:row.try_get("last_name")?,
+            phone_number:row.try_get("username")?,
+            email:row.try_get("email")?,
+            status:row.try_get("status")?,
+            joining_fee:row.try_get("joining_fee")?,
+            fee_paid:row.try_get::<Option<NaiveDateTime>, _>("fee_paid_at")?.is_some(),
+            reason:row.try_get("reason")?,
+            created_at:row.try_get("created_at")?,
+        });
+    }
+    Ok(requests)
+}
+
+/// Pays the joining fee on a request into the chama's savings. Applicants pay
+/// from their wallet; an official paying for them records cash received.
+pub async fn pay_joining_fee(pool:&MySqlPool, user_id:&str, request_id:&i64) -> Result<ChamaMembershipRequest, AppError> {
+    let payer_id = loan_service::parse_user_id(user_id)?;
+
+    let mut tx = pool.begin().await?;
+    let mut request = lock_request(&mut tx, request_id).await?;
+    let source = if request.user_id == payer_id {
+        LedgerAccountKey::Wallet(payer_id)
+    } else {
+        chama_service::require_permission(pool, user_id, &request.chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+        LedgerAccountKey::Cash
+    };
+    if request.status != MembershipRequestStatus::PENDING {
+        return Err(AppError::Conflict(format!("Membership request is already {:?}", request.status)));
+    }
+    if !request.joining_fee.is_positive() {
+        return Err(AppError::BadRequest("This chama has no joining fee".to_string()));
+    }
+    if request.fee_paid_at.is_some() {
+        return Err(AppError::Conflict("The joining fee has already been paid".to_string()));
+    }
+
+    ledger_service::post_journal(
+        &mut tx,
+        &JournalRequest {
+            reference:&format!("JOINING-FEE-{}", request_id),
+            entry_type:"JOINING_FEE",
+            narration:&format!("Joining fee of user {} for chama {}", request.user_id, request.chama_id),
+            created_by:Some(payer_id),
+        },
+        &[
+            PostingLine::debit(source, request.joining_fee),
+            PostingLine::credit(LedgerAccountKey::ChamaSavings(request.chama_id), request.joining_fee),
+        ],
+    ).await?;
+
+    let now_eat = utils::now_eat();
+    sqlx::query("UPDATE chama_membership_request SET fee_paid_at = ?, updated_at = ? WHERE id = ?")
+        .bind(now_eat)
+        .bind(now_eat)
+        .bind(request_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("Joining fee of {} paid on membership request {} from {:?}", request.joining_fee, request_id, source);
+    request.fee_paid_at = Some(now_eat);
+    Ok(request)
+}
+
+/// Lets the applicant in once any joining fee is paid and the chama still has
+/// room. A former member who was removed gets their old membership back.
+pub async fn approve_request(pool:&MySqlPool, user_id:&str, request_id:&i64, payload:&MembershipApproveDto) -> Result<ChamaMember, AppError> {
+    let reviewer_id = loan_service::parse_user_id(user_id)?;
+    if payload.contribution_amount.is_negative() {
+        return Err(AppError::BadRequest("Contribution amount cannot be negative".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let request = lock_request(&mut tx, request_id).await?;
+    chama_service::require_permission(pool, user_id, &request.chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+    if request.status != MembershipRequestStatus::PENDING {
+        return Err(AppError::Conflict(format!("Membership request is already {:?}", request.status)));
+    }
+    if request.joining_fee.is_positive() && request.fee_paid_at.is_none() {
+        return Err(AppError::BadRequest(format!("The joining fee of {} has not been paid", request.joining_fee)));
+    }
+    chama_service::require_assignable_position(pool, user_id, &request.chama_id, &payload.position).await?;
+    require_capacity(&mut tx, &request.chama_id).await?;
+
+    let now_eat = utils::now_eat();
+    let existing = sqlx::query_as::<_, ChamaMember>(
+        "SELECT * FROM chama_member WHERE chama_id = ? AND user_id = ? ORDER BY id DESC LIMIT 1 FOR UPDATE"
+    )
+    .bind(request.chama_id)
+    .bind(request.user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let mut member = ChamaMember {
+        id:None,
+        user_id:request.user_id,
+        chama_id:request.chama_id,
+        position:payload.position,
+        contribution_amount:payload.contribution_amount,
+        created_at:now_eat,
+        updated_at:now_eat,
+        created_by:reviewer_id,
+        is_active:1
+    };
+    match existing {
+        Some(ChamaMember { id:Some(member_id), created_at, .. }) => {
+            sqlx::query("UPDATE chama_member SET position = ?, contribution_amount = ?, created_by = ?, is_active = 1, updated_at = ? WHERE id = ?")
+                .bind(member.position)
+                .bind(member.contribution_amount)
+                .bind(reviewer_id)
+                .bind(now_eat)
+                .bind(member_id)
+                .execute(&mut *tx)
+                .await?;
+            member.id = Some(member_id);
+            member.created_at = created_at;
+        },
+        _ => {
+            let member_repository = data_repository::DataRepository::<ChamaMember> {
+                pool,
+                table_name: "chama_member",
+                pk_column: "id",
+                phantom: std::marker::PhantomData,
+            };
+            member.id = Some(member_repository.insert_trx(&mut tx, &member).await?);
+        },
+    }
+
+    sqlx::query("UPDATE chama_membership_request SET status = ?, chama_member_id = ?, reviewed_by = ?, reviewed_at = ?, updated_at = ? WHERE id = ?")
+        .bind(MembershipRequestStatus::APPROVED)
+        .bind(member.id)
+        .bind(reviewer_id)
+        .bind(now_eat)
+        .bind(now_eat)
+        .bind(request_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("Membership request {} approved by {}, user {} joined chama {}", request_id, reviewer_id, request.user_id, request.chama_id);
+    notification_service::notify_user(pool, &request.user_id, "Chama membership",
+        "Your request to join the chama has been approved. Welcome aboard.").await;
+    Ok(member)
+}
+
+/// Returns a paid joining fee to the applicant's wallet.
+async fn refund_joining_fee(conn:&mut MySqlConnection, request:&ChamaMembershipRequest, created_by:i64) -> Result<(), AppError> {
+    if request.fee_paid_at.is_none() {
+        return Ok(());
+    }
+
+    ledger_service::post_journal(
+        conn,
+        &JournalRequest {
+            reference:&format!("JOINING-FEE-{}-REFUND", request.id.unwrap_or_default()),
+            entry_type:"JOINING_FEE_REFUND",
+            narration:&format!("Joining fee refund to user {} from chama {}", request.user_id, request.chama_id),
+            created_by:Some(created_by),
+        },
+        &[
+            PostingLine::debit(LedgerAccountKey::ChamaSavings(request.chama_id), request.joining_fee),
+            PostingLine::credit(LedgerAccountKey::Wallet(request.user_id), request.joining_fee),
+        ],
+    ).await?;
+    Ok(())
+}
+
+async fn close_request(conn:&mut MySqlConnection, request:&ChamaMembershipRequest, status:MembershipRequestStatus, reason:Option<&str>, closed_by:i64) -> Result<(), AppError> {
+    refund_joining_fee(conn, request, closed_by).await?;
+
+    let now_eat = utils::now_eat();
+    sqlx::query("UPDATE chama_membership_request SET status = ?, reason = ?, reviewed_by = ?, reviewed_at = ?, updated_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(reason)
+        .bind(closed_by)
+        .bind(now_eat)
+        .bind(now_eat)
+        .bind(request.id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Turns an applicant away. The reason is kept on the request and sent to
+/// them, and any joining fee they paid goes back to their wallet.
+pub async fn reject_request(pool:&MySqlPool, user_id:&str, request_id:&i64, payload:&MembershipRejectDto) -> Result<ChamaMembershipRequest, AppError> {
+    let reviewer_id = loan_service::parse_user_id(user_id)?;
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::BadRequest("A reason is required".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut request = lock_request(&mut tx, request_id).await?;
+    chama_service::require_permission(pool, user_id, &request.chama_id, ChamaPermission::MANAGEMEMBERS).await?;
+    if request.status != MembershipRequestStatus::PENDING {
+        return Err(AppError::Conflict(format!("Membership request is already {:?}", request.status)));
+    }
+    close_request(&mut tx, &request, MembershipRequestStatus::REJECTED, Some(reason), reviewer_id).await?;
+    tx.commit().await?;
+
+    info!("Membership request {} rejected by {}: {}", request_id, reviewer_id, reason);
+    let message = format!("Your request to join the chama was not approved: {}", reason);
+    notification_service::notify_user(pool, &request.user_id, "Chama membership", &message).await;
+
+    request.status = MembershipRequestStatus::REJECTED;
+    request.reason = Some(reason.to_string());
+    request.reviewed_by = Some(reviewer_id);
+    Ok(request)
+}
+
+/// Withdraws the caller's own request, refunding any joining fee.
+pub async fn cancel_request(pool:&MySqlPool, user_id:&str, request_id:&i64) -> Result<ChamaMembershipRequest, AppError> {
+    let applicant_id = loan_service::parse_user_id(user_id)?;
+
+    let mut tx = pool.begin().await?;
+    let mut request = lock_request(&mut tx, request_id).await?;
+    if request.user_id != applicant_id {
+        return Err(AppError::Forbidden("Only the applicant can cancel this request".to_string()));
+    }
+    if request.status != MembershipRequestStatus::PENDING {
+        return Err(AppError::Conflict(format!("Membership request is already {:?}", request.status)));
+    }
+    close_request(&mut tx, &request, MembershipRequestStatus::CANCELLED, None, applicant_id).await?;
+    tx.commit().await?;
+
+    info!("Membership request {} cancelled by the applicant", request_id);
+    request.status = MembershipRequestStatus::CANCELLED;
+    Ok(request)
+}
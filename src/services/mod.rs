@@ -1,4 +1,23 @@
 pub mod authentication_service;
 pub mod account_service;
 pub mod email_service;
-pub mod chama_service;
\ No newline at end of file
+pub mod chama_service;
+pub mod loan_service;
+pub mod guarantee_service;
+pub mod schedule_service;
+pub mod repayment_service;
+pub mod notification_service;
+pub mod default_service;
+pub mod ledger_service;
+pub mod deposit_service;
+pub mod withdrawal_service;
+pub mod reconciliation_service;
+pub mod contribution_service;
+pub mod rotation_service;
+pub mod fine_service;
+pub mod dividend_service;
+pub mod meeting_service;
+pub mod proposal_service;
+pub mod chama_wallet_service;
+pub mod membership_service;
+pub mod exit_service;
@@ -0,0 +1,31 @@
+use sqlx::MySqlPool;
+use tracing::{info, error};
+
+use crate::services::{authentication_service, email_service};
+
+
+/// Emails a user in the background so a slow or failing mail server never
+/// holds up the caller. Users without an email address are skipped.
+pub async fn notify_user(pool:&MySqlPool, user_id:&i64, subject:&str, message:&str) {
+    let Some(user) = authentication_service::get_auth_user_by_id(pool, &user_id.to_string()).await else {
+        error!("Cannot notify unknown user {}", user_id);
+        return;
+    };
+    let Some(email) = user.email.filter(|email| !email.is_empty()) else {
+        info!("User {} has no email address, skipping notification", user_id);
+        return;
+    };
+
+    let body = format!(
+        r#"<p>Hi {},</p>
+        <p>{}</p>
+        <p>Thanks,<br>YourApp Team</p>"#,
+        user.first_name, message
+    );
+    let subject = subject.to_string();
+
+    tokio::spawn(async move {
+        let response = email_service::send_email(email, subject, body).await;
+        info!("Email send response: {}", response);
+    });
+}
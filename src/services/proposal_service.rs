@@ -0,0 +1,401 @@
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::{info, error};
+
+use crate::dtos::chama::ChamaLoadLimitDto;
+use crate::dtos::proposal::{ProposalDetailDto, ProposalDto, ProposalVoteDto};
+use crate::error::AppError;
+use crate::models::chama::ChamaPermission;
+use crate::models::proposal::{Proposal, ProposalStatus, ProposalTypeEnum, ProposalVote, VoteChoiceEnum};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, contribution_service, exit_service, loan_service, notification_service};
+use crate::utils;
+
+
+async fn find_proposal(pool:&MySqlPool, proposal_id:&i64) -> Result<Proposal, AppError> {
+    let proposal = sqlx::query_as::<_, Proposal>("SELECT * FROM proposal WHERE id = ?")
+        .bind(proposal_id)
+        .fetch_optional(pool)
+        .await?;
+
+    proposal.ok_or_else(|| AppError::NotFound("No such proposal".to_string()))
+}
+
+async fn lock_proposal(conn:&mut MySqlConnection, proposal_id:&i64) -> Result<Proposal, AppError> {
+    let proposal = sqlx::query_as::<_, Proposal>("SELECT * FROM proposal WHERE id = ? FOR UPDATE")
+        .bind(proposal_id)
+        .fetch_optional(conn)
+        .await?;
+
+    proposal.ok_or_else(|| AppError::NotFound("No such proposal".to_string()))
+}
+
+async fn notify_members(pool:&MySqlPool, chama_id:&i64, subject:&str, message:&str) -> Result<(), AppError> {
+    for member_id in chama_service::active_member_ids(pool, chama_id).await? {
+        notification_service::notify_user(pool, &member_id, subject, message).await;
+    }
+    Ok(())
+}
+
+/// Checks a proposal carries what its effect needs.
+async fn check_effect(pool:&MySqlPool, payload:&ProposalDto) -> Result<(), AppError> {
+    match payload.proposal_type {
+        ProposalTypeEnum::GENERAL => {}
+        ProposalTypeEnum::LOANLIMIT => {
+            let (Some(amount), Some(centage)) = (payload.amount, payload.centage_member_savings) else {
+                return Err(AppError::BadRequest("A loan limit proposal needs an amount and a savings percentage".to_string()));
+            };
+            if !amount.is_positive() || centage <= 0.0 {
+                return Err(AppError::BadRequest("Loan limit amount and savings percentage must be greater than zero".to_string()));
+            }
+        }
+        ProposalTypeEnum::POSITIONCHANGE | ProposalTypeEnum::MEMBERREMOVAL => {
+            let Some(target_user_id) = payload.target_user_id else {
+                return Err(AppError::BadRequest("The proposal needs the member it is about".to_string()));
+            };
+            if !chama_service::is_active_member(pool, &payload.chama_id, &target_user_id).await? {
+                return Err(AppError::BadRequest(format!("User {} is not a member of the chama", target_user_id)));
+            }
+            let pending: (i64,) = sqlx::query_as(
+                "SELECT COUNT(*) FROM proposal WHERE chama_id = ? AND proposal_type = ? AND target_user_id = ? AND status = ?"
+            )
+            .bind(payload.chama_id)
+            .bind(payload.proposal_type)
+            .bind(target_user_id)
+            .bind(ProposalStatus::OPEN)
+            .fetch_one(pool)
+            .await?;
+            if pending.0 > 0 {
+                return Err(AppError::Conflict("A proposal like this is already being voted on".to_string()));
+            }
+        }
+    }
+    if payload.proposal_type == ProposalTypeEnum::POSITIONCHANGE {
+        let Some(position_id) = payload.position_id else {
+            return Err(AppError::BadRequest("A position change needs the new position".to_string()));
+        };
+        let found: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM chama_position WHERE id = ? AND chama_id = ?")
+            .bind(position_id)
+            .bind(payload.chama_id)
+            .fetch_one(pool)
+            .await?;
+        if found.0 == 0 {
+            return Err(AppError::NotFound("No such position".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Puts a change to the members' vote. Any active member may propose.
+pub async fn create_proposal(pool:&MySqlPool, user_id:&str, payload:&ProposalDto) -> Result<Proposal, AppError> {
+    let created_by = loan_service::parse_user_id(user_id)?;
+    if !chama_service::is_active_member(pool, &payload.chama_id, &created_by).await? {
+        return Err(AppError::Forbidden("Only members can make proposals".to_string()));
+    }
+    let title = payload.title.trim();
+    if title.is_empty() {
+        return Err(AppError::BadRequest("Proposals need a title".to_string()));
+    }
+    let now_eat = utils::now_eat();
+    let opens_at = payload.opens_at.unwrap_or(now_eat).max(now_eat);
+    if payload.closes_at <= opens_at {
+        return Err(AppError::BadRequest("Voting must close after it opens".to_string()));
+    }
+    let quorum_rate = payload.quorum_rate.unwrap_or(50.0);
+    if quorum_rate <= 0.0 || quorum_rate > 100.0 {
+        return Err(AppError::BadRequest("Quorum must be between 0 and 100 percent".to_string()));
+    }
+    let threshold_rate = payload.threshold_rate.unwrap_or(50.0);
+    if !(50.0..=100.0).contains(&threshold_rate) {
+        return Err(AppError::BadRequest("Threshold must be between 50 and 100 percent".to_string()));
+    }
+    check_effect(pool, payload).await?;
+
+    let mut proposal = Proposal {
+        id:None,
+        chama_id:payload.chama_id,
+        proposal_type:payload.proposal_type,
+        title:title.to_string(),
+        description:payload.description.clone(),
+        target_user_id:payload.target_user_id,
+        position_id:payload.position_id,
+        amount:payload.amount,
+        centage_member_savings:payload.centage_member_savings,
+        opens_at,
+        closes_at:payload.closes_at,
+        quorum_rate,
+        threshold_rate,
+        status:ProposalStatus::OPEN,
+        eligible_voters:None,
+        yes_votes:0,
+        no_votes:0,
+        abstain_votes:0,
+        applied_at:None,
+        apply_error:None,
+        created_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let proposal_repository = data_repository::DataRepository::<Proposal> {
+        pool,
+        table_name: "proposal",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    proposal.id = Some(proposal_repository.insert(&proposal).await?);
+
+    info!("Proposal {:?} ({:?}) opened in chama {}", proposal.id, proposal.proposal_type, proposal.chama_id);
+    let message = format!("New proposal: {}. Voting is open from {} to {}.", proposal.title,
+        proposal.opens_at.format("%d/%m/%Y %H:%M"), proposal.closes_at.format("%d/%m/%Y %H:%M"));
+    notify_members(pool, &proposal.chama_id, "Chama proposal", &message).await?;
+    Ok(proposal)
+}
+
+pub async fn get_proposals(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<Vec<Proposal>, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let proposals = sqlx::query_as::<_, Proposal>("SELECT * FROM proposal WHERE chama_id = ? ORDER BY id DESC")
+        .bind(chama_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(proposals)
+}
+
+pub async fn get_proposal(pool:&MySqlPool, user_id:&str, proposal_id:&i64) -> Result<ProposalDetailDto, AppError> {
+    let proposal = find_proposal(pool, proposal_id).await?;
+    contribution_service::require_member(pool, user_id, &proposal.chama_id).await?;
+
+    let votes = sqlx::query_as::<_, ProposalVote>("SELECT * FROM proposal_vote WHERE proposal_id = ? ORDER BY id")
+        .bind(proposal_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(ProposalDetailDto { proposal, votes })
+}
+
+/// Casts the member's one vote. Voting closes early once every active member
+/// has voted.
+pub async fn cast_vote(pool:&MySqlPool, user_id:&str, proposal_id:&i64, payload:&ProposalVoteDto) -> Result<Proposal, AppError> {
+    let voter_id = loan_service::parse_user_id(user_id)?;
+
+    let mut tx = pool.begin().await?;
+    let proposal = lock_proposal(&mut tx, proposal_id).await?;
+    if !chama_service::is_active_member(pool, &proposal.chama_id, &voter_id).await? {
+        return Err(AppError::Forbidden("Only members can vote".to_string()));
+    }
+    let now_eat = utils::now_eat();
+    if proposal.status != ProposalStatus::OPEN || now_eat >= proposal.closes_at {
+        return Err(AppError::Conflict("Voting on this proposal has closed".to_string()));
+    }
+    if now_eat < proposal.opens_at {
+        return Err(AppError::Conflict("Voting on this proposal has not opened yet".to_string()));
+    }
+    let voted: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM proposal_vote WHERE proposal_id = ? AND user_id = ?")
+        .bind(proposal_id)
+        .bind(voter_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if voted.0 > 0 {
+        return Err(AppError::Conflict("You have already voted on this proposal".to_string()));
+    }
+
+    sqlx::query("INSERT INTO proposal_vote (proposal_id, user_id, choice, created_at) VALUES (?, ?, ?, ?)")
+        .bind(proposal_id)
+        .bind(voter_id)
+        .bind(payload.choice)
+        .bind(now_eat)
+        .execute(&mut *tx)
+        .await?;
+    let column = match payload.choice {
+        VoteChoiceEnum::YES => "yes_votes",
+        VoteChoiceEnum::NO => "no_votes",
+        VoteChoiceEnum::ABSTAIN => "abstain_votes",
+    };
+    sqlx::query(&format!("UPDATE proposal SET {} = {} + 1, updated_at = ? WHERE id = ?", column, column))
+        .bind(now_eat)
+        .bind(proposal_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let proposal = find_proposal(pool, proposal_id).await?;
+    let cast = proposal.yes_votes + proposal.no_votes + proposal.abstain_votes;
+    if cast as usize >= chama_service::active_member_ids(pool, &proposal.chama_id).await?.len() {
+        return close_proposal(pool, proposal_id).await;
+    }
+    Ok(proposal)
+}
+
+/// Withdraws an open proposal. Only whoever made it or a chama admin can.
+pub async fn cancel_proposal(pool:&MySqlPool, user_id:&str, proposal_id:&i64) -> Result<Proposal, AppError> {
+    let caller = loan_service::parse_user_id(user_id)?;
+    let mut proposal = find_proposal(pool, proposal_id).await?;
+    if proposal.created_by != caller {
+        chama_service::require_permission(pool, user_id, &proposal.chama_id, ChamaPermission::MANAGEMEETINGS).await?;
+    }
+
+    let now_eat = utils::now_eat();
+    let updated = sqlx::query("UPDATE proposal SET status = ?, updated_at = ? WHERE id = ? AND status = ?")
+        .bind(ProposalStatus::CANCELLED)
+        .bind(now_eat)
+        .bind(proposal_id)
+        .bind(ProposalStatus::OPEN)
+        .execute(pool)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("Proposal is {:?} and cannot be cancelled", proposal.status)));
+    }
+
+    info!("Proposal {} of chama {} cancelled by {}", proposal_id, proposal.chama_id, caller);
+    proposal.status = ProposalStatus::CANCELLED;
+    proposal.updated_at = now_eat;
+    Ok(proposal)
+}
+
+/// The outcome of the votes cast. Abstentions count towards the quorum but
+/// not the threshold, and a tie never passes.
+fn tally(proposal:&Proposal, eligible_voters:i32) -> ProposalStatus {
+    let cast = (proposal.yes_votes + proposal.no_votes + proposal.abstain_votes) as f64;
+    if eligible_voters == 0 || cast * 100.0 < proposal.quorum_rate * eligible_voters as f64 {
+        return ProposalStatus::NOQUORUM;
+    }
+    let decided = (proposal.yes_votes + proposal.no_votes) as f64;
+    if proposal.yes_votes > proposal.no_votes && proposal.yes_votes as f64 * 100.0 >= proposal.threshold_rate * decided {
+        ProposalStatus::PASSED
+    } else {
+        ProposalStatus::REJECTED
+    }
+}
+
+/// Makes the change a passed proposal was about.
+async fn apply_effect(pool:&MySqlPool, proposal:&Proposal) -> Result<(), AppError> {
+    match proposal.proposal_type {
+        ProposalTypeEnum::GENERAL => {}
+        ProposalTypeEnum::LOANLIMIT => {
+            let limit = ChamaLoadLimitDto {
+                id:None,
+                chama_id:proposal.chama_id,
+                amount:proposal.amount,
+                centage_member_savings:proposal.centage_member_savings,
+            };
+            chama_service::add_loan_limit(pool, &limit).await?;
+        }
+        ProposalTypeEnum::POSITIONCHANGE => {
+            let (Some(target_user_id), Some(position_id)) = (proposal.target_user_id, proposal.position_id) else {
+                return Err(AppError::BadRequest("Proposal has no member or position".to_string()));
+            };
+            if chama_service::set_member_position(pool, &proposal.chama_id, &target_user_id, &position_id).await? == 0 {
+                return Err(AppError::NotFound(format!("User {} is no longer a member", target_user_id)));
+            }
+        }
+        ProposalTypeEnum::MEMBERREMOVAL => {
+            let Some(target_user_id) = proposal.target_user_id else {
+                return Err(AppError::BadRequest("Proposal has no member".to_string()));
+            };
+            // Members leave with their savings settled, so removal opens their exit
+            let reason = format!("Removed by proposal {}: {}", proposal.id.unwrap_or_default(), proposal.title);
+            exit_service::open_exit(pool, &proposal.chama_id, &target_user_id, proposal.created_by, &reason).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Counts the votes on a proposal and, when it passes, applies its effect.
+/// A proposal that passed but could not be applied keeps the reason.
+pub async fn close_proposal(pool:&MySqlPool, proposal_id:&i64) -> Result<Proposal, AppError> {
+    let mut tx = pool.begin().await?;
+    let proposal = lock_proposal(&mut tx, proposal_id).await?;
+    if proposal.status != ProposalStatus::OPEN {
+        return Ok(proposal);
+    }
+    let eligible_voters = chama_service::active_member_ids(pool, &proposal.chama_id).await?.len() as i32;
+    let status = tally(&proposal, eligible_voters);
+
+    sqlx::query("UPDATE proposal SET status = ?, eligible_voters = ?, updated_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(eligible_voters)
+        .bind(utils::now_eat())
+        .bind(proposal_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("Proposal {} closed {:?}: {} yes, {} no, {} abstained of {} members",
+        proposal_id, status, proposal.yes_votes, proposal.no_votes, proposal.abstain_votes, eligible_voters);
+    if status == ProposalStatus::PASSED {
+        apply_passed(pool, proposal_id).await?;
+    }
+
+    let proposal = find_proposal(pool, proposal_id).await?;
+    let message = format!("Voting on \"{}\" has closed: {:?} with {} yes, {} no and {} abstaining.",
+        proposal.title, proposal.status, proposal.yes_votes, proposal.no_votes, proposal.abstain_votes);
+    notify_members(pool, &proposal.chama_id, "Chama proposal result", &message).await?;
+    Ok(proposal)
+}
+
+/// Applies a passed proposal once; the lock keeps the job and a closing vote
+/// from applying it twice.
+async fn apply_passed(pool:&MySqlPool, proposal_id:&i64) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let proposal = lock_proposal(&mut tx, proposal_id).await?;
+    if proposal.status != ProposalStatus::PASSED || proposal.applied_at.is_some() || proposal.apply_error.is_some() {
+        return Ok(());
+    }
+
+    let now_eat = utils::now_eat();
+    match apply_effect(pool, &proposal).await {
+        Ok(()) => {
+            sqlx::query("UPDATE proposal SET applied_at = ?, updated_at = ? WHERE id = ?")
+                .bind(now_eat)
+                .bind(now_eat)
+                .bind(proposal_id)
+                .execute(&mut *tx)
+                .await?;
+            info!("Proposal {} applied", proposal_id);
+        }
+        Err(e) => {
+            error!("Could not apply proposal {}: {}", proposal_id, e);
+            sqlx::query("UPDATE proposal SET apply_error = ?, updated_at = ? WHERE id = ?")
+                .bind(e.to_string())
+                .bind(now_eat)
+                .bind(proposal_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Closes the proposals whose voting window has ended, and applies any that
+/// passed without being applied. Returns how many proposals were closed.
+pub async fn close_due_proposals(pool:&MySqlPool) -> Result<u32, AppError> {
+    let due: Vec<(i64,)> = sqlx::query_as("SELECT id FROM proposal WHERE status = ? AND closes_at <= ?")
+        .bind(ProposalStatus::OPEN)
+        .bind(utils::now_eat())
+        .fetch_all(pool)
+        .await?;
+
+    let mut closed = 0;
+    for (proposal_id,) in due {
+        match close_proposal(pool, &proposal_id).await {
+            Ok(_) => closed += 1,
+            Err(e) => error!("Could not close proposal {}: {}", proposal_id, e),
+        }
+    }
+
+    let unapplied: Vec<(i64,)> = sqlx::query_as(
+        "SELECT id FROM proposal WHERE status = ? AND applied_at IS NULL AND apply_error IS NULL"
+    )
+    .bind(ProposalStatus::PASSED)
+    .fetch_all(pool)
+    .await?;
+    for (proposal_id,) in unapplied {
+        if let Err(e) = apply_passed(pool, &proposal_id).await {
+            error!("Could not apply proposal {}: {}", proposal_id, e);
+        }
+    }
+
+    Ok(closed)
+}
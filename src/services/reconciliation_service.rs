@@ -0,0 +1,510 @@
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+
+use chrono::{Duration, NaiveDateTime};
+use sqlx::MySqlPool;
+use tracing::{info, error};
+
+use crate::dtos::payment::{
+    C2bPaymentDto,
+    PaymentDiscrepancyDto,
+    PendingSweepDto,
+    ReconciliationQueryDto,
+    ReversePaymentDto,
+    StatementImportQueryDto,
+    StatementImportResponseDto
+};
+use crate::error::AppError;
+use crate::gateways::{MobileMoneyGateway, PaymentDirection, PaymentStatus, StatusQuery};
+use crate::money::Money;
+use crate::models::statement::ProviderStatementLine;
+use crate::models::transaction::{Deposit, DepositStatus, Withdrawal, WithdrawalStatus};
+use crate::services::{authentication_service, deposit_service, loan_service, withdrawal_service};
+use crate::utils;
+
+
+/// Requests younger than this are left to their callbacks.
+const DEFAULT_PENDING_AGE_SECS: i64 = 300;
+
+/// Asks the provider what became of deposits and withdrawals that have been
+/// pending for a while, and settles the ones it has an answer for.
+pub async fn sweep_pending(pool:&MySqlPool, gateway:&dyn MobileMoneyGateway) -> Result<PendingSweepDto, AppError> {
+    let age = env::var("PAYMENT_PENDING_AGE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_PENDING_AGE_SECS);
+    let cutoff = utils::now_eat() - Duration::seconds(age);
+
+    let deposits = sqlx::query_as::<_, Deposit>("SELECT * FROM deposit WHERE status = ? AND created_at < ?")
+        .bind(DepositStatus::PENDING)
+        .bind(cutoff)
+        .fetch_all(pool)
+        .await?;
+    let withdrawals = sqlx::query_as::<_, Withdrawal>("SELECT * FROM withdrawal WHERE status IN (?, ?) AND created_at < ?")
+        .bind(WithdrawalStatus::PENDING)
+        .bind(WithdrawalStatus::SUBMITTED)
+        .bind(cutoff)
+        .fetch_all(pool)
+        .await?;
+
+    let mut sweep = PendingSweepDto { checked:0, settled:0 };
+
+    for deposit in deposits {
+        sweep.checked += 1;
+        let query = StatusQuery {
+            gateway:deposit.gateway.clone(),
+            direction:PaymentDirection::Collection,
+            reference:deposit.bill_no.clone(),
+            provider_reference:Some(deposit.vendor_reference.clone()),
+        };
+        let status = match gateway.query_status(&query).await {
+            Ok(PaymentStatus::Pending) => continue,
+            Ok(status) => status,
+            Err(e) => {
+                error!("Could not query deposit {:?} with {}: {}", deposit.id, deposit.gateway, e);
+                continue;
+            }
+        };
+        match deposit_service::settle_deposit(pool, &deposit.vendor_reference, &status).await {
+            Ok(_) => sweep.settled += 1,
+            Err(e) => error!("Could not settle deposit {:?}: {}", deposit.id, e),
+        }
+    }
+
+    for withdrawal in withdrawals {
+        sweep.checked += 1;
+        let query = StatusQuery {
+            gateway:withdrawal.gateway.clone(),
+            direction:PaymentDirection::Payout,
+            reference:withdrawal.reference.clone(),
+            provider_reference:withdrawal.conversation_id.clone(),
+        };
+        let status = match gateway.query_status(&query).await {
+            Ok(PaymentStatus::Pending) => continue,
+            Ok(status) => status,
+            Err(e) => {
+                error!("Could not query withdrawal {:?} with {}: {}", withdrawal.id, withdrawal.gateway, e);
+                continue;
+            }
+        };
+        match withdrawal_service::settle_withdrawal(pool, &withdrawal.reference, &status, "Settled from a status query").await {
+            Ok(_) => sweep.settled += 1,
+            Err(e) => error!("Could not settle withdrawal {:?}: {}", withdrawal.id, e),
+        }
+    }
+
+    info!("Pending payment sweep checked {}, settled {}", sweep.checked, sweep.settled);
+    Ok(sweep)
+}
+
+/// Splits a CSV row, honouring quoted fields and doubled quotes.
+fn split_csv_row(row:&str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+fn parse_statement_time(value:&str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%d %H:%M:%S", "%d-%m-%Y %H:%M:%S", "%d/%m/%Y %H:%M:%S", "%d/%m/%Y %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+}
+
+/// Statement amounts come as "1,000.00", with withdrawals sometimes negative.
+fn parse_statement_amount(value:&str) -> Option<Money> {
+    let value = value.replace(',', "");
+    let value = value.trim().trim_start_matches('-');
+    if value.is_empty() {
+        return Some(Money::ZERO);
+    }
+    Money::from_str(value).ok()
+}
+
+/// Reads an M-Pesa organisation statement export. Lines before the header row
+/// (the statement's title block) are ignored; columns are found by name.
+fn parse_statement(csv:&str) -> Result<Vec<ProviderStatementLine>, AppError> {
+    let mut rows = csv.lines().map(split_csv_row);
+
+    let header = rows
+        .by_ref()
+        .find(|row| row.iter().any(|f| f.eq_ignore_ascii_case("Receipt No.") || f.eq_ignore_ascii_case("Receipt No")))
+        .ok_or_else(|| AppError::BadRequest("Statement has no Receipt No. column".to_string()))?;
+    let column = |name:&str| header.iter().position(|f| f.trim_end_matches('.').eq_ignore_ascii_case(name));
+
+    let (Some(receipt), Some(completed), Some(paid_in), Some(withdrawn)) =
+        (column("Receipt No"), column("Completion Time"), column("Paid In"), column("Withdrawn")) else {
+        return Err(AppError::BadRequest("Statement needs Receipt No., Completion Time, Paid In and Withdrawn columns".to_string()));
+    };
+    let details = column("Details");
+    let status = column("Transaction Status");
+    let other_party = column("Other Party Info");
+    let account_no = column("A/C No");
+
+    let now_eat = utils::now_eat();
+    let mut lines = Vec::new();
+    for (number, row) in rows.enumerate() {
+        let field = |i:Option<usize>| i.and_then(|i| row.get(i)).cloned().unwrap_or_default();
+        let receipt_no = field(Some(receipt));
+        if receipt_no.is_empty() {
+            continue;
+        }
+        let bad_line = || AppError::BadRequest(format!("Statement line {} ({}) could not be read", number + 1, receipt_no));
+
+        lines.push(ProviderStatementLine {
+            id:None,
+            provider_statement_id:0,
+            completed_at:parse_statement_time(&field(Some(completed))).ok_or_else(bad_line)?,
+            details:field(details),
+            transaction_status:field(status),
+            paid_in:parse_statement_amount(&field(Some(paid_in))).ok_or_else(bad_line)?,
+            withdrawn:parse_statement_amount(&field(Some(withdrawn))).ok_or_else(bad_line)?,
+            other_party:field(other_party),
+            account_no:field(account_no),
+            receipt_no,
+            created_at:now_eat,
+        });
+    }
+    Ok(lines)
+}
+
+/// Stores the lines of a provider statement that fall in the given period.
+/// Lines already imported from an overlapping statement are skipped, so the
+/// same file can be imported again safely.
+pub async fn import_statement(
+    pool:&MySqlPool,
+    user_id:&str,
+    query:&StatementImportQueryDto,
+    csv:&str) -> Result<StatementImportResponseDto, AppError> {
+
+    authentication_service::require_staff(pool, user_id).await?;
+    let staff_id = loan_service::parse_user_id(user_id)?;
+    if query.from > query.to {
+        return Err(AppError::BadRequest("Period starts after it ends".to_string()));
+    }
+
+    let lines = parse_statement(csv)?;
+
+    let mut tx = pool.begin().await?;
+    let statement = sqlx::query(
+        "INSERT INTO provider_statement (gateway, period_start, period_end, line_count, imported_by, created_at)
+        VALUES (?, ?, ?, 0, ?, ?)"
+    )
+    .bind(&query.gateway)
+    .bind(query.from)
+    .bind(query.to)
+    .bind(staff_id)
+    .bind(utils::now_eat())
+    .execute(&mut *tx)
+    .await?;
+    let statement_id = statement.last_insert_id() as i64;
+
+    let mut response = StatementImportResponseDto { statement_id, imported:0, skipped:0 };
+    for line in lines {
+        let date = line.completed_at.date();
+        let seen: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM provider_statement_line WHERE receipt_no = ?")
+            .bind(&line.receipt_no)
+            .fetch_one(&mut *tx)
+            .await?;
+        if date < query.from || date > query.to || seen.0 > 0 {
+            response.skipped += 1;
+            continue;
+        }
+
+        sqlx::query(
+            "INSERT INTO provider_statement_line (provider_statement_id, receipt_no, completed_at, details,
+                transaction_status, paid_in, withdrawn, other_party, account_no, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(statement_id)
+        .bind(&line.receipt_no)
+        .bind(line.completed_at)
+        .bind(&line.details)
+        .bind(&line.transaction_status)
+        .bind(line.paid_in)
+        .bind(line.withdrawn)
+        .bind(&line.other_party)
+        .bind(&line.account_no)
+        .bind(line.created_at)
+        .execute(&mut *tx)
+        .await?;
+        response.imported += 1;
+    }
+
+    sqlx::query("UPDATE provider_statement SET line_count = ? WHERE id = ?")
+        .bind(response.imported as i32)
+        .bind(statement_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!("Imported statement {} from {}: {} lines, {} skipped", statement_id, query.gateway, response.imported, response.skipped);
+    Ok(response)
+}
+
+/// The customer's phone number from "2547XXXXXXXX - JANE DOE".
+fn statement_phone(other_party:&str) -> String {
+    let phone = other_party.split(" - ").next().unwrap_or_default().trim();
+    utils::is_valid_phone(phone).unwrap_or_else(|| phone.to_string())
+}
+
+/// Transaction charges are listed as lines of their own and are not payments.
+fn is_charge(line:&ProviderStatementLine) -> bool {
+    line.details.to_lowercase().contains("charge")
+}
+
+/// The oldest payout still waiting on the provider to this phone for this amount.
+async fn find_withdrawal_candidate(pool:&MySqlPool, line:&ProviderStatementLine) -> Result<Option<Withdrawal>, AppError> {
+    let withdrawal = sqlx::query_as::<_, Withdrawal>(
+        "SELECT * FROM withdrawal WHERE msisdn = ? AND amount = ? AND status IN (?, ?) ORDER BY created_at LIMIT 1"
+    )
+    .bind(statement_phone(&line.other_party))
+    .bind(line.withdrawn)
+    .bind(WithdrawalStatus::PENDING)
+    .bind(WithdrawalStatus::SUBMITTED)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(withdrawal)
+}
+
+/// Compares the imported statement lines for a period with the deposits and
+/// withdrawals we recorded for it. Statement lines we have no record of can
+/// be settled into the books; records the statement does not show, or that
+/// are still pending, can be reversed.
+pub async fn discrepancy_report(pool:&MySqlPool, user_id:&str, query:&ReconciliationQueryDto) -> Result<Vec<PaymentDiscrepancyDto>, AppError> {
+    authentication_service::require_staff(pool, user_id).await?;
+
+    let (Some(start), Some(end)) = (query.from.and_hms_opt(0, 0, 0), query.to.succ_opt().and_then(|d| d.and_hms_opt(0, 0, 0))) else {
+        return Err(AppError::BadRequest("Invalid period".to_string()));
+    };
+
+    let lines = sqlx::query_as::<_, ProviderStatementLine>(
+        "SELECT * FROM provider_statement_line
+        WHERE completed_at >= ? AND completed_at < ? AND (transaction_status = '' OR transaction_status = 'Completed')
+        ORDER BY completed_at"
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await?;
+    let deposits = sqlx::query_as::<_, Deposit>(
+        "SELECT * FROM deposit WHERE bill_date >= ? AND bill_date < ? AND status IN (?, ?, ?) ORDER BY bill_date"
+    )
+    .bind(start)
+    .bind(end)
+    .bind(DepositStatus::PENDING)
+    .bind(DepositStatus::COMPLETED)
+    .bind(DepositStatus::SUSPENSE)
+    .fetch_all(pool)
+    .await?;
+    let withdrawals = sqlx::query_as::<_, Withdrawal>(
+        "SELECT * FROM withdrawal WHERE created_at >= ? AND created_at < ? AND status IN (?, ?, ?) ORDER BY created_at"
+    )
+    .bind(start)
+    .bind(end)
+    .bind(WithdrawalStatus::PENDING)
+    .bind(WithdrawalStatus::SUBMITTED)
+    .bind(WithdrawalStatus::COMPLETED)
+    .fetch_all(pool)
+    .await?;
+
+    let mut report = Vec::new();
+    let mut on_statement = HashSet::new();
+
+    for line in lines.iter().filter(|line| !is_charge(line)) {
+        on_statement.insert(line.receipt_no.clone());
+
+        if line.paid_in.is_positive() {
+            let deposit = sqlx::query_as::<_, Deposit>("SELECT * FROM deposit WHERE receipt_no = ?")
+                .bind(&line.receipt_no)
+                .fetch_optional(pool)
+                .await?;
+            let (kind, candidate) = match &deposit {
+                None => match deposit_service::find_by_statement_line(pool, &line.account_no, line.paid_in).await? {
+                    // Completed from a status query, only its receipt is missing
+                    Some(candidate) if candidate.status == DepositStatus::COMPLETED => {
+                        on_statement.extend(candidate.receipt_no.clone());
+                        ("RECEIPT_MISSING", Some(candidate))
+                    }
+                    candidate => ("MISSING_IN_BOOKS", candidate),
+                },
+                Some(deposit) if deposit.amount != line.paid_in => ("AMOUNT_MISMATCH", None),
+                Some(_) => continue,
+            };
+            let record = deposit.as_ref().or(candidate.as_ref());
+            report.push(PaymentDiscrepancyDto {
+                kind:kind.to_string(),
+                direction:PaymentDirection::Collection,
+                receipt_no:Some(line.receipt_no.clone()),
+                reference:record.map(|d| d.bill_no.clone()),
+                record_id:record.and_then(|d| d.id),
+                statement_line_id:line.id,
+                statement_amount:Some(line.paid_in),
+                book_amount:record.map(|d| d.amount),
+                action:deposit.is_none().then(|| "SETTLE".to_string()),
+            });
+        } else if line.withdrawn.is_positive() {
+            let withdrawal = sqlx::query_as::<_, Withdrawal>("SELECT * FROM withdrawal WHERE receipt_no = ?")
+                .bind(&line.receipt_no)
+                .fetch_optional(pool)
+                .await?;
+            let (kind, candidate) = match &withdrawal {
+                None => ("MISSING_IN_BOOKS", find_withdrawal_candidate(pool, line).await?),
+                Some(withdrawal) if withdrawal.amount != line.withdrawn => ("AMOUNT_MISMATCH", None),
+                Some(_) => continue,
+            };
+            let record = withdrawal.as_ref().or(candidate.as_ref());
+            report.push(PaymentDiscrepancyDto {
+                kind:kind.to_string(),
+                direction:PaymentDirection::Payout,
+                receipt_no:Some(line.receipt_no.clone()),
+                reference:record.map(|w| w.reference.clone()),
+                record_id:record.and_then(|w| w.id),
+                statement_line_id:line.id,
+                statement_amount:Some(line.withdrawn),
+                book_amount:record.map(|w| w.amount),
+                action:candidate.is_some().then(|| "SETTLE".to_string()),
+            });
+        }
+    }
+
+    for deposit in &deposits {
+        let kind = match (&deposit.status, &deposit.receipt_no) {
+            (DepositStatus::PENDING, _) => "STILL_PENDING",
+            (_, Some(receipt_no)) if on_statement.contains(receipt_no) => continue,
+            _ => "MISSING_ON_STATEMENT",
+        };
+        // A deposit the statement shows under its bill number is listed above
+        if kind == "STILL_PENDING" && report.iter().any(|d| d.direction == PaymentDirection::Collection && d.record_id == deposit.id) {
+            continue;
+        }
+        report.push(PaymentDiscrepancyDto {
+            kind:kind.to_string(),
+            direction:PaymentDirection::Collection,
+            receipt_no:deposit.receipt_no.clone(),
+            reference:Some(deposit.bill_no.clone()),
+            record_id:deposit.id,
+            statement_line_id:None,
+            statement_amount:None,
+            book_amount:Some(deposit.amount),
+            action:Some("REVERSE".to_string()),
+        });
+    }
+
+    for withdrawal in &withdrawals {
+        let kind = match (&withdrawal.status, &withdrawal.receipt_no) {
+            (WithdrawalStatus::PENDING | WithdrawalStatus::SUBMITTED, _) => "STILL_PENDING",
+            (_, Some(receipt_no)) if on_statement.contains(receipt_no) => continue,
+            _ => "MISSING_ON_STATEMENT",
+        };
+        // A payout the statement shows under another receipt is listed above
+        if kind == "STILL_PENDING" && report.iter().any(|d| d.direction == PaymentDirection::Payout && d.record_id == withdrawal.id) {
+            continue;
+        }
+        report.push(PaymentDiscrepancyDto {
+            kind:kind.to_string(),
+            direction:PaymentDirection::Payout,
+            receipt_no:withdrawal.receipt_no.clone(),
+            reference:Some(withdrawal.reference.clone()),
+            record_id:withdrawal.id,
+            statement_line_id:None,
+            statement_amount:None,
+            book_amount:Some(withdrawal.amount),
+            action:Some("REVERSE".to_string()),
+        });
+    }
+
+    info!("Payment reconciliation {} to {} found {} discrepancies", query.from, query.to, report.len());
+    Ok(report)
+}
+
+/// Brings a statement line we have no record of into the books. Money paid
+/// in for a deposit completed without its receipt only records the receipt.
+/// Otherwise it is recorded like a paybill confirmation, completing the
+/// pending deposit it was for or landing in the payer's wallet, a chama or
+/// suspense. Money
+/// paid out completes the pending withdrawal it matches. Returns the id of
+/// the deposit or withdrawal.
+pub async fn settle_statement_line(pool:&MySqlPool, user_id:&str, statement_line_id:&i64) -> Result<i64, AppError> {
+    authentication_service::require_staff(pool, user_id).await?;
+
+    let line = sqlx::query_as::<_, ProviderStatementLine>("SELECT * FROM provider_statement_line WHERE id = ?")
+        .bind(statement_line_id)
+        .fetch_optional(pool)
+        .await?;
+    let Some(line) = line else {
+        return Err(AppError::NotFound("No such statement line".to_string()));
+    };
+
+    if line.paid_in.is_positive() {
+        if let Some(deposit) = deposit_service::find_by_statement_line(pool, &line.account_no, line.paid_in).await?
+            && deposit.status == DepositStatus::COMPLETED {
+            let deposit = deposit_service::settle_placeholder_receipt(pool, &deposit.id.unwrap_or_default(), &line.receipt_no).await?;
+            return Ok(deposit.id.unwrap_or_default());
+        }
+        let payment = C2bPaymentDto {
+            transaction_type:"Statement".to_string(),
+            trans_id:line.receipt_no.clone(),
+            trans_time:line.completed_at.format("%Y%m%d%H%M%S").to_string(),
+            trans_amount:line.paid_in,
+            business_short_code:String::new(),
+            bill_ref_number:line.account_no.clone(),
+            msisdn:statement_phone(&line.other_party),
+            first_name:None,
+        };
+        let deposit = deposit_service::confirm_c2b_payment(pool, &payment).await?;
+        return Ok(deposit.id.unwrap_or_default());
+    }
+
+    let withdrawn: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM withdrawal WHERE receipt_no = ?")
+        .bind(&line.receipt_no)
+        .fetch_one(pool)
+        .await?;
+    if withdrawn.0 > 0 {
+        return Err(AppError::Conflict(format!("Receipt {} is already recorded", line.receipt_no)));
+    }
+    let Some(withdrawal) = find_withdrawal_candidate(pool, &line).await? else {
+        return Err(AppError::BadRequest("No pending withdrawal matches this line".to_string()));
+    };
+
+    let outcome = PaymentStatus::Completed { receipt_no:line.receipt_no.clone(), amount:Some(line.withdrawn) };
+    let withdrawal = withdrawal_service::settle_withdrawal(pool, &withdrawal.reference, &outcome, "Settled from the provider statement").await?;
+    Ok(withdrawal.id.unwrap_or_default())
+}
+
+/// Reverses a deposit or withdrawal reconciliation found to be wrong.
+pub async fn reverse_payment(pool:&MySqlPool, user_id:&str, payload:&ReversePaymentDto) -> Result<i64, AppError> {
+    authentication_service::require_staff(pool, user_id).await?;
+    let staff_id = loan_service::parse_user_id(user_id)?;
+
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::BadRequest("A reason is required".to_string()));
+    }
+
+    match payload.direction {
+        PaymentDirection::Collection => {
+            deposit_service::reverse_deposit(pool, &staff_id, &payload.record_id, reason).await?;
+        }
+        PaymentDirection::Payout => {
+            withdrawal_service::reverse_withdrawal_by_staff(pool, &staff_id, &payload.record_id, reason).await?;
+        }
+    }
+    Ok(payload.record_id)
+}
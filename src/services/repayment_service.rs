@@ -0,0 +1,403 @@
+use sqlx::{MySql, MySqlPool, Transaction};
+use tracing::info;
+
+use crate::dtos::loan::LoanRepaymentDto;
+use crate::error::AppError;
+use crate::money::Money;
+use crate::models::chama::ChamaPermission;
+use crate::models::loan::{
+    InstallmentStatus,
+    LoadRequestStatus,
+    LoanRepayment,
+    LoanRepaymentMethod,
+    LoanRepaymentSchedule,
+    LoanRequest
+};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, fine_service, guarantee_service, ledger_service, loan_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+#[derive(Debug, Default)]
+pub struct RepaymentAllocation {
+    pub penalty:Money,
+    pub fees:Money,
+    pub interest:Money,
+    pub principal:Money,
+    pub overpayment:Money,
+}
+
+/// Takes what can be taken of `available` towards `due - paid`, returning the amount applied.
+fn apply(available:&mut Money, due:Money, paid:&mut Money) -> Money {
+    let applied = (*available).min(due - *paid).max(Money::ZERO);
+    *paid += applied;
+    *available -= applied;
+    applied
+}
+
+/// Spreads a payment over the schedule oldest installment first. Within an
+/// installment money goes to penalties, then fees, then interest and finally
+/// principal, and the next installment is only touched once the current one
+/// is cleared. Whatever is left after the last installment is overpayment.
+pub fn allocate_payment(installments:&mut [LoanRepaymentSchedule], amount:Money) -> RepaymentAllocation {
+    let mut available = amount;
+    let mut allocation = RepaymentAllocation::default();
+
+    for installment in installments.iter_mut() {
+        if !available.is_positive() {
+            break;
+        }
+        allocation.penalty += apply(&mut available, installment.penalty, &mut installment.penalty_paid);
+        allocation.fees += apply(&mut available, installment.fees, &mut installment.fees_paid);
+        allocation.interest += apply(&mut available, installment.interest, &mut installment.interest_paid);
+        allocation.principal += apply(&mut available, installment.principal, &mut installment.principal_paid);
+
+        installment.status = if !installment.outstanding().is_positive() {
+            InstallmentStatus::PAID
+        } else if (installment.principal_paid + installment.interest_paid + installment.fees_paid + installment.penalty_paid).is_positive() {
+            InstallmentStatus::PARTIAL
+        } else {
+            InstallmentStatus::PENDING
+        };
+    }
+
+    allocation.overpayment = available;
+    allocation
+}
+
+/// Applies a payment to a disbursed loan inside the caller's transaction.
+///
+/// The money is journalled from where it came from, the borrower's wallet,
+/// the chama's savings for recoveries from savings or cash otherwise. The
+/// principal goes back to whatever funded the loan and the rest to the
+/// income accounts. Overpayment is credited to the borrower's wallet and the
+/// loan moves to REPAID, releasing its guarantors, once nothing is left
+/// outstanding.
+pub async fn post_repayment(
+    pool:&MySqlPool,
+    tx:&mut Transaction<'_, MySql>,
+    loan_id:&i64,
+    amount:Money,
+    method:LoanRepaymentMethod,
+    reference:Option<String>) -> Result<LoanRepayment, AppError> {
+
+    if !amount.is_positive() {
+        return Err(AppError::BadRequest("Repayment amount must be greater than zero".to_string()));
+    }
+
+    let loan = loan_service::find_loan(pool, loan_id).await?;
+    let status = loan_service::lock_loan_status(tx, loan_id).await?;
+    if !matches!(status, LoadRequestStatus::DISBURSED | LoadRequestStatus::DEFAULTED) {
+        return Err(AppError::Conflict(format!("Loan is {:?} and cannot take repayments", status)));
+    }
+
+    if let Some(reference) = &reference {
+        let existing: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM loan_repayment WHERE load_request_id = ? AND reference = ?"
+        )
+        .bind(loan_id)
+        .bind(reference)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        if existing.0 > 0 {
+            return Err(AppError::Conflict("Repayment with this reference is already recorded".to_string()));
+        }
+    }
+
+    let mut installments = sqlx::query_as::<_, LoanRepaymentSchedule>(
+        "SELECT * FROM loan_repayment_schedule WHERE load_request_id = ? AND status <> 'PAID'
+        ORDER BY installment_no FOR UPDATE"
+    )
+    .bind(loan_id)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let allocation = allocate_payment(&mut installments, amount);
+
+    let now_eat = utils::now_eat();
+    for installment in &installments {
+        sqlx::query(
+            "UPDATE loan_repayment_schedule SET principal_paid = ?, interest_paid = ?, fees_paid = ?,
+                penalty_paid = ?, status = ?, updated_at = ?
+            WHERE id = ?"
+        )
+        .bind(installment.principal_paid)
+        .bind(installment.interest_paid)
+        .bind(installment.fees_paid)
+        .bind(installment.penalty_paid)
+        .bind(installment.status)
+        .bind(now_eat)
+        .bind(installment.id)
+        .execute(&mut **tx)
+        .await?;
+        fine_service::settle_installment_fines(tx, installment).await?;
+    }
+
+    let loan_balance: Money = installments.iter().map(|i| i.outstanding()).sum();
+
+    let repayment = LoanRepayment {
+        id:None,
+        load_request_id:*loan_id,
+        amount,
+        penalty_paid:allocation.penalty,
+        fees_paid:allocation.fees,
+        interest_paid:allocation.interest,
+        principal_paid:allocation.principal,
+        overpayment:allocation.overpayment,
+        loan_balance,
+        payment_mothod:format!("{:?}", method),
+        reference:reference.clone(),
+        paid_at:now_eat,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let repayment_repository = data_repository::DataRepository::<LoanRepayment> {
+        pool,
+        table_name: "loan_repayment",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let repayment_id = repayment_repository.insert_trx(tx, &repayment).await?;
+
+    let source = match method {
+        LoanRepaymentMethod::ACCOUNT => LedgerAccountKey::Wallet(loan.user_id),
+        LoanRepaymentMethod::GUARANTEE | LoanRepaymentMethod::SAVINGS => match loan.chama_id {
+            Some(chama_id) => LedgerAccountKey::ChamaSavings(chama_id),
+            None => return Err(AppError::BadRequest("Only chama loans are recovered from savings".to_string())),
+        },
+        _ => LedgerAccountKey::Cash,
+    };
+    let principal_account = loan_funding_account(tx, loan_id, &loan).await?;
+    ledger_service::post_journal(
+        tx,
+        &JournalRequest {
+            reference:&format!("REPAYMENT-{}", repayment_id),
+            entry_type:"LOAN_REPAYMENT",
+            narration:&format!("Repayment of loan {} via {:?}{}", loan_id, method,
+                reference.map(|r| format!(", ref {}", r)).unwrap_or_default()),
+            created_by:None,
+        },
+        &[
+            PostingLine::debit(source, repayment.amount),
+            PostingLine::credit(principal_account, repayment.principal_paid),
+            PostingLine::credit(LedgerAccountKey::InterestIncome, repayment.interest_paid),
+            PostingLine::credit(LedgerAccountKey::FeeIncome, repayment.fees_paid),
+            PostingLine::credit(LedgerAccountKey::PenaltyIncome, repayment.penalty_paid),
+            PostingLine::credit(LedgerAccountKey::Wallet(loan.user_id), repayment.overpayment),
+        ],
+    ).await?;
+
+    if !loan_balance.is_positive() {
+        loan_service::transition_loan_status(tx, loan_id, status, LoadRequestStatus::REPAID).await?;
+        guarantee_service::release_guarantees(tx, loan_id).await?;
+        info!("Loan {} fully repaid", loan_id);
+    }
+
+    info!("Repayment of {} posted to loan {}, balance {}", amount, loan_id, loan_balance);
+    Ok(LoanRepayment { id:Some(repayment_id), ..repayment })
+}
+
+/// Borrowers pay from their wallet, anything else is a payment received
+/// outside the app that a chama admin records against the loan.
+pub async fn make_repayment(pool:&MySqlPool, user_id:&str, loan_id:&i64, payload:&LoanRepaymentDto) -> Result<LoanRepayment, AppError> {
+    let payer_id = loan_service::parse_user_id(user_id)?;
+    let loan = loan_service::find_loan(pool, loan_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let repayment = match payload.payment_method {
+        LoanRepaymentMethod::ACCOUNT => {
+            if loan.user_id != payer_id {
+                return Err(AppError::NotFound("No such loan".to_string()));
+            }
+            // Never pull more from the wallet than the loan needs
+            let outstanding = get_outstanding_balance(&mut tx, loan_id).await?;
+            let amount = payload.amount.min(outstanding);
+            post_repayment(pool, &mut tx, loan_id, amount, payload.payment_method, None).await?
+        }
+        LoanRepaymentMethod::GUARANTEE | LoanRepaymentMethod::SAVINGS => {
+            return Err(AppError::BadRequest("Recoveries from savings are posted by the system".to_string()));
+        }
+        _ => {
+            let Some(chama_id) = loan.chama_id else {
+                return Err(AppError::Forbidden("User not allowed to perform this action".to_string()));
+            };
+            chama_service::require_permission(pool, user_id, &chama_id, ChamaPermission::MANAGELOANS).await?;
+            post_repayment(pool, &mut tx, loan_id, payload.amount, payload.payment_method, payload.reference.clone()).await?
+        }
+    };
+
+    tx.commit().await?;
+    Ok(repayment)
+}
+
+pub async fn get_outstanding_balance(tx:&mut Transaction<'_, MySql>, loan_id:&i64) -> Result<Money, AppError> {
+    let outstanding: (Money,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(principal - principal_paid + interest - interest_paid
+            + fees - fees_paid + penalty - penalty_paid), 0)
+        FROM loan_repayment_schedule WHERE load_request_id = ?"
+    )
+    .bind(loan_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(outstanding.0)
+}
+
+pub async fn get_repayments(pool:&MySqlPool, user_id:&str, loan_id:&i64) -> Result<Vec<LoanRepayment>, AppError> {
+    // Same visibility as the loan itself
+    loan_service::get_loan(pool, user_id, loan_id).await?;
+
+    let repayments = sqlx::query_as::<_, LoanRepayment>(
+        "SELECT * FROM loan_repayment WHERE load_request_id = ? ORDER BY paid_at"
+    )
+    .bind(loan_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(repayments)
+}
+
+/// Where a loan's principal was paid out from and is repaid to. Chama loans
+/// are lent out of the chama's savings; those disbursed before that, like
+/// any loan the platform funds, sit on the borrower's loan receivable.
+async fn loan_funding_account(tx:&mut Transaction<'_, MySql>, loan_id:&i64, loan:&LoanRequest) -> Result<LedgerAccountKey, AppError> {
+    let Some(chama_id) = loan.chama_id else {
+        return Ok(LedgerAccountKey::LoanReceivable(loan.user_id));
+    };
+    let (from_savings,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM journal_entry je
+        INNER JOIN ledger_posting p ON p.journal_entry_id = je.id
+        INNER JOIN ledger_account a ON a.id = p.ledger_account_id
+        WHERE je.reference = ? AND a.code = ?"
+    )
+    .bind(format!("DISBURSE-{}", loan_id))
+    .bind(LedgerAccountKey::ChamaSavings(chama_id).code())
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(if from_savings > 0 {
+        LedgerAccountKey::ChamaSavings(chama_id)
+    } else {
+        LedgerAccountKey::LoanReceivable(loan.user_id)
+    })
+}
+
+/// Pays an approved chama loan out of the chama's savings to the borrower's
+/// account. Refused when the chama does not hold enough.
+pub async fn disburse_loan(pool:&MySqlPool, user_id:&str, loan_id:&i64) -> Result<(), AppError> {
+    let loan = loan_service::find_loan(pool, loan_id).await?;
+
+    let Some(chama_id) = loan.chama_id else {
+        return Err(AppError::BadRequest("Only chama loans are disbursed here".to_string()));
+    };
+    chama_service::require_permission(pool, user_id, &chama_id, ChamaPermission::MANAGELOANS).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let status = loan_service::lock_loan_status(&mut tx, loan_id).await?;
+    loan_service::transition_loan_status(&mut tx, loan_id, status, LoadRequestStatus::DISBURSED).await?;
+
+    let source = LedgerAccountKey::ChamaSavings(chama_id);
+    if ledger_service::lock_balance(&mut tx, &source).await? < loan.amount_approved {
+        return Err(AppError::BadRequest("Insufficient balance in the chama wallet".to_string()));
+    }
+    ledger_service::post_journal(
+        &mut tx,
+        &JournalRequest {
+            reference:&format!("DISBURSE-{}", loan_id),
+            entry_type:"LOAN_DISBURSEMENT",
+            narration:&format!("Disbursement of loan {}", loan_id),
+            created_by:loan_service::parse_user_id(user_id).ok(),
+        },
+        &[
+            PostingLine::debit(source, loan.amount_approved),
+            PostingLine::credit(LedgerAccountKey::Wallet(loan.user_id), loan.amount_approved),
+        ],
+    ).await?;
+
+    tx.commit().await?;
+    info!("Loan {} disbursed: {}", loan_id, loan.amount_approved);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installment(installment_no:i32, principal:&str, interest:&str, fees:&str, penalty:&str) -> LoanRepaymentSchedule {
+        let now = chrono::Utc::now().naive_utc();
+        let (principal, interest, fees, penalty) = (Money::kes(principal), Money::kes(interest), Money::kes(fees), Money::kes(penalty));
+        LoanRepaymentSchedule {
+            id:Some(installment_no as i64),
+            load_request_id:1,
+            installment_no,
+            due_date:now,
+            principal,
+            interest,
+            fees,
+            penalty,
+            amount_due:principal + interest + fees + penalty,
+            remaining_balance:Money::ZERO,
+            principal_paid:Money::ZERO,
+            interest_paid:Money::ZERO,
+            fees_paid:Money::ZERO,
+            penalty_paid:Money::ZERO,
+            status:InstallmentStatus::PENDING,
+            created_at:now,
+            updated_at:now,
+        }
+    }
+
+    #[test]
+    fn pays_penalty_then_fees_then_interest_then_principal() {
+        let mut schedule = vec![installment(1, "100.00", "20.00", "10.00", "5.00")];
+
+        let allocation = allocate_payment(&mut schedule, Money::kes("30.00"));
+        assert_eq!(allocation.penalty, Money::kes("5.00"));
+        assert_eq!(allocation.fees, Money::kes("10.00"));
+        assert_eq!(allocation.interest, Money::kes("15.00"));
+        assert_eq!(allocation.principal, Money::ZERO);
+        assert_eq!(schedule[0].status, InstallmentStatus::PARTIAL);
+
+        let allocation = allocate_payment(&mut schedule, Money::kes("50.00"));
+        assert_eq!(allocation.interest, Money::kes("5.00"));
+        assert_eq!(allocation.principal, Money::kes("45.00"));
+        assert_eq!(schedule[0].outstanding(), Money::kes("55.00"));
+    }
+
+    #[test]
+    fn clears_the_oldest_installment_before_the_next() {
+        let mut schedule = vec![
+            installment(1, "100.00", "10.00", "0.00", "0.00"),
+            installment(2, "100.00", "10.00", "0.00", "0.00"),
+        ];
+
+        let allocation = allocate_payment(&mut schedule, Money::kes("120.00"));
+        assert_eq!(schedule[0].status, InstallmentStatus::PAID);
+        assert_eq!(schedule[1].status, InstallmentStatus::PARTIAL);
+        // Interest on the second installment comes before its principal
+        assert_eq!(schedule[1].interest_paid, Money::kes("10.00"));
+        assert_eq!(schedule[1].principal_paid, Money::ZERO);
+        assert_eq!(allocation.interest, Money::kes("20.00"));
+        assert_eq!(allocation.principal, Money::kes("100.00"));
+        assert_eq!(allocation.overpayment, Money::ZERO);
+    }
+
+    #[test]
+    fn keeps_what_is_left_after_the_last_installment_as_overpayment() {
+        let mut schedule = vec![installment(1, "100.00", "10.00", "0.00", "0.00")];
+
+        let allocation = allocate_payment(&mut schedule, Money::kes("150.00"));
+        assert_eq!(allocation.principal + allocation.interest, Money::kes("110.00"));
+        assert_eq!(allocation.overpayment, Money::kes("40.00"));
+        assert_eq!(schedule[0].status, InstallmentStatus::PAID);
+
+        // A settled schedule takes nothing further
+        let allocation = allocate_payment(&mut schedule, Money::kes("10.00"));
+        assert_eq!(allocation.overpayment, Money::kes("10.00"));
+    }
+}
@@ -0,0 +1,678 @@
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use sqlx::{MySql, MySqlConnection, MySqlPool, Transaction};
+use tracing::{info, error};
+
+use crate::dtos::rotation::{RotationBidDto, RotationDetailDto, RotationDto};
+use crate::error::AppError;
+use crate::models::chama::ChamaPermission;
+use crate::models::contribution::{Contribution, ContributionCycle};
+use crate::models::rotation::{
+    Rotation,
+    RotationArrearsPolicyEnum,
+    RotationBid,
+    RotationMethodEnum,
+    RotationPayout,
+    RotationPayoutShare,
+    RotationPayoutStatus,
+    RotationSlot,
+    RotationSlotStatus,
+    RotationStatus
+};
+use crate::money::Money;
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, contribution_service, ledger_service, loan_service, notification_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+/// The cycle running today. Writes that depend on it open due cycles first.
+async fn get_current_cycle(pool:&MySqlPool, chama_id:&i64) -> Result<Option<ContributionCycle>, AppError> {
+    let cycle = sqlx::query_as::<_, ContributionCycle>(
+        "SELECT * FROM contribution_cycle WHERE chama_id = ? AND start_date <= ? AND end_date >= ?"
+    )
+    .bind(chama_id)
+    .bind(utils::now_eat().date())
+    .bind(utils::now_eat().date())
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(cycle)
+}
+
+async fn get_rotation_by_id(pool:&MySqlPool, rotation_id:&i64) -> Result<Rotation, AppError> {
+    let rotation = sqlx::query_as::<_, Rotation>("SELECT * FROM rotation WHERE id = ?")
+        .bind(rotation_id)
+        .fetch_optional(pool)
+        .await?;
+
+    rotation.ok_or_else(|| AppError::NotFound("No such rotation".to_string()))
+}
+
+/// Starts a merry-go-round over the chama's active members. The chama needs
+/// a contribution schedule, each of its cycles pays one member.
+pub async fn create_rotation(pool:&MySqlPool, user_id:&str, payload:&RotationDto) -> Result<RotationDetailDto, AppError> {
+    chama_service::require_permission(pool, user_id, &payload.chama_id, ChamaPermission::MANAGECONTRIBUTIONS).await?;
+    let created_by = loan_service::parse_user_id(user_id)?;
+
+    let active: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM rotation WHERE chama_id = ? AND status = ?")
+        .bind(payload.chama_id)
+        .bind(RotationStatus::ACTIVE)
+        .fetch_one(pool)
+        .await?;
+    if active.0 > 0 {
+        return Err(AppError::Conflict("The chama already has a rotation running".to_string()));
+    }
+    contribution_service::open_cycles(pool, &payload.chama_id).await?;
+    let Some(current_cycle) = get_current_cycle(pool, &payload.chama_id).await? else {
+        return Err(AppError::BadRequest("Set a contribution schedule before starting a rotation".to_string()));
+    };
+    let start_cycle_no = payload.start_cycle_no.unwrap_or(current_cycle.cycle_no);
+    if start_cycle_no < current_cycle.cycle_no {
+        return Err(AppError::BadRequest(format!("Rotation cannot start before the current cycle {}", current_cycle.cycle_no)));
+    }
+
+    let members: Vec<(i64,)> = sqlx::query_as("SELECT user_id FROM chama_member WHERE chama_id = ? AND is_active = 1 ORDER BY id")
+        .bind(payload.chama_id)
+        .fetch_all(pool)
+        .await?;
+    let mut order: Vec<i64> = members.into_iter().map(|m| m.0).collect();
+    if order.len() < 2 {
+        return Err(AppError::BadRequest("A rotation needs at least two active members".to_string()));
+    }
+
+    match (payload.method, &payload.order) {
+        (RotationMethodEnum::FIXED, Some(fixed)) => {
+            let mut given = fixed.clone();
+            given.sort_unstable();
+            let mut expected = order.clone();
+            expected.sort_unstable();
+            if given != expected {
+                return Err(AppError::BadRequest("The order must list every active member once".to_string()));
+            }
+            order = fixed.clone();
+        }
+        (RotationMethodEnum::RANDOM, _) => order.shuffle(&mut OsRng),
+        _ => {}
+    }
+
+    let now_eat = utils::now_eat();
+    let mut rotation = Rotation {
+        id:None,
+        chama_id:payload.chama_id,
+        method:payload.method,
+        arrears_policy:payload.arrears_policy,
+        start_cycle_no,
+        status:RotationStatus::ACTIVE,
+        created_by,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let rotation_repository = data_repository::DataRepository::<Rotation> {
+        pool,
+        table_name: "rotation",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let slot_repository = data_repository::DataRepository::<RotationSlot> {
+        pool,
+        table_name: "rotation_slot",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let mut tx = pool.begin().await?;
+    let rotation_id = rotation_repository.insert_trx(&mut tx, &rotation).await?;
+    rotation.id = Some(rotation_id);
+    for (position, member_id) in order.iter().enumerate() {
+        slot_repository.insert_trx(&mut tx, &RotationSlot {
+            id:None,
+            rotation_id,
+            user_id:*member_id,
+            position:position as i32 + 1,
+            status:RotationSlotStatus::WAITING,
+            created_at:now_eat,
+            updated_at:now_eat,
+        }).await?;
+    }
+    tx.commit().await?;
+
+    info!("Rotation {} ({:?}) started for chama {} from cycle {}", rotation_id, rotation.method, rotation.chama_id, start_cycle_no);
+    get_rotation_detail(pool, rotation).await
+}
+
+async fn get_rotation_detail(pool:&MySqlPool, rotation:Rotation) -> Result<RotationDetailDto, AppError> {
+    let slots = sqlx::query_as::<_, RotationSlot>("SELECT * FROM rotation_slot WHERE rotation_id = ? ORDER BY position")
+        .bind(rotation.id)
+        .fetch_all(pool)
+        .await?;
+    let payouts = sqlx::query_as::<_, RotationPayout>("SELECT * FROM rotation_payout WHERE rotation_id = ? ORDER BY id")
+        .bind(rotation.id)
+        .fetch_all(pool)
+        .await?;
+    let bids = match get_current_cycle(pool, &rotation.chama_id).await? {
+        Some(cycle) if rotation.method == RotationMethodEnum::BIDDING => {
+            sqlx::query_as::<_, RotationBid>("SELECT * FROM rotation_bid WHERE rotation_id = ? AND cycle_id = ? ORDER BY amount DESC, updated_at")
+                .bind(rotation.id)
+                .bind(cycle.id)
+                .fetch_all(pool)
+                .await?
+        }
+        _ => Vec::new(),
+    };
+
+    Ok(RotationDetailDto { rotation, slots, payouts, bids })
+}
+
+/// The chama's latest rotation with its order, payouts and current bids.
+pub async fn get_rotation(pool:&MySqlPool, user_id:&str, chama_id:&i64) -> Result<RotationDetailDto, AppError> {
+    contribution_service::require_member(pool, user_id, chama_id).await?;
+
+    let rotation = sqlx::query_as::<_, Rotation>("SELECT * FROM rotation WHERE chama_id = ? ORDER BY id DESC LIMIT 1")
+        .bind(chama_id)
+        .fetch_optional(pool)
+        .await?;
+    let Some(rotation) = rotation else {
+        return Err(AppError::NotFound("The chama has no rotation".to_string()));
+    };
+
+    get_rotation_detail(pool, rotation).await
+}
+
+pub async fn cancel_rotation(pool:&MySqlPool, user_id:&str, rotation_id:&i64) -> Result<RotationDetailDto, AppError> {
+    let mut rotation = get_rotation_by_id(pool, rotation_id).await?;
+    chama_service::require_permission(pool, user_id, &rotation.chama_id, ChamaPermission::MANAGECONTRIBUTIONS).await?;
+
+    if rotation.status != RotationStatus::ACTIVE {
+        return Err(AppError::Conflict(format!("Rotation is already {:?}", rotation.status)));
+    }
+    sqlx::query("UPDATE rotation SET status = ?, updated_at = ? WHERE id = ?")
+        .bind(RotationStatus::CANCELLED)
+        .bind(utils::now_eat())
+        .bind(rotation_id)
+        .execute(pool)
+        .await?;
+    rotation.status = RotationStatus::CANCELLED;
+
+    info!("Rotation {} cancelled by {}", rotation_id, user_id);
+    get_rotation_detail(pool, rotation).await
+}
+
+/// Bids for the current cycle's pot. A member still waiting for their turn
+/// offers part of the pot to the chama; the highest offer wins when the
+/// cycle ends. Bidding again replaces the member's earlier bid.
+pub async fn place_bid(pool:&MySqlPool, user_id:&str, rotation_id:&i64, payload:&RotationBidDto) -> Result<RotationBid, AppError> {
+    let member_id = loan_service::parse_user_id(user_id)?;
+    let rotation = get_rotation_by_id(pool, rotation_id).await?;
+
+    if rotation.status != RotationStatus::ACTIVE || rotation.method != RotationMethodEnum::BIDDING {
+        return Err(AppError::BadRequest("Rotation is not taking bids".to_string()));
+    }
+    if payload.amount.is_negative() {
+        return Err(AppError::BadRequest("Bid cannot be negative".to_string()));
+    }
+    let waiting: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM rotation_slot WHERE rotation_id = ? AND user_id = ? AND status = ?")
+        .bind(rotation_id)
+        .bind(member_id)
+        .bind(RotationSlotStatus::WAITING)
+        .fetch_one(pool)
+        .await?;
+    if waiting.0 == 0 {
+        return Err(AppError::Forbidden("Only members still waiting for their turn may bid".to_string()));
+    }
+    contribution_service::open_cycles(pool, &rotation.chama_id).await?;
+    let Some(cycle) = get_current_cycle(pool, &rotation.chama_id).await? else {
+        return Err(AppError::BadRequest("No contribution cycle is running".to_string()));
+    };
+    if cycle.cycle_no < rotation.start_cycle_no {
+        return Err(AppError::BadRequest(format!("Bidding opens in cycle {}", rotation.start_cycle_no)));
+    }
+    let cycle_id = cycle.id.unwrap_or_default();
+
+    let now_eat = utils::now_eat();
+    sqlx::query("DELETE FROM rotation_bid WHERE rotation_id = ? AND cycle_id = ? AND user_id = ?")
+        .bind(rotation_id)
+        .bind(cycle_id)
+        .bind(member_id)
+        .execute(pool)
+        .await?;
+    let mut bid = RotationBid {
+        id:None,
+        rotation_id:*rotation_id,
+        cycle_id,
+        user_id:member_id,
+        amount:payload.amount,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let bid_repository = data_repository::DataRepository::<RotationBid> {
+        pool,
+        table_name: "rotation_bid",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    bid.id = Some(bid_repository.insert(&bid).await?);
+
+    info!("Member {} bid {} on cycle {} of rotation {}", member_id, bid.amount, cycle.cycle_no, rotation_id);
+    Ok(bid)
+}
+
+/// Contributions paid during a cycle, per member.
+async fn cycle_contributions(pool:&MySqlPool, cycle:&ContributionCycle) -> Result<Vec<(i64, Money)>, AppError> {
+    let contributions: Vec<(i64, Money)> = sqlx::query_as(
+        "SELECT user_id, SUM(amount) FROM contribution
+        WHERE chama_id = ? AND is_active = 1 AND amount > 0 AND DATE(paid_at) BETWEEN ? AND ?
+        GROUP BY user_id"
+    )
+    .bind(cycle.chama_id)
+    .bind(cycle.start_date)
+    .bind(cycle.end_date)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(contributions)
+}
+
+/// Records what each member put into a payout's pot, so a held pot is paid
+/// out of the same contributions it was settled on.
+async fn insert_shares(pool:&MySqlPool, tx:&mut Transaction<'_, MySql>, payout_id:&i64, contributions:&[(i64, Money)]) -> Result<(), AppError> {
+    let share_repository = data_repository::DataRepository::<RotationPayoutShare> {
+        pool,
+        table_name: "rotation_payout_share",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let now_eat = utils::now_eat();
+    for (member_id, amount) in contributions {
+        share_repository.insert_trx(tx, &RotationPayoutShare {
+            id:None,
+            rotation_payout_id:*payout_id,
+            user_id:*member_id,
+            amount:*amount,
+            created_at:now_eat,
+        }).await?;
+    }
+    Ok(())
+}
+
+async fn get_shares(conn:&mut MySqlConnection, payout_id:&i64) -> Result<Vec<(i64, Money)>, AppError> {
+    let shares: Vec<(i64, Money)> = sqlx::query_as("SELECT user_id, amount FROM rotation_payout_share WHERE rotation_payout_id = ? ORDER BY id")
+        .bind(payout_id)
+        .fetch_all(conn)
+        .await?;
+
+    Ok(shares)
+}
+
+/// Pays a cycle's pot out of the chama's savings to the member's wallet.
+/// What each member put into the pot no longer counts as their savings; what
+/// was kept back against the recipient's arrears counts as their contribution
+/// to the cycle. When the savings cannot cover the pot, because the chama
+/// has lent it out, nothing moves and the payout is held instead. Returns
+/// the payout's status. Callers must hold the payout row lock.
+async fn pay_out(
+    conn:&mut MySqlConnection,
+    rotation:&Rotation,
+    cycle:&ContributionCycle,
+    payout:&RotationPayout,
+    contributions:&[(i64, Money)]) -> Result<RotationPayoutStatus, AppError> {
+
+    let payout_id = payout.id.unwrap_or_default();
+    let now_eat = utils::now_eat();
+
+    let savings = LedgerAccountKey::ChamaSavings(rotation.chama_id);
+    let balance = ledger_service::lock_balance(&mut *conn, &savings).await?;
+    if balance < payout.amount {
+        hold_payout(conn, &payout_id, &format!("The chama's savings of {} cannot cover the pot", balance)).await?;
+        return Ok(RotationPayoutStatus::HELD);
+    }
+
+    for (member_id, amount) in contributions {
+        contribution_service::record_savings_deduction(
+            conn,
+            &rotation.chama_id,
+            member_id,
+            *amount,
+            &format!("Merry-go-round pot of cycle {}", cycle.cycle_no),
+        ).await?;
+    }
+    if payout.withheld.is_positive() {
+        contribution_service::insert_contribution(conn, &Contribution {
+            id:None,
+            chama_id:rotation.chama_id,
+            user_id:payout.user_id,
+            deposit_id:None,
+            amount:payout.withheld,
+            paid_at:cycle.end_date.and_hms_opt(23, 59, 59).unwrap_or(now_eat),
+            narration:format!("Kept from merry-go-round payout of cycle {}", cycle.cycle_no),
+            recorded_by:0,
+            is_active:1,
+            created_at:now_eat,
+            updated_at:now_eat,
+        }).await?;
+    }
+
+    if payout.amount.is_positive() {
+        ledger_service::post_journal(
+            conn,
+            &JournalRequest {
+                reference:&format!("ROTATION-PAYOUT-{}", payout_id),
+                entry_type:"ROTATION_PAYOUT",
+                narration:&format!("Merry-go-round payout of cycle {} to member {}", cycle.cycle_no, payout.user_id),
+                created_by:None,
+            },
+            &[
+                PostingLine::debit(savings, payout.amount),
+                PostingLine::credit(LedgerAccountKey::Wallet(payout.user_id), payout.amount),
+            ],
+        ).await?;
+    }
+
+    sqlx::query("UPDATE rotation_payout SET status = ?, hold_reason = NULL, paid_at = ?, updated_at = ? WHERE id = ?")
+        .bind(RotationPayoutStatus::PAID)
+        .bind(now_eat)
+        .bind(now_eat)
+        .bind(payout_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("UPDATE rotation_slot SET status = ?, updated_at = ? WHERE rotation_id = ? AND user_id = ?")
+        .bind(RotationSlotStatus::PAID)
+        .bind(now_eat)
+        .bind(rotation.id)
+        .bind(payout.user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(RotationPayoutStatus::PAID)
+}
+
+async fn hold_payout(conn:&mut MySqlConnection, payout_id:&i64, reason:&str) -> Result<(), AppError> {
+    sqlx::query("UPDATE rotation_payout SET status = ?, hold_reason = ?, updated_at = ? WHERE id = ?")
+        .bind(RotationPayoutStatus::HELD)
+        .bind(reason)
+        .bind(utils::now_eat())
+        .bind(payout_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn notify_payout(pool:&MySqlPool, payout:&RotationPayout, cycle:&ContributionCycle) {
+    let message = match payout.status {
+        RotationPayoutStatus::PAID => format!(
+            "Your merry-go-round payout of KES {} for cycle {} has been credited to your wallet.",
+            payout.amount, cycle.cycle_no
+        ),
+        RotationPayoutStatus::HELD => format!(
+            "Your merry-go-round payout for cycle {} is on hold: {}.",
+            cycle.cycle_no, payout.hold_reason.as_deref().unwrap_or(ARREARS_HOLD)
+        ),
+    };
+    notification_service::notify_user(pool, &payout.user_id, "Merry-go-round payout", &message).await;
+}
+
+/// Members whose turn may come this cycle, in the order they would get it:
+/// those still waiting without a held payout by position, or for a bidding
+/// rotation the bidders by highest bid first, then everyone else. Returns
+/// each member with their bid.
+async fn turn_order(pool:&MySqlPool, rotation:&Rotation, cycle:&ContributionCycle) -> Result<Vec<(i64, Money)>, AppError> {
+    let waiting: Vec<(i64,)> = sqlx::query_as(
+        "SELECT rs.user_id FROM rotation_slot rs
+        WHERE rs.rotation_id = ? AND rs.status = ?
+        AND NOT EXISTS (SELECT 1 FROM rotation_payout rp WHERE rp.rotation_id = rs.rotation_id AND rp.user_id = rs.user_id)
+        ORDER BY rs.position"
+    )
+    .bind(rotation.id)
+    .bind(RotationSlotStatus::WAITING)
+    .fetch_all(pool)
+    .await?;
+    let mut order: Vec<(i64, Money)> = waiting.into_iter().map(|w| (w.0, Money::ZERO)).collect();
+
+    if rotation.method == RotationMethodEnum::BIDDING {
+        let bids = sqlx::query_as::<_, RotationBid>(
+            "SELECT * FROM rotation_bid WHERE rotation_id = ? AND cycle_id = ? ORDER BY amount DESC, updated_at"
+        )
+        .bind(rotation.id)
+        .bind(cycle.id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut bidders: Vec<(i64, Money)> = bids
+            .iter()
+            .filter(|bid| order.iter().any(|(member_id, _)| *member_id == bid.user_id))
+            .map(|bid| (bid.user_id, bid.amount))
+            .collect();
+        order.retain(|(member_id, _)| !bidders.iter().any(|(bidder, _)| bidder == member_id));
+        bidders.extend(order);
+        order = bidders;
+    }
+
+    Ok(order)
+}
+
+const ARREARS_HOLD: &str = "waiting for your contribution arrears to be cleared";
+
+/// Decides who receives an ended cycle's pot and pays them, or holds the
+/// pot for them when the arrears policy says so.
+async fn settle_cycle(pool:&MySqlPool, rotation:&Rotation, cycle:&ContributionCycle) -> Result<Option<RotationPayout>, AppError> {
+    let contributions = cycle_contributions(pool, cycle).await?;
+    let pot: Money = contributions.iter().map(|c| c.1).sum();
+    if !pot.is_positive() {
+        info!("Cycle {} of chama {} has an empty pot, nobody is paid", cycle.cycle_no, rotation.chama_id);
+        return Ok(None);
+    }
+
+    let order = turn_order(pool, rotation, cycle).await?;
+    if order.is_empty() {
+        return Ok(None);
+    }
+
+    let mut chosen = None;
+    if rotation.arrears_policy == RotationArrearsPolicyEnum::SKIP {
+        for (member_id, bid) in &order {
+            if !contribution_service::member_arrears(pool, &rotation.chama_id, member_id).await?.is_positive() {
+                chosen = Some((*member_id, *bid));
+                break;
+            }
+        }
+    }
+    // Under SKIP with everyone in arrears the pot waits for the first of them
+    let (member_id, bid) = chosen.unwrap_or(order[0]);
+    let bid = bid.min(pot);
+    let arrears = contribution_service::member_arrears(pool, &rotation.chama_id, &member_id).await?;
+
+    let (withheld, status) = match rotation.arrears_policy {
+        RotationArrearsPolicyEnum::DEDUCT => (arrears.min(pot - bid), RotationPayoutStatus::PAID),
+        _ if arrears.is_positive() => (Money::ZERO, RotationPayoutStatus::HELD),
+        _ => (Money::ZERO, RotationPayoutStatus::PAID),
+    };
+
+    let now_eat = utils::now_eat();
+    let mut payout = RotationPayout {
+        id:None,
+        rotation_id:rotation.id.unwrap_or_default(),
+        cycle_id:cycle.id.unwrap_or_default(),
+        user_id:member_id,
+        pot,
+        bid,
+        withheld,
+        amount:pot - bid - withheld,
+        status:RotationPayoutStatus::HELD,
+        hold_reason:(status == RotationPayoutStatus::HELD).then(|| ARREARS_HOLD.to_string()),
+        paid_at:None,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let payout_repository = data_repository::DataRepository::<RotationPayout> {
+        pool,
+        table_name: "rotation_payout",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let mut tx = pool.begin().await?;
+    // The job and an official running payouts by hand must not both pay a cycle
+    sqlx::query("SELECT id FROM rotation WHERE id = ? FOR UPDATE")
+        .bind(rotation.id)
+        .execute(&mut *tx)
+        .await?;
+    let settled: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM rotation_payout WHERE rotation_id = ? AND cycle_id = ?")
+        .bind(rotation.id)
+        .bind(cycle.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if settled.0 > 0 {
+        return Ok(None);
+    }
+    let payout_id = payout_repository.insert_trx(&mut tx, &payout).await?;
+    payout.id = Some(payout_id);
+    insert_shares(pool, &mut tx, &payout_id, &contributions).await?;
+    if status == RotationPayoutStatus::PAID {
+        payout.status = pay_out(&mut tx, rotation, cycle, &payout, &contributions).await?;
+        if payout.status == RotationPayoutStatus::PAID {
+            payout.hold_reason = None;
+            payout.paid_at = Some(now_eat);
+        }
+    }
+    tx.commit().await?;
+    if payout.status == RotationPayoutStatus::HELD {
+        payout = sqlx::query_as::<_, RotationPayout>("SELECT * FROM rotation_payout WHERE id = ?")
+            .bind(payout_id)
+            .fetch_one(pool)
+            .await?;
+    }
+
+    info!("Cycle {} pot of {} in rotation {:?} {:?} for member {}", cycle.cycle_no, pot, rotation.id, payout.status, member_id);
+    notify_payout(pool, &payout, cycle).await;
+    Ok(Some(payout))
+}
+
+/// Pays a held pot once its member has cleared their arrears and the
+/// chama's savings can cover it.
+async fn release_held_payout(pool:&MySqlPool, rotation:&Rotation, payout_id:&i64) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    let payout = sqlx::query_as::<_, RotationPayout>("SELECT * FROM rotation_payout WHERE id = ? FOR UPDATE")
+        .bind(payout_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(mut payout) = payout else {
+        return Err(AppError::NotFound("No such payout".to_string()));
+    };
+    // Under DEDUCT arrears were settled out of the pot, only savings held it
+    if payout.status != RotationPayoutStatus::HELD
+        || (rotation.arrears_policy != RotationArrearsPolicyEnum::DEDUCT
+            && contribution_service::member_arrears(pool, &rotation.chama_id, &payout.user_id).await?.is_positive()) {
+        return Ok(());
+    }
+    let cycle = sqlx::query_as::<_, ContributionCycle>("SELECT * FROM contribution_cycle WHERE id = ?")
+        .bind(payout.cycle_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let mut contributions = get_shares(&mut tx, payout_id).await?;
+    if contributions.is_empty() {
+        // Held before shares were recorded
+        contributions = cycle_contributions(pool, &cycle).await?;
+    }
+    if pay_out(&mut tx, rotation, &cycle, &payout, &contributions).await? == RotationPayoutStatus::HELD {
+        // Still short, the member was told when it was first held
+        tx.commit().await?;
+        return Ok(());
+    }
+    tx.commit().await?;
+
+    info!("Held payout {} of rotation {:?} released to member {}", payout_id, rotation.id, payout.user_id);
+    payout.status = RotationPayoutStatus::PAID;
+    notify_payout(pool, &payout, &cycle).await;
+    Ok(())
+}
+
+/// Settles every ended cycle of the rotation not yet paid out and releases
+/// held pots whose members are now up to date. The rotation completes once
+/// every member has been paid. Returns how many payouts were made or held.
+pub async fn process_rotation(pool:&MySqlPool, rotation:&Rotation) -> Result<u32, AppError> {
+    let rotation_id = rotation.id.unwrap_or_default();
+
+    let held: Vec<(i64,)> = sqlx::query_as("SELECT id FROM rotation_payout WHERE rotation_id = ? AND status = ?")
+        .bind(rotation_id)
+        .bind(RotationPayoutStatus::HELD)
+        .fetch_all(pool)
+        .await?;
+    for (payout_id,) in held {
+        release_held_payout(pool, rotation, &payout_id).await?;
+    }
+
+    contribution_service::open_cycles(pool, &rotation.chama_id).await?;
+    let last_paid_cycle: (Option<i32>,) = sqlx::query_as(
+        "SELECT MAX(cc.cycle_no) FROM rotation_payout rp INNER JOIN contribution_cycle cc ON cc.id = rp.cycle_id WHERE rp.rotation_id = ?"
+    )
+    .bind(rotation_id)
+    .fetch_one(pool)
+    .await?;
+    let ended_cycles = sqlx::query_as::<_, ContributionCycle>(
+        "SELECT * FROM contribution_cycle WHERE chama_id = ? AND end_date < ? AND cycle_no >= ? AND cycle_no > ? ORDER BY cycle_no"
+    )
+    .bind(rotation.chama_id)
+    .bind(utils::now_eat().date())
+    .bind(rotation.start_cycle_no)
+    .bind(last_paid_cycle.0.unwrap_or(0))
+    .fetch_all(pool)
+    .await?;
+
+    let mut settled = 0;
+    for cycle in &ended_cycles {
+        if settle_cycle(pool, rotation, cycle).await?.is_some() {
+            settled += 1;
+        }
+    }
+
+    let remaining: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM rotation_slot WHERE rotation_id = ? AND status = ?")
+        .bind(rotation_id)
+        .bind(RotationSlotStatus::WAITING)
+        .fetch_one(pool)
+        .await?;
+    if remaining.0 == 0 {
+        sqlx::query("UPDATE rotation SET status = ?, updated_at = ? WHERE id = ? AND status = ?")
+            .bind(RotationStatus::COMPLETED)
+            .bind(utils::now_eat())
+            .bind(rotation_id)
+            .bind(RotationStatus::ACTIVE)
+            .execute(pool)
+            .await?;
+        info!("Rotation {} completed, every member has been paid", rotation_id);
+    }
+
+    Ok(settled)
+}
+
+/// Runs the payouts of one rotation now rather than waiting for the job.
+pub async fn run_rotation(pool:&MySqlPool, user_id:&str, rotation_id:&i64) -> Result<RotationDetailDto, AppError> {
+    let rotation = get_rotation_by_id(pool, rotation_id).await?;
+    chama_service::require_permission(pool, user_id, &rotation.chama_id, ChamaPermission::MANAGECONTRIBUTIONS).await?;
+    if rotation.status != RotationStatus::ACTIVE {
+        return Err(AppError::Conflict(format!("Rotation is already {:?}", rotation.status)));
+    }
+
+    process_rotation(pool, &rotation).await?;
+    let rotation = get_rotation_by_id(pool, rotation_id).await?;
+    get_rotation_detail(pool, rotation).await
+}
+
+/// Processes every running rotation.
+pub async fn run_due_payouts(pool:&MySqlPool) -> Result<u32, AppError> {
+    let rotations = sqlx::query_as::<_, Rotation>("SELECT * FROM rotation WHERE status = ?")
+        .bind(RotationStatus::ACTIVE)
+        .fetch_all(pool)
+        .await?;
+
+    let mut settled = 0;
+    for rotation in &rotations {
+        match process_rotation(pool, rotation).await {
+            Ok(count) => settled += count,
+            Err(e) => error!("Could not process rotation {:?}: {}", rotation.id, e),
+        }
+    }
+    Ok(settled)
+}
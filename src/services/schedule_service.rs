@@ -0,0 +1,291 @@
+use std::str::FromStr;
+
+use chrono::{Duration, Months, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{MySql, MySqlPool, Transaction};
+use tracing::info;
+
+use crate::enums::{InterestMethodEnum, LoanRepaymentFrequecyEnum};
+use crate::error::AppError;
+use crate::money::{self, Money};
+use crate::models::loan::{InstallmentStatus, LoanRepaymentSchedule, LoanRequest};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{chama_service, loan_service};
+use crate::utils;
+
+
+pub struct ScheduleTerms {
+    pub principal:Money,
+    // Percentage of the principal charged per month
+    pub monthly_rate:Decimal,
+    pub method:InterestMethodEnum,
+    pub processing_fee_centage:Decimal,
+    pub frequency:LoanRepaymentFrequecyEnum,
+    pub months:u32,
+    pub start:NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledInstallment {
+    pub installment_no:i32,
+    pub due_date:NaiveDateTime,
+    pub principal:Money,
+    pub interest:Money,
+    pub fees:Money,
+    pub amount_due:Money,
+    pub remaining_balance:Money,
+}
+
+fn installment_due_date(terms:&ScheduleTerms, installment_no:u32) -> Option<NaiveDateTime> {
+    match terms.frequency {
+        LoanRepaymentFrequecyEnum::MONTHLY => terms.start.checked_add_months(Months::new(installment_no)),
+        LoanRepaymentFrequecyEnum::BIWEEKLY => terms.start.checked_add_signed(Duration::days(14 * installment_no as i64)),
+        LoanRepaymentFrequecyEnum::WEEKLY => terms.start.checked_add_signed(Duration::days(7 * installment_no as i64)),
+    }
+}
+
+/// Splits a loan into installments.
+///
+/// Flat rate charges interest on the original principal for the whole term and
+/// spreads it evenly. Reducing balance charges interest on what is still owed
+/// each period and keeps installments level (annuity). Interest and fees are
+/// rounded half up to the cent when charged and principal is split exactly,
+/// with the last installment clearing whatever balance remains, so the
+/// principal column always sums to the amount lent. The processing fee is due
+/// with the first installment.
+pub fn build_schedule(terms:&ScheduleTerms) -> Result<Vec<ScheduledInstallment>, AppError> {
+    let periods = terms.months * terms.frequency.periods_per_month();
+    if periods == 0 || !terms.principal.is_positive() {
+        return Err(AppError::BadRequest("Loan has nothing to schedule".to_string()));
+    }
+
+    let period_rate = terms.monthly_rate / Decimal::ONE_HUNDRED / Decimal::from(terms.frequency.periods_per_month());
+    let fees = terms.principal.percent_rounded(terms.processing_fee_centage);
+
+    let flat_interest = terms.principal
+        .percent_rounded(terms.monthly_rate * Decimal::from(terms.months))
+        .split(periods);
+    let flat_principal = terms.principal.split(periods);
+
+    // Level annuity payment P * r / (1 - (1 + r)^-n), rounded to the cent
+    let level_payment = if period_rate.is_zero() {
+        terms.principal.mul_rounded(Decimal::ONE / Decimal::from(periods))
+    } else {
+        let growth = (0..periods).fold(Decimal::ONE, |acc, _| acc * (Decimal::ONE + period_rate));
+        terms.principal.mul_rounded(period_rate * growth / (growth - Decimal::ONE))
+    };
+
+    let mut installments = Vec::with_capacity(periods as usize);
+    let mut balance = terms.principal;
+
+    for n in 1..=periods {
+        let last = n == periods;
+        let index = (n - 1) as usize;
+
+        let (principal, interest) = match terms.method {
+            InterestMethodEnum::FLAT => (flat_principal[index], flat_interest[index]),
+            InterestMethodEnum::REDUCINGBALANCE => {
+                let interest = balance.mul_rounded(period_rate);
+                let principal = if last { balance } else { (level_payment - interest).min(balance).max(Money::ZERO) };
+                (principal, interest)
+            }
+        };
+
+        balance -= principal;
+        let installment_fees = if n == 1 { fees } else { Money::ZERO };
+
+        let due_date = installment_due_date(terms, n)
+            .ok_or_else(|| AppError::BadRequest("Repayment period is too long".to_string()))?;
+
+        installments.push(ScheduledInstallment {
+            installment_no:n as i32,
+            due_date,
+            principal,
+            interest,
+            fees:installment_fees,
+            amount_due:principal + interest + installment_fees,
+            remaining_balance:balance,
+        });
+    }
+
+    Ok(installments)
+}
+
+/// Builds the schedule for an approved loan from its chama's repayment settings
+/// and stores it, moving the loan's due date to the final installment.
+pub async fn generate_schedule(pool:&MySqlPool, tx:&mut Transaction<'_, MySql>, loan:&LoanRequest, principal:Money) -> Result<(), AppError> {
+    let loan_id = loan.id.unwrap_or_default();
+
+    let existing: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM loan_repayment_schedule WHERE load_request_id = ?"
+    )
+    .bind(loan_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    if existing.0 > 0 {
+        return Err(AppError::Conflict("Loan already has a repayment schedule".to_string()));
+    }
+
+    let settings = match loan.chama_id {
+        Some(chama_id) => chama_service::get_active_repayment_limit(pool, &chama_id).await?,
+        None => None,
+    };
+
+    if let Some(settings) = &settings
+        && loan.repayment_months > settings.max_repayment_months {
+        return Err(AppError::BadRequest(format!(
+            "Repayment period of {} months exceeds the chama maximum of {} months",
+            loan.repayment_months, settings.max_repayment_months
+        )));
+    }
+
+    let now_eat = utils::now_eat();
+    let terms = ScheduleTerms {
+        principal,
+        monthly_rate:money::rate(settings.as_ref().map(|s| s.interest_rate).unwrap_or(0.0)),
+        method:settings.as_ref().map(|s| s.interest_method).unwrap_or(InterestMethodEnum::FLAT),
+        processing_fee_centage:money::rate(settings.as_ref().map(|s| s.processing_fee_centage).unwrap_or(0.0)),
+        frequency:match &settings {
+            Some(s) => LoanRepaymentFrequecyEnum::from_str(&s.repayment_frequency).map_err(AppError::BadRequest)?,
+            None => LoanRepaymentFrequecyEnum::from_bill_frequency(&loan.frequency),
+        },
+        months:loan.repayment_months.max(0) as u32,
+        start:now_eat,
+    };
+
+    let installments = build_schedule(&terms)?;
+
+    let schedule_repository = data_repository::DataRepository::<LoanRepaymentSchedule> {
+        pool,
+        table_name: "loan_repayment_schedule",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    let mut final_due_date = loan.due_date;
+    for installment in &installments {
+        let row = LoanRepaymentSchedule {
+            id:None,
+            load_request_id:loan_id,
+            installment_no:installment.installment_no,
+            due_date:installment.due_date,
+            principal:installment.principal,
+            interest:installment.interest,
+            fees:installment.fees,
+            penalty:Money::ZERO,
+            amount_due:installment.amount_due,
+            remaining_balance:installment.remaining_balance,
+            principal_paid:Money::ZERO,
+            interest_paid:Money::ZERO,
+            fees_paid:Money::ZERO,
+            penalty_paid:Money::ZERO,
+            status:InstallmentStatus::PENDING,
+            created_at:now_eat,
+            updated_at:now_eat,
+        };
+        schedule_repository.insert_trx(tx, &row).await?;
+        final_due_date = installment.due_date;
+    }
+
+    sqlx::query("UPDATE loan_request SET due_date = ? WHERE id = ?")
+        .bind(final_due_date)
+        .bind(loan_id)
+        .execute(&mut **tx)
+        .await?;
+
+    info!("Generated {} installments for loan {}", installments.len(), loan_id);
+    Ok(())
+}
+
+pub async fn get_schedule(pool:&MySqlPool, user_id:&str, loan_id:&i64) -> Result<Vec<LoanRepaymentSchedule>, AppError> {
+    // Same visibility as the loan itself
+    loan_service::get_loan(pool, user_id, loan_id).await?;
+
+    let schedule = sqlx::query_as::<_, LoanRepaymentSchedule>(
+        "SELECT * FROM loan_repayment_schedule WHERE load_request_id = ? ORDER BY installment_no"
+    )
+    .bind(loan_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn terms(method:InterestMethodEnum) -> ScheduleTerms {
+        ScheduleTerms {
+            principal:Money::kes("1000.00"),
+            monthly_rate:Decimal::TEN,
+            method,
+            processing_fee_centage:Decimal::TWO,
+            frequency:LoanRepaymentFrequecyEnum::MONTHLY,
+            months:3,
+            start:NaiveDate::from_ymd_opt(2026, 1, 31).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn flat_rate_spreads_interest_evenly_and_leaves_the_remainder_to_the_last_installment() {
+        let schedule = build_schedule(&terms(InterestMethodEnum::FLAT)).unwrap();
+
+        let principal: Vec<Money> = schedule.iter().map(|i| i.principal).collect();
+        let interest: Vec<Money> = schedule.iter().map(|i| i.interest).collect();
+        assert_eq!(principal, vec![Money::kes("333.33"), Money::kes("333.33"), Money::kes("333.34")]);
+        assert_eq!(interest, vec![Money::kes("100.00"), Money::kes("100.00"), Money::kes("100.00")]);
+        assert_eq!(schedule.last().unwrap().remaining_balance, Money::ZERO);
+    }
+
+    #[test]
+    fn reducing_balance_charges_interest_on_what_is_owed() {
+        let schedule = build_schedule(&terms(InterestMethodEnum::REDUCINGBALANCE)).unwrap();
+
+        let interest: Vec<Money> = schedule.iter().map(|i| i.interest).collect();
+        let principal: Vec<Money> = schedule.iter().map(|i| i.principal).collect();
+        // 697.89 * 10% = 69.789 and 365.57 * 10% = 36.557 are rounded to the cent
+        assert_eq!(interest, vec![Money::kes("100.00"), Money::kes("69.79"), Money::kes("36.56")]);
+        // Level payment of 402.11, the last installment clears the rounding
+        assert_eq!(principal, vec![Money::kes("302.11"), Money::kes("332.32"), Money::kes("365.57")]);
+        assert_eq!(principal.iter().copied().sum::<Money>(), Money::kes("1000.00"));
+        assert_eq!(schedule.last().unwrap().remaining_balance, Money::ZERO);
+    }
+
+    #[test]
+    fn processing_fee_is_due_with_the_first_installment() {
+        let schedule = build_schedule(&terms(InterestMethodEnum::FLAT)).unwrap();
+
+        assert_eq!(schedule[0].fees, Money::kes("20.00"));
+        assert_eq!(schedule[0].amount_due, Money::kes("453.33"));
+        assert!(schedule[1..].iter().all(|i| i.fees.is_zero()));
+    }
+
+    #[test]
+    fn due_dates_follow_the_frequency() {
+        let schedule = build_schedule(&terms(InterestMethodEnum::FLAT)).unwrap();
+        // Month ends are clamped rather than rolling into the next month
+        assert_eq!(schedule[0].due_date.date(), NaiveDate::from_ymd_opt(2026, 2, 28).unwrap());
+
+        let mut weekly = terms(InterestMethodEnum::FLAT);
+        weekly.frequency = LoanRepaymentFrequecyEnum::WEEKLY;
+        let schedule = build_schedule(&weekly).unwrap();
+        assert_eq!(schedule.len(), 12);
+        assert_eq!(schedule[0].due_date.date(), NaiveDate::from_ymd_opt(2026, 2, 7).unwrap());
+    }
+
+    #[test]
+    fn nothing_to_schedule_is_rejected() {
+        let mut empty = terms(InterestMethodEnum::FLAT);
+        empty.principal = Money::ZERO;
+        assert!(build_schedule(&empty).is_err());
+
+        let mut no_term = terms(InterestMethodEnum::FLAT);
+        no_term.months = 0;
+        assert!(build_schedule(&no_term).is_err());
+    }
+}
@@ -0,0 +1,439 @@
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::{info, error};
+
+use crate::dtos::payment::{B2cResultDto, WithdrawalRequestDto};
+use crate::error::AppError;
+use crate::gateways::{B2cRequest, GatewayError, MobileMoneyGateway, PaymentStatus};
+use crate::money::Money;
+use crate::models::chama_wallet::ChamaWithdrawalStatus;
+use crate::models::transaction::{Withdrawal, WithdrawalStatus};
+use crate::repositories::crud_repository_trait::CrudRepositoryTrait;
+use crate::repositories::data_repository;
+use crate::services::{authentication_service, ledger_service, loan_service, notification_service};
+use crate::services::ledger_service::{JournalRequest, LedgerAccountKey, PostingLine};
+use crate::utils;
+
+
+/// Pays money out of a user's wallet to their phone.
+pub async fn initiate_withdrawal(
+    pool:&MySqlPool,
+    gateway:&dyn MobileMoneyGateway,
+    user_id:&str,
+    payload:&WithdrawalRequestDto) -> Result<Withdrawal, AppError> {
+
+    let Some(user) = authentication_service::get_auth_user_by_id(pool, user_id).await else {
+        return Err(AppError::Forbidden("Invalid user".to_string()));
+    };
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    if !payload.amount.is_positive() {
+        return Err(AppError::BadRequest("Withdrawal amount must be greater than zero".to_string()));
+    }
+    let phone = payload.phone_number.as_deref().unwrap_or(&user.username);
+    let Some(msisdn) = utils::is_valid_phone(phone) else {
+        return Err(AppError::BadRequest("Phone number not valid".to_string()));
+    };
+
+    let now_eat = utils::now_eat();
+    let withdrawal = Withdrawal {
+        id:None,
+        user_id,
+        chama_id:None,
+        amount:payload.amount,
+        msisdn,
+        reference:format!("WDR{}", utils::generate_invite_hash_64().to_uppercase()),
+        narration:payload.narration.clone().unwrap_or_else(|| "Wallet withdrawal".to_string()),
+        status:WithdrawalStatus::PENDING,
+        gateway:gateway.name().to_string(),
+        conversation_id:None,
+        receipt_no:None,
+        result_desc:None,
+        created_at:now_eat,
+        updated_at:now_eat,
+    };
+    let withdrawal_id = submit_payout(pool, gateway, withdrawal).await?;
+
+    get_withdrawal(pool, &user_id.to_string(), &withdrawal_id).await
+}
+
+/// The wallet a payout's money comes from and goes back to.
+fn source_account(withdrawal:&Withdrawal) -> LedgerAccountKey {
+    match withdrawal.chama_id {
+        Some(chama_id) => LedgerAccountKey::ChamaSavings(chama_id),
+        None => LedgerAccountKey::Wallet(withdrawal.user_id),
+    }
+}
+
+/// Records a payout and sends it to the provider, returning its id.
+///
+/// The amount is first moved from the source wallet into payout clearing, so
+/// it can not be spent twice while the provider works. The payout then stays
+/// held until the provider reports a result, see `settle_b2c_result`. When
+/// the request was sent but no answer came back we do not know whether the
+/// provider took it, so the hold is kept and the withdrawal stays PENDING.
+pub async fn submit_payout(pool:&MySqlPool, gateway:&dyn MobileMoneyGateway, mut withdrawal:Withdrawal) -> Result<i64, AppError> {
+    let source = source_account(&withdrawal);
+
+    let mut tx = pool.begin().await?;
+    // User wallets refuse to go negative when posted, a chama's is checked here
+    if withdrawal.chama_id.is_some() && ledger_service::lock_balance(&mut tx, &source).await? < withdrawal.amount {
+        return Err(AppError::BadRequest("Insufficient balance in the chama wallet".to_string()));
+    }
+    let withdrawal_repository = data_repository::DataRepository::<Withdrawal> {
+        pool,
+        table_name: "withdrawal",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+    let withdrawal_id = withdrawal_repository.insert_trx(&mut tx, &withdrawal).await?;
+    withdrawal.id = Some(withdrawal_id);
+
+    // Fails with insufficient balance when the wallet cannot cover the amount
+    ledger_service::post_journal(
+        &mut tx,
+        &JournalRequest {
+            reference:&format!("WITHDRAWAL-{}-HOLD", withdrawal_id),
+            entry_type:"WITHDRAWAL",
+            narration:&withdrawal.narration,
+            created_by:Some(withdrawal.user_id),
+        },
+        &[
+            PostingLine::debit(source, withdrawal.amount),
+            PostingLine::credit(LedgerAccountKey::PayoutClearing, withdrawal.amount),
+        ],
+    ).await?;
+    tx.commit().await?;
+
+    let request = B2cRequest {
+        msisdn:withdrawal.msisdn.clone(),
+        amount:withdrawal.amount,
+        reference:withdrawal.reference.clone(),
+        remarks:withdrawal.narration.clone(),
+    };
+    match gateway.b2c_payment(&request).await {
+        Ok(response) => {
+            // The result may already have arrived, only move on from PENDING
+            sqlx::query(
+                "UPDATE withdrawal SET status = ?, gateway = ?, conversation_id = ?, updated_at = ? WHERE id = ? AND status = ?"
+            )
+            .bind(WithdrawalStatus::SUBMITTED)
+            .bind(&response.gateway)
+            .bind(&response.conversation_id)
+            .bind(utils::now_eat())
+            .bind(withdrawal_id)
+            .bind(WithdrawalStatus::PENDING)
+            .execute(pool)
+            .await?;
+
+            info!("Withdrawal {} of {} submitted to {} as {}", withdrawal_id, withdrawal.amount, response.gateway, response.conversation_id);
+        }
+        Err(GatewayError::Rejected(reason)) => {
+            info!("Withdrawal {} rejected by {}: {}", withdrawal_id, gateway.name(), reason);
+            let mut tx = pool.begin().await?;
+            let locked = lock_withdrawal_by_reference(&mut tx, &withdrawal.reference).await?;
+            if locked.status == WithdrawalStatus::PENDING {
+                reverse_withdrawal(&mut tx, &locked, &reason).await?;
+            }
+            tx.commit().await?;
+        }
+        // A payout that never reached a provider is equally safe to reverse
+        Err(GatewayError::Unavailable(reason)) => {
+            info!("Withdrawal {} not sent, {} unavailable: {}", withdrawal_id, gateway.name(), reason);
+            let mut tx = pool.begin().await?;
+            let locked = lock_withdrawal_by_reference(&mut tx, &withdrawal.reference).await?;
+            if locked.status == WithdrawalStatus::PENDING {
+                reverse_withdrawal(&mut tx, &locked, &reason).await?;
+            }
+            tx.commit().await?;
+        }
+        Err(GatewayError::Uncertain(reason)) => {
+            error!("Withdrawal {} left pending, no answer from {}: {}", withdrawal_id, gateway.name(), reason);
+        }
+    }
+
+    Ok(withdrawal_id)
+}
+
+async fn lock_withdrawal_by_reference(conn:&mut MySqlConnection, reference:&str) -> Result<Withdrawal, AppError> {
+    let withdrawal = sqlx::query_as::<_, Withdrawal>(
+        "SELECT * FROM withdrawal WHERE reference = ? FOR UPDATE"
+    )
+    .bind(reference)
+    .fetch_optional(conn)
+    .await?;
+
+    withdrawal.ok_or_else(|| AppError::NotFound("No such withdrawal".to_string()))
+}
+
+/// Carries a payout's outcome over to the chama withdrawal request it pays,
+/// if there is one.
+async fn update_chama_withdrawal(conn:&mut MySqlConnection, withdrawal_id:&i64, status:ChamaWithdrawalStatus, result_desc:&str) -> Result<(), AppError> {
+    sqlx::query("UPDATE chama_withdrawal SET status = ?, result_desc = ?, updated_at = ? WHERE withdrawal_id = ?")
+        .bind(status)
+        .bind(result_desc)
+        .bind(utils::now_eat())
+        .bind(withdrawal_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Releases the hold of a payout that did not happen back to its wallet.
+/// Callers must hold the withdrawal row lock and have checked it is open.
+async fn reverse_withdrawal(conn:&mut MySqlConnection, withdrawal:&Withdrawal, reason:&str) -> Result<(), AppError> {
+    let withdrawal_id = withdrawal.id.unwrap_or_default();
+
+    sqlx::query("UPDATE withdrawal SET status = ?, result_desc = ?, updated_at = ? WHERE id = ?")
+        .bind(WithdrawalStatus::REVERSED)
+        .bind(reason)
+        .bind(utils::now_eat())
+        .bind(withdrawal_id)
+        .execute(&mut *conn)
+        .await?;
+    update_chama_withdrawal(&mut *conn, &withdrawal_id, ChamaWithdrawalStatus::FAILED, reason).await?;
+
+    ledger_service::post_journal(
+        conn,
+        &JournalRequest {
+            reference:&format!("WITHDRAWAL-{}-REVERSAL", withdrawal_id),
+            entry_type:"WITHDRAWAL_REVERSAL",
+            narration:&format!("Reversal of {}: {}", withdrawal.reference, reason),
+            created_by:None,
+        },
+        &[
+            PostingLine::debit(LedgerAccountKey::PayoutClearing, withdrawal.amount),
+            PostingLine::credit(source_account(withdrawal), withdrawal.amount),
+        ],
+    ).await?;
+
+    Ok(())
+}
+
+/// Finalises a payout the provider confirmed: the held amount leaves our float.
+/// Callers must hold the withdrawal row lock and have checked it is open.
+async fn complete_withdrawal(conn:&mut MySqlConnection, withdrawal:&Withdrawal, receipt_no:&str, result_desc:&str) -> Result<(), AppError> {
+    let withdrawal_id = withdrawal.id.unwrap_or_default();
+
+    sqlx::query("UPDATE withdrawal SET status = ?, receipt_no = ?, result_desc = ?, updated_at = ? WHERE id = ?")
+        .bind(WithdrawalStatus::COMPLETED)
+        .bind(receipt_no)
+        .bind(result_desc)
+        .bind(utils::now_eat())
+        .bind(withdrawal_id)
+        .execute(&mut *conn)
+        .await?;
+    update_chama_withdrawal(&mut *conn, &withdrawal_id, ChamaWithdrawalStatus::PAID, result_desc).await?;
+
+    ledger_service::post_journal(
+        conn,
+        &JournalRequest {
+            reference:&format!("WITHDRAWAL-{}", withdrawal_id),
+            entry_type:"WITHDRAWAL",
+            narration:&format!("Mobile money payout {} to {}", receipt_no, withdrawal.msisdn),
+            created_by:None,
+        },
+        &[
+            PostingLine::debit(LedgerAccountKey::PayoutClearing, withdrawal.amount),
+            PostingLine::credit(LedgerAccountKey::Cash, withdrawal.amount),
+        ],
+    ).await?;
+
+    Ok(())
+}
+
+/// Applies a provider's verdict on a payout. Only PENDING and SUBMITTED
+/// withdrawals are settled, so repeated or late results and status queries
+/// are acknowledged and ignored and a payout can never be both paid and
+/// reversed.
+pub async fn settle_withdrawal(pool:&MySqlPool, reference:&str, outcome:&PaymentStatus, result_desc:&str) -> Result<Withdrawal, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let mut withdrawal = lock_withdrawal_by_reference(&mut tx, reference).await?;
+    if withdrawal.status != WithdrawalStatus::PENDING && withdrawal.status != WithdrawalStatus::SUBMITTED {
+        info!("Withdrawal {:?} already {:?}, ignoring repeated result", withdrawal.id, withdrawal.status);
+        return Ok(withdrawal);
+    }
+
+    let (receipt_no, amount) = match outcome {
+        PaymentStatus::Pending => return Ok(withdrawal),
+        PaymentStatus::Failed { reason } => {
+            reverse_withdrawal(&mut tx, &withdrawal, reason).await?;
+            tx.commit().await?;
+
+            info!("Withdrawal {:?} reversed: {}", withdrawal.id, reason);
+            notification_service::notify_user(
+                pool,
+                &withdrawal.user_id,
+                "Withdrawal failed",
+                &format!("Your withdrawal of KES {} could not be paid and has been returned to {}.", withdrawal.amount,
+                    if withdrawal.chama_id.is_some() { "the chama wallet" } else { "your wallet" }),
+            ).await;
+
+            withdrawal.status = WithdrawalStatus::REVERSED;
+            withdrawal.result_desc = Some(reason.clone());
+            return Ok(withdrawal);
+        }
+        PaymentStatus::Completed { receipt_no, amount } => (receipt_no, amount),
+    };
+
+    if let Some(amount) = amount
+        && *amount != withdrawal.amount {
+        error!("Withdrawal {:?} paid amount {} does not match {}", withdrawal.id, amount, withdrawal.amount);
+        return Err(AppError::BadRequest("Paid amount does not match the withdrawal".to_string()));
+    }
+
+    complete_withdrawal(&mut tx, &withdrawal, receipt_no, result_desc).await?;
+    tx.commit().await?;
+
+    info!("Withdrawal {:?} of {} paid with receipt {}", withdrawal.id, withdrawal.amount, receipt_no);
+    notification_service::notify_user(
+        pool,
+        &withdrawal.user_id,
+        "Withdrawal paid",
+        &format!("KES {} has been sent to {}. M-Pesa receipt {}.", withdrawal.amount, withdrawal.msisdn, receipt_no),
+    ).await;
+
+    withdrawal.status = WithdrawalStatus::COMPLETED;
+    withdrawal.receipt_no = Some(receipt_no.clone());
+    Ok(withdrawal)
+}
+
+/// Applies the B2C result callback. `timed_out` marks results posted to the
+/// queue timeout URL, which means the provider dropped the request without
+/// paying.
+pub async fn settle_b2c_result(pool:&MySqlPool, result:&B2cResultDto, timed_out:bool) -> Result<Withdrawal, AppError> {
+    let outcome = if timed_out {
+        PaymentStatus::Failed { reason:format!("Timed out: {}", result.result_desc) }
+    } else if result.result_code != 0 {
+        PaymentStatus::Failed { reason:result.result_desc.clone() }
+    } else {
+        let amount = match result.parameter("TransactionAmount") {
+            Some(value) => match serde_json::from_value::<Money>(value.clone()) {
+                Ok(amount) => Some(amount),
+                Err(_) => return Err(AppError::BadRequest("Result carries an unreadable amount".to_string())),
+            },
+            None => None,
+        };
+        let Some(receipt_no) = result.transaction_id.as_deref().filter(|id| !id.is_empty()) else {
+            return Err(AppError::BadRequest("Result carries no transaction id".to_string()));
+        };
+        PaymentStatus::Completed { receipt_no:receipt_no.to_string(), amount }
+    };
+
+    settle_withdrawal(pool, &result.originator_conversation_id, &outcome, &result.result_desc).await
+}
+
+/// Gives the money of a payout back to its wallet when reconciliation shows
+/// it never left: releases the hold of one still in flight, or refunds one
+/// we recorded as paid that the provider's statement does not show.
+pub async fn reverse_withdrawal_by_staff(pool:&MySqlPool, staff_id:&i64, withdrawal_id:&i64, reason:&str) -> Result<Withdrawal, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let withdrawal = sqlx::query_as::<_, Withdrawal>("SELECT * FROM withdrawal WHERE id = ? FOR UPDATE")
+        .bind(withdrawal_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(mut withdrawal) = withdrawal else {
+        return Err(AppError::NotFound("No such withdrawal".to_string()));
+    };
+
+    match withdrawal.status {
+        WithdrawalStatus::PENDING | WithdrawalStatus::SUBMITTED => {
+            reverse_withdrawal(&mut tx, &withdrawal, reason).await?;
+        }
+        WithdrawalStatus::COMPLETED => {
+            sqlx::query("UPDATE withdrawal SET status = ?, result_desc = ?, updated_at = ? WHERE id = ?")
+                .bind(WithdrawalStatus::REVERSED)
+                .bind(reason)
+                .bind(utils::now_eat())
+                .bind(withdrawal_id)
+                .execute(&mut *tx)
+                .await?;
+            update_chama_withdrawal(&mut tx, withdrawal_id, ChamaWithdrawalStatus::FAILED, reason).await?;
+
+            ledger_service::post_journal(
+                &mut tx,
+                &JournalRequest {
+                    reference:&format!("WITHDRAWAL-{}-REFUND", withdrawal_id),
+                    entry_type:"WITHDRAWAL_REVERSAL",
+                    narration:&format!("Refund of {}: {}", withdrawal.reference, reason),
+                    created_by:Some(*staff_id),
+                },
+                &[
+                    PostingLine::debit(LedgerAccountKey::Cash, withdrawal.amount),
+                    PostingLine::credit(source_account(&withdrawal), withdrawal.amount),
+                ],
+            ).await?;
+        }
+        WithdrawalStatus::REVERSED => {
+            return Err(AppError::Conflict("Withdrawal is already reversed".to_string()));
+        }
+    }
+    tx.commit().await?;
+
+    info!("Withdrawal {} reversed by {} from {:?}: {}", withdrawal_id, staff_id, withdrawal.status, reason);
+    withdrawal.status = WithdrawalStatus::REVERSED;
+    withdrawal.result_desc = Some(reason.to_string());
+    Ok(withdrawal)
+}
+
+/// Stands in for the provider when the mock gateway is in use, reporting a
+/// result for the caller's payout exactly as the provider would.
+pub async fn complete_mock_withdrawal(pool:&MySqlPool, user_id:&str, reference:&str, result_code:i64) -> Result<Withdrawal, AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    let withdrawal = sqlx::query_as::<_, Withdrawal>("SELECT * FROM withdrawal WHERE reference = ?")
+        .bind(reference)
+        .fetch_optional(pool)
+        .await?;
+    let Some(withdrawal) = withdrawal.filter(|withdrawal| withdrawal.user_id == user_id) else {
+        return Err(AppError::NotFound("No such withdrawal".to_string()));
+    };
+    if withdrawal.gateway != "mock" {
+        return Err(AppError::BadRequest("Withdrawal was not made through the mock gateway".to_string()));
+    }
+
+    let result = B2cResultDto {
+        result_code,
+        result_desc:if result_code == 0 {
+            "The service request is processed successfully.".to_string()
+        } else {
+            "The payout was declined by the mock gateway.".to_string()
+        },
+        originator_conversation_id:withdrawal.reference.clone(),
+        conversation_id:withdrawal.conversation_id.clone(),
+        transaction_id:Some(format!("MOCK{}", utils::generate_invite_hash_64().to_uppercase())),
+        result_parameters:None,
+    };
+
+    settle_b2c_result(pool, &result, false).await
+}
+
+pub async fn get_user_withdrawals(pool:&MySqlPool, user_id:&str) -> Result<Vec<Withdrawal>, AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    let withdrawals = sqlx::query_as::<_, Withdrawal>(
+        "SELECT * FROM withdrawal WHERE user_id = ? AND chama_id IS NULL ORDER BY created_at DESC"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(withdrawals)
+}
+
+pub async fn get_withdrawal(pool:&MySqlPool, user_id:&str, withdrawal_id:&i64) -> Result<Withdrawal, AppError> {
+    let user_id = loan_service::parse_user_id(user_id)?;
+
+    let withdrawal_repository = data_repository::DataRepository::<Withdrawal> {
+        pool,
+        table_name: "withdrawal",
+        pk_column: "id",
+        phantom: std::marker::PhantomData,
+    };
+
+    match withdrawal_repository.find_by_id(withdrawal_id).await? {
+        Some(withdrawal) if withdrawal.user_id == user_id => Ok(withdrawal),
+        _ => Err(AppError::NotFound("No such withdrawal".to_string())),
+    }
+}